@@ -1,12 +1,43 @@
-use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
-use hashmemo::HashMemo;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use hashmemo::{dedup_memoized, ArcMemo, DynState, HashMemo, MemoCache, Probe};
 use std::{
-    collections::HashMap,
+    borrow::Borrow,
+    cell::RefCell,
+    collections::{HashMap, HashSet},
     fmt::{Display, Formatter},
     hash::{BuildHasher, Hash, RandomState},
+    hint::black_box,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Barrier,
+    },
+    time::{Duration, Instant},
 };
 
 use ahash::RandomState as AHashBuilder;
+use internment::Intern;
+
+#[cfg(feature = "fxhash")]
+use hashmemo::FxHashMemo;
+#[cfg(feature = "fxhash")]
+type FxBuildHasher = std::hash::BuildHasherDefault<rustc_hash::FxHasher>;
+
+#[cfg(all(
+    feature = "gxhash",
+    any(
+        all(
+            target_arch = "x86_64",
+            target_feature = "aes",
+            target_feature = "sse2"
+        ),
+        all(
+            target_arch = "aarch64",
+            target_feature = "aes",
+            target_feature = "neon"
+        ),
+    )
+))]
+use {gxhash::GxBuildHasher, hashmemo::GxHashMemo};
 
 #[derive(Clone, Eq, PartialEq, Hash)]
 struct BigStruct {
@@ -58,6 +89,7 @@ impl Display for Param {
     }
 }
 
+#[allow(clippy::single_element_loop)]
 fn bench(c: &mut Criterion) {
     let mut group = c.benchmark_group("HashMemo vs AHash");
 
@@ -127,6 +159,84 @@ fn bench(c: &mut Criterion) {
                         .collect::<Vec<_>>(),
                 );
 
+                // FxHash: a cheap hasher leaves memoization less to save
+                #[cfg(feature = "fxhash")]
+                {
+                    bench_hashmap::<_, FxBuildHasher>(
+                        &mut group,
+                        "String",
+                        Param {
+                            map_size,
+                            word_length,
+                            steps,
+                            variant: "FxHash",
+                        },
+                        &string_keys,
+                    );
+
+                    bench_hashmap::<FxHashMemo<String>, FxBuildHasher>(
+                        &mut group,
+                        "HashMemo<String>",
+                        Param {
+                            map_size,
+                            word_length,
+                            steps,
+                            variant: "FxHash",
+                        },
+                        &string_keys
+                            .iter()
+                            .cloned()
+                            .map(HashMemo::new_fx)
+                            .collect::<Vec<_>>(),
+                    );
+                }
+
+                // GxHash: hardware-accelerated, fastest on long keys
+                #[cfg(all(
+                    feature = "gxhash",
+                    any(
+                        all(
+                            target_arch = "x86_64",
+                            target_feature = "aes",
+                            target_feature = "sse2"
+                        ),
+                        all(
+                            target_arch = "aarch64",
+                            target_feature = "aes",
+                            target_feature = "neon"
+                        ),
+                    )
+                ))]
+                {
+                    bench_hashmap::<_, GxBuildHasher>(
+                        &mut group,
+                        "String",
+                        Param {
+                            map_size,
+                            word_length,
+                            steps,
+                            variant: "GxHash",
+                        },
+                        &string_keys,
+                    );
+
+                    bench_hashmap::<GxHashMemo<String>, GxBuildHasher>(
+                        &mut group,
+                        "HashMemo<String>",
+                        Param {
+                            map_size,
+                            word_length,
+                            steps,
+                            variant: "GxHash",
+                        },
+                        &string_keys
+                            .iter()
+                            .cloned()
+                            .map(HashMemo::new_gxhash)
+                            .collect::<Vec<_>>(),
+                    );
+                }
+
                 // --- Data: big struct ---
                 let bigs: Vec<_> = (0..map_size)
                     .map(|i| BigStruct::new(i.to_string().repeat(word_length)))
@@ -183,6 +293,51 @@ fn bench(c: &mut Criterion) {
                         .map(|b| HashMemo::with_hasher(b, ahash::RandomState::default()))
                         .collect::<Vec<_>>(),
                 );
+
+                #[cfg(all(
+                    feature = "gxhash",
+                    any(
+                        all(
+                            target_arch = "x86_64",
+                            target_feature = "aes",
+                            target_feature = "sse2"
+                        ),
+                        all(
+                            target_arch = "aarch64",
+                            target_feature = "aes",
+                            target_feature = "neon"
+                        ),
+                    )
+                ))]
+                {
+                    bench_hashmap::<_, GxBuildHasher>(
+                        &mut group,
+                        "BigStruct",
+                        Param {
+                            map_size,
+                            word_length,
+                            steps,
+                            variant: "GxHash",
+                        },
+                        &bigs,
+                    );
+
+                    bench_hashmap::<GxHashMemo<BigStruct>, GxBuildHasher>(
+                        &mut group,
+                        "HashMemo<BigStruct>",
+                        Param {
+                            map_size,
+                            word_length,
+                            steps,
+                            variant: "GxHash",
+                        },
+                        &bigs
+                            .iter()
+                            .cloned()
+                            .map(HashMemo::new_gxhash)
+                            .collect::<Vec<_>>(),
+                    );
+                }
             }
         }
     }
@@ -214,5 +369,933 @@ fn bench_hashmap<T, S>(
     });
 }
 
-criterion_group!(benches, bench);
+/// Cold hashes of `BigStruct` memos under the optional inner hashers,
+/// against ahash.
+fn bench_inner_hashers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Cold BigStruct memo by inner hasher");
+    let state = RandomState::new();
+    let value = BigStruct::new("inner".repeat(100));
+
+    let ahash = HashMemo::with_hasher(value.clone(), AHashBuilder::new());
+    group.bench_function("ahash", |b| {
+        b.iter_batched(
+            || ahash.clone_without_hash(),
+            |memo| state.hash_one(&memo),
+            BatchSize::SmallInput,
+        );
+    });
+
+    let std = HashMemo::new(value.clone());
+    group.bench_function("std", |b| {
+        b.iter_batched(
+            || std.clone_without_hash(),
+            |memo| state.hash_one(&memo),
+            BatchSize::SmallInput,
+        );
+    });
+
+    let fixed = HashMemo::new_fixed(value.clone());
+    group.bench_function("fixed", |b| {
+        b.iter_batched(
+            || fixed.clone_without_hash(),
+            |memo| state.hash_one(&memo),
+            BatchSize::SmallInput,
+        );
+    });
+
+    // The same hashers behind `DynState`, for the cost of dispatching.
+    for (name, dyn_state) in [
+        ("dyn std", DynState::std()),
+        ("dyn fixed", DynState::fixed()),
+    ] {
+        let memo = HashMemo::with_hasher(value.clone(), dyn_state);
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || memo.clone_without_hash(),
+                |memo| state.hash_one(&memo),
+                BatchSize::SmallInput,
+            );
+        });
+    }
+
+    #[cfg(feature = "xxhash")]
+    {
+        let xxh3 = HashMemo::new_xxh3(value.clone());
+        group.bench_function("xxh3", |b| {
+            b.iter_batched(
+                || xxh3.clone_without_hash(),
+                |memo| state.hash_one(&memo),
+                BatchSize::SmallInput,
+            );
+        });
+    }
+
+    #[cfg(feature = "wyhash")]
+    {
+        let wyhash = HashMemo::new_wyhash(value.clone());
+        group.bench_function("wyhash", |b| {
+            b.iter_batched(
+                || wyhash.clone_without_hash(),
+                |memo| state.hash_one(&memo),
+                BatchSize::SmallInput,
+            );
+        });
+    }
+
+    #[cfg(all(
+        feature = "gxhash",
+        any(
+            all(
+                target_arch = "x86_64",
+                target_feature = "aes",
+                target_feature = "sse2"
+            ),
+            all(
+                target_arch = "aarch64",
+                target_feature = "aes",
+                target_feature = "neon"
+            ),
+        )
+    ))]
+    {
+        let gxhash = HashMemo::new_gxhash(value.clone());
+        group.bench_function("gxhash", |b| {
+            b.iter_batched(
+                || gxhash.clone_without_hash(),
+                |memo| state.hash_one(&memo),
+                BatchSize::SmallInput,
+            );
+        });
+    }
+
+    #[cfg(feature = "metrohash")]
+    {
+        let metrohash = HashMemo::new_metrohash(value.clone());
+        group.bench_function("metrohash", |b| {
+            b.iter_batched(
+                || metrohash.clone_without_hash(),
+                |memo| state.hash_one(&memo),
+                BatchSize::SmallInput,
+            );
+        });
+    }
+
+    #[cfg(feature = "seahash")]
+    {
+        let seahash = HashMemo::new_seahash(value.clone());
+        group.bench_function("seahash", |b| {
+            b.iter_batched(
+                || seahash.clone_without_hash(),
+                |memo| state.hash_one(&memo),
+                BatchSize::SmallInput,
+            );
+        });
+    }
+
+    #[cfg(feature = "siphash")]
+    {
+        let siphash = HashMemo::new_siphash(value.clone(), 0x5eed, 0xc0ffee);
+        group.bench_function("siphash-1-3 (keyed)", |b| {
+            b.iter_batched(
+                || siphash.clone_without_hash(),
+                |memo| state.hash_one(&memo),
+                BatchSize::SmallInput,
+            );
+        });
+    }
+
+    #[cfg(feature = "t1ha")]
+    {
+        let t1ha = HashMemo::new_t1ha(value.clone());
+        group.bench_function("t1ha", |b| {
+            b.iter_batched(
+                || t1ha.clone_without_hash(),
+                |memo| state.hash_one(&memo),
+                BatchSize::SmallInput,
+            );
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_byte_eq(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Equality on long byte values");
+
+    for &len in [1 << 10, 1 << 16, 1 << 20].iter() {
+        let a = vec![7u8; len];
+        let mut b = a.clone();
+        *b.last_mut().unwrap() = 8;
+
+        group.bench_with_input(
+            BenchmarkId::new("Vec<u8>", len),
+            &(&a, &b),
+            |bench, (a, b)| {
+                bench.iter(|| black_box(*a) == black_box(*b));
+            },
+        );
+
+        let (a, b) = (HashMemo::new(a.clone()), HashMemo::new(b.clone()));
+        group.bench_with_input(
+            BenchmarkId::new("HashMemo<Vec<u8>>", len),
+            &(&a, &b),
+            |bench, (a, b)| {
+                bench.iter(|| black_box(*a) == black_box(*b));
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_arc_eq(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Equality on large shared structs");
+
+    let value = Arc::new(BigStruct::new("shared".repeat(16)));
+    let copy = Arc::new((*value).clone());
+    let mut differs = (*value).clone();
+    *differs.payload.last_mut().unwrap() = 8;
+    let differs = Arc::new(differs);
+    let memo = ArcMemo::new((*value).clone());
+    memo.memo().precompute();
+
+    let pairs = [
+        ("same allocation", Arc::clone(&value)),
+        ("equal copy", copy),
+        ("unequal copy", differs),
+    ];
+    for (case, other) in pairs {
+        group.bench_with_input(
+            BenchmarkId::new("Arc<BigStruct>", case),
+            &(&value, &other),
+            |bench, (a, b)| {
+                bench.iter(|| black_box(*a) == black_box(*b));
+            },
+        );
+
+        let other_memo = if Arc::ptr_eq(&value, &other) {
+            memo.clone()
+        } else {
+            ArcMemo::new((*other).clone())
+        };
+        other_memo.memo().precompute();
+
+        let (a, b) = (HashMemo::new(Arc::clone(&value)), HashMemo::new(other));
+        a.precompute();
+        b.precompute();
+        group.bench_with_input(
+            BenchmarkId::new("HashMemo<Arc<BigStruct>>", case),
+            &(&a, &b),
+            |bench, (a, b)| {
+                bench.iter(|| black_box(*a) == black_box(*b));
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("ArcMemo<BigStruct>::fast_eq", case),
+            &(&memo, &other_memo),
+            |bench, (a, b)| {
+                bench.iter(|| black_box(*a).fast_eq(black_box(*b)));
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_concurrent_first_hash(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Concurrent first hash");
+    let state = RandomState::new();
+
+    for &threads in [2, 8].iter() {
+        group.bench_with_input(
+            BenchmarkId::new("HashMemo<BigStruct>", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_batched(
+                    || {
+                        (0..64)
+                            .map(|i| HashMemo::new(BigStruct::new(i.to_string())))
+                            .collect::<Vec<_>>()
+                    },
+                    |memos| {
+                        std::thread::scope(|s| {
+                            for _ in 0..threads {
+                                s.spawn(|| {
+                                    for memo in &memos {
+                                        black_box(state.hash_one(memo));
+                                    }
+                                });
+                            }
+                        });
+                    },
+                    BatchSize::SmallInput,
+                );
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_shared_hashing(c: &mut Criterion) {
+    const HASHES_PER_THREAD: u64 = 1000;
+
+    let mut group = c.benchmark_group("Shared value hashed by many threads");
+    let state = RandomState::new();
+    let bare = Arc::new(BigStruct::new("shared".repeat(16)));
+    let memo = Arc::new(HashMemo::new(BigStruct::new("shared".repeat(16))));
+
+    for &threads in [1, 4, 8].iter() {
+        group.throughput(Throughput::Elements(threads * HASHES_PER_THREAD));
+
+        group.bench_with_input(
+            BenchmarkId::new("Arc<BigStruct>", threads),
+            &threads,
+            |b, &threads| b.iter(|| hash_from_threads(&bare, &state, threads, HASHES_PER_THREAD)),
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("Arc<HashMemo<BigStruct>>", threads),
+            &threads,
+            |b, &threads| b.iter(|| hash_from_threads(&memo, &state, threads, HASHES_PER_THREAD)),
+        );
+    }
+
+    group.finish();
+}
+
+fn hash_from_threads<T: Hash + Send + Sync>(
+    value: &Arc<T>,
+    state: &RandomState,
+    threads: u64,
+    hashes: u64,
+) {
+    std::thread::scope(|s| {
+        for _ in 0..threads {
+            let value = Arc::clone(value);
+            s.spawn(move || {
+                for _ in 0..hashes {
+                    black_box(state.hash_one(&*value));
+                }
+            });
+        }
+    });
+}
+
+fn bench_contended(c: &mut Criterion) {
+    const KEYS: usize = 64;
+
+    let state = RandomState::new();
+    let bare: Vec<Arc<BigStruct>> = (0..KEYS)
+        .map(|i| Arc::new(BigStruct::new(i.to_string())))
+        .collect();
+    let memos: Vec<Arc<HashMemo<BigStruct>>> = (0..KEYS)
+        .map(|i| Arc::new(HashMemo::new(BigStruct::new(i.to_string()))))
+        .collect();
+
+    // Phase (a): every round starts from cold memos, which all threads
+    // race to hash.
+    let mut group = c.benchmark_group("Contended first hash");
+    for &threads in [2, 8, 16].iter() {
+        group.throughput(Throughput::Elements((threads * KEYS) as u64));
+
+        group.bench_with_input(
+            BenchmarkId::new("Arc<BigStruct>", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|rounds| {
+                    time_contended(
+                        threads,
+                        rounds,
+                        || {},
+                        || {
+                            for value in &bare {
+                                black_box(state.hash_one(&**value));
+                            }
+                        },
+                    )
+                });
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("Arc<HashMemo<BigStruct>>", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|rounds| {
+                    time_contended(
+                        threads,
+                        rounds,
+                        || memos.iter().for_each(|memo| memo.forget_hash()),
+                        || {
+                            for memo in &memos {
+                                black_box(state.hash_one(&**memo));
+                            }
+                        },
+                    )
+                });
+            },
+        );
+    }
+    group.finish();
+
+    // The same race with the memo's plain-store publish against a
+    // compare-exchange, on cheap hashes so that publishing dominates.
+    let caches: Vec<AtomicU64> = (0..KEYS).map(|_| AtomicU64::new(0)).collect();
+    let reset = || {
+        caches
+            .iter()
+            .for_each(|cache| cache.store(0, Ordering::Relaxed))
+    };
+
+    let mut group = c.benchmark_group("Contended publish");
+    for &threads in [2, 8, 16].iter() {
+        group.throughput(Throughput::Elements((threads * KEYS) as u64));
+
+        group.bench_with_input(
+            BenchmarkId::new("store", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|rounds| {
+                    time_contended(threads, rounds, reset, || {
+                        for (i, cache) in caches.iter().enumerate() {
+                            if cache.load(Ordering::Relaxed) == 0 {
+                                cache.store(state.hash_one(i) | 1, Ordering::Relaxed);
+                            }
+                        }
+                    })
+                });
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("compare_exchange", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|rounds| {
+                    time_contended(threads, rounds, reset, || {
+                        for (i, cache) in caches.iter().enumerate() {
+                            if cache.load(Ordering::Relaxed) == 0 {
+                                let _ = cache.compare_exchange(
+                                    0,
+                                    state.hash_one(i) | 1,
+                                    Ordering::Relaxed,
+                                    Ordering::Relaxed,
+                                );
+                            }
+                        }
+                    })
+                });
+            },
+        );
+    }
+    group.finish();
+
+    // Phase (b): lookups of warm keys in a shared, pre-populated map.
+    let bare_map: HashMap<BigStruct, usize> = bare
+        .iter()
+        .enumerate()
+        .map(|(i, value)| ((**value).clone(), i))
+        .collect();
+    let memo_map: HashMap<HashMemo<BigStruct>, usize> = memos
+        .iter()
+        .enumerate()
+        .map(|(i, memo)| ((**memo).clone(), i))
+        .collect();
+    memos.iter().for_each(|memo| memo.precompute());
+
+    let mut group = c.benchmark_group("Contended steady-state lookups");
+    for &threads in [2, 8, 16].iter() {
+        group.throughput(Throughput::Elements((threads * KEYS) as u64));
+
+        group.bench_with_input(
+            BenchmarkId::new("HashMap<BigStruct, _>", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|rounds| {
+                    time_contended(
+                        threads,
+                        rounds,
+                        || {},
+                        || {
+                            for value in &bare {
+                                black_box(bare_map.get(&**value));
+                            }
+                        },
+                    )
+                });
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("HashMap<HashMemo<BigStruct>, _>", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|rounds| {
+                    time_contended(
+                        threads,
+                        rounds,
+                        || {},
+                        || {
+                            for memo in &memos {
+                                black_box(memo_map.get(&**memo));
+                            }
+                        },
+                    )
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Runs `work` on `threads` threads for `rounds` rounds and returns the
+/// time spent in the rounds alone.
+///
+/// The threads are spawned once, up front, and every round starts and ends
+/// on a barrier shared with the timing thread, so neither spawning nor the
+/// untimed `reset` run before each round is measured.
+fn time_contended(
+    threads: usize,
+    rounds: u64,
+    reset: impl Fn(),
+    work: impl Fn() + Sync,
+) -> Duration {
+    let barrier = Barrier::new(threads + 1);
+    std::thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(|| {
+                for _ in 0..rounds {
+                    barrier.wait();
+                    work();
+                    barrier.wait();
+                }
+            });
+        }
+
+        let mut elapsed = Duration::ZERO;
+        for _ in 0..rounds {
+            reset();
+            barrier.wait();
+            let start = Instant::now();
+            barrier.wait();
+            elapsed += start.elapsed();
+        }
+        elapsed
+    })
+}
+
+/// Stored keys per map in the lookup benches.
+const LOOKUP_KEYS: usize = 1000;
+/// Lookups per iteration in the lookup benches.
+const LOOKUPS: usize = 10_000;
+
+/// Deterministic xorshift, so every variant replays the same lookups.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Picks `LOOKUPS` indices into `2 * LOOKUP_KEYS` keys, of which the first
+/// half is stored: a `hit_rate` share of stored keys, the rest absent, each
+/// half drawn by Zipf (s = 1) rank so a few keys dominate.
+fn zipf_lookups(hit_rate: f64) -> Vec<usize> {
+    let weights: Vec<f64> = (1..=LOOKUP_KEYS).map(|rank| 1.0 / rank as f64).collect();
+    let total: f64 = weights.iter().sum();
+    let cdf: Vec<f64> = weights
+        .iter()
+        .scan(0.0, |acc, w| {
+            *acc += w / total;
+            Some(*acc)
+        })
+        .collect();
+
+    let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+    (0..LOOKUPS)
+        .map(|_| {
+            let hit = rng.next_f64() < hit_rate;
+            let u = rng.next_f64();
+            let rank = cdf.partition_point(|&c| c < u).min(LOOKUP_KEYS - 1);
+            if hit {
+                rank
+            } else {
+                LOOKUP_KEYS + rank
+            }
+        })
+        .collect()
+}
+
+const DISTRIBUTIONS: [(&str, f64); 2] = [("hit-heavy", 0.95), ("miss-heavy", 0.2)];
+
+/// Repeated `get` with mostly hits or mostly misses, by key length.
+///
+/// Each group plots the variants against the key length, so the length at
+/// which memoized keys overtake plain ones reads off the report directly.
+/// "reused" looks up with warm memos built once, "fresh probe" builds a
+/// [`Probe`] from a `&str` for every lookup, hashing it each time.
+fn bench_lookups(c: &mut Criterion) {
+    for (distribution, hit_rate) in DISTRIBUTIONS {
+        let lookups = zipf_lookups(hit_rate);
+        let mut group = c.benchmark_group(format!("Lookups of strings, {distribution}"));
+        group.throughput(Throughput::Elements(LOOKUPS as u64));
+
+        for &key_len in [8, 32, 128, 1024].iter() {
+            let keys: Vec<String> = (0..2 * LOOKUP_KEYS)
+                .map(|i| format!("{i:0key_len$}"))
+                .collect();
+            let plain_map: HashMap<String, usize> =
+                keys[..LOOKUP_KEYS].iter().cloned().zip(0..).collect();
+            let memo_map: HashMap<HashMemo<String>, usize> = keys[..LOOKUP_KEYS]
+                .iter()
+                .cloned()
+                .map(HashMemo::new)
+                .zip(0..)
+                .collect();
+
+            let plain: Vec<&String> = lookups.iter().map(|&i| &keys[i]).collect();
+            group.bench_with_input(BenchmarkId::new("String", key_len), &plain, |b, plain| {
+                b.iter(|| plain.iter().filter(|k| plain_map.contains_key(**k)).count());
+            });
+
+            let memos: Vec<HashMemo<String>> = (0..2 * LOOKUP_KEYS)
+                .map(|i| HashMemo::new(keys[i].clone()))
+                .collect();
+            memos.iter().for_each(|memo| memo.precompute());
+            let reused: Vec<&HashMemo<String>> = lookups.iter().map(|&i| &memos[i]).collect();
+            group.bench_with_input(
+                BenchmarkId::new("HashMemo<String> reused", key_len),
+                &reused,
+                |b, reused| {
+                    b.iter(|| reused.iter().filter(|k| memo_map.contains_key(**k)).count());
+                },
+            );
+
+            let fresh: Vec<&str> = lookups.iter().map(|&i| keys[i].as_str()).collect();
+            group.bench_with_input(
+                BenchmarkId::new("HashMemo<String> fresh probe", key_len),
+                &fresh,
+                |b, fresh| {
+                    b.iter(|| {
+                        fresh
+                            .iter()
+                            .filter(|k| memo_map.contains_key(Probe::new(**k).as_lookup()))
+                            .count()
+                    });
+                },
+            );
+        }
+
+        group.finish();
+    }
+}
+
+/// Repeated `get` on `u64` keys, where memoizing can only cost.
+fn bench_small_key_lookups(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Lookups of u64 keys");
+    group.throughput(Throughput::Elements(LOOKUPS as u64));
+
+    let plain_map: HashMap<u64, usize> = (0..LOOKUP_KEYS as u64).zip(0..).collect();
+    let memo_map: HashMap<HashMemo<u64>, usize> = (0..LOOKUP_KEYS as u64)
+        .map(HashMemo::new)
+        .zip(0..)
+        .collect();
+    let memos: Vec<HashMemo<u64>> = (0..2 * LOOKUP_KEYS as u64).map(HashMemo::new).collect();
+    memos.iter().for_each(|memo| memo.precompute());
+
+    for (distribution, hit_rate) in DISTRIBUTIONS {
+        let lookups = zipf_lookups(hit_rate);
+
+        let plain: Vec<u64> = lookups.iter().map(|&i| i as u64).collect();
+        group.bench_with_input(BenchmarkId::new("u64", distribution), &plain, |b, plain| {
+            b.iter(|| plain.iter().filter(|k| plain_map.contains_key(*k)).count());
+        });
+
+        let reused: Vec<&HashMemo<u64>> = lookups.iter().map(|&i| &memos[i]).collect();
+        group.bench_with_input(
+            BenchmarkId::new("HashMemo<u64> reused", distribution),
+            &reused,
+            |b, reused| {
+                b.iter(|| reused.iter().filter(|k| memo_map.contains_key(**k)).count());
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("HashMemo<u64> fresh probe", distribution),
+            &plain,
+            |b, plain| {
+                b.iter(|| {
+                    plain
+                        .iter()
+                        .filter(|k| memo_map.contains_key(Probe::new(*k).as_lookup()))
+                        .count()
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+/// A hand-rolled interner: equal values share one `Arc`.
+struct ArcInterner<T: ?Sized>(RefCell<HashSet<Arc<T>>>);
+
+impl<T: ?Sized + Eq + Hash> ArcInterner<T> {
+    fn new() -> Self {
+        Self(RefCell::new(HashSet::new()))
+    }
+
+    fn intern<Q>(&self, value: Q) -> Arc<T>
+    where
+        Q: Borrow<T>,
+        Arc<T>: From<Q>,
+    {
+        let mut set = self.0.borrow_mut();
+        if let Some(shared) = set.get(value.borrow()) {
+            return Arc::clone(shared);
+        }
+        let shared = Arc::from(value);
+        set.insert(Arc::clone(&shared));
+        shared
+    }
+}
+
+/// Counts `tokens` in a map keyed by `key(token)`, then looks every token
+/// up again.
+fn count_tokens<K: Eq + Hash>(tokens: &[usize], key: impl Fn(usize) -> K) -> usize {
+    let mut counts: HashMap<K, usize> = HashMap::new();
+    for &token in tokens {
+        *counts.entry(key(token)).or_insert(0) += 1;
+    }
+    tokens
+        .iter()
+        .filter(|&&token| counts.contains_key(&key(token)))
+        .count()
+}
+
+/// `HashMemo` against interning with `internment::Intern` and an `Arc`
+/// dedup table, for compiler-like workloads.
+///
+/// Every map uses std's `RandomState`, so the variants differ only in how
+/// their keys hash, compare and clone. "from tokens" builds each key from
+/// the raw token as it arrives, paying for interning or memoizing; "reused"
+/// keys the map by references to keys built once up front. The memory side
+/// of the trade-off is measured by `cargo bench --bench memory`.
+fn bench_interning(c: &mut Criterion) {
+    const FANOUT: usize = 16;
+
+    let tokens = zipf_lookups(1.0);
+    let words: Vec<String> = (0..LOOKUP_KEYS).map(|i| i.to_string().repeat(10)).collect();
+    let bigs: Vec<BigStruct> = words.iter().cloned().map(BigStruct::new).collect();
+
+    let memo_words: Vec<HashMemo<String>> = words.iter().cloned().map(HashMemo::new).collect();
+    let interned_words: Vec<Intern<String>> = words.iter().cloned().map(Intern::new).collect();
+    let arc_words = ArcInterner::<str>::new();
+    let shared_words: Vec<Arc<str>> = words.iter().map(|w| arc_words.intern(w.as_str())).collect();
+
+    let memo_bigs: Vec<HashMemo<BigStruct>> = bigs.iter().cloned().map(HashMemo::new).collect();
+    let interned_bigs: Vec<Intern<BigStruct>> = bigs.iter().cloned().map(Intern::new).collect();
+    let arc_bigs = ArcInterner::<BigStruct>::new();
+    let shared_bigs: Vec<Arc<BigStruct>> =
+        bigs.iter().map(|b| arc_bigs.intern(b.clone())).collect();
+
+    let mut group = c.benchmark_group("Interning: Zipf insert/lookup churn");
+    group.throughput(Throughput::Elements(2 * LOOKUPS as u64));
+
+    group.bench_function(BenchmarkId::new("HashMemo<String>", "from tokens"), |b| {
+        b.iter(|| count_tokens(&tokens, |t| HashMemo::new(words[t].clone())));
+    });
+    group.bench_function(BenchmarkId::new("Intern<String>", "from tokens"), |b| {
+        b.iter(|| count_tokens(&tokens, |t| Intern::new(words[t].clone())));
+    });
+    group.bench_function(BenchmarkId::new("Arc<str> dedup", "from tokens"), |b| {
+        b.iter(|| count_tokens(&tokens, |t| arc_words.intern(words[t].as_str())));
+    });
+
+    group.bench_function(BenchmarkId::new("HashMemo<String>", "reused"), |b| {
+        b.iter(|| count_tokens(&tokens, |t| &memo_words[t]));
+    });
+    group.bench_function(BenchmarkId::new("Intern<String>", "reused"), |b| {
+        b.iter(|| count_tokens(&tokens, |t| interned_words[t]));
+    });
+    group.bench_function(BenchmarkId::new("Arc<str> dedup", "reused"), |b| {
+        b.iter(|| count_tokens(&tokens, |t| &shared_words[t]));
+    });
+
+    group.bench_function(BenchmarkId::new("HashMemo<BigStruct>", "reused"), |b| {
+        b.iter(|| count_tokens(&tokens, |t| &memo_bigs[t]));
+    });
+    group.bench_function(BenchmarkId::new("Intern<BigStruct>", "reused"), |b| {
+        b.iter(|| count_tokens(&tokens, |t| interned_bigs[t]));
+    });
+    group.bench_function(BenchmarkId::new("Arc<BigStruct> dedup", "reused"), |b| {
+        b.iter(|| count_tokens(&tokens, |t| &shared_bigs[t]));
+    });
+
+    group.finish();
+
+    let mut group = c.benchmark_group("Interning: clone fanout");
+    group.throughput(Throughput::Elements((FANOUT * LOOKUP_KEYS) as u64));
+
+    fn fanout<K: Clone>(keys: &[K]) -> Vec<K> {
+        keys.iter()
+            .flat_map(|key| std::iter::repeat_n(key, FANOUT).cloned())
+            .collect()
+    }
+
+    group.bench_function("HashMemo<String>", |b| b.iter(|| fanout(&memo_words)));
+    group.bench_function("Intern<String>", |b| b.iter(|| fanout(&interned_words)));
+    group.bench_function("Arc<str> dedup", |b| b.iter(|| fanout(&shared_words)));
+    group.bench_function("HashMemo<BigStruct>", |b| b.iter(|| fanout(&memo_bigs)));
+    group.bench_function("Intern<BigStruct>", |b| b.iter(|| fanout(&interned_bigs)));
+    group.bench_function("Arc<BigStruct> dedup", |b| b.iter(|| fanout(&shared_bigs)));
+
+    group.finish();
+}
+
+fn bench_bounded_cache(c: &mut Criterion) {
+    const CAPACITY: usize = 1024;
+
+    // Four times the capacity, so every put in the cycle misses and evicts.
+    // Both caches hash with SipHash, so the difference is the hashing of
+    // evicted keys that `MemoCache` skips.
+    let keys: Vec<String> = (0..4 * CAPACITY).map(|i| format!("{i:0>200}")).collect();
+
+    let mut group = c.benchmark_group("Bounded cache: 200-byte key churn");
+    group.throughput(Throughput::Elements(keys.len() as u64));
+
+    group.bench_function("MemoCache<String, usize>", |b| {
+        let mut cache = MemoCache::with_capacity(CAPACITY);
+        b.iter(|| {
+            for (i, key) in keys.iter().enumerate() {
+                black_box(cache.put(key.clone(), i));
+            }
+        });
+    });
+    #[cfg(feature = "lru")]
+    group.bench_function("lru::LruCache<String, usize>", |b| {
+        let capacity = std::num::NonZeroUsize::new(CAPACITY).unwrap();
+        let mut cache = lru::LruCache::with_hasher(capacity, RandomState::new());
+        b.iter(|| {
+            for (i, key) in keys.iter().enumerate() {
+                black_box(cache.push(key.clone(), i));
+            }
+        });
+    });
+
+    group.finish();
+
+    let mut group = c.benchmark_group("Bounded cache: 200-byte key hits");
+    group.throughput(Throughput::Elements(CAPACITY as u64));
+    let resident = &keys[..CAPACITY];
+
+    group.bench_function("MemoCache<String, usize>", |b| {
+        let mut cache = MemoCache::with_capacity(CAPACITY);
+        for (i, key) in resident.iter().enumerate() {
+            cache.put(key.clone(), i);
+        }
+        b.iter(|| {
+            for key in resident {
+                black_box(cache.get(key.as_str()));
+            }
+        });
+    });
+    #[cfg(feature = "lru")]
+    group.bench_function("lru::LruCache<String, usize>", |b| {
+        let capacity = std::num::NonZeroUsize::new(CAPACITY).unwrap();
+        let mut cache = lru::LruCache::with_hasher(capacity, RandomState::new());
+        for (i, key) in resident.iter().enumerate() {
+            cache.put(key.clone(), i);
+        }
+        b.iter(|| {
+            for key in resident {
+                black_box(cache.get(key.as_str()));
+            }
+        });
+    });
+
+    group.finish();
+}
+
+fn bench_dedup(c: &mut Criterion) {
+    // 10k large strings, each repeated about four times.
+    let items: Vec<String> = (0..40_000)
+        .map(|i| format!("{:0>1000}", i % 10_000))
+        .collect();
+
+    let mut group = c.benchmark_group("Dedup: 1000-byte strings");
+    group.throughput(Throughput::Elements(items.len() as u64));
+
+    group.bench_function("dedup_memoized", |b| {
+        b.iter_batched(|| items.clone(), dedup_memoized, BatchSize::LargeInput);
+    });
+    group.bench_function("HashSet<String>", |b| {
+        b.iter_batched(
+            || items.clone(),
+            |items| {
+                items
+                    .into_iter()
+                    .collect::<HashSet<_>>()
+                    .into_iter()
+                    .collect::<Vec<_>>()
+            },
+            BatchSize::LargeInput,
+        );
+    });
+
+    group.finish();
+}
+
+fn bench_repeated_probes(c: &mut Criterion) {
+    const PROBES: usize = 1000;
+
+    let key = "k".repeat(1 << 16);
+    let memo = HashMemo::new(key.clone());
+
+    let mut group = c.benchmark_group("Repeated probes: one 64 KiB key");
+    group.throughput(Throughput::Elements(PROBES as u64));
+
+    group.bench_function("HashSet<String>", |b| {
+        let mut set = HashSet::new();
+        set.insert(key.clone());
+        b.iter(|| {
+            for _ in 0..PROBES {
+                black_box(set.contains(black_box(&key)));
+            }
+        });
+    });
+    group.bench_function("HashSet<HashMemo<String>>", |b| {
+        let mut set = HashSet::new();
+        set.insert(memo.clone());
+        b.iter(|| {
+            for _ in 0..PROBES {
+                black_box(set.contains(black_box(&memo)));
+            }
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench,
+    bench_inner_hashers,
+    bench_byte_eq,
+    bench_arc_eq,
+    bench_concurrent_first_hash,
+    bench_shared_hashing,
+    bench_contended,
+    bench_lookups,
+    bench_small_key_lookups,
+    bench_interning,
+    bench_bounded_cache,
+    bench_dedup,
+    bench_repeated_probes
+);
 criterion_main!(benches);
@@ -0,0 +1,24 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use hashmemo::HashMemo;
+use std::hash::{BuildHasher, RandomState};
+use std::hint::black_box;
+
+fn bench_vector_hash(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Hashing a 100k-element im::Vector");
+    let state = RandomState::new();
+    let vector: im::Vector<u64> = (0..100_000).collect();
+
+    group.bench_function("im::Vector<u64>", |b| {
+        b.iter(|| state.hash_one(black_box(&vector)));
+    });
+
+    let memo = HashMemo::new(vector.clone());
+    group.bench_function("HashMemo<im::Vector<u64>>", |b| {
+        b.iter(|| state.hash_one(black_box(&memo)));
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_vector_hash);
+criterion_main!(benches);
@@ -0,0 +1,103 @@
+//! Measures the heap cost of memoizing a million `String` keys.
+//!
+//! Not a timing bench: it counts the bytes live on the heap through a
+//! counting global allocator and prints the footprint of a `Vec<String>`
+//! against a `Vec<HashMemo<String>>` holding the same strings, then of the
+//! same number of entries drawn from a few distinct keys, memoized against
+//! interned with `internment::Intern` or deduplicated through `Arc<str>`.
+
+use hashmemo::HashMemo;
+use internment::Intern;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashSet;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+const ENTRIES: usize = 1_000_000;
+const DISTINCT: usize = 10_000;
+
+struct Counting;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Returns the bytes `build` leaves live on the heap.
+fn footprint<T>(build: impl FnOnce() -> T) -> usize {
+    let before = LIVE.load(Ordering::Relaxed);
+    let value = black_box(build());
+    let used = LIVE.load(Ordering::Relaxed) - before;
+    drop(value);
+    used
+}
+
+fn main() {
+    let key = |i: usize| format!("key-{i:08}");
+
+    let strings = footprint(|| (0..ENTRIES).map(key).collect::<Vec<String>>());
+    let memos = footprint(|| {
+        (0..ENTRIES)
+            .map(|i| HashMemo::new(key(i)))
+            .collect::<Vec<HashMemo<String>>>()
+    });
+
+    println!("{ENTRIES} entries of {} bytes each", key(0).len());
+    println!("Vec<String>:           {strings:>12} bytes");
+    println!("Vec<HashMemo<String>>: {memos:>12} bytes");
+    println!(
+        "overhead:              {:>12} bytes ({:.2} per entry)",
+        memos - strings,
+        (memos - strings) as f64 / ENTRIES as f64
+    );
+
+    // Interners keep their tables, so the footprints below include them.
+    let token = |i: usize| format!("token-{:08}", i % DISTINCT);
+
+    let memoized = footprint(|| {
+        (0..ENTRIES)
+            .map(|i| HashMemo::new(token(i)))
+            .collect::<Vec<HashMemo<String>>>()
+    });
+    let interned = footprint(|| {
+        (0..ENTRIES)
+            .map(|i| Intern::new(token(i)))
+            .collect::<Vec<Intern<String>>>()
+    });
+    let deduped = footprint(|| {
+        let mut table: HashSet<Arc<str>> = HashSet::new();
+        let entries: Vec<Arc<str>> = (0..ENTRIES)
+            .map(|i| {
+                let token = token(i);
+                match table.get(token.as_str()) {
+                    Some(shared) => Arc::clone(shared),
+                    None => {
+                        let shared: Arc<str> = token.into();
+                        table.insert(Arc::clone(&shared));
+                        shared
+                    }
+                }
+            })
+            .collect();
+        (table, entries)
+    });
+
+    println!();
+    println!("{ENTRIES} entries of {DISTINCT} distinct keys");
+    println!("Vec<HashMemo<String>>: {memoized:>12} bytes");
+    println!("Vec<Intern<String>>:   {interned:>12} bytes");
+    println!("Vec<Arc<str>> + table: {deduped:>12} bytes");
+}
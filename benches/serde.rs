@@ -0,0 +1,156 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hashmemo::{HashMemo, HashMemoTagged, HashMemoVerified};
+use std::collections::HashMap;
+use std::hint::black_box;
+
+const KEYS: usize = 10_000;
+const KEY_LEN: usize = 1024;
+
+/// How the keys are written and read back.
+#[derive(Clone, Copy)]
+enum Mode {
+    /// `Vec<String>`, the baseline without memos.
+    Plain,
+    /// `Vec<HashMemo<String>>`, serialized as the strings alone.
+    Transparent,
+    /// `HashMemoTagged`, trusting the persisted hashes.
+    Trust,
+    /// `HashMemoVerified`, recomputing and checking the persisted hashes.
+    Verify,
+}
+
+impl Mode {
+    const ALL: [Mode; 4] = [Mode::Plain, Mode::Transparent, Mode::Trust, Mode::Verify];
+
+    fn name(self) -> &'static str {
+        match self {
+            Mode::Plain => "plain Vec<String>",
+            Mode::Transparent => "transparent",
+            Mode::Trust => "tagged (trust)",
+            Mode::Verify => "tagged (verify)",
+        }
+    }
+}
+
+/// Distinct 1 KB keys, each a padded index repeated to length.
+fn keys() -> Vec<String> {
+    (0..KEYS)
+        .map(|i| format!("{i:0>8}").repeat(KEY_LEN / 8))
+        .collect()
+}
+
+/// Warm memos of `keys`, as a long-running process would hold them.
+fn memos(keys: &[String]) -> Vec<HashMemo<String>> {
+    keys.iter()
+        .map(|key| {
+            let memo = HashMemo::new(key.clone());
+            memo.precompute();
+            memo
+        })
+        .collect()
+}
+
+fn serialize(mode: Mode, keys: &[String], memos: &[HashMemo<String>]) -> Vec<u8> {
+    match mode {
+        Mode::Plain => serde_json::to_vec(keys),
+        Mode::Transparent => serde_json::to_vec(memos),
+        // Both tagged modes write the same shape.
+        Mode::Trust | Mode::Verify => {
+            let tagged: Vec<_> = memos.iter().cloned().map(HashMemoTagged).collect();
+            serde_json::to_vec(&tagged)
+        }
+    }
+    .unwrap()
+}
+
+fn deserialize(mode: Mode, json: &[u8]) -> Vec<HashMemo<String>> {
+    match mode {
+        Mode::Plain => serde_json::from_slice::<Vec<String>>(json)
+            .unwrap()
+            .into_iter()
+            .map(HashMemo::new)
+            .collect(),
+        Mode::Transparent => serde_json::from_slice(json).unwrap(),
+        Mode::Trust => serde_json::from_slice::<Vec<HashMemoTagged<String>>>(json)
+            .unwrap()
+            .into_iter()
+            .map(|HashMemoTagged(memo)| memo)
+            .collect(),
+        Mode::Verify => serde_json::from_slice::<Vec<HashMemoVerified<String>>>(json)
+            .unwrap()
+            .into_iter()
+            .map(|HashMemoVerified(memo)| memo)
+            .collect(),
+    }
+}
+
+/// Builds the map a reloading process would serve lookups from.
+fn build_map(memos: Vec<HashMemo<String>>) -> HashMap<HashMemo<String>, usize> {
+    memos.into_iter().zip(0..).collect()
+}
+
+/// Checks that the modes read each other's output into equal memos with
+/// equal hashes, before any of them is timed.
+fn check_interop(keys: &[String], memos: &[HashMemo<String>]) {
+    let plain = serialize(Mode::Plain, keys, memos);
+    let transparent = serialize(Mode::Transparent, keys, memos);
+    let tagged = serialize(Mode::Trust, keys, memos);
+    assert_eq!(
+        plain, transparent,
+        "transparent memos are invisible on the wire"
+    );
+
+    for (mode, json) in [
+        (Mode::Plain, &transparent),
+        (Mode::Transparent, &plain),
+        (Mode::Trust, &tagged),
+        (Mode::Verify, &tagged),
+    ] {
+        let reloaded = deserialize(mode, json);
+        assert_eq!(reloaded, memos, "{}", mode.name());
+        for (memo, original) in reloaded.iter().zip(memos) {
+            assert!(memo.hash_matches(original.hash_one_cached()));
+        }
+    }
+
+    let map = build_map(deserialize(Mode::Trust, &tagged));
+    assert!(keys
+        .iter()
+        .enumerate()
+        .all(|(i, key)| map[&HashMemo::new(key.clone())] == i));
+}
+
+fn bench_round_trip(c: &mut Criterion) {
+    let keys = keys();
+    let memos = memos(&keys);
+    check_interop(&keys, &memos);
+
+    let mut group = c.benchmark_group("Serde round trip of 10k 1 KB keys");
+    group.throughput(Throughput::Bytes((KEYS * KEY_LEN) as u64));
+    group.sample_size(20);
+
+    for mode in Mode::ALL {
+        let json = serialize(mode, &keys, &memos);
+
+        group.bench_function(BenchmarkId::new("serialize", mode.name()), |b| {
+            b.iter(|| serialize(mode, black_box(&keys), black_box(&memos)));
+        });
+
+        group.bench_function(BenchmarkId::new("deserialize", mode.name()), |b| {
+            b.iter(|| deserialize(mode, black_box(&json)));
+        });
+
+        // The warm-start cost: from bytes to a map ready for lookups.
+        group.bench_function(
+            BenchmarkId::new("deserialize + HashMap", mode.name()),
+            |b| {
+                b.iter(|| build_map(deserialize(mode, black_box(&json))));
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_round_trip);
+criterion_main!(benches);
@@ -0,0 +1,58 @@
+//! Probes a `HashSet` with the same large key over and over, and counts
+//! how often the key is hashed with and without memoization.
+//!
+//! ```text
+//! cargo run --release --example hashset_probes
+//! ```
+
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use hashmemo::HashMemo;
+
+const PROBES: usize = 100_000;
+
+static HASHES: AtomicUsize = AtomicUsize::new(0);
+
+/// A string that counts how often it is hashed.
+#[derive(Clone, PartialEq, Eq)]
+struct Counted(String);
+
+impl Hash for Counted {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        HASHES.fetch_add(1, Ordering::Relaxed);
+        self.0.hash(state);
+    }
+}
+
+fn run<K: Hash + Eq + Clone>(name: &str, make_key: impl FnOnce() -> K) {
+    let hashes = HASHES.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let key = make_key();
+
+    let mut set = HashSet::new();
+    for _ in 0..PROBES {
+        if !set.contains(&key) {
+            set.insert(key.clone());
+        }
+    }
+
+    println!(
+        "{name:<24} {PROBES} probes: {:>6} hashes in {:?}",
+        HASHES.load(Ordering::Relaxed) - hashes,
+        start.elapsed()
+    );
+}
+
+fn main() {
+    let key = Counted("k".repeat(1 << 16));
+    run("HashSet<String>", || key.clone());
+    run("HashSet<HashMemo<String>>", || {
+        // Clones carry the cache over, so prime the key before cloning it.
+        let memo = HashMemo::new(key.clone());
+        memo.precompute();
+        memo
+    });
+}
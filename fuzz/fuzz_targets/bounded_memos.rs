@@ -0,0 +1,46 @@
+//! Fuzzes memos generated with `HashMemo::arbitrary_bounded`.
+//!
+//! Each input becomes a batch of memos over byte strings of at most
+//! `MAX_LEN` bytes, primed or cold at random, so the fuzzer's budget goes
+//! into exploring cache states and collisions rather than allocating large
+//! values. Every memo must agree with a fresh memo of its value, and the
+//! batch must behave in a set exactly like the bare values do.
+
+#![no_main]
+
+use std::collections::HashSet;
+use std::hash::{BuildHasher, RandomState};
+
+use hashmemo::HashMemo;
+use libfuzzer_sys::arbitrary::{Result, Unstructured};
+use libfuzzer_sys::fuzz_target;
+
+const MAX_LEN: usize = 64;
+
+fn memos(u: &mut Unstructured<'_>) -> Result<Vec<HashMemo<Vec<u8>>>> {
+    let mut memos = Vec::new();
+    while !u.is_empty() {
+        memos.push(HashMemo::arbitrary_bounded(u, MAX_LEN)?);
+    }
+    Ok(memos)
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(memos) = memos(&mut Unstructured::new(data)) else {
+        return;
+    };
+
+    let state = RandomState::new();
+    let mut set = HashSet::new();
+    let mut model = HashSet::new();
+    for memo in &memos {
+        assert!(memo.as_ref().len() <= MAX_LEN);
+
+        let fresh = HashMemo::new(memo.as_ref().clone());
+        assert!(*memo == fresh);
+        assert_eq!(state.hash_one(memo), state.hash_one(&fresh));
+
+        assert_eq!(set.insert(memo), model.insert(memo.as_ref()));
+    }
+    assert_eq!(set.len(), model.len());
+});
@@ -0,0 +1,197 @@
+//! Differential fuzzing of memo keyed maps against plain maps.
+//!
+//! The input is decoded into a sequence of operations, each applied both to
+//! a map keyed by memos and to a model map keyed by the bare values. Any
+//! observable difference, or a memo whose `Hash`/`Eq` disagree with its
+//! clone or a fresh memo, is a bug.
+//!
+//! A second pair of maps keys `u64` memos under [`PassthroughState`], where
+//! a value's memoized hash is the value itself. That pins `0` and `1` to the
+//! same cached hash through the zero remap, so lookups must fall back to
+//! `Eq` to tell them apart.
+
+#![no_main]
+
+use std::collections::HashMap;
+use std::hash::{BuildHasher, RandomState};
+
+use hashmemo::{HashMemo, PassthroughState, Probe};
+use libfuzzer_sys::fuzz_target;
+
+/// Keys of the pinned maps, chosen to include the remapped `0` and the `1`
+/// it collides with.
+const PINNED_KEYS: [u64; 4] = [0, 1, 2, u64::MAX];
+
+#[derive(Debug)]
+enum Op {
+    Insert(Vec<u8>),
+    InsertClone(usize),
+    Lookup(Vec<u8>),
+    Clone(usize),
+    MutateAndReinsert(usize, u8),
+    Remove(Vec<u8>),
+    PinnedInsert(u64),
+    PinnedRemove(u64),
+}
+
+/// Decodes operations from the fuzzer input until it runs out.
+///
+/// The first byte of each operation selects it; keys are a length byte
+/// followed by up to 15 bytes, and indices pick among previously seen keys.
+struct Decoder<'a>(&'a [u8]);
+
+impl Decoder<'_> {
+    fn byte(&mut self) -> Option<u8> {
+        let (&byte, rest) = self.0.split_first()?;
+        self.0 = rest;
+        Some(byte)
+    }
+
+    fn key(&mut self) -> Option<Vec<u8>> {
+        let len = usize::from(self.byte()? % 16).min(self.0.len());
+        let (key, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(key.to_vec())
+    }
+
+    fn pinned_key(&mut self) -> Option<u64> {
+        Some(PINNED_KEYS[usize::from(self.byte()?) % PINNED_KEYS.len()])
+    }
+}
+
+impl Iterator for Decoder<'_> {
+    type Item = Op;
+
+    fn next(&mut self) -> Option<Op> {
+        Some(match self.byte()? % 8 {
+            0 => Op::Insert(self.key()?),
+            1 => Op::InsertClone(self.byte()?.into()),
+            2 => Op::Lookup(self.key()?),
+            3 => Op::Clone(self.byte()?.into()),
+            4 => Op::MutateAndReinsert(self.byte()?.into(), self.byte()?),
+            5 => Op::Remove(self.key()?),
+            6 => Op::PinnedInsert(self.pinned_key()?),
+            _ => Op::PinnedRemove(self.pinned_key()?),
+        })
+    }
+}
+
+#[derive(Default)]
+struct Harness {
+    memos: HashMap<HashMemo<Vec<u8>>, u32>,
+    model: HashMap<Vec<u8>, u32>,
+    pinned: HashMap<HashMemo<u64, PassthroughState>, u32, PassthroughState>,
+    pinned_model: HashMap<u64, u32>,
+    /// Memos seen so far, some cold and some warm, for clone operations.
+    seen: Vec<HashMemo<Vec<u8>>>,
+    state: RandomState,
+    next_value: u32,
+}
+
+impl Harness {
+    fn value(&mut self) -> u32 {
+        self.next_value = self.next_value.wrapping_add(1);
+        self.next_value
+    }
+
+    fn pick(&self, index: usize) -> Option<&HashMemo<Vec<u8>>> {
+        self.seen.get(index.checked_rem(self.seen.len())?)
+    }
+
+    fn insert(&mut self, memo: HashMemo<Vec<u8>>) {
+        let value = self.value();
+        let key = memo.as_ref().clone();
+        assert_eq!(
+            self.memos.insert(memo, value),
+            self.model.insert(key, value)
+        );
+    }
+
+    fn apply(&mut self, op: Op) {
+        match op {
+            Op::Insert(key) => {
+                let memo = HashMemo::new(key);
+                self.seen.push(memo.clone());
+                self.insert(memo);
+            }
+            Op::InsertClone(index) => {
+                if let Some(memo) = self.pick(index).cloned() {
+                    self.insert(memo);
+                }
+            }
+            Op::Lookup(key) => {
+                let expected = self.model.get(&key);
+                assert_eq!(self.memos.get(&HashMemo::new(key.clone())), expected);
+                assert_eq!(self.memos.get(Probe::new(&key[..]).as_lookup()), expected);
+            }
+            Op::Clone(index) => {
+                if let Some(memo) = self.pick(index) {
+                    let clone = memo.clone();
+                    assert!(clone == *memo);
+                    assert_eq!(self.state.hash_one(&clone), self.state.hash_one(memo));
+                    self.seen.push(clone);
+                }
+            }
+            Op::MutateAndReinsert(index, byte) => {
+                let Some(memo) = self.pick(index).cloned() else {
+                    return;
+                };
+                let removed = self.memos.remove(&memo);
+                assert_eq!(removed, self.model.remove(memo.as_ref()));
+                let mut key = memo.into_inner();
+                key.push(byte);
+                let memo = HashMemo::new(key);
+                self.seen.push(memo.clone());
+                self.insert(memo);
+            }
+            Op::Remove(key) => {
+                assert_eq!(
+                    self.memos.remove(&HashMemo::new(key.clone())),
+                    self.model.remove(&key)
+                );
+            }
+            Op::PinnedInsert(key) => {
+                let value = self.value();
+                assert_eq!(
+                    self.pinned
+                        .insert(HashMemo::with_hasher(key, PassthroughState), value),
+                    self.pinned_model.insert(key, value),
+                );
+            }
+            Op::PinnedRemove(key) => {
+                assert_eq!(
+                    self.pinned
+                        .remove(&HashMemo::with_hasher(key, PassthroughState)),
+                    self.pinned_model.remove(&key),
+                );
+            }
+        }
+    }
+
+    fn check_invariants(&self) {
+        assert_eq!(self.memos.len(), self.model.len());
+        for (key, value) in &self.model {
+            let fresh = HashMemo::new(key.clone());
+            assert_eq!(self.memos.get(&fresh), Some(value));
+            assert_eq!(
+                self.memos.get(Probe::new(&key[..]).as_lookup()),
+                Some(value)
+            );
+        }
+
+        assert_eq!(self.pinned.len(), self.pinned_model.len());
+        for (&key, value) in &self.pinned_model {
+            let memo = HashMemo::with_hasher(key, PassthroughState);
+            assert_eq!(PassthroughState.hash_one(&memo), key.max(1));
+            assert_eq!(self.pinned.get(&memo), Some(value));
+        }
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut harness = Harness::default();
+    for op in Decoder(data) {
+        harness.apply(op);
+        harness.check_invariants();
+    }
+});
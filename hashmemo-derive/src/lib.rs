@@ -0,0 +1,198 @@
+//! Derive macro for structs embedding a [`hashmemo`] hash cache.
+//!
+//! Use it through the `derive` feature of `hashmemo`, which re-exports
+//! [`HashMemoize`] next to the `HashCache` field type it relies on.
+//!
+//! [`hashmemo`]: https://docs.rs/hashmemo
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Generics, Ident, Result};
+
+/// Derives a memoizing `Hash`, together with `PartialEq`, `Eq`, `Debug` and
+/// invalidating setters, for a struct that embeds a `hashmemo::HashCache`.
+///
+/// See `hashmemo::HashMemoize` for the full documentation.
+#[proc_macro_derive(HashMemoize, attributes(hashmemo))]
+pub fn derive_hash_memoize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// A named field and what its `#[hashmemo(...)]` attributes ask for.
+struct Field<'a> {
+    field: &'a syn::Field,
+    ident: &'a Ident,
+    cache: bool,
+    skip: bool,
+    no_setter: bool,
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2> {
+    let named = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(named) => named,
+            _ => return Err(not_a_named_struct(input.ident.span())),
+        },
+        _ => return Err(not_a_named_struct(input.ident.span())),
+    };
+
+    let mut fields = Vec::new();
+    for field in &named.named {
+        fields.push(parse_field(field)?);
+    }
+
+    let mut caches = fields.iter().filter(|f| f.cache);
+    let cache = caches.next().ok_or_else(|| {
+        Error::new(
+            input.ident.span(),
+            "HashMemoize needs a `#[hashmemo(cache)]` field of type `hashmemo::HashCache`",
+        )
+    })?;
+    if let Some(duplicate) = caches.next() {
+        return Err(Error::new(
+            duplicate.ident.span(),
+            "duplicate `#[hashmemo(cache)]` field",
+        ));
+    }
+    let cache = cache.ident;
+    let data: Vec<_> = fields.iter().filter(|f| !f.cache).collect();
+
+    let name = &input.ident;
+    let name_str = name.to_string();
+
+    let hashed = data.iter().filter(|f| !f.skip).map(|f| f.ident);
+    let compared = data.iter().map(|f| f.ident);
+    let debugged = data.iter().map(|f| f.ident);
+    let debugged_str = data.iter().map(|f| f.ident.to_string());
+    let eq_asserted = data.iter().map(|f| &f.field.ty);
+
+    let setters = data.iter().filter(|f| !f.no_setter).map(|f| {
+        let (ident, ty, vis) = (f.ident, &f.field.ty, &f.field.vis);
+        let set = format_ident!("set_{}", ident);
+        let get_mut = format_ident!("{}_mut", ident);
+        let invalidate = (!f.skip).then(|| quote!(self.#cache.invalidate();));
+        let doc_set = format!("Sets `{ident}`, invalidating the cached hash if it is hashed.");
+        let doc_mut =
+            format!("Borrows `{ident}` mutably, invalidating the cached hash if it is hashed.");
+        quote! {
+            #[doc = #doc_set]
+            #[inline]
+            #vis fn #set(&mut self, value: #ty) {
+                self.#ident = value;
+                #invalidate
+            }
+
+            #[doc = #doc_mut]
+            #[inline]
+            #vis fn #get_mut(&mut self) -> &mut #ty {
+                #invalidate
+                &mut self.#ident
+            }
+        }
+    });
+
+    let hash_generics = bounded(&input.generics, quote!(::core::hash::Hash));
+    let (hash_impl, _, hash_where) = hash_generics.split_for_impl();
+    let eq_generics = bounded(&input.generics, quote!(::core::cmp::PartialEq));
+    let (eq_impl, _, eq_where) = eq_generics.split_for_impl();
+    let total_eq_generics = bounded(&input.generics, quote!(::core::cmp::Eq));
+    let (total_eq_impl, _, total_eq_where) = total_eq_generics.split_for_impl();
+    let debug_generics = bounded(&input.generics, quote!(::core::fmt::Debug));
+    let (debug_impl, _, debug_where) = debug_generics.split_for_impl();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #hash_impl ::core::hash::Hash for #name #ty_generics #hash_where {
+            fn hash<__H: ::core::hash::Hasher>(&self, state: &mut __H) {
+                let hash = self.#cache.get_or_insert_with(|| {
+                    let mut hasher = ::std::hash::DefaultHasher::new();
+                    #(::core::hash::Hash::hash(&self.#hashed, &mut hasher);)*
+                    ::core::hash::Hasher::finish(&hasher)
+                });
+                state.write_u64(hash);
+            }
+        }
+
+        impl #eq_impl ::core::cmp::PartialEq for #name #ty_generics #eq_where {
+            fn eq(&self, other: &Self) -> bool {
+                true #(&& self.#compared == other.#compared)*
+            }
+        }
+
+        impl #total_eq_impl ::core::cmp::Eq for #name #ty_generics #total_eq_where {}
+
+        impl #total_eq_impl #name #ty_generics #total_eq_where {
+            #[doc(hidden)]
+            #[allow(dead_code)]
+            fn __hashmemo_assert_fields_are_eq() {
+                fn assert_eq<T: ?Sized + ::core::cmp::Eq>() {}
+                #(assert_eq::<#eq_asserted>();)*
+            }
+        }
+
+        impl #debug_impl ::core::fmt::Debug for #name #ty_generics #debug_where {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.debug_struct(#name_str)
+                    #(.field(#debugged_str, &self.#debugged))*
+                    .finish()
+            }
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#setters)*
+        }
+    })
+}
+
+fn parse_field(field: &syn::Field) -> Result<Field<'_>> {
+    let mut parsed = Field {
+        field,
+        ident: field.ident.as_ref().expect("named field"),
+        cache: false,
+        skip: false,
+        no_setter: false,
+    };
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("hashmemo")) {
+        attr.parse_nested_meta(|meta| {
+            let flag = if meta.path.is_ident("cache") {
+                &mut parsed.cache
+            } else if meta.path.is_ident("skip") {
+                &mut parsed.skip
+            } else if meta.path.is_ident("no_setter") {
+                &mut parsed.no_setter
+            } else {
+                return Err(meta
+                    .error("unknown hashmemo attribute, expected `cache`, `skip` or `no_setter`"));
+            };
+            *flag = true;
+            Ok(())
+        })?;
+    }
+    if parsed.cache && (parsed.skip || parsed.no_setter) {
+        return Err(Error::new(
+            parsed.ident.span(),
+            "the `#[hashmemo(cache)]` field cannot take other hashmemo attributes",
+        ));
+    }
+    Ok(parsed)
+}
+
+/// Adds `bound` to every type parameter, as std's derives do.
+fn bounded(generics: &Generics, bound: TokenStream2) -> Generics {
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(syn::parse_quote!(#bound));
+    }
+    generics
+}
+
+fn not_a_named_struct(span: Span) -> Error {
+    Error::new(
+        span,
+        "HashMemoize can only be derived for structs with named fields",
+    )
+}
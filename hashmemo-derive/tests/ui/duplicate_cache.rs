@@ -0,0 +1,12 @@
+use hashmemo::{HashCache, HashMemoize};
+
+#[derive(HashMemoize)]
+struct Key {
+    name: String,
+    #[hashmemo(cache)]
+    first: HashCache,
+    #[hashmemo(cache)]
+    second: HashCache,
+}
+
+fn main() {}
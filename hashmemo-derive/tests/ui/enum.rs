@@ -0,0 +1,9 @@
+use hashmemo::HashMemoize;
+
+#[derive(HashMemoize)]
+enum Key {
+    A,
+    B,
+}
+
+fn main() {}
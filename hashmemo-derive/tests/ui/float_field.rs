@@ -0,0 +1,11 @@
+use hashmemo::{HashCache, HashMemoize};
+
+#[derive(HashMemoize)]
+struct Key {
+    #[hashmemo(skip)]
+    weight: f64,
+    #[hashmemo(cache)]
+    cache: HashCache,
+}
+
+fn main() {}
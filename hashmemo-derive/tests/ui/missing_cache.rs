@@ -0,0 +1,8 @@
+use hashmemo::HashMemoize;
+
+#[derive(HashMemoize)]
+struct Key {
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use hashmemo::{HashCache, HashMemoize};
+
+#[derive(HashMemoize)]
+struct Key {
+    name: String,
+    #[hashmemo(cache, skip)]
+    cache: HashCache,
+}
+
+fn main() {}
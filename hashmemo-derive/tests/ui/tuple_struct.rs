@@ -0,0 +1,6 @@
+use hashmemo::{HashCache, HashMemoize};
+
+#[derive(HashMemoize)]
+struct Key(String, HashCache);
+
+fn main() {}
@@ -0,0 +1,11 @@
+use hashmemo::{HashCache, HashMemoize};
+
+#[derive(HashMemoize)]
+struct Key {
+    #[hashmemo(ignore)]
+    name: String,
+    #[hashmemo(cache)]
+    cache: HashCache,
+}
+
+fn main() {}
@@ -0,0 +1,169 @@
+//! [`arbitrary`](::arbitrary) support for fuzzing inputs that contain memos.
+//!
+//! A generated memo first consumes one byte deciding whether its cache is
+//! primed, then generates the value. Fuzzers therefore explore both cold and
+//! warm memos, and a primed memo always caches the hash its hasher computes,
+//! so both kinds behave identically as map keys.
+
+use std::hash::{BuildHasher, Hash};
+
+use ::arbitrary::size_hint;
+use ::arbitrary::{Arbitrary, MaxRecursionReached, Result, Unstructured};
+
+use crate::HashMemo;
+
+impl<'a, T, H> Arbitrary<'a> for HashMemo<T, H>
+where
+    T: Eq + Hash + Arbitrary<'a>,
+    H: BuildHasher + Default,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let primed = bool::arbitrary(u)?;
+        let memo = Self::with_hasher(T::arbitrary(u)?, H::default());
+        if primed {
+            memo.precompute();
+        }
+        Ok(memo)
+    }
+
+    fn arbitrary_take_rest(mut u: Unstructured<'a>) -> Result<Self> {
+        let primed = bool::arbitrary(&mut u)?;
+        let memo = Self::with_hasher(T::arbitrary_take_rest(u)?, H::default());
+        if primed {
+            memo.precompute();
+        }
+        Ok(memo)
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        size_hint::and(bool::size_hint(depth), T::size_hint(depth))
+    }
+
+    fn try_size_hint(depth: usize) -> Result<(usize, Option<usize>), MaxRecursionReached> {
+        Ok(size_hint::and(
+            bool::try_size_hint(depth)?,
+            T::try_size_hint(depth)?,
+        ))
+    }
+}
+
+impl<T, H> HashMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher + Default,
+{
+    /// Like [`Arbitrary::arbitrary`], but generates the value from at most
+    /// `max_len` bytes of input.
+    ///
+    /// Values built from arbitrary input grow with the input, and the large
+    /// values this crate is meant for can make a fuzzer spend its budget on
+    /// allocation. After the priming byte this consumes a length in
+    /// `0..=max_len`, then generates the value with
+    /// [`Arbitrary::arbitrary_take_rest`] from that many bytes, which bounds
+    /// the value for any `T` whose size follows its input.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use arbitrary::Unstructured;
+    /// use hashmemo::HashMemo;
+    ///
+    /// let data = [7; 4096];
+    /// let mut u = Unstructured::new(&data);
+    /// let memo: HashMemo<Vec<u8>> = HashMemo::arbitrary_bounded(&mut u, 16).unwrap();
+    /// assert!(memo.as_ref().len() <= 16);
+    /// ```
+    pub fn arbitrary_bounded<'a>(u: &mut Unstructured<'a>, max_len: usize) -> Result<Self>
+    where
+        T: Arbitrary<'a>,
+    {
+        let primed = bool::arbitrary(u)?;
+        let len = u.int_in_range(0..=max_len)?;
+        let bytes = u.bytes(len.min(u.len()))?;
+        let memo = Self::with_hasher(
+            T::arbitrary_take_rest(Unstructured::new(bytes))?,
+            H::default(),
+        );
+        if primed {
+            memo.precompute();
+        }
+        Ok(memo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn generates_cold_and_primed_memos() {
+        let cold: HashMemo<String> = Unstructured::new(b"\x00\x05hello").arbitrary().unwrap();
+        let primed: HashMemo<String> = Unstructured::new(b"\x01\x05hello").arbitrary().unwrap();
+        assert_eq!(cold.hash.load(Ordering::Relaxed), 0);
+        assert_ne!(primed.hash.load(Ordering::Relaxed), 0);
+        assert!(cold == primed);
+
+        let mut map = HashMap::new();
+        map.insert(primed, 1);
+        assert_eq!(map.get(&cold), Some(&1));
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn take_rest_wraps_the_inner_value() {
+        let memo = HashMemo::<Vec<u8>>::arbitrary_take_rest(Unstructured::new(b"\x01abc")).unwrap();
+        let value = Vec::<u8>::arbitrary_take_rest(Unstructured::new(b"abc")).unwrap();
+        assert_eq!(memo.value, value);
+        assert_ne!(memo.hash.load(Ordering::Relaxed), 0);
+        assert_eq!(memo.memoized_hash(), HashMemo::new(value).memoized_hash());
+    }
+
+    #[test]
+    fn bounded_generation_caps_the_value() {
+        let data = [0xff; 1 << 16];
+        let mut u = Unstructured::new(&data);
+        let memos: Vec<HashMemo<Vec<u8>>> = (0..32)
+            .map(|_| HashMemo::arbitrary_bounded(&mut u, 64).unwrap())
+            .collect();
+        assert!(memos.iter().all(|memo| memo.value.len() <= 64));
+        assert!(u.len() >= data.len() - 32 * (1 + 1 + 64));
+
+        let unbounded: HashMemo<Vec<u8>> = Unstructured::new(&data).arbitrary().unwrap();
+        assert!(unbounded.value.len() > 64);
+    }
+
+    #[test]
+    fn size_hint_adds_the_priming_byte() {
+        assert_eq!(HashMemo::<u32>::size_hint(0), (5, Some(5)));
+        assert_eq!(HashMemo::<u32>::try_size_hint(0).unwrap(), (5, Some(5)));
+    }
+
+    #[test]
+    fn derived_structs_can_hold_memos() {
+        #[derive(Debug)]
+        struct Request {
+            key: HashMemo<String>,
+            id: u16,
+        }
+
+        impl<'a> Arbitrary<'a> for Request {
+            fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+                Ok(Self {
+                    key: u.arbitrary()?,
+                    id: u.arbitrary()?,
+                })
+            }
+        }
+
+        let data = [1, 3, b'k', b'e', b'y', 7, 0, 2];
+        let request = Request::arbitrary(&mut Unstructured::new(&data)).unwrap();
+
+        let mut u = Unstructured::new(&data[1..]);
+        assert_eq!(request.key.value, String::arbitrary(&mut u).unwrap());
+        assert_eq!(request.id, u16::arbitrary(&mut u).unwrap());
+    }
+}
@@ -0,0 +1,464 @@
+//! Shared memos with pointer-identity fast paths.
+//!
+//! [`ArcMemo`] shares a whole memo, cache included. `HashMemo<Arc<str>>` and
+//! `HashMemo<Arc<[u8]>>` instead share only the value, which suits values
+//! that are already deduplicated into `Arc`s elsewhere. Equality of `Arc`s
+//! of `Eq` types checks `Arc::ptr_eq` before comparing contents, so memos
+//! over clones of one allocation compare without reading the bytes, and
+//! they hash like the `str` or `[u8]` they point to, so maps keyed by them
+//! can be probed through [`Probe`](crate::Probe).
+//!
+//! ```rust
+//! use hashmemo::{HashMemo, Probe};
+//! use std::collections::HashSet;
+//! use std::sync::Arc;
+//!
+//! let mut set: HashSet<HashMemo<Arc<str>>> = HashSet::new();
+//! set.insert(HashMemo::from("interned"));
+//! assert!(set.contains(Probe::new("interned").as_lookup()));
+//! ```
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::{HashMemo, MemoLookup};
+
+/// A [`HashMemo`] behind an `Arc`, cheap to clone and shared along with its
+/// cached hash.
+///
+/// Clones point at the same allocation, so comparing two of them returns
+/// immediately via `Arc::ptr_eq` instead of walking `T`. Memos in distinct
+/// allocations fall back to comparing their values. This agrees with `Eq`,
+/// since an `Eq` value always equals itself. [`fast_eq`](Self::fast_eq)
+/// also lets differing cached hashes prove inequality.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::ArcMemo;
+/// use std::collections::BTreeMap;
+///
+/// let key = ArcMemo::new("a long shared key".to_string());
+/// let mut map = BTreeMap::new();
+/// map.insert(key.clone(), 1);
+/// assert_eq!(map.get(&key), Some(&1));
+/// ```
+#[derive(Debug)]
+pub struct ArcMemo<T, H = BuildHasherDefault<DefaultHasher>>(pub(crate) Arc<HashMemo<T, H>>)
+where
+    T: Eq + Hash,
+    H: BuildHasher;
+
+impl<T> ArcMemo<T>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new `ArcMemo` with the default hasher.
+    pub fn new(value: T) -> Self {
+        Self(Arc::new(HashMemo::new(value)))
+    }
+}
+
+impl<T, H> ArcMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Creates a new `ArcMemo` with a custom hasher.
+    pub fn with_hasher(value: T, hasher: H) -> Self {
+        Self(Arc::new(HashMemo::with_hasher(value, hasher)))
+    }
+
+    /// Returns `true` if both point at the same allocation.
+    #[inline]
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.0, &other.0)
+    }
+
+    /// Returns the shared memo.
+    #[inline]
+    pub fn memo(&self) -> &HashMemo<T, H> {
+        &self.0
+    }
+
+    /// Compares like `==`, trying the cheap answers first: clones of one
+    /// allocation are equal without further work, then two warm caches
+    /// holding different hashes prove the values unequal, and only then
+    /// are the values compared.
+    ///
+    /// The hash check is [`HashMemo::fast_eq`]'s, and holds under the same
+    /// condition: both memos must hash with the same hasher type and seed.
+    /// `==` itself never consults the hashes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::ArcMemo;
+    ///
+    /// let a = ArcMemo::new(vec![0u8; 1 << 20]);
+    /// let b = ArcMemo::new(vec![1u8; 1 << 20]);
+    /// assert!(a.fast_eq(&a.clone()), "one allocation");
+    ///
+    /// a.memo().precompute();
+    /// b.memo().precompute();
+    /// assert!(!a.fast_eq(&b), "settled by the hashes alone");
+    /// ```
+    #[inline]
+    pub fn fast_eq(&self, other: &Self) -> bool {
+        Self::ptr_eq(self, other) || self.0.fast_eq(&other.0)
+    }
+}
+
+impl<T, H> Clone for ArcMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<T, H> From<HashMemo<T, H>> for ArcMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    fn from(memo: HashMemo<T, H>) -> Self {
+        Self(Arc::new(memo))
+    }
+}
+
+impl<T, H> AsRef<T> for ArcMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    fn as_ref(&self) -> &T {
+        &self.0.value
+    }
+}
+
+impl<T, H> PartialEq for ArcMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    fn eq(&self, other: &Self) -> bool {
+        Self::ptr_eq(self, other) || self.0 == other.0
+    }
+}
+
+impl<T, H> Eq for ArcMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+}
+
+impl<T, H> PartialOrd for ArcMemo<T, H>
+where
+    T: Eq + Hash + Ord,
+    H: BuildHasher,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, H> Ord for ArcMemo<T, H>
+where
+    T: Eq + Hash + Ord,
+    H: BuildHasher,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        if Self::ptr_eq(self, other) {
+            return Ordering::Equal;
+        }
+        self.0.cmp(&other.0)
+    }
+}
+
+impl<T, H> Hash for ArcMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    fn hash<H2: Hasher>(&self, state: &mut H2) {
+        self.0.hash(state);
+    }
+}
+
+impl<'a, T, H, Q> Borrow<dyn MemoLookup<Q> + 'a> for ArcMemo<T, H>
+where
+    T: Eq + Hash + Borrow<Q> + 'a,
+    H: BuildHasher + 'a,
+    Q: ?Sized + 'a,
+{
+    #[inline]
+    fn borrow(&self) -> &(dyn MemoLookup<Q> + 'a) {
+        &*self.0
+    }
+}
+
+impl<T, H> HashMemo<Arc<T>, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Returns the shared value if this memo holds the only `Arc` to it, or
+    /// the memo back, cache intact, if the value is still shared.
+    ///
+    /// This finalizes values built up shared and then owned exclusively.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    /// use std::sync::Arc;
+    ///
+    /// let shared = Arc::new("table".to_string());
+    /// let memo = HashMemo::new(Arc::clone(&shared));
+    /// let memo = memo.try_into_inner().unwrap_err();
+    ///
+    /// drop(shared);
+    /// assert_eq!(memo.try_into_inner().unwrap(), "table");
+    /// ```
+    pub fn try_into_inner(self) -> Result<T, Self> {
+        let HashMemo {
+            value,
+            hash,
+            hasher,
+        } = self;
+        Arc::try_unwrap(value).map_err(|value| HashMemo {
+            value,
+            hash,
+            hasher,
+        })
+    }
+}
+
+impl<T, H> HashMemo<Rc<T>, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Like the `Arc` version, returns the value if this memo holds the
+    /// only `Rc` to it, or the memo back, cache intact.
+    pub fn try_into_inner(self) -> Result<T, Self> {
+        let HashMemo {
+            value,
+            hash,
+            hasher,
+        } = self;
+        Rc::try_unwrap(value).map_err(|value| HashMemo {
+            value,
+            hash,
+            hasher,
+        })
+    }
+}
+
+impl From<&str> for HashMemo<Arc<str>> {
+    fn from(value: &str) -> Self {
+        Self::new(Arc::from(value))
+    }
+}
+
+impl From<String> for HashMemo<Arc<str>> {
+    fn from(value: String) -> Self {
+        Self::new(Arc::from(value))
+    }
+}
+
+impl From<&[u8]> for HashMemo<Arc<[u8]>> {
+    fn from(value: &[u8]) -> Self {
+        Self::new(Arc::from(value))
+    }
+}
+
+impl From<Vec<u8>> for HashMemo<Arc<[u8]>> {
+    fn from(value: Vec<u8>) -> Self {
+        Self::new(Arc::from(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    thread_local! {
+        static COMPARISONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(PartialEq, Eq, Hash)]
+    struct Counted(String);
+
+    impl PartialOrd for Counted {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Counted {
+        fn cmp(&self, other: &Self) -> Ordering {
+            COMPARISONS.with(|c| c.set(c.get() + 1));
+            self.0.cmp(&other.0)
+        }
+    }
+
+    #[test]
+    fn shared_allocation_compares_without_touching_the_value() {
+        let a = ArcMemo::new(Counted("shared".to_string()));
+        let b = a.clone();
+
+        COMPARISONS.with(|c| c.set(0));
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+        assert_eq!(a.partial_cmp(&b), Some(Ordering::Equal));
+        assert_eq!(COMPARISONS.with(Cell::get), 0);
+    }
+
+    #[test]
+    fn distinct_allocations_fall_back_to_the_value() {
+        let a = ArcMemo::new(Counted("same".to_string()));
+        let b = ArcMemo::new(Counted("same".to_string()));
+        let c = ArcMemo::new(Counted("other".to_string()));
+        assert!(!ArcMemo::ptr_eq(&a, &b));
+
+        COMPARISONS.with(|c| c.set(0));
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+        assert_eq!(a.cmp(&c), Ordering::Greater);
+        assert_eq!(COMPARISONS.with(Cell::get), 2);
+        assert!(a == b && a != c);
+    }
+
+    thread_local! {
+        static EQS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Counts its `eq` calls in `EQS`.
+    struct Spy(&'static str);
+
+    impl Hash for Spy {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.0.hash(state);
+        }
+    }
+
+    impl PartialEq for Spy {
+        fn eq(&self, other: &Self) -> bool {
+            EQS.with(|c| c.set(c.get() + 1));
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for Spy {}
+
+    #[test]
+    fn shared_arc_values_compare_by_pointer_first() {
+        EQS.with(|c| c.set(0));
+        let shared = Arc::new(Spy("value"));
+        let a = HashMemo::new(Arc::clone(&shared));
+        let b = HashMemo::new(shared);
+        assert!(a == b);
+        assert_eq!(EQS.with(Cell::get), 0);
+
+        let c = HashMemo::new(Arc::new(Spy("value")));
+        assert!(a == c);
+        assert_eq!(EQS.with(Cell::get), 1);
+        assert_eq!(a.memoized_hash(), c.memoized_hash());
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn fast_eq_checks_the_pointer_then_the_hashes() {
+        EQS.with(|c| c.set(0));
+        let a = ArcMemo::new(Spy("value"));
+        let other = ArcMemo::new(Spy("other"));
+
+        // Clones of one allocation need neither hash nor comparison.
+        assert!(a.fast_eq(&a.clone()));
+        assert_eq!(EQS.with(Cell::get), 0);
+        assert_eq!(a.memo().hash.load(std::sync::atomic::Ordering::Relaxed), 0);
+
+        // Cold caches prove nothing, so the values are compared.
+        assert!(!a.fast_eq(&other));
+        assert_eq!(EQS.with(Cell::get), 1);
+
+        // Warm caches that differ settle it without reading the values.
+        a.memo().precompute();
+        other.memo().precompute();
+        assert!(!a.fast_eq(&other));
+        assert_eq!(EQS.with(Cell::get), 1);
+
+        // Matching hashes fall through to the full comparison.
+        let copy = ArcMemo::new(Spy("value"));
+        copy.memo().precompute();
+        assert!(a.fast_eq(&copy));
+        assert_eq!(EQS.with(Cell::get), 2, "matching hashes compare contents");
+    }
+
+    #[test]
+    fn eq_does_not_consult_the_hashes() {
+        EQS.with(|c| c.set(0));
+        let a = ArcMemo::with_hasher(Spy("value"), std::hash::RandomState::new());
+        let b = ArcMemo::with_hasher(Spy("value"), std::hash::RandomState::new());
+        a.memo().precompute();
+        b.memo().precompute();
+        assert!(a == b, "differently seeded caches");
+        assert_eq!(EQS.with(Cell::get), 1);
+    }
+
+    #[test]
+    fn arc_str_and_bytes_memos_hash_like_their_contents() {
+        let a: HashMemo<Arc<str>> = HashMemo::from("key");
+        let b: HashMemo<Arc<str>> = HashMemo::from("key".to_string());
+        assert!(a == b);
+        assert_eq!(a.memoized_hash(), HashMemo::new("key").memoized_hash());
+        assert_eq!(a.memoized_hash(), crate::Probe::new("key").hash());
+
+        let bytes: HashMemo<Arc<[u8]>> = HashMemo::from(&b"key"[..]);
+        assert!(bytes == HashMemo::from(b"key".to_vec()));
+        assert_eq!(
+            bytes.memoized_hash(),
+            HashMemo::new(b"key".to_vec()).memoized_hash()
+        );
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn try_into_inner_unwraps_unique_values_only() {
+        let shared = Arc::new("value".to_string());
+        let memo = HashMemo::new(Arc::clone(&shared));
+        let hash = memo.memoized_hash();
+
+        let memo = memo.try_into_inner().unwrap_err();
+        assert_eq!(memo.hash.load(std::sync::atomic::Ordering::Relaxed), hash);
+        drop(shared);
+        assert_eq!(memo.try_into_inner().unwrap(), "value");
+
+        let shared = Rc::new(vec![1u8, 2, 3]);
+        let memo = HashMemo::new(Rc::clone(&shared));
+        let memo = memo.try_into_inner().unwrap_err();
+        assert!(Rc::ptr_eq(memo.as_ref(), &shared));
+        drop(shared);
+        assert_eq!(memo.try_into_inner().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn works_as_a_btree_key() {
+        let mut map = BTreeMap::new();
+        let key = ArcMemo::new("beta".to_string());
+        map.insert(ArcMemo::new("alpha".to_string()), 1);
+        map.insert(key.clone(), 2);
+
+        assert_eq!(map.get(&key), Some(&2));
+        assert_eq!(map.get(&ArcMemo::new("alpha".to_string())), Some(&1));
+    }
+}
@@ -0,0 +1,206 @@
+//! A shared memo whose value can be replaced while readers hold it.
+
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash};
+use std::sync::{PoisonError, RwLock};
+
+use crate::ArcMemo;
+
+/// A slot holding the current version of a shared value, each version an
+/// [`ArcMemo`] with its own cached hash.
+///
+/// Readers [`load`](Self::load) a snapshot, writers [`store`](Self::store)
+/// or [`swap`](Self::swap) in a new value. A writer hashes the new value
+/// before publishing it, so the hash is computed once per version, off the
+/// readers' path, and a snapshot always pairs a value with its own hash:
+/// readers never see a new value with an old hash or the reverse.
+///
+/// The slot is a `RwLock` held only to clone or replace the `Arc`, never
+/// while hashing or comparing, so readers do not wait on each other and
+/// wait on a writer only for a pointer swap. A snapshot is an ordinary
+/// `ArcMemo` that stays valid, and unchanged, after newer versions are
+/// stored.
+///
+/// That includes snapshots used as map keys: a key inserted from an old
+/// version keeps that version's value and hash, and finding it again
+/// takes a snapshot of that version or an equal value. Storing a new
+/// version does not re-key any map.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::ArcSwapMemo;
+/// use std::collections::HashMap;
+///
+/// let config = ArcSwapMemo::new("v1".to_string());
+/// let mut seen = HashMap::new();
+/// seen.insert(config.load(), "first");
+///
+/// let old = config.swap("v2".to_string());
+/// assert_eq!(config.load().as_ref(), "v2");
+/// assert_eq!(seen.get(&old), Some(&"first"));
+/// assert_eq!(seen.get(&config.load()), None);
+/// ```
+pub struct ArcSwapMemo<T, H = BuildHasherDefault<DefaultHasher>>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    current: RwLock<ArcMemo<T, H>>,
+    hasher: H,
+}
+
+impl<T> ArcSwapMemo<T>
+where
+    T: Eq + Hash,
+{
+    /// Creates a slot holding `value`, hashed with the default hasher.
+    pub fn new(value: T) -> Self {
+        Self::with_hasher(value, BuildHasherDefault::default())
+    }
+}
+
+impl<T, H> ArcSwapMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher + Clone,
+{
+    /// Creates a slot holding `value`, hashing every version with a clone
+    /// of `hasher`.
+    pub fn with_hasher(value: T, hasher: H) -> Self {
+        let current = RwLock::new(Self::version(value, hasher.clone()));
+        Self { current, hasher }
+    }
+
+    /// Returns a snapshot of the current version.
+    #[inline]
+    pub fn load(&self) -> ArcMemo<T, H> {
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Replaces the current version with `value`.
+    ///
+    /// The old version is dropped here unless snapshots of it are still
+    /// held.
+    pub fn store(&self, value: T) {
+        drop(self.swap(value));
+    }
+
+    /// Replaces the current version with `value`, returning the old one.
+    pub fn swap(&self, value: T) -> ArcMemo<T, H> {
+        let next = Self::version(value, self.hasher.clone());
+        let mut current = self.current.write().unwrap_or_else(PoisonError::into_inner);
+        std::mem::replace(&mut *current, next)
+    }
+
+    /// Builds a version with its hash already cached.
+    fn version(value: T, hasher: H) -> ArcMemo<T, H> {
+        let memo = ArcMemo::with_hasher(value, hasher);
+        memo.memo().hash_one_cached();
+        memo
+    }
+}
+
+impl<T, H> fmt::Debug for ArcSwapMemo<T, H>
+where
+    T: Eq + Hash + fmt::Debug,
+    H: BuildHasher + Clone + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArcSwapMemo")
+            .field("current", &self.load())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::hash::RandomState;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::*;
+    use crate::remap_zero;
+
+    /// A value whose halves must always match, so a torn read shows.
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    struct Version {
+        id: u64,
+        payload: Vec<u64>,
+    }
+
+    impl Version {
+        fn new(id: u64) -> Self {
+            Version {
+                id,
+                payload: vec![id; 64],
+            }
+        }
+    }
+
+    #[test]
+    fn readers_never_see_torn_snapshots() {
+        let state = RandomState::new();
+        let slot = Arc::new(ArcSwapMemo::with_hasher(Version::new(0), state.clone()));
+        let done = Arc::new(AtomicBool::new(false));
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let slot = Arc::clone(&slot);
+                let done = Arc::clone(&done);
+                let state = state.clone();
+                thread::spawn(move || {
+                    let mut last = 0;
+                    let mut loads = 0;
+                    while !done.load(Ordering::Relaxed) || loads == 0 {
+                        let snapshot = slot.load();
+                        let version = snapshot.as_ref();
+                        assert!(version.payload.iter().all(|&x| x == version.id));
+                        assert_eq!(
+                            snapshot.memo().hash_one_cached(),
+                            remap_zero(state.hash_one(version))
+                        );
+                        assert!(version.id >= last, "versions only move forward");
+                        last = version.id;
+                        loads += 1;
+                    }
+                })
+            })
+            .collect();
+
+        for id in 1..=2000 {
+            if id % 2 == 0 {
+                slot.store(Version::new(id));
+            } else {
+                assert_eq!(slot.swap(Version::new(id)).as_ref().id, id - 1);
+            }
+        }
+        done.store(true, Ordering::Relaxed);
+        readers.into_iter().for_each(|r| r.join().unwrap());
+        assert_eq!(slot.load().as_ref().id, 2000);
+    }
+
+    #[test]
+    fn old_snapshots_keep_their_identity_as_keys() {
+        let slot = ArcSwapMemo::new("v1".to_string());
+        let v1 = slot.load();
+        let mut keys = HashSet::new();
+        keys.insert(slot.load());
+
+        slot.store("v2".to_string());
+        assert!(keys.contains(&v1));
+        assert!(!keys.contains(&slot.load()));
+        assert_eq!(v1.as_ref(), "v1");
+
+        slot.store("v1".to_string());
+        let v1_again = slot.load();
+        assert!(!ArcMemo::ptr_eq(&v1, &v1_again));
+        assert!(keys.contains(&v1_again), "equal values find the old key");
+        assert!(v1_again.memo().hash_matches(v1.memo().hash_one_cached()));
+    }
+}
@@ -0,0 +1,379 @@
+//! An interner assigning dense integer ids to distinct memoized values.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash};
+
+#[cfg(feature = "get-size")]
+use ::get_size::GetSize;
+
+use crate::{HashMemo, PassthroughState, Probe};
+
+/// The id of a value interned in a [`MemoArena`].
+///
+/// Ids are dense and assigned in interning order from `0`, so tables of
+/// per-value data can be plain `Vec`s indexed by [`index`](Self::index).
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Id(u32);
+
+impl Id {
+    /// Returns the id as a `u32`.
+    #[inline]
+    pub const fn as_u32(self) -> u32 {
+        self.0
+    }
+
+    /// Returns the id as an index into tables in id order.
+    #[inline]
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// Returns the id of the value at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` does not fit in a `u32`.
+    fn from_index(index: usize) -> Self {
+        match u32::try_from(index) {
+            Ok(id) => Id(id),
+            Err(_) => panic!("MemoArena is full: ids are u32, so it holds at most 2^32 values"),
+        }
+    }
+}
+
+impl From<Id> for u32 {
+    #[inline]
+    fn from(id: Id) -> u32 {
+        id.0
+    }
+}
+
+/// Maps distinct values to dense [`Id`]s and back.
+///
+/// Values are stored once, in id order, as memos. The index maps each
+/// memoized hash to the newest id with that hash, and ids sharing a hash
+/// are chained, so the index itself holds no copy of any value and never
+/// rehashes one as it grows. Each distinct value is hashed once, when it
+/// is first interned; a repeat costs one hash of the query.
+///
+/// The arena holds at most 2^32 values, the number of `u32` ids. Interning
+/// a new value into a full arena panics; repeats of interned values are
+/// still found.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::MemoArena;
+///
+/// let mut arena: MemoArena<String> = MemoArena::new();
+/// let a = arena.intern("alpha".to_string());
+/// let b = arena.intern_ref("beta");
+/// assert_eq!(arena.intern_ref("alpha"), a);
+///
+/// assert_eq!((a.index(), b.index()), (0, 1));
+/// assert_eq!(arena.resolve(b), "beta");
+/// assert_eq!(arena.get("gamma"), None);
+/// ```
+pub struct MemoArena<T, H = BuildHasherDefault<DefaultHasher>>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    values: Vec<HashMemo<T, H>>,
+    /// For each id, the next older id with the same hash.
+    chains: Vec<Option<Id>>,
+    heads: HashMap<u64, Id, PassthroughState>,
+    hasher: H,
+}
+
+impl<T> MemoArena<T>
+where
+    T: Eq + Hash,
+{
+    /// Creates an empty arena whose values use the default hasher.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_hasher(BuildHasherDefault::new())
+    }
+}
+
+impl<T> Default for MemoArena<T>
+where
+    T: Eq + Hash,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, H> MemoArena<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher + Clone,
+{
+    /// Creates an empty arena whose values hash with `hasher`.
+    pub fn with_hasher(hasher: H) -> Self {
+        Self {
+            values: Vec::new(),
+            chains: Vec::new(),
+            heads: HashMap::default(),
+            hasher,
+        }
+    }
+
+    /// Returns the id of `value`, interning it if it is new.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is new and the arena already holds 2^32 values.
+    pub fn intern(&mut self, value: T) -> Id {
+        let memo = HashMemo::with_hasher(value, self.hasher.clone());
+        let hash = memo.memoized_hash();
+        match self.find(hash, memo.as_ref()) {
+            Some(id) => id,
+            None => self.push(memo, hash),
+        }
+    }
+
+    /// Like [`intern`](Self::intern), by a borrowed value, cloned into the
+    /// arena only if it is new.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is new and the arena already holds 2^32 values.
+    pub fn intern_ref<Q>(&mut self, value: &Q) -> Id
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Eq + Hash + ToOwned<Owned = T>,
+    {
+        let hash = Probe::with_hasher(value, &self.hasher).hash();
+        match self.find(hash, value) {
+            Some(id) => id,
+            None => {
+                let memo = HashMemo::with_hasher_const(value.to_owned(), self.hasher.clone(), hash);
+                self.push(memo, hash)
+            }
+        }
+    }
+
+    /// Returns the id of `value`, if it is interned.
+    pub fn get<Q>(&self, value: &Q) -> Option<Id>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.find(Probe::with_hasher(value, &self.hasher).hash(), value)
+    }
+
+    /// Walks the chain of ids whose values hash to `hash`.
+    fn find<Q>(&self, hash: u64, value: &Q) -> Option<Id>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Eq,
+    {
+        let mut next = self.heads.get(&hash).copied();
+        while let Some(id) = next {
+            if self.values[id.index()].as_ref().borrow() == value {
+                return Some(id);
+            }
+            next = self.chains[id.index()];
+        }
+        None
+    }
+
+    fn push(&mut self, memo: HashMemo<T, H>, hash: u64) -> Id {
+        let id = Id::from_index(self.values.len());
+        self.values.push(memo);
+        self.chains.push(self.heads.insert(hash, id));
+        id
+    }
+}
+
+impl<T, H> MemoArena<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Returns the value with id `id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` was not issued by this arena.
+    #[inline]
+    pub fn resolve(&self, id: Id) -> &T {
+        self.values[id.index()].as_ref()
+    }
+
+    /// Returns the memo holding the value with id `id`, with its cached
+    /// hash.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` was not issued by this arena.
+    #[inline]
+    pub fn resolve_memo(&self, id: Id) -> &HashMemo<T, H> {
+        &self.values[id.index()]
+    }
+
+    /// Returns the number of interned values, which is also the next id.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if no value is interned.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Iterates over the ids and values, in id order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (Id, &T)> + '_ {
+        self.values
+            .iter()
+            .enumerate()
+            .map(|(i, memo)| (Id(i as u32), memo.as_ref()))
+    }
+
+    /// Returns the values in id order, dropping the index.
+    pub fn into_values(self) -> Vec<T> {
+        self.values.into_iter().map(HashMemo::into_inner).collect()
+    }
+}
+
+impl<T, H> fmt::Debug for MemoArena<T, H>
+where
+    T: Eq + Hash + fmt::Debug,
+    H: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.iter().map(|(id, value)| (id.0, value)))
+            .finish()
+    }
+}
+
+#[cfg(feature = "get-size")]
+impl GetSize for Id {}
+
+#[cfg(feature = "get-size")]
+impl<T, H> GetSize for MemoArena<T, H>
+where
+    T: Eq + Hash + GetSize,
+    H: BuildHasher,
+{
+    fn get_heap_size(&self) -> usize {
+        self.values.get_heap_size()
+            + self.chains.get_heap_size()
+            + self.heads.capacity() * (u64::get_stack_size() + Id::get_stack_size())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hasher;
+
+    use super::*;
+    use crate::test_util::CountingState;
+    use crate::FixedState;
+
+    /// Hashes every value alike, so all ids share one chain.
+    #[derive(Clone, Default)]
+    struct ConstantState;
+
+    impl BuildHasher for ConstantState {
+        type Hasher = ConstantHasher;
+
+        fn build_hasher(&self) -> ConstantHasher {
+            ConstantHasher
+        }
+    }
+
+    struct ConstantHasher;
+
+    impl Hasher for ConstantHasher {
+        fn finish(&self) -> u64 {
+            42
+        }
+
+        fn write(&mut self, _: &[u8]) {}
+    }
+
+    #[test]
+    fn ids_are_dense_stable_and_round_trip() {
+        let mut arena = MemoArena::with_hasher(FixedState);
+        let words = ["c", "a", "b", "a", "c", "d"];
+        let ids: Vec<Id> = words.iter().map(|w| arena.intern(w.to_string())).collect();
+
+        assert_eq!(
+            ids.iter().map(|id| id.as_u32()).collect::<Vec<_>>(),
+            [0, 1, 2, 1, 0, 3]
+        );
+        for (word, id) in words.iter().zip(&ids) {
+            assert_eq!(arena.resolve(*id), word);
+            assert_eq!(arena.get(*word), Some(*id));
+            assert_eq!(arena.intern_ref(*word), *id);
+        }
+        assert_eq!(arena.len(), 4);
+        assert_eq!(arena.get("e"), None);
+
+        let in_order: Vec<_> = arena
+            .iter()
+            .map(|(id, w)| (id.index(), w.as_str()))
+            .collect();
+        assert_eq!(in_order, [(0, "c"), (1, "a"), (2, "b"), (3, "d")]);
+        assert_eq!(arena.into_values(), ["c", "a", "b", "d"]);
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn duplicate_heavy_input_hashes_each_value_once() {
+        let counter = CountingState::default();
+        let mut arena = MemoArena::with_hasher(counter.clone());
+        let values: Vec<String> = (0..100).map(|i| format!("{:0>100}", i)).collect();
+
+        for value in &values {
+            arena.intern(value.clone());
+        }
+        assert_eq!(counter.count(), 100);
+
+        // Repeats hash the query, never the stored values, however much
+        // the index has grown.
+        for _ in 0..10 {
+            for (i, value) in values.iter().enumerate() {
+                assert_eq!(arena.intern_ref(value.as_str()).index(), i);
+            }
+        }
+        assert_eq!(counter.count(), 100 + 1000);
+        assert_eq!(arena.len(), 100);
+
+        // A new value found by reference is hashed once, for the probe.
+        arena.intern_ref("new");
+        assert_eq!(counter.count(), 1101);
+        arena.resolve_memo(Id(100)).memoized_hash();
+        assert_eq!(counter.count(), 1101);
+    }
+
+    #[test]
+    fn colliding_values_are_told_apart() {
+        let mut arena = MemoArena::with_hasher(ConstantState);
+        let ids: Vec<Id> = (0..50u32).map(|i| arena.intern(i)).collect();
+        for (i, id) in ids.iter().enumerate() {
+            assert_eq!(arena.get(&(i as u32)), Some(*id));
+            assert_eq!(arena.intern(i as u32), *id);
+        }
+        assert_eq!(arena.len(), 50);
+        assert_eq!(arena.get(&50), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "MemoArena is full")]
+    fn ids_past_u32_panic() {
+        assert_eq!(Id::from_index(u32::MAX as usize).as_u32(), u32::MAX);
+        Id::from_index(usize::MAX);
+    }
+}
@@ -0,0 +1,502 @@
+//! A bidirectional map with memoized keys on both sides.
+
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+
+#[cfg(feature = "get-size")]
+use ::get_size::GetSize;
+
+use crate::{HashMemo, MemoLookup, PassthroughState, Probe};
+
+/// A pair, shared between the two indexes of a [`MemoBiMap`].
+struct Pair<L, R, H>
+where
+    L: Eq + Hash,
+    R: Eq + Hash,
+    H: BuildHasher,
+{
+    left: HashMemo<L, H>,
+    right: HashMemo<R, H>,
+}
+
+impl<L, R, H> Pair<L, R, H>
+where
+    L: Eq + Hash,
+    R: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Unwraps a pair that has been removed from both indexes.
+    fn into_inner(pair: Arc<Self>) -> (L, R) {
+        let pair = Arc::into_inner(pair).expect("a removed pair is no longer shared");
+        (pair.left.into_inner(), pair.right.into_inner())
+    }
+}
+
+/// Generates an index entry that hashes, compares and borrows as one side
+/// of its pair.
+macro_rules! side {
+    ($name:ident, $side:ident, $key:ident) => {
+        struct $name<L, R, H>(Arc<Pair<L, R, H>>)
+        where
+            L: Eq + Hash,
+            R: Eq + Hash,
+            H: BuildHasher;
+
+        impl<L, R, H> Hash for $name<L, R, H>
+        where
+            L: Eq + Hash,
+            R: Eq + Hash,
+            H: BuildHasher,
+        {
+            #[inline]
+            fn hash<H2: Hasher>(&self, state: &mut H2) {
+                self.0.$side.hash(state);
+            }
+        }
+
+        impl<L, R, H> PartialEq for $name<L, R, H>
+        where
+            L: Eq + Hash,
+            R: Eq + Hash,
+            H: BuildHasher,
+        {
+            #[inline]
+            fn eq(&self, other: &Self) -> bool {
+                self.0.$side == other.0.$side
+            }
+        }
+
+        impl<L, R, H> Eq for $name<L, R, H>
+        where
+            L: Eq + Hash,
+            R: Eq + Hash,
+            H: BuildHasher,
+        {
+        }
+
+        impl<'a, L, R, H, Q> Borrow<dyn MemoLookup<Q> + 'a> for $name<L, R, H>
+        where
+            L: Eq + Hash + 'a,
+            R: Eq + Hash + 'a,
+            $key: Borrow<Q>,
+            H: BuildHasher + 'a,
+            Q: ?Sized + 'a,
+        {
+            #[inline]
+            fn borrow(&self) -> &(dyn MemoLookup<Q> + 'a) {
+                &self.0.$side
+            }
+        }
+    };
+}
+
+side!(ByLeft, left, L);
+side!(ByRight, right, R);
+
+/// The pairs an insertion into a [`MemoBiMap`] displaced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Overwritten<L, R> {
+    /// Neither value was present; nothing was displaced.
+    Neither,
+    /// The left value was paired with another right value, which was
+    /// removed with it.
+    Left(L, R),
+    /// The right value was paired with another left value, which was
+    /// removed with it.
+    Right(L, R),
+    /// The same pair was already present and has been replaced.
+    Pair(L, R),
+    /// Both values were present in distinct pairs, which were both removed:
+    /// the one holding the left value first.
+    Both((L, R), (L, R)),
+}
+
+/// A one-to-one map between left and right values, with lookups both ways.
+///
+/// Each pair is stored once, shared by an index on each side keyed by that
+/// side's [`HashMemo`] under [`PassthroughState`]. Both values are hashed
+/// once, on insertion; removals and index growth reuse the cached hashes.
+/// Lookups take any `&Q` the side borrows as, so a `String` side is found
+/// by `&str`, and build a [`Probe`] with the map's hasher.
+///
+/// Every left value maps to exactly one right value and back.
+/// [`insert`](Self::insert) keeps it that way by removing the pairs that
+/// held either value, and reports them as [`Overwritten`].
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{MemoBiMap, Overwritten};
+///
+/// let mut symbols: MemoBiMap<String, u32> = MemoBiMap::new();
+/// symbols.insert("std::collections::HashMap".to_string(), 1);
+/// symbols.insert("std::collections::HashSet".to_string(), 2);
+///
+/// assert_eq!(symbols.get_by_left("std::collections::HashMap"), Some(&1));
+/// assert_eq!(
+///     symbols.get_by_right(&2).map(String::as_str),
+///     Some("std::collections::HashSet")
+/// );
+///
+/// let displaced = symbols.insert("std::collections::HashMap".to_string(), 3);
+/// assert_eq!(
+///     displaced,
+///     Overwritten::Left("std::collections::HashMap".to_string(), 1)
+/// );
+/// ```
+pub struct MemoBiMap<L, R, H = BuildHasherDefault<DefaultHasher>>
+where
+    L: Eq + Hash,
+    R: Eq + Hash,
+    H: BuildHasher,
+{
+    left: HashSet<ByLeft<L, R, H>, PassthroughState>,
+    right: HashSet<ByRight<L, R, H>, PassthroughState>,
+    hasher: H,
+}
+
+impl<L, R> MemoBiMap<L, R>
+where
+    L: Eq + Hash,
+    R: Eq + Hash,
+{
+    /// Creates an empty bimap whose values use the default hasher.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_hasher(BuildHasherDefault::new())
+    }
+}
+
+impl<L, R, H> MemoBiMap<L, R, H>
+where
+    L: Eq + Hash,
+    R: Eq + Hash,
+    H: BuildHasher + Clone,
+{
+    /// Creates an empty bimap whose values hash with `hasher`.
+    #[inline]
+    pub fn with_hasher(hasher: H) -> Self {
+        Self {
+            left: HashSet::default(),
+            right: HashSet::default(),
+            hasher,
+        }
+    }
+
+    /// Pairs `left` with `right`, removing any pairs that held either.
+    pub fn insert(&mut self, left: L, right: R) -> Overwritten<L, R> {
+        let pair = Arc::new(Pair {
+            left: HashMemo::with_hasher(left, self.hasher.clone()),
+            right: HashMemo::with_hasher(right, self.hasher.clone()),
+        });
+        let by_left = self
+            .left
+            .take(&pair.left as &dyn MemoLookup<L>)
+            .map(|entry| entry.0);
+        let by_right = self
+            .right
+            .take(&pair.right as &dyn MemoLookup<R>)
+            .map(|entry| entry.0);
+
+        let overwritten = match (by_left, by_right) {
+            (None, None) => Overwritten::Neither,
+            (Some(old), None) => {
+                self.right.take(&old.right as &dyn MemoLookup<R>);
+                let (l, r) = Pair::into_inner(old);
+                Overwritten::Left(l, r)
+            }
+            (None, Some(old)) => {
+                self.left.take(&old.left as &dyn MemoLookup<L>);
+                let (l, r) = Pair::into_inner(old);
+                Overwritten::Right(l, r)
+            }
+            (Some(a), Some(b)) if Arc::ptr_eq(&a, &b) => {
+                drop(b);
+                let (l, r) = Pair::into_inner(a);
+                Overwritten::Pair(l, r)
+            }
+            (Some(a), Some(b)) => {
+                self.right.take(&a.right as &dyn MemoLookup<R>);
+                self.left.take(&b.left as &dyn MemoLookup<L>);
+                Overwritten::Both(Pair::into_inner(a), Pair::into_inner(b))
+            }
+        };
+
+        self.left.insert(ByLeft(pair.clone()));
+        self.right.insert(ByRight(pair));
+        overwritten
+    }
+
+    /// Returns the right value paired with `left`.
+    pub fn get_by_left<Q>(&self, left: &Q) -> Option<&R>
+    where
+        L: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.left
+            .get(Probe::with_hasher(left, &self.hasher).as_lookup())
+            .map(|entry| &entry.0.right.value)
+    }
+
+    /// Returns the left value paired with `right`.
+    pub fn get_by_right<Q>(&self, right: &Q) -> Option<&L>
+    where
+        R: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.right
+            .get(Probe::with_hasher(right, &self.hasher).as_lookup())
+            .map(|entry| &entry.0.left.value)
+    }
+
+    /// Returns `true` if `left` is paired with a right value.
+    pub fn contains_left<Q>(&self, left: &Q) -> bool
+    where
+        L: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.get_by_left(left).is_some()
+    }
+
+    /// Returns `true` if `right` is paired with a left value.
+    pub fn contains_right<Q>(&self, right: &Q) -> bool
+    where
+        R: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.get_by_right(right).is_some()
+    }
+
+    /// Removes and returns the pair holding `left`.
+    pub fn remove_by_left<Q>(&mut self, left: &Q) -> Option<(L, R)>
+    where
+        L: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let pair = self
+            .left
+            .take(Probe::with_hasher(left, &self.hasher).as_lookup())?
+            .0;
+        self.right.take(&pair.right as &dyn MemoLookup<R>);
+        Some(Pair::into_inner(pair))
+    }
+
+    /// Removes and returns the pair holding `right`.
+    pub fn remove_by_right<Q>(&mut self, right: &Q) -> Option<(L, R)>
+    where
+        R: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let pair = self
+            .right
+            .take(Probe::with_hasher(right, &self.hasher).as_lookup())?
+            .0;
+        self.left.take(&pair.left as &dyn MemoLookup<L>);
+        Some(Pair::into_inner(pair))
+    }
+}
+
+impl<L, R, H> MemoBiMap<L, R, H>
+where
+    L: Eq + Hash,
+    R: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Returns every pair, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&L, &R)> {
+        self.left
+            .iter()
+            .map(|entry| (&entry.0.left.value, &entry.0.right.value))
+    }
+
+    /// Returns the number of pairs.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.left.len()
+    }
+
+    /// Returns `true` if the bimap holds no pairs.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.left.is_empty()
+    }
+
+    /// Removes every pair.
+    pub fn clear(&mut self) {
+        self.left.clear();
+        self.right.clear();
+    }
+}
+
+impl<L, R, H> Extend<(L, R)> for MemoBiMap<L, R, H>
+where
+    L: Eq + Hash,
+    R: Eq + Hash,
+    H: BuildHasher + Clone,
+{
+    fn extend<I: IntoIterator<Item = (L, R)>>(&mut self, iter: I) {
+        for (left, right) in iter {
+            self.insert(left, right);
+        }
+    }
+}
+
+impl<L, R, H> FromIterator<(L, R)> for MemoBiMap<L, R, H>
+where
+    L: Eq + Hash,
+    R: Eq + Hash,
+    H: BuildHasher + Clone + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (L, R)>>(iter: I) -> Self {
+        let mut map = Self::default();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<L, R, H> Default for MemoBiMap<L, R, H>
+where
+    L: Eq + Hash,
+    R: Eq + Hash,
+    H: BuildHasher + Clone + Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+impl<L, R, H> fmt::Debug for MemoBiMap<L, R, H>
+where
+    L: Eq + Hash + fmt::Debug,
+    R: Eq + Hash + fmt::Debug,
+    H: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(feature = "get-size")]
+impl<L, R, H> GetSize for MemoBiMap<L, R, H>
+where
+    L: Eq + Hash + GetSize,
+    R: Eq + Hash + GetSize,
+    H: BuildHasher,
+{
+    fn get_heap_size(&self) -> usize {
+        let slot = std::mem::size_of::<Arc<Pair<L, R, H>>>();
+        let indexes = (self.left.capacity() + self.right.capacity()) * slot;
+        self.left.iter().fold(indexes, |size, entry| {
+            size + std::mem::size_of::<Pair<L, R, H>>()
+                + entry.0.left.get_heap_size()
+                + entry.0.right.get_heap_size()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    /// Counts how often it is hashed, across all instances.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct Counted(String);
+
+    impl Hash for Counted {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            COUNT.fetch_add(1, Ordering::Relaxed);
+            self.0.hash(state);
+        }
+    }
+
+    fn assert_bijection<L, R>(map: &MemoBiMap<L, R>)
+    where
+        L: Eq + Hash + fmt::Debug,
+        R: Eq + Hash + fmt::Debug,
+    {
+        assert_eq!(map.left.len(), map.right.len());
+        for (left, right) in map.iter() {
+            assert_eq!(map.get_by_left(left), Some(right));
+            assert_eq!(map.get_by_right(right), Some(left));
+        }
+    }
+
+    #[test]
+    fn overwrites_report_and_remove_displaced_pairs() {
+        let mut map: MemoBiMap<String, u32> = MemoBiMap::new();
+        assert_eq!(map.insert("a".to_string(), 1), Overwritten::Neither);
+        assert_eq!(map.insert("b".to_string(), 2), Overwritten::Neither);
+        assert_bijection(&map);
+
+        assert_eq!(
+            map.insert("a".to_string(), 3),
+            Overwritten::Left("a".to_string(), 1)
+        );
+        assert!(!map.contains_right(&1));
+        assert_eq!(
+            map.insert("c".to_string(), 3),
+            Overwritten::Right("a".to_string(), 3)
+        );
+        assert!(!map.contains_left("a"));
+        assert_eq!(
+            map.insert("c".to_string(), 3),
+            Overwritten::Pair("c".to_string(), 3)
+        );
+        assert_eq!(
+            map.insert("b".to_string(), 3),
+            Overwritten::Both(("b".to_string(), 2), ("c".to_string(), 3))
+        );
+        assert_bijection(&map);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get_by_left("b"), Some(&3));
+    }
+
+    #[test]
+    fn removals_keep_both_sides_in_step() {
+        let mut map: MemoBiMap<String, u32> =
+            (0..100u32).map(|i| (format!("name{i}"), i)).collect();
+        assert_bijection(&map);
+
+        for i in (0..100u32).step_by(2) {
+            assert_eq!(
+                map.remove_by_left(format!("name{i}").as_str()),
+                Some((format!("name{i}"), i))
+            );
+        }
+        for i in (1..100u32).step_by(4) {
+            assert_eq!(map.remove_by_right(&i), Some((format!("name{i}"), i)));
+        }
+        assert_eq!(map.remove_by_right(&0), None);
+        assert_eq!(map.remove_by_left("name1"), None);
+        assert_eq!(map.len(), 25);
+        assert_bijection(&map);
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn values_are_hashed_once_on_insertion() {
+        let names: Vec<Counted> = (0..64)
+            .map(|i| Counted(format!("a::long::path::{i}")))
+            .collect();
+        let mut map = MemoBiMap::new();
+        let before = COUNT.load(Ordering::Relaxed);
+
+        for (i, name) in names.iter().enumerate() {
+            map.insert(name.clone(), i);
+        }
+        assert_eq!(COUNT.load(Ordering::Relaxed) - before, 64);
+
+        for i in 0..64 {
+            map.remove_by_right(&i);
+        }
+        assert!(map.is_empty());
+        assert_eq!(COUNT.load(Ordering::Relaxed) - before, 64);
+    }
+}
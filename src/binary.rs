@@ -0,0 +1,411 @@
+//! A first-class memoized binary key.
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher};
+use std::ops::Deref;
+use std::sync::atomic::Ordering as AtomicOrdering;
+
+use crate::{HashMemo, MemoLookup};
+
+/// How many leading bytes `Debug` prints before truncating.
+const DEBUG_PREVIEW: usize = 32;
+
+/// A memoized `Vec<u8>`, for use wherever a `Vec<u8>` key was used before.
+///
+/// It derefs to `[u8]` and converts and compares like `Vec<u8>`. As with
+/// [`MemoString`](crate::MemoString), borrowed lookups go through
+/// [`Probe`](crate::Probe) rather than `Borrow<[u8]>`, since the key hashes
+/// as its cached `u64`.
+///
+/// `Debug` prints the payload as hex, truncated after 32 bytes, along with
+/// the length and the full cached hash, if any; [`to_hex`](Self::to_hex)
+/// and [`from_hex`](Self::from_hex) convert whole payloads for fixtures and
+/// logs.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{MemoBytes, Probe};
+/// use std::collections::HashMap;
+///
+/// let mut map: HashMap<MemoBytes, u32> = HashMap::new();
+/// map.insert(MemoBytes::from_hex("cafe").unwrap(), 1);
+///
+/// assert_eq!(map.get(Probe::new(&[0xca, 0xfe][..]).as_lookup()), Some(&1));
+/// ```
+#[derive(Clone)]
+pub struct MemoBytes<H = BuildHasherDefault<DefaultHasher>>(HashMemo<Vec<u8>, H>)
+where
+    H: BuildHasher;
+
+impl MemoBytes {
+    /// Creates a new `MemoBytes` with the default hasher.
+    #[inline]
+    pub const fn new(value: Vec<u8>) -> Self {
+        Self(HashMemo::new(value))
+    }
+
+    /// Decodes a hex string, in either case, into a `MemoBytes`.
+    pub fn from_hex(hex: &str) -> Result<Self, FromHexError> {
+        let hex = hex.as_bytes();
+        if !hex.len().is_multiple_of(2) {
+            return Err(FromHexError::OddLength);
+        }
+
+        let digit = |index: usize| match hex[index] {
+            b @ b'0'..=b'9' => Ok(b - b'0'),
+            b @ b'a'..=b'f' => Ok(b - b'a' + 10),
+            b @ b'A'..=b'F' => Ok(b - b'A' + 10),
+            _ => Err(FromHexError::InvalidDigit { index }),
+        };
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| Ok(digit(i)? << 4 | digit(i + 1)?))
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(bytes))
+    }
+}
+
+impl<H> MemoBytes<H>
+where
+    H: BuildHasher,
+{
+    /// Creates a new `MemoBytes` with a custom hasher.
+    #[inline]
+    pub const fn with_hasher(value: Vec<u8>, hasher: H) -> Self {
+        Self(HashMemo::with_hasher(value, hasher))
+    }
+
+    /// Returns the bytes.
+    #[inline]
+    pub fn as_slice(&self) -> &[u8] {
+        self.0.value.as_slice()
+    }
+
+    /// Consumes the `MemoBytes` and returns the `Vec<u8>`.
+    #[inline]
+    #[must_use]
+    pub fn into_vec(self) -> Vec<u8> {
+        self.0.into_inner()
+    }
+
+    /// Returns the underlying memo.
+    #[inline]
+    pub fn memo(&self) -> &HashMemo<Vec<u8>, H> {
+        &self.0
+    }
+
+    /// Consumes the `MemoBytes` and returns the underlying memo, cache
+    /// included.
+    #[inline]
+    pub fn into_memo(self) -> HashMemo<Vec<u8>, H> {
+        self.0
+    }
+
+    /// Encodes the bytes as lowercase hex.
+    pub fn to_hex(&self) -> String {
+        let mut hex = String::with_capacity(self.len() * 2);
+        write_hex(&mut hex, self).expect("writing to a String cannot fail");
+        hex
+    }
+}
+
+fn write_hex(f: &mut impl fmt::Write, bytes: &[u8]) -> fmt::Result {
+    bytes.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+}
+
+/// The error returned by [`MemoBytes::from_hex`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FromHexError {
+    /// The input has an odd number of digits.
+    OddLength,
+    /// The input has a character that is not a hex digit.
+    InvalidDigit {
+        /// The byte offset of the character.
+        index: usize,
+    },
+}
+
+impl fmt::Display for FromHexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OddLength => f.write_str("hex input has an odd number of digits"),
+            Self::InvalidDigit { index } => write!(f, "invalid hex digit at offset {index}"),
+        }
+    }
+}
+
+impl std::error::Error for FromHexError {}
+
+impl<H> Deref for MemoBytes<H>
+where
+    H: BuildHasher,
+{
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl<H> AsRef<[u8]> for MemoBytes<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl<'a, H> Borrow<dyn MemoLookup<[u8]> + 'a> for MemoBytes<H>
+where
+    H: BuildHasher + 'a,
+{
+    #[inline]
+    fn borrow(&self) -> &(dyn MemoLookup<[u8]> + 'a) {
+        &self.0
+    }
+}
+
+impl<H> Hash for MemoBytes<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn hash<H2: Hasher>(&self, state: &mut H2) {
+        self.0.hash(state);
+    }
+}
+
+impl<H> PartialEq for MemoBytes<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<H> Eq for MemoBytes<H> where H: BuildHasher {}
+
+impl<H> PartialEq<[u8]> for MemoBytes<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn eq(&self, other: &[u8]) -> bool {
+        self.as_slice() == other
+    }
+}
+
+impl<H> PartialEq<&[u8]> for MemoBytes<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn eq(&self, other: &&[u8]) -> bool {
+        self.as_slice() == *other
+    }
+}
+
+impl<H> PartialEq<Vec<u8>> for MemoBytes<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn eq(&self, other: &Vec<u8>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<H> PartialOrd for MemoBytes<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<H> Ord for MemoBytes<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_slice().cmp(other.as_slice())
+    }
+}
+
+impl<H> fmt::Debug for MemoBytes<H>
+where
+    H: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Hex<'a>(&'a [u8]);
+
+        impl fmt::Debug for Hex<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write_hex(f, &self.0[..self.0.len().min(DEBUG_PREVIEW)])?;
+                if self.0.len() > DEBUG_PREVIEW {
+                    f.write_str("…")?;
+                }
+                Ok(())
+            }
+        }
+
+        struct CachedHash(u64);
+
+        impl fmt::Debug for CachedHash {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self.0 {
+                    0 => f.write_str("None"),
+                    hash => write!(f, "{hash:#018x}"),
+                }
+            }
+        }
+
+        f.debug_struct("MemoBytes")
+            .field("len", &self.len())
+            .field("hex", &Hex(self))
+            .field(
+                "hash",
+                &CachedHash(self.0.hash.load(AtomicOrdering::Relaxed)),
+            )
+            .finish()
+    }
+}
+
+impl<H> Default for MemoBytes<H>
+where
+    H: BuildHasher + Default,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::from(Vec::new())
+    }
+}
+
+impl<H> From<Vec<u8>> for MemoBytes<H>
+where
+    H: BuildHasher + Default,
+{
+    #[inline]
+    fn from(value: Vec<u8>) -> Self {
+        Self::with_hasher(value, H::default())
+    }
+}
+
+impl<H> From<&[u8]> for MemoBytes<H>
+where
+    H: BuildHasher + Default,
+{
+    #[inline]
+    fn from(value: &[u8]) -> Self {
+        Self::from(value.to_vec())
+    }
+}
+
+impl<H> From<HashMemo<Vec<u8>, H>> for MemoBytes<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn from(memo: HashMemo<Vec<u8>, H>) -> Self {
+        Self(memo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::Probe;
+
+    #[test]
+    fn empty_payloads_are_ordinary_keys() {
+        let empty: MemoBytes = Vec::new().into();
+        assert!(empty.is_empty());
+        assert_eq!(empty, MemoBytes::default());
+        let slice: &[u8] = b"";
+        assert!(empty == slice);
+        assert!(empty == *slice);
+        assert_eq!(empty.to_hex(), "");
+        assert_eq!(MemoBytes::from_hex("").unwrap(), empty);
+
+        let mut map = HashMap::new();
+        map.insert(empty, 1);
+        assert_eq!(map.get(Probe::new(&b""[..]).as_lookup()), Some(&1));
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn megabyte_payloads_are_hashed_once() {
+        use crate::test_util::CountingState;
+
+        let state = CountingState::default();
+        let payload = vec![0x5a; 8 << 20];
+        let key = MemoBytes::with_hasher(payload.clone(), state.clone());
+        key.memo().precompute();
+
+        let mut map = HashMap::new();
+        map.insert(key.clone(), 1);
+        for _ in 0..10 {
+            assert_eq!(map.get(&key), Some(&1));
+        }
+        assert_eq!(state.count(), 1);
+        assert!(key == payload);
+    }
+
+    #[test]
+    fn hex_round_trips() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let memo = MemoBytes::from(&bytes[..]);
+        let hex = memo.to_hex();
+        assert_eq!(&hex[..8], "00010203");
+        assert_eq!(MemoBytes::from_hex(&hex).unwrap(), memo);
+        assert_eq!(MemoBytes::from_hex("CAFE").unwrap(), vec![0xca, 0xfe]);
+
+        assert_eq!(MemoBytes::from_hex("abc"), Err(FromHexError::OddLength));
+        assert_eq!(
+            MemoBytes::from_hex("0g"),
+            Err(FromHexError::InvalidDigit { index: 1 })
+        );
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn debug_truncates_and_shows_the_cached_hash() {
+        let short = MemoBytes::from_hex("cafe").unwrap();
+        assert_eq!(
+            format!("{short:?}"),
+            r#"MemoBytes { len: 2, hex: cafe, hash: None }"#
+        );
+
+        let long = MemoBytes::new(vec![0xab; 100]);
+        let hash = long.memo().memoized_hash();
+        assert_eq!(
+            format!("{long:?}"),
+            format!(
+                "MemoBytes {{ len: 100, hex: {}…, hash: {hash:#018x} }}",
+                "ab".repeat(DEBUG_PREVIEW)
+            )
+        );
+    }
+
+    #[test]
+    fn hashes_like_a_vec_memo() {
+        let state = std::hash::RandomState::new();
+        let key = MemoBytes::new(b"key".to_vec());
+        let memo = HashMemo::new(b"key".to_vec());
+        assert_eq!(state.hash_one(&key), state.hash_one(&memo));
+        assert_eq!(key.into_memo(), memo);
+    }
+}
@@ -0,0 +1,206 @@
+//! [`bincode`](::bincode) 2 `Encode`/`Decode` support.
+//!
+//! `HashMemo` itself encodes transparently: its bytes are exactly those of
+//! the wrapped value, and decoding yields a memo with a cold cache and a
+//! hasher built via `Default`.
+//!
+//! [`WithHash`] additionally carries the cached hash, for snapshots that
+//! should reload without rehashing.
+
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash};
+use std::sync::atomic::Ordering;
+
+use ::bincode::de::{BorrowDecoder, Decoder};
+use ::bincode::enc::Encoder;
+use ::bincode::error::{DecodeError, EncodeError};
+use ::bincode::{BorrowDecode, Decode, Encode};
+
+use crate::{hasher_tag, HashMemo};
+
+impl<T, H> Encode for HashMemo<T, H>
+where
+    T: Eq + Hash + Encode,
+    H: BuildHasher,
+{
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.value.encode(encoder)
+    }
+}
+
+impl<T, H, Context> Decode<Context> for HashMemo<T, H>
+where
+    T: Eq + Hash + Decode<Context>,
+    H: BuildHasher + Default,
+{
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        T::decode(decoder).map(|value| Self::with_hasher(value, H::default()))
+    }
+}
+
+impl<'de, T, H, Context> BorrowDecode<'de, Context> for HashMemo<T, H>
+where
+    T: Eq + Hash + BorrowDecode<'de, Context>,
+    H: BuildHasher + Default,
+{
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        T::borrow_decode(decoder).map(|value| Self::with_hasher(value, H::default()))
+    }
+}
+
+/// Encodes a [`HashMemo`] together with its cached hash.
+///
+/// The value is followed by the hash (computed first if the cache is cold)
+/// and a fingerprint of the hasher. Decoding seeds the cache only when the
+/// decoded hasher produces the same fingerprint, so a snapshot written with
+/// a different hasher reloads cold rather than with a wrong hash.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{bincode::WithHash, HashMemo};
+///
+/// let config = bincode::config::standard();
+/// let bytes = bincode::encode_to_vec(WithHash(HashMemo::new(42u64)), config).unwrap();
+/// let (WithHash(memo), _): (WithHash<u64>, _) = bincode::decode_from_slice(&bytes, config).unwrap();
+/// assert_eq!(memo.into_inner(), 42);
+/// ```
+#[derive(Debug, Clone)]
+pub struct WithHash<T, H = BuildHasherDefault<DefaultHasher>>(pub HashMemo<T, H>)
+where
+    T: Eq + Hash,
+    H: BuildHasher;
+
+impl<T, H> WithHash<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher + Default,
+{
+    fn from_parts(value: T, hash: u64, tag: u64) -> Self {
+        let memo = HashMemo::with_hasher(value, H::default());
+        if tag == hasher_tag(&memo.hasher) {
+            memo.hash.store(hash, Ordering::Relaxed);
+        }
+        Self(memo)
+    }
+}
+
+impl<T, H> From<HashMemo<T, H>> for WithHash<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    fn from(memo: HashMemo<T, H>) -> Self {
+        Self(memo)
+    }
+}
+
+impl<T, H> Encode for WithHash<T, H>
+where
+    T: Eq + Hash + Encode,
+    H: BuildHasher,
+{
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.0.value.encode(encoder)?;
+        self.0.memoized_hash().encode(encoder)?;
+        hasher_tag(&self.0.hasher).encode(encoder)
+    }
+}
+
+impl<T, H, Context> Decode<Context> for WithHash<T, H>
+where
+    T: Eq + Hash + Decode<Context>,
+    H: BuildHasher + Default,
+{
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let value = T::decode(decoder)?;
+        let hash = u64::decode(decoder)?;
+        let tag = u64::decode(decoder)?;
+        Ok(Self::from_parts(value, hash, tag))
+    }
+}
+
+impl<'de, T, H, Context> BorrowDecode<'de, Context> for WithHash<T, H>
+where
+    T: Eq + Hash + BorrowDecode<'de, Context>,
+    H: BuildHasher + Default,
+{
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        let value = T::borrow_decode(decoder)?;
+        let hash = u64::borrow_decode(decoder)?;
+        let tag = u64::borrow_decode(decoder)?;
+        Ok(Self::from_parts(value, hash, tag))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::RandomState;
+
+    use ::bincode::config;
+
+    use super::*;
+
+    #[test]
+    fn transparent_encoding_matches_inner_value() {
+        let value = "transparent".repeat(10);
+        let memo = HashMemo::new(value.clone());
+        assert_eq!(
+            ::bincode::encode_to_vec(&memo, config::standard()).unwrap(),
+            ::bincode::encode_to_vec(&value, config::standard()).unwrap(),
+        );
+    }
+
+    #[test]
+    fn decoded_memo_is_cold_and_hashes_like_a_fresh_one() {
+        let memo = HashMemo::new(vec![1u32, 2, 3]);
+        let _ = memo.memoized_hash();
+        let bytes = ::bincode::encode_to_vec(&memo, config::standard()).unwrap();
+
+        let (decoded, _): (HashMemo<Vec<u32>>, _) =
+            ::bincode::decode_from_slice(&bytes, config::standard()).unwrap();
+        assert_eq!(decoded.hash.load(Ordering::Relaxed), 0);
+
+        let fresh = HashMemo::new(vec![1u32, 2, 3]);
+        let hasher = RandomState::new();
+        assert_eq!(hasher.hash_one(&decoded), hasher.hash_one(&fresh));
+    }
+
+    #[test]
+    fn borrow_decode_keeps_references_into_the_input() {
+        let bytes = ::bincode::encode_to_vec("borrowed", config::standard()).unwrap();
+        let (memo, _): (HashMemo<&str>, _) =
+            ::bincode::borrow_decode_from_slice(&bytes, config::standard()).unwrap();
+        assert_eq!(memo.into_inner(), "borrowed");
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn with_hash_round_trip_keeps_the_cache() {
+        let memo = HashMemo::new("snapshot".to_string());
+        let hash = memo.memoized_hash();
+        let bytes = ::bincode::encode_to_vec(WithHash(memo), config::standard()).unwrap();
+
+        let (WithHash(decoded), _): (WithHash<String>, _) =
+            ::bincode::decode_from_slice(&bytes, config::standard()).unwrap();
+        assert_eq!(decoded.value, "snapshot");
+        assert_eq!(decoded.hash.load(Ordering::Relaxed), hash);
+    }
+
+    #[test]
+    fn with_hash_from_a_different_hasher_reloads_cold() {
+        let bytes = ::bincode::encode_to_vec(
+            WithHash(HashMemo::new("snapshot".to_string())),
+            config::standard(),
+        )
+        .unwrap();
+
+        let (WithHash(decoded), _): (WithHash<String, RandomState>, _) =
+            ::bincode::decode_from_slice(&bytes, config::standard()).unwrap();
+        assert_eq!(decoded.value, "snapshot");
+        assert_eq!(decoded.hash.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,188 @@
+//! A builder gathering the construction knobs of a memo in one place.
+
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash};
+use std::marker::PhantomData;
+
+use crate::{HashMemo, HashMemo32, HashMemoUsize};
+
+/// The width of a memo's hash cache, selecting the type a
+/// [`HashMemoBuilder`] builds.
+///
+/// Each width is a constant of a different type, so the choice is made at
+/// compile time: [`Width::U64`] builds a [`HashMemo`], [`Width::U32`] a
+/// [`HashMemo32`] and [`Width::USIZE`] a [`HashMemoUsize`].
+#[derive(Debug, Clone, Copy)]
+pub struct Width<W>(PhantomData<W>);
+
+impl Width<u64> {
+    /// A full 64-bit cache, building a [`HashMemo`].
+    pub const U64: Self = Self(PhantomData);
+}
+
+impl Width<u32> {
+    /// A 32-bit cache, building a [`HashMemo32`].
+    pub const U32: Self = Self(PhantomData);
+}
+
+impl Width<usize> {
+    /// A word-sized cache, building a [`HashMemoUsize`].
+    pub const USIZE: Self = Self(PhantomData);
+}
+
+/// Builds a memo from a value, a hasher and a cache width.
+///
+/// Unset knobs keep the defaults of [`HashMemo::new`]: the default hasher
+/// and a 64-bit cache. The cache itself always starts empty and publishes
+/// with relaxed atomics; neither its ordering nor its `0` sentinel is
+/// configurable, since every variant relies on both.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{FixedState, HashMemoBuilder, Width};
+///
+/// let memo = HashMemoBuilder::new("hello world")
+///     .hasher(FixedState)
+///     .width(Width::U32)
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct HashMemoBuilder<T, H = BuildHasherDefault<DefaultHasher>, W = u64> {
+    value: T,
+    hasher: H,
+    width: Width<W>,
+}
+
+impl<T> HashMemoBuilder<T>
+where
+    T: Eq + Hash,
+{
+    /// Starts a builder for `value` with the default hasher and width.
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            hasher: BuildHasherDefault::default(),
+            width: Width::U64,
+        }
+    }
+}
+
+impl<T, H, W> HashMemoBuilder<T, H, W>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Sets the hasher the memo computes its hash with.
+    pub fn hasher<H2: BuildHasher>(self, hasher: H2) -> HashMemoBuilder<T, H2, W> {
+        HashMemoBuilder {
+            value: self.value,
+            hasher,
+            width: self.width,
+        }
+    }
+
+    /// Sets the width of the hash cache, see [`Width`].
+    pub fn width<W2>(self, width: Width<W2>) -> HashMemoBuilder<T, H, W2> {
+        HashMemoBuilder {
+            value: self.value,
+            hasher: self.hasher,
+            width,
+        }
+    }
+}
+
+impl<T, H> HashMemoBuilder<T, H, u64>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Builds a [`HashMemo`].
+    pub fn build(self) -> HashMemo<T, H> {
+        HashMemo::with_hasher(self.value, self.hasher)
+    }
+}
+
+impl<T, H> HashMemoBuilder<T, H, u32>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Builds a [`HashMemo32`].
+    pub fn build(self) -> HashMemo32<T, H> {
+        HashMemo32::with_hasher(self.value, self.hasher)
+    }
+}
+
+impl<T, H> HashMemoBuilder<T, H, usize>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Builds a [`HashMemoUsize`].
+    pub fn build(self) -> HashMemoUsize<T, H> {
+        HashMemoUsize::with_hasher(self.value, self.hasher)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::RandomState;
+
+    use super::*;
+    use crate::FixedState;
+
+    #[test]
+    fn defaults_reproduce_new() {
+        let built: HashMemo<String> = HashMemoBuilder::new("key".to_string()).build();
+        let memo = HashMemo::new("key".to_string());
+        assert!(built == memo);
+        assert_eq!(built.memoized_hash(), memo.memoized_hash());
+
+        let built = HashMemoBuilder::new("key".to_string())
+            .width(Width::U64)
+            .build();
+        assert_eq!(built.memoized_hash(), memo.memoized_hash());
+    }
+
+    #[test]
+    fn every_width_with_every_hasher() {
+        let value = "key".to_string();
+        let default = BuildHasherDefault::<DefaultHasher>::default().hash_one(&value);
+        let fixed = FixedState.hash_one(&value);
+
+        let builder = HashMemoBuilder::new(value.clone());
+        assert_eq!(builder.clone().build().memoized_hash(), default);
+        assert_eq!(
+            builder.clone().width(Width::U32).build().memoized_hash(),
+            default as u32
+        );
+        assert_eq!(
+            builder.width(Width::USIZE).build().memoized_hash(),
+            default as usize
+        );
+
+        let builder = HashMemoBuilder::new(value.clone()).hasher(FixedState);
+        assert_eq!(builder.clone().build().memoized_hash(), fixed);
+        assert_eq!(
+            builder.clone().width(Width::U32).build().memoized_hash(),
+            fixed as u32
+        );
+        assert_eq!(
+            builder.width(Width::USIZE).build().memoized_hash(),
+            fixed as usize
+        );
+
+        // Knobs can be set in any order.
+        let memo = HashMemoBuilder::new(value.clone())
+            .width(Width::U32)
+            .hasher(FixedState)
+            .build();
+        assert_eq!(memo.memoized_hash(), fixed as u32);
+
+        let state = RandomState::new();
+        let memo = HashMemoBuilder::new(value.clone())
+            .hasher(state.clone())
+            .build();
+        assert_eq!(memo.memoized_hash(), state.hash_one(&value));
+    }
+}
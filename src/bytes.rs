@@ -0,0 +1,93 @@
+//! [`bytes`](::bytes) support for memoized payload keys.
+//!
+//! `Bytes` is already cheap to clone, so [`MemoSharedBytes`] only adds the
+//! cached hash; cloning a memo clones the `Bytes` handle, never the buffer.
+//! `Bytes` hashes like `[u8]`, so a `MemoSharedBytes` hashes and compares
+//! like a `HashMemo<Vec<u8>>` or a [`MemoBytes`](crate::MemoBytes) with the
+//! same content, and maps keyed by it can be probed with a `&[u8]` through
+//! [`Probe`](crate::Probe).
+//!
+//! Slicing is done on the inner `Bytes`: `Bytes::slice` yields new content,
+//! so wrap the result in a new, uncached memo rather than reusing the
+//! parent's hash.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use bytes::Bytes;
+//! use hashmemo::{MemoSharedBytes, Probe};
+//! use std::collections::HashMap;
+//!
+//! let payload = Bytes::from_static(b"GET /index.html");
+//! let mut map = HashMap::new();
+//! map.insert(MemoSharedBytes::new(payload.clone()), 1);
+//! map.insert(MemoSharedBytes::new(payload.slice(4..)), 2);
+//!
+//! assert_eq!(map.get(Probe::new(&b"/index.html"[..]).as_lookup()), Some(&2));
+//! ```
+
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher};
+
+use ::bytes::{Bytes, BytesMut};
+
+use crate::HashMemo;
+
+/// A [`HashMemo`] over a `bytes::Bytes` payload.
+pub type MemoSharedBytes<H = BuildHasherDefault<DefaultHasher>> = HashMemo<Bytes, H>;
+
+impl<H> HashMemo<Bytes, H>
+where
+    H: BuildHasher,
+{
+    /// Returns the wrapped bytes.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.value
+    }
+}
+
+impl From<BytesMut> for HashMemo<Bytes> {
+    fn from(bytes: BytesMut) -> Self {
+        Self::new(bytes.freeze())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::Probe;
+
+    #[test]
+    fn hashes_and_compares_like_a_vec_memo() {
+        let bytes = MemoSharedBytes::new(Bytes::from_static(b"payload"));
+        let vec = HashMemo::new(b"payload".to_vec());
+        assert_eq!(bytes.memoized_hash(), vec.memoized_hash());
+        assert_eq!(bytes.as_bytes(), vec.as_ref().as_slice());
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"payload");
+        assert!(MemoSharedBytes::from(buf) == bytes);
+    }
+
+    #[test]
+    fn clone_shares_the_buffer() {
+        let memo = MemoSharedBytes::new(Bytes::from(vec![7u8; 1024]));
+        let clone = memo.clone();
+        assert_eq!(memo.as_bytes().as_ptr(), clone.as_bytes().as_ptr());
+        assert_eq!(memo.memoized_hash(), clone.memoized_hash());
+    }
+
+    #[test]
+    fn slices_are_looked_up_by_borrowed_bytes() {
+        let packet = Bytes::from_static(b"header:body");
+        let mut set = HashSet::new();
+        set.insert(MemoSharedBytes::new(packet.slice(..6)));
+        set.insert(MemoSharedBytes::new(packet.slice(7..)));
+
+        assert!(set.contains(Probe::new(&b"header"[..]).as_lookup()));
+        assert!(set.contains(Probe::new(&b"body"[..]).as_lookup()));
+        assert!(!set.contains(Probe::new(&b"header:body"[..]).as_lookup()));
+    }
+}
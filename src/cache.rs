@@ -0,0 +1,532 @@
+//! A bounded LRU cache keyed by memoized values.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher};
+use std::iter;
+use std::mem;
+use std::sync::Arc;
+
+#[cfg(feature = "get-size")]
+use ::get_size::GetSize;
+
+use crate::{HashMemo, MemoLookup, PassthroughState, Probe};
+
+/// The end of the recency list.
+const NIL: usize = usize::MAX;
+
+/// A map key sharing its memo with the node it indexes.
+struct Slot<K, H>(Arc<HashMemo<K, H>>)
+where
+    K: Eq + Hash,
+    H: BuildHasher;
+
+impl<K, H> Hash for Slot<K, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher,
+{
+    #[inline]
+    fn hash<H2: Hasher>(&self, state: &mut H2) {
+        self.0.hash(state);
+    }
+}
+
+impl<K, H> PartialEq for Slot<K, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher,
+{
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<K, H> Eq for Slot<K, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher,
+{
+}
+
+impl<'a, K, H, Q> Borrow<dyn MemoLookup<Q> + 'a> for Slot<K, H>
+where
+    K: Eq + Hash + Borrow<Q> + 'a,
+    H: BuildHasher + 'a,
+    Q: ?Sized + 'a,
+{
+    #[inline]
+    fn borrow(&self) -> &(dyn MemoLookup<Q> + 'a) {
+        &*self.0
+    }
+}
+
+/// An entry, linked into the recency list.
+struct Node<K, V, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher,
+{
+    key: Arc<HashMemo<K, H>>,
+    value: V,
+    prev: usize,
+    next: usize,
+}
+
+/// A bounded cache that evicts its least recently used entry, and never
+/// re-hashes a resident key.
+///
+/// Entries live in a slab linked into a recency list, indexed by a map
+/// from each key's [`HashMemo`] under [`PassthroughState`]. The map and
+/// the slab share the memo, so a key is hashed once, on insertion:
+/// eviction, removal and map growth reuse the cached hash, where caches
+/// keyed by plain values hash the evicted key again to unlink it.
+///
+/// Lookups take any `&Q` the key borrows as and build a [`Probe`] with the
+/// cache's hasher, which hashes the query once. A cache of capacity `0`
+/// stores nothing.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::MemoCache;
+///
+/// let mut cache: MemoCache<String, u32> = MemoCache::with_capacity(2);
+/// cache.put("a".to_string(), 1);
+/// cache.put("b".to_string(), 2);
+/// assert_eq!(cache.get("a"), Some(&1));
+///
+/// // "b" is now the least recently used entry.
+/// assert_eq!(cache.put("c".to_string(), 3), Some(("b".to_string(), 2)));
+/// let order: Vec<_> = cache.iter().map(|(key, _)| key.as_str()).collect();
+/// assert_eq!(order, ["c", "a"]);
+/// ```
+pub struct MemoCache<K, V, H = BuildHasherDefault<DefaultHasher>>
+where
+    K: Eq + Hash,
+    H: BuildHasher,
+{
+    map: HashMap<Slot<K, H>, usize, PassthroughState>,
+    nodes: Vec<Option<Node<K, V, H>>>,
+    free: Vec<usize>,
+    head: usize,
+    tail: usize,
+    capacity: usize,
+    hasher: H,
+}
+
+impl<K, V> MemoCache<K, V>
+where
+    K: Eq + Hash,
+{
+    /// Creates an empty cache holding at most `capacity` entries, whose keys
+    /// use the default hasher.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, BuildHasherDefault::new())
+    }
+}
+
+impl<K, V, H> MemoCache<K, V, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher + Clone,
+{
+    /// Creates an empty cache holding at most `capacity` entries, whose keys
+    /// hash with `hasher`.
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: H) -> Self {
+        Self {
+            map: HashMap::default(),
+            nodes: Vec::new(),
+            free: Vec::new(),
+            head: NIL,
+            tail: NIL,
+            capacity,
+            hasher,
+        }
+    }
+
+    /// Returns the value of `key`, marking it as most recently used.
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let index = self.index_of(key)?;
+        self.touch(index);
+        Some(&self.node(index).value)
+    }
+
+    /// Like [`get`](Self::get), returning the value mutably.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let index = self.index_of(key)?;
+        self.touch(index);
+        Some(&mut self.node_mut(index).value)
+    }
+
+    /// Returns the value of `key`, leaving the recency order untouched.
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.index_of(key).map(|index| &self.node(index).value)
+    }
+
+    /// Returns `true` if `key` is in the cache, leaving the recency order
+    /// untouched.
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.index_of(key).is_some()
+    }
+
+    /// Inserts `key` as the most recently used entry.
+    ///
+    /// If `key` was present, its value is replaced and `key` is returned
+    /// with the old value, keeping the resident key and its cached hash.
+    /// Otherwise, if the cache is full, the least recently used entry is
+    /// evicted and returned. A cache of capacity `0` returns the entry
+    /// itself.
+    pub fn put(&mut self, key: K, value: V) -> Option<(K, V)> {
+        if self.capacity == 0 {
+            return Some((key, value));
+        }
+
+        let key = HashMemo::with_hasher(key, self.hasher.clone());
+        if let Some(&index) = self.map.get(&key as &dyn MemoLookup<K>) {
+            self.touch(index);
+            let old = mem::replace(&mut self.node_mut(index).value, value);
+            return Some((key.into_inner(), old));
+        }
+
+        let evicted = if self.map.len() == self.capacity {
+            self.pop_lru()
+        } else {
+            None
+        };
+        self.insert_front(key, value);
+        evicted
+    }
+
+    /// Returns the value of `key`, marking it as most recently used, or
+    /// inserts `f()` under an owned copy of `key`, evicting the least
+    /// recently used entry if the cache is full.
+    ///
+    /// The key is hashed once either way. Returns `None`, without calling
+    /// `f`, only if the capacity is `0`.
+    pub fn get_or_insert_with<Q, F>(&mut self, key: &Q, f: F) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash + ToOwned<Owned = K>,
+        F: FnOnce() -> V,
+    {
+        let probe = Probe::with_hasher(key, &self.hasher);
+        let index = match self.map.get(probe.as_lookup()) {
+            Some(&index) => {
+                self.touch(index);
+                index
+            }
+            None if self.capacity == 0 => return None,
+            None => {
+                if self.map.len() == self.capacity {
+                    self.pop_lru();
+                }
+                let key =
+                    HashMemo::with_hasher_const(key.to_owned(), self.hasher.clone(), probe.hash());
+                self.insert_front(key, f())
+            }
+        };
+        Some(&self.node(index).value)
+    }
+
+    /// Removes `key` and returns its entry.
+    pub fn pop<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let index = self
+            .map
+            .remove(Probe::with_hasher(key, &self.hasher).as_lookup())?;
+        Some(self.take(index))
+    }
+
+    /// Looks up `key`, hashing it with the cache's hasher.
+    fn index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.map
+            .get(Probe::with_hasher(key, &self.hasher).as_lookup())
+            .copied()
+    }
+}
+
+impl<K, V, H> MemoCache<K, V, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Removes and returns the least recently used entry.
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        let index = self.tail;
+        let node = self.nodes.get(index)?.as_ref()?;
+        self.map.remove(&*node.key as &dyn MemoLookup<K>);
+        Some(self.take(index))
+    }
+
+    /// Returns the entries from most to least recently used.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        let mut next = self.head;
+        iter::from_fn(move || {
+            let node = self.nodes.get(next)?.as_ref()?;
+            next = node.next;
+            Some((&node.key.value, &node.value))
+        })
+    }
+
+    /// Returns the number of entries.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the cache holds no entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the maximum number of entries.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.nodes.clear();
+        self.free.clear();
+        self.head = NIL;
+        self.tail = NIL;
+    }
+
+    fn node(&self, index: usize) -> &Node<K, V, H> {
+        self.nodes[index].as_ref().expect("linked node is occupied")
+    }
+
+    fn node_mut(&mut self, index: usize) -> &mut Node<K, V, H> {
+        self.nodes[index].as_mut().expect("linked node is occupied")
+    }
+
+    /// Stores a new entry at the front of the recency list and indexes it.
+    fn insert_front(&mut self, key: HashMemo<K, H>, value: V) -> usize {
+        let key = Arc::new(key);
+        let node = Node {
+            key: key.clone(),
+            value,
+            prev: NIL,
+            next: NIL,
+        };
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = Some(node);
+                index
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        };
+        self.map.insert(Slot(key), index);
+        self.link_front(index);
+        index
+    }
+
+    /// Frees the node at `index`, whose map entry is already removed.
+    fn take(&mut self, index: usize) -> (K, V) {
+        self.unlink(index);
+        let node = self.nodes[index].take().expect("linked node is occupied");
+        self.free.push(index);
+        let key = Arc::into_inner(node.key).expect("an unindexed key is no longer shared");
+        (key.into_inner(), node.value)
+    }
+
+    fn touch(&mut self, index: usize) {
+        if self.head != index {
+            self.unlink(index);
+            self.link_front(index);
+        }
+    }
+
+    fn unlink(&mut self, index: usize) {
+        let (prev, next) = {
+            let node = self.node(index);
+            (node.prev, node.next)
+        };
+        match prev {
+            NIL => self.head = next,
+            prev => self.node_mut(prev).next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            next => self.node_mut(next).prev = prev,
+        }
+    }
+
+    fn link_front(&mut self, index: usize) {
+        let head = self.head;
+        let node = self.node_mut(index);
+        node.prev = NIL;
+        node.next = head;
+        match head {
+            NIL => self.tail = index,
+            head => self.node_mut(head).prev = index,
+        }
+        self.head = index;
+    }
+}
+
+impl<K, V, H> fmt::Debug for MemoCache<K, V, H>
+where
+    K: Eq + Hash + fmt::Debug,
+    V: fmt::Debug,
+    H: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(feature = "get-size")]
+impl<K, V, H> GetSize for MemoCache<K, V, H>
+where
+    K: Eq + Hash + GetSize,
+    V: GetSize,
+    H: BuildHasher,
+{
+    fn get_heap_size(&self) -> usize {
+        let slot = mem::size_of::<Slot<K, H>>() + usize::get_stack_size();
+        let slab = self.nodes.capacity() * mem::size_of::<Option<Node<K, V, H>>>();
+        self.nodes.iter().flatten().fold(
+            self.map.capacity() * slot + slab + self.free.get_heap_size(),
+            |size, node| size + HashMemo::get_size(&node.key) + node.value.get_heap_size(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    /// Counts how often it is hashed, across all instances.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct Counted(u32);
+
+    impl Hash for Counted {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            COUNT.fetch_add(1, Ordering::Relaxed);
+            self.0.hash(state);
+        }
+    }
+
+    fn keys<K: Eq + Hash + Clone, V, H: BuildHasher>(cache: &MemoCache<K, V, H>) -> Vec<K> {
+        cache.iter().map(|(key, _)| key.clone()).collect()
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_entry() {
+        let mut cache = MemoCache::with_capacity(3);
+        for key in 1..=3u32 {
+            assert_eq!(cache.put(key, key * 10), None);
+        }
+        assert_eq!(keys(&cache), [3, 2, 1]);
+
+        assert_eq!(cache.get(&1), Some(&10));
+        assert_eq!(cache.peek(&2), Some(&20));
+        assert_eq!(keys(&cache), [1, 3, 2]);
+
+        assert_eq!(cache.put(4, 40), Some((2, 20)));
+        assert_eq!(cache.put(5, 50), Some((3, 30)));
+        assert_eq!(keys(&cache), [5, 4, 1]);
+
+        assert_eq!(cache.pop(&4), Some((4, 40)));
+        assert_eq!(cache.put(6, 60), None);
+        assert_eq!(cache.pop_lru(), Some((1, 10)));
+        assert_eq!(keys(&cache), [6, 5]);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn re_putting_a_key_replaces_it_and_refreshes_recency() {
+        let mut cache: MemoCache<String, u32> = MemoCache::with_capacity(2);
+        cache.put("a".to_string(), 1);
+        cache.put("b".to_string(), 2);
+
+        assert_eq!(cache.put("a".to_string(), 3), Some(("a".to_string(), 1)));
+        assert_eq!(cache.put("c".to_string(), 4), Some(("b".to_string(), 2)));
+        assert_eq!(cache.get("a"), Some(&3));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn capacity_one_keeps_only_the_latest_entry() {
+        let mut cache = MemoCache::with_capacity(1);
+        assert_eq!(cache.put(1, "one"), None);
+        assert_eq!(cache.put(2, "two"), Some((1, "one")));
+        assert_eq!(cache.get_or_insert_with(&3, || "three"), Some(&"three"));
+        assert_eq!(keys(&cache), [3]);
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.nodes.len(), 1);
+    }
+
+    #[test]
+    fn capacity_zero_stores_nothing() {
+        let mut cache = MemoCache::with_capacity(0);
+        assert_eq!(cache.put(1, "one"), Some((1, "one")));
+        assert_eq!(
+            cache.get_or_insert_with(&1, || unreachable!("not stored")),
+            None
+        );
+        assert!(cache.is_empty());
+        assert_eq!(cache.pop_lru(), None);
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn resident_keys_are_never_rehashed() {
+        let mut cache = MemoCache::with_capacity(16);
+        let before = COUNT.load(Ordering::Relaxed);
+
+        for i in 0..64 {
+            cache.put(Counted(i), i);
+        }
+        assert_eq!(COUNT.load(Ordering::Relaxed) - before, 64);
+
+        for i in 48..64 {
+            assert_eq!(cache.get_or_insert_with(&Counted(i), || 0), Some(&i));
+        }
+        for i in 64..80 {
+            assert_eq!(cache.get_or_insert_with(&Counted(i), || i), Some(&i));
+        }
+        assert_eq!(COUNT.load(Ordering::Relaxed) - before, 96);
+
+        while cache.pop_lru().is_some() {}
+        assert_eq!(COUNT.load(Ordering::Relaxed) - before, 96);
+    }
+}
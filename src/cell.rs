@@ -0,0 +1,417 @@
+//! The hash cache shared by every memo with a 64-bit hash.
+
+use std::cell::Cell;
+use std::num::NonZeroU64;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+use crate::AtomicU64;
+
+/// The cache of a [`HashMemo`](crate::HashMemo).
+#[cfg(not(feature = "disable-cache"))]
+pub(crate) type MemoCell = AtomicU64;
+
+/// The cache of a [`HashMemo`](crate::HashMemo), which never holds a hash
+/// under `disable-cache`.
+#[cfg(feature = "disable-cache")]
+pub(crate) type MemoCell = NoCache;
+
+/// A slot holding a memoized hash, or nothing.
+///
+/// Hashes are stored as `NonZeroU64`, so how a cell marks itself empty is
+/// its own business and callers never see the sentinel.
+/// A computed `0` is remapped to `1` by [`nonzero`] before it is stored.
+pub(crate) trait HashCell {
+    /// Returns the cached hash, if any.
+    fn load(&self) -> Option<NonZeroU64>;
+
+    /// Caches `hash`.
+    fn publish(&self, hash: NonZeroU64);
+
+    /// Empties the cell.
+    fn clear(&mut self);
+}
+
+/// Uses `0` to mark an empty cache, so the cell is one word that fits in a
+/// `static`. Loads and stores are `Relaxed`: a published hash is a pure
+/// function of data the cell's owner does not let change behind `&self`,
+/// so racing threads can only publish the same value.
+impl HashCell for AtomicU64 {
+    #[inline]
+    fn load(&self) -> Option<NonZeroU64> {
+        NonZeroU64::new(AtomicU64::load(self, Ordering::Relaxed))
+    }
+
+    #[inline]
+    fn publish(&self, hash: NonZeroU64) {
+        self.store(hash.get(), Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        *self.get_mut() = 0;
+    }
+}
+
+/// The narrow caches of [`HashMemo32`](crate::HashMemo32) and
+/// [`HashMemoUsize`](crate::HashMemoUsize), using `0` to mark an empty cache
+/// as `AtomicU64` does.
+///
+/// They hold only hashes that fit the word: callers truncate the computed
+/// hash before [`get_or_compute`] remaps it, so a zero low word is cached as
+/// `1`.
+macro_rules! narrow_cell {
+    ($atomic:ty, $word:ty) => {
+        impl HashCell for $atomic {
+            #[inline]
+            fn load(&self) -> Option<NonZeroU64> {
+                NonZeroU64::new(<$atomic>::load(self, Ordering::Relaxed) as u64)
+            }
+
+            #[inline]
+            fn publish(&self, hash: NonZeroU64) {
+                debug_assert!(hash.get() <= <$word>::MAX as u64, "untruncated hash");
+                self.store(hash.get() as $word, Ordering::Relaxed);
+            }
+
+            #[inline]
+            fn clear(&mut self) {
+                *self.get_mut() = 0;
+            }
+        }
+    };
+}
+
+narrow_cell!(AtomicU32, u32);
+narrow_cell!(AtomicUsize, usize);
+
+/// A single-threaded cache, for memos that are not shared between threads
+/// and so need no atomics.
+impl HashCell for Cell<Option<NonZeroU64>> {
+    #[inline]
+    fn load(&self) -> Option<NonZeroU64> {
+        self.get()
+    }
+
+    #[inline]
+    fn publish(&self, hash: NonZeroU64) {
+        self.set(Some(hash));
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        *self.get_mut() = None;
+    }
+}
+
+/// A cache written at most once between clears. A racing second publish is
+/// dropped, which is harmless: it can only carry the same hash.
+impl HashCell for OnceLock<NonZeroU64> {
+    #[inline]
+    fn load(&self) -> Option<NonZeroU64> {
+        self.get().copied()
+    }
+
+    #[inline]
+    fn publish(&self, hash: NonZeroU64) {
+        let _ = self.set(hash);
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.take();
+    }
+}
+
+/// A cell that is always empty, taking no space.
+///
+/// Mirrors the parts of the `AtomicU64` API that memos use, so code that
+/// reads and writes the cache compiles unchanged: loads return `0`, the
+/// empty marker, and stores are dropped.
+#[cfg(feature = "disable-cache")]
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct NoCache;
+
+#[cfg(feature = "disable-cache")]
+impl NoCache {
+    #[inline]
+    pub(crate) const fn new(_hash: u64) -> Self {
+        NoCache
+    }
+
+    #[inline]
+    pub(crate) fn load(&self, _order: Ordering) -> u64 {
+        0
+    }
+
+    #[inline]
+    pub(crate) fn store(&self, _hash: u64, _order: Ordering) {}
+
+    #[inline]
+    pub(crate) fn into_inner(self) -> u64 {
+        0
+    }
+}
+
+#[cfg(feature = "disable-cache")]
+impl HashCell for NoCache {
+    #[inline]
+    fn load(&self) -> Option<NonZeroU64> {
+        None
+    }
+
+    #[inline]
+    fn publish(&self, _hash: NonZeroU64) {}
+
+    #[inline]
+    fn clear(&mut self) {}
+}
+
+/// Returns the hash cached in `cell`, or computes it with `compute`,
+/// remapping `0` to `1`, caches it and returns it.
+#[inline]
+pub(crate) fn get_or_compute<C: HashCell>(cell: &C, compute: impl FnOnce() -> u64) -> u64 {
+    if let Some(hash) = cell.load() {
+        #[cfg(feature = "stats")]
+        crate::stats::hit();
+        return hash.get();
+    }
+
+    let hash = nonzero(compute());
+    #[cfg(feature = "stats")]
+    crate::stats::computation();
+    cell.publish(hash);
+    hash.get()
+}
+
+/// Maps a genuine `0` hash to `1`, since cells have no room for `0`.
+///
+/// Every remapping in the crate goes through here, including
+/// [`remap_zero`](crate::remap_zero).
+#[inline]
+pub(crate) const fn nonzero(hash: u64) -> NonZeroU64 {
+    match NonZeroU64::new(hash) {
+        Some(hash) => hash,
+        None => NonZeroU64::MIN,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atomic_cells_start_empty_and_clear() {
+        let mut cell = AtomicU64::new(0);
+        assert_eq!(HashCell::load(&cell), None);
+
+        cell.publish(NonZeroU64::new(42).unwrap());
+        assert_eq!(HashCell::load(&cell), NonZeroU64::new(42));
+
+        cell.clear();
+        assert_eq!(HashCell::load(&cell), None);
+        assert_eq!(cell.into_inner(), 0);
+    }
+
+    #[test]
+    fn get_or_compute_remaps_zero_and_computes_once() {
+        let computed = Cell::new(0);
+        let compute = |hash| {
+            computed.set(computed.get() + 1);
+            hash
+        };
+
+        let cell = AtomicU64::new(0);
+        assert_eq!(get_or_compute(&cell, || compute(0)), 1, "zero is remapped");
+        assert_eq!(get_or_compute(&cell, || compute(7)), 1, "the cache is warm");
+        assert_eq!(computed.get(), 1);
+
+        let cell = AtomicU64::new(0);
+        assert_eq!(get_or_compute(&cell, || compute(u64::MAX)), u64::MAX);
+        assert_eq!(HashCell::load(&cell), NonZeroU64::new(u64::MAX));
+        assert_eq!(nonzero(0), NonZeroU64::MIN);
+    }
+
+    /// Runs an empty `C` through a cold computation of `0`, a warm hit, a
+    /// clear and a recomputation.
+    fn caches_remaps_and_clears<C: HashCell + Default>() {
+        let mut cell = C::default();
+        assert_eq!(cell.load(), None);
+
+        assert_eq!(get_or_compute(&cell, || 0), 1, "zero is remapped");
+        assert_eq!(cell.load(), Some(NonZeroU64::MIN));
+        assert_eq!(get_or_compute(&cell, || unreachable!()), 1);
+
+        cell.clear();
+        assert_eq!(cell.load(), None);
+        assert_eq!(get_or_compute(&cell, || 42), 42);
+        assert_eq!(cell.load(), NonZeroU64::new(42));
+    }
+
+    #[test]
+    fn unsync_cells_cache_remap_and_clear() {
+        caches_remaps_and_clears::<Cell<Option<NonZeroU64>>>();
+    }
+
+    #[test]
+    fn once_cells_cache_remap_and_clear() {
+        caches_remaps_and_clears::<OnceLock<NonZeroU64>>();
+
+        let cell = OnceLock::new();
+        cell.publish(NonZeroU64::new(7).unwrap());
+        cell.publish(NonZeroU64::new(8).unwrap());
+        assert_eq!(cell.load(), NonZeroU64::new(7), "the first publish wins");
+    }
+
+    #[test]
+    fn narrow_cells_cache_remap_and_clear() {
+        caches_remaps_and_clears::<AtomicU32>();
+        caches_remaps_and_clears::<AtomicUsize>();
+
+        let cell = AtomicU32::new(0);
+        assert_eq!(get_or_compute(&cell, || u32::MAX as u64), u32::MAX as u64);
+        assert_eq!(cell.into_inner(), u32::MAX);
+    }
+}
+
+/// Kani proofs of the cell protocol, over every `u64` a hasher can return
+/// and every word a cell can hold.
+///
+/// Loom, in `tests/loom.rs`, covers the interleavings of racing threads;
+/// these harnesses cover the values. The cell harnesses go through
+/// [`HashCell`] and [`get_or_compute`] only and are generic over the cell,
+/// so a new cell type is checked by instantiating them for it. Run with
+///
+/// ```text
+/// cargo install --locked kani-verifier && cargo kani setup
+/// cargo kani --lib
+/// ```
+///
+/// or pick one with `--harness`. The memo harnesses assume a cache, so
+/// they are not built under `disable-cache`.
+#[cfg(kani)]
+mod proofs {
+    use super::*;
+
+    /// Computes a nondeterministic hash into an empty `C`.
+    fn cold_computation_is_cached_nonzero<C: HashCell + Default>() {
+        let hash: u64 = kani::any();
+        let cell = C::default();
+        assert!(cell.load().is_none());
+
+        let served = get_or_compute(&cell, || hash);
+        assert_ne!(served, 0);
+        assert!(served == hash || (hash == 0 && served == 1));
+        assert_eq!(cell.load(), NonZeroU64::new(served));
+    }
+
+    /// The operations [`cell_follows_its_model`] performs.
+    const STEPS: usize = 4;
+
+    /// Runs `C` through a bounded sequence of nondeterministic operations,
+    /// checking every load against a model of what it should hold.
+    fn cell_follows_its_model<C: HashCell + Default>() {
+        let mut cell = C::default();
+        let mut model: Option<NonZeroU64> = None;
+        for _ in 0..STEPS {
+            let hash: u64 = kani::any();
+            match kani::any::<u8>() % 3 {
+                0 => {
+                    let served = get_or_compute(&cell, || hash);
+                    let expected = model.unwrap_or(nonzero(hash));
+                    assert_eq!(served, expected.get());
+                    model = Some(expected);
+                }
+                1 => {
+                    cell.clear();
+                    model = None;
+                }
+                _ => {
+                    cell.publish(nonzero(hash));
+                    model = Some(nonzero(hash));
+                }
+            }
+            assert_eq!(cell.load(), model);
+        }
+    }
+
+    #[kani::proof]
+    fn nonzero_only_remaps_zero() {
+        let hash: u64 = kani::any();
+        assert_ne!(nonzero(hash).get(), 0);
+        assert_eq!(nonzero(hash).get() == hash, hash != 0);
+        assert_eq!(crate::remap_zero(hash), nonzero(hash).get());
+    }
+
+    #[kani::proof]
+    fn atomic_loads_never_report_the_sentinel() {
+        let word: u64 = kani::any();
+        let cell = AtomicU64::new(word);
+        match HashCell::load(&cell) {
+            Some(hash) => assert_eq!(hash.get(), word),
+            None => assert_eq!(word, 0),
+        }
+    }
+
+    #[kani::proof]
+    fn atomic_cold_computation_is_cached_nonzero() {
+        cold_computation_is_cached_nonzero::<AtomicU64>();
+    }
+
+    #[kani::proof]
+    #[kani::unwind(5)]
+    fn atomic_cell_follows_its_model() {
+        cell_follows_its_model::<AtomicU64>();
+    }
+
+    #[kani::proof]
+    fn unsync_cold_computation_is_cached_nonzero() {
+        cold_computation_is_cached_nonzero::<Cell<Option<NonZeroU64>>>();
+    }
+
+    #[kani::proof]
+    #[kani::unwind(5)]
+    fn unsync_cell_follows_its_model() {
+        cell_follows_its_model::<Cell<Option<NonZeroU64>>>();
+    }
+
+    // A full `OnceLock` drops later publishes, so it does not follow the
+    // overwriting model.
+    #[kani::proof]
+    fn once_cold_computation_is_cached_nonzero() {
+        cold_computation_is_cached_nonzero::<OnceLock<NonZeroU64>>();
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[kani::proof]
+    fn memo_caches_every_hash_but_zero_as_is() {
+        use crate::{HashMemo, PassthroughState};
+
+        // `PassthroughState` returns the single `u64` written, so the memo
+        // sees every possible hash output.
+        let value: u64 = kani::any();
+        let memo = HashMemo::with_hasher(value, PassthroughState);
+        assert_eq!(memo.memoized_hash(), crate::remap_zero(value));
+        assert_eq!(memo.hash.load(Ordering::Relaxed), crate::remap_zero(value));
+        assert_eq!(memo.is_zero_remapped(), value == 0);
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[kani::proof]
+    fn seeded_memos_serve_their_seed() {
+        use std::hash::BuildHasher;
+
+        use crate::{FixedState, HashMemo};
+
+        let seed: u64 = kani::any();
+        let value: u8 = kani::any();
+        let memo = HashMemo::with_hasher_const(value, FixedState, seed);
+        assert_eq!(memo.memoized_hash(), crate::remap_zero(seed));
+
+        memo.forget_hash();
+        assert_eq!(
+            memo.memoized_hash(),
+            crate::remap_zero(FixedState.hash_one(value))
+        );
+    }
+}
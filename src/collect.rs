@@ -0,0 +1,164 @@
+//! Collecting iterators of values into containers of memos.
+
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+
+use crate::HashMemo;
+
+/// Wraps the items of an iterator in [`HashMemo`]s while collecting them.
+///
+/// `collect` cannot wrap items on its own: a `HashSet<HashMemo<String>>`
+/// implements `FromIterator<HashMemo<String>>`, not `FromIterator<String>`,
+/// and a newtype collection implementing the latter would not be the
+/// container asked for. [`collect_memos`](Self::collect_memos) instead
+/// names the container the way `collect` does and wraps each item in a
+/// cold memo on its way in.
+///
+/// It is not called `collect_into` so as not to collide with the unstable
+/// `Iterator::collect_into`, which extends an existing collection.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{HashMemo, MemoizeExt};
+/// use std::collections::HashSet;
+///
+/// let raw = vec!["a".to_string(), "b".to_string(), "a".to_string()];
+/// let set: HashSet<HashMemo<String>> = raw.into_iter().collect_memos();
+/// assert_eq!(set.len(), 2);
+/// ```
+pub trait MemoizeExt: Iterator + Sized
+where
+    Self::Item: Eq + Hash,
+{
+    /// Wraps every item in a [`HashMemo`] with the default hasher and
+    /// collects the memos into `C`.
+    fn collect_memos<C>(self) -> C
+    where
+        C: FromIterator<HashMemo<Self::Item>>,
+    {
+        self.map(HashMemo::new).collect()
+    }
+
+    /// Like [`collect_memos`](Self::collect_memos), with every memo hashing
+    /// with a clone of `hasher`.
+    fn collect_memos_with<C, H>(self, hasher: H) -> C
+    where
+        H: BuildHasher + Clone,
+        C: FromIterator<HashMemo<Self::Item, H>>,
+    {
+        self.map(|item| HashMemo::with_hasher(item, hasher.clone()))
+            .collect()
+    }
+}
+
+impl<I> MemoizeExt for I
+where
+    I: Iterator,
+    I::Item: Eq + Hash,
+{
+}
+
+/// Builds a map keyed by memos of the keys of `entries`, each hashed once,
+/// here.
+///
+/// Every key is wrapped in a [`HashMemo`] and
+/// [`precompute`](HashMemo::precompute)d before it is inserted, so building
+/// the map is the only time the keys are hashed: growing the map and
+/// looking keys up reuse the cached hashes. As with
+/// `HashMap::insert`, a repeated key keeps its first memo and its last
+/// value.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{prehash_map, HashMemo};
+/// use std::collections::HashMap;
+/// use std::hash::RandomState;
+///
+/// let map: HashMap<HashMemo<String>, u32, RandomState> =
+///     prehash_map([("a".to_string(), 1), ("b".to_string(), 2)]);
+/// assert_eq!(map[&HashMemo::new("b".to_string())], 2);
+/// ```
+pub fn prehash_map<K, V, S>(entries: impl IntoIterator<Item = (K, V)>) -> HashMap<HashMemo<K>, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    let entries = entries.into_iter();
+    let mut map = HashMap::with_capacity_and_hasher(entries.size_hint().0, S::default());
+    for (key, value) in entries {
+        let key = HashMemo::new(key);
+        key.precompute();
+        map.insert(key, value);
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::hash::RandomState;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{unwrap_vec, FixedState};
+
+    #[test]
+    fn collects_into_a_vec_in_order() {
+        let values: Vec<String> = (0..10).map(|i| i.to_string()).collect();
+        let memos: Vec<HashMemo<String>> = values.iter().cloned().collect_memos();
+        assert_eq!(unwrap_vec(memos), values);
+    }
+
+    #[test]
+    fn collects_into_a_hash_set() {
+        let set: HashSet<HashMemo<&str>> = ["a", "b", "a", "c"].into_iter().collect_memos();
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(&HashMemo::new("b")));
+
+        let fixed: HashSet<HashMemo<&str, FixedState>> =
+            ["a", "b"].into_iter().collect_memos_with(FixedState);
+        assert!(fixed.contains(&HashMemo::new_fixed("a")));
+    }
+
+    /// Counts how often it is hashed, across all instances.
+    #[derive(PartialEq, Eq)]
+    struct Counted(u32);
+
+    static HASHED: AtomicUsize = AtomicUsize::new(0);
+
+    impl Hash for Counted {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            HASHED.fetch_add(1, Ordering::Relaxed);
+            self.0.hash(state);
+        }
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn prehash_map_hashes_each_key_once() {
+        let map: HashMap<HashMemo<Counted>, u32, RandomState> =
+            prehash_map((0..1000).map(|i| (Counted(i), i * 2)));
+        assert_eq!(map.len(), 1000);
+        assert_eq!(
+            HASHED.load(Ordering::Relaxed),
+            1000,
+            "once each, growth included"
+        );
+
+        let probes: Vec<_> = (0..1000).map(|i| HashMemo::new(Counted(i))).collect();
+        probes.iter().for_each(HashMemo::precompute);
+        let before = HASHED.load(Ordering::Relaxed);
+        for _ in 0..3 {
+            for (i, probe) in (0..).zip(&probes) {
+                assert_eq!(map[probe], i * 2);
+            }
+        }
+        assert_eq!(
+            HASHED.load(Ordering::Relaxed),
+            before,
+            "lookups reuse both caches"
+        );
+    }
+}
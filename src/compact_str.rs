@@ -0,0 +1,80 @@
+//! [`compact_str`](::compact_str) support for small-string keys.
+//!
+//! `CompactString` hashes exactly like the `str` it holds, so a
+//! [`MemoCompactString`] has the same memoized hash as a `HashMemo<String>`
+//! or `HashMemo<&str>` with the same text and the same hasher. Maps keyed by
+//! it can be probed with a `&str` through [`Probe`](crate::Probe).
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::{MemoCompactString, Probe};
+//! use std::collections::HashMap;
+//!
+//! let mut map = HashMap::new();
+//! map.insert(MemoCompactString::from("short"), 1);
+//! assert_eq!(map.get(Probe::new("short").as_lookup()), Some(&1));
+//! ```
+
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher};
+
+use ::compact_str::CompactString;
+
+use crate::HashMemo;
+
+/// A [`HashMemo`] over a `compact_str::CompactString`.
+pub type MemoCompactString<H = BuildHasherDefault<DefaultHasher>> = HashMemo<CompactString, H>;
+
+impl From<&str> for HashMemo<CompactString> {
+    fn from(value: &str) -> Self {
+        Self::new(CompactString::from(value))
+    }
+}
+
+impl From<String> for HashMemo<CompactString> {
+    fn from(value: String) -> Self {
+        Self::new(CompactString::from(value))
+    }
+}
+
+impl<H> AsRef<str> for HashMemo<CompactString, H>
+where
+    H: BuildHasher,
+{
+    fn as_ref(&self) -> &str {
+        &self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::Probe;
+
+    #[test]
+    fn hashes_like_string_memos_with_the_same_text() {
+        for text in ["inline", "a key long enough to spill onto the heap"] {
+            let compact = MemoCompactString::from(text);
+            assert_eq!(
+                compact.memoized_hash(),
+                HashMemo::new(text.to_string()).memoized_hash()
+            );
+            assert_eq!(compact.memoized_hash(), HashMemo::new(text).memoized_hash());
+            assert_eq!(AsRef::<str>::as_ref(&compact), text);
+            assert_eq!(compact.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn lookups_by_str() {
+        let mut set = HashSet::new();
+        set.insert(MemoCompactString::from("inline"));
+        set.insert(MemoCompactString::from("x".repeat(64)));
+
+        assert!(set.contains(Probe::new("inline").as_lookup()));
+        assert!(set.contains(Probe::new("x".repeat(64).as_str()).as_lookup()));
+        assert!(!set.contains(Probe::new("missing").as_lookup()));
+    }
+}
@@ -0,0 +1,70 @@
+//! Hashing while counting the bytes fed to the hasher, an approximation of
+//! the size of the value, for the `tracing` and `hook` features.
+
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use crate::remap_zero;
+
+/// Hashes `value` like `hasher.hash_one(value)`, remapping `0` to `1`, and
+/// returns the hash with the number of bytes written to the hasher.
+pub(crate) fn hash_counted<T, H>(hasher: &H, value: &T) -> (u64, usize)
+where
+    T: Hash + ?Sized,
+    H: BuildHasher,
+{
+    let mut state = CountingHasher {
+        inner: hasher.build_hasher(),
+        bytes: 0,
+    };
+    value.hash(&mut state);
+    (remap_zero(state.finish()), state.bytes)
+}
+
+/// Forwards every write to the inner hasher, counting the bytes written.
+///
+/// Every method is forwarded as is, so hashers that treat integers apart
+/// from byte slices produce the same hash as through `hash_one`.
+struct CountingHasher<S> {
+    inner: S,
+    bytes: usize,
+}
+
+macro_rules! forward_writes {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            #[inline]
+            fn $method(&mut self, i: $ty) {
+                self.bytes += std::mem::size_of::<$ty>();
+                self.inner.$method(i);
+            }
+        )*
+    };
+}
+
+impl<S: Hasher> Hasher for CountingHasher<S> {
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.inner.finish()
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        self.bytes += bytes.len();
+        self.inner.write(bytes);
+    }
+
+    forward_writes!(
+        write_u8(u8),
+        write_u16(u16),
+        write_u32(u32),
+        write_u64(u64),
+        write_u128(u128),
+        write_usize(usize),
+        write_i8(i8),
+        write_i16(i16),
+        write_i32(i32),
+        write_i64(i64),
+        write_i128(i128),
+        write_isize(isize),
+    );
+}
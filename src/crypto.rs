@@ -0,0 +1,264 @@
+//! [`blake3`](::blake3) support, for cache keys authenticated with a keyed
+//! MAC.
+//!
+//! A [`HashMemo`] fingerprint is a 64-bit hash that anyone who knows the
+//! hasher can compute, so keys derived from user-controlled data and written
+//! to a shared store can be predicted or forged across tenants.
+//! [`MacMemo`] instead memoizes a 32-byte keyed BLAKE3 tag over the value,
+//! which cannot be computed or verified without the 32-byte secret key.
+//!
+//! The tag covers the stream of bytes the value's `Hash` impl writes, fed
+//! portably: `write` passes its bytes through unchanged, integer writes feed
+//! their little-endian bytes, and `usize` and `isize` are widened to 64 bits
+//! first, as with the `stable` feature's hasher. A `str` is its bytes then
+//! `0xff`, and a `Vec<u8>` its length then its bytes, so the tag of a value
+//! is `blake3::keyed_hash(key, stream)` on every platform. As a map key, a
+//! `MacMemo` hashes as the first 8 bytes of its tag, little-endian.
+//!
+//! With the `zeroize` feature, the key copy a memo holds is zeroized when
+//! it is dropped.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::MacMemo;
+//!
+//! let key = [7u8; 32];
+//! let memo = MacMemo::with_key("tenant-42/report", &key);
+//! let tag = *memo.tag();
+//! assert!(MacMemo::with_key("tenant-42/report", &key).verify_tag(&tag));
+//! assert!(!MacMemo::with_key("tenant-43/report", &key).verify_tag(&tag));
+//! ```
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::OnceLock;
+
+/// A value with a memoized keyed BLAKE3 tag.
+///
+/// Two memos are equal if their keys and values are equal, so equal memos
+/// always carry equal tags. The tag is computed on first use and cached.
+pub struct MacMemo<T>
+where
+    T: Eq + Hash,
+{
+    value: T,
+    key: MacKey,
+    tag: OnceLock<[u8; 32]>,
+}
+
+/// The key of a [`MacMemo`], zeroized on drop with the `zeroize` feature.
+#[derive(Clone, PartialEq, Eq)]
+struct MacKey([u8; 32]);
+
+#[cfg(feature = "zeroize")]
+impl Drop for MacKey {
+    fn drop(&mut self) {
+        ::zeroize::Zeroize::zeroize(&mut self.0);
+    }
+}
+
+/// Feeds a value's `Hash` stream, portably, into a keyed BLAKE3 hasher.
+struct TagSink(::blake3::Hasher);
+
+impl Hasher for TagSink {
+    fn finish(&self) -> u64 {
+        let tag = self.0.finalize();
+        u64::from_le_bytes(tag.as_bytes()[..8].try_into().unwrap())
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.0.update(&[i]);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.0.update(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.0.update(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.0.update(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.0.update(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as i64 as u64);
+    }
+}
+
+impl<T> MacMemo<T>
+where
+    T: Eq + Hash,
+{
+    /// Creates a `MacMemo` tagging `value` under `key`.
+    ///
+    /// The memo keeps its own copy of the key.
+    #[inline]
+    pub fn with_key(value: T, key: &[u8; 32]) -> Self {
+        Self {
+            value,
+            key: MacKey(*key),
+            tag: OnceLock::new(),
+        }
+    }
+
+    /// Returns the keyed BLAKE3 tag of the value, computing and caching it
+    /// on first use.
+    pub fn tag(&self) -> &[u8; 32] {
+        self.tag.get_or_init(|| {
+            let mut sink = TagSink(::blake3::Hasher::new_keyed(&self.key.0));
+            self.value.hash(&mut sink);
+            *sink.0.finalize().as_bytes()
+        })
+    }
+
+    /// Returns `true` if `tag` is the tag of the value, comparing in
+    /// constant time.
+    pub fn verify_tag(&self, tag: &[u8; 32]) -> bool {
+        ::blake3::Hash::from_bytes(*self.tag()) == *tag
+    }
+
+    /// Returns the value, dropping the key and the tag.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> AsRef<T> for MacMemo<T>
+where
+    T: Eq + Hash,
+{
+    #[inline]
+    fn as_ref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> Hash for MacMemo<T>
+where
+    T: Eq + Hash,
+{
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let prefix = self.tag()[..8].try_into().unwrap();
+        state.write_u64(u64::from_le_bytes(prefix));
+    }
+}
+
+impl<T> PartialEq for MacMemo<T>
+where
+    T: Eq + Hash,
+{
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key && self.value == other.value
+    }
+}
+
+impl<T> Eq for MacMemo<T> where T: Eq + Hash {}
+
+impl<T> Clone for MacMemo<T>
+where
+    T: Eq + Hash + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            key: self.key.clone(),
+            tag: self.tag.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for MacMemo<T>
+where
+    T: Eq + Hash + fmt::Debug,
+{
+    /// Shows the value and whether the tag is cached, never the key.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MacMemo")
+            .field("value", &self.value)
+            .field("tagged", &self.tag.get().is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    /// The key of the official BLAKE3 test vectors.
+    const KEY: &[u8; 32] = b"whats the Elvish word for friend";
+
+    #[test]
+    fn matches_the_reference_keyed_hash() {
+        // `()` writes nothing, so its tag is the keyed hash of no input,
+        // the `input_len: 0` entry of the official test vectors.
+        assert_eq!(
+            MacMemo::with_key((), KEY).tag(),
+            &hex("92b2b75604ed3c761f9d6f62392c8a9227ad0ea3f09573e783f1498a4ed60d26")
+        );
+
+        let memo = MacMemo::with_key("tenant", KEY);
+        assert_eq!(
+            memo.tag(),
+            ::blake3::keyed_hash(KEY, b"tenant\xff").as_bytes()
+        );
+
+        let memo = MacMemo::with_key(vec![1u8, 2, 3], KEY);
+        let stream = [3, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3];
+        assert_eq!(memo.tag(), ::blake3::keyed_hash(KEY, &stream).as_bytes());
+
+        let memo = MacMemo::with_key(0x0102_0304u32, KEY);
+        let stream = [0x04, 0x03, 0x02, 0x01];
+        assert_eq!(memo.tag(), ::blake3::keyed_hash(KEY, &stream).as_bytes());
+    }
+
+    #[test]
+    fn tags_depend_on_the_key_and_verify() {
+        let memo = MacMemo::with_key("tenant", KEY);
+        let other = MacMemo::with_key("tenant", &[0; 32]);
+        assert_ne!(memo.tag(), other.tag());
+        assert_ne!(memo, other);
+
+        let tag = *memo.tag();
+        assert!(memo.verify_tag(&tag));
+        assert!(!other.verify_tag(&tag));
+        let mut forged = tag;
+        forged[31] ^= 1;
+        assert!(!memo.verify_tag(&forged));
+    }
+
+    #[test]
+    fn works_as_a_set_key() {
+        let set: HashSet<_> = (0..100).map(|i| MacMemo::with_key(i, KEY)).collect();
+        assert!(set.contains(&MacMemo::with_key(42, KEY)));
+        assert!(!set.contains(&MacMemo::with_key(42, &[0; 32])));
+        assert!(format!("{:?}", MacMemo::with_key(1, KEY)).contains("tagged: false"));
+    }
+
+    fn hex(s: &str) -> [u8; 32] {
+        let mut out = [0; 32];
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).unwrap();
+        }
+        out
+    }
+}
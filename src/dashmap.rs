@@ -0,0 +1,94 @@
+//! [`dashmap`](::dashmap) interop for concurrent maps keyed by memoized values.
+//!
+//! `DashMap` looks keys up through `Borrow`, like `std`'s `HashMap`, so a
+//! plain `&str` does not find a `HashMemo<String>` key. Probe with a
+//! [`Probe`](crate::Probe) instead; it hashes exactly like the stored memo:
+//!
+//! ```rust
+//! use hashmemo::{HashMemo, MemoDashMap, Probe};
+//!
+//! let map: MemoDashMap<String, u32> = MemoDashMap::default();
+//! map.insert(HashMemo::new("k".to_string()), 1);
+//!
+//! assert_eq!(*map.get(Probe::new("k").as_lookup()).unwrap(), 1);
+//! assert_eq!(map.remove(Probe::new("k").as_lookup()).map(|(_, v)| v), Some(1));
+//! ```
+
+use std::hash::{BuildHasherDefault, DefaultHasher};
+
+use ::dashmap::DashMap;
+
+use crate::{HashMemo, PassthroughState};
+
+/// A `DashMap` keyed by [`HashMemo`], using the memoized hash directly for
+/// both shard selection and bucket placement.
+///
+/// `H` is the hasher of the keys; lookups must use a
+/// [`Probe`](crate::Probe) built with the same hasher.
+pub type MemoDashMap<K, V, H = BuildHasherDefault<DefaultHasher>> =
+    DashMap<HashMemo<K, H>, V, PassthroughState>;
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::{FixedState, Probe};
+
+    #[test]
+    fn borrowed_lookups_find_memoized_keys() {
+        let map: MemoDashMap<String, u32> = MemoDashMap::default();
+        map.insert(HashMemo::new("alpha".to_string()), 1);
+        map.insert(HashMemo::new("beta".to_string()), 2);
+
+        assert_eq!(*map.get(Probe::new("alpha").as_lookup()).unwrap(), 1);
+        assert!(map.get(Probe::new("gamma").as_lookup()).is_none());
+        *map.get_mut(Probe::new("beta").as_lookup()).unwrap() += 10;
+        assert_eq!(*map.get(Probe::new("beta").as_lookup()).unwrap(), 12);
+        assert!(map.remove(Probe::new("alpha").as_lookup()).is_some());
+        assert!(!map.contains_key(Probe::new("alpha").as_lookup()));
+    }
+
+    #[test]
+    fn borrowed_lookups_with_a_custom_hasher() {
+        let map: MemoDashMap<Vec<u8>, u32, FixedState> = MemoDashMap::default();
+        map.insert(HashMemo::new_fixed(vec![1, 2, 3]), 7);
+
+        let probe = Probe::with_hasher(&[1u8, 2, 3][..], &FixedState);
+        assert_eq!(*map.get(probe.as_lookup()).unwrap(), 7);
+    }
+
+    #[test]
+    fn concurrent_insert_get_remove() {
+        const THREADS: usize = 8;
+        const KEYS: usize = 500;
+
+        let map: MemoDashMap<String, usize> = MemoDashMap::default();
+        thread::scope(|s| {
+            for t in 0..THREADS {
+                let map = &map;
+                s.spawn(move || {
+                    for i in 0..KEYS {
+                        let key = format!("{t}-{i}");
+                        map.insert(HashMemo::new(key.clone()), i);
+                        assert_eq!(*map.get(Probe::new(key.as_str()).as_lookup()).unwrap(), i);
+                    }
+                    for i in (0..KEYS).step_by(2) {
+                        let key = format!("{t}-{i}");
+                        let removed = map.remove(Probe::new(key.as_str()).as_lookup());
+                        assert_eq!(removed.map(|(_, v)| v), Some(i));
+                    }
+                });
+            }
+        });
+
+        assert_eq!(map.len(), THREADS * KEYS / 2);
+        for t in 0..THREADS {
+            for i in 0..KEYS {
+                let key = format!("{t}-{i}");
+                let found = map.get(Probe::new(key.as_str()).as_lookup()).map(|v| *v);
+                assert_eq!(found, (i % 2 == 1).then_some(i));
+            }
+        }
+    }
+}
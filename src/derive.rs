@@ -0,0 +1,263 @@
+//! Hash caches embedded in structs, for use with `#[derive(HashMemoize)]`.
+
+use std::fmt;
+use std::num::NonZeroU64;
+use std::sync::atomic::Ordering;
+
+use crate::cell::{self, HashCell};
+use crate::AtomicU64;
+
+/// Derives a memoizing `Hash` for a struct that embeds a [`HashCache`].
+///
+/// Wrapping a struct in [`HashMemo`](crate::HashMemo) changes its type
+/// everywhere it is used; the derive keeps the type and embeds the cache
+/// instead. A derive cannot add fields, so the struct declares the cache
+/// itself and marks it `#[hashmemo(cache)]`. The derive then generates:
+///
+/// - `Hash`, hashing the other fields in order with the default hasher on
+///   first use and writing the cached `u64` afterwards, exactly like
+///   `HashMemo` over a tuple of those fields
+/// - `PartialEq`, `Eq` and `Debug` over every field but the cache
+/// - for each field `x`, `set_x` and `x_mut` with the field's visibility,
+///   which clear the cache before the field can change
+///
+/// Field attributes:
+///
+/// - `#[hashmemo(cache)]`: the [`HashCache`] field, exactly one is required
+/// - `#[hashmemo(skip)]`: leave the field out of the hash; it still takes
+///   part in equality, and its setters do not clear the cache
+/// - `#[hashmemo(no_setter)]`: do not generate `set_x` and `x_mut`
+///
+/// Constructors initialize the cache with [`HashCache::new`], which is
+/// `const`, or through `#[derive(Default)]`. Fields assigned directly,
+/// rather than through the setters, leave a stale hash behind; keep them
+/// private where that matters.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{HashCache, HashMemoize};
+/// use std::collections::HashSet;
+///
+/// #[derive(Clone, Default, HashMemoize)]
+/// struct Document {
+///     title: String,
+///     body: Vec<u8>,
+///     #[hashmemo(skip)]
+///     views: u64,
+///     #[hashmemo(cache)]
+///     cache: HashCache,
+/// }
+///
+/// let mut doc = Document::default();
+/// doc.set_body(vec![0; 1 << 20]);
+///
+/// let mut seen = HashSet::new();
+/// seen.insert(doc.clone());
+/// assert!(seen.contains(&doc));
+/// ```
+pub use hashmemo_derive::HashMemoize;
+
+/// The hash cache embedded in structs deriving [`HashMemoize`].
+///
+/// Like the cache inside [`HashMemo`](crate::HashMemo) it uses `0` to mark
+/// an empty cache and reports a genuine `0` hash as `1`. Cloning copies the
+/// cached hash, and it never takes part in equality or `Debug` output of
+/// the derived impls.
+#[derive(Default)]
+pub struct HashCache {
+    hash: AtomicU64,
+}
+
+impl HashCache {
+    /// Creates an empty cache.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            hash: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the cached hash, or computes it with `f`, caches it and
+    /// returns it.
+    #[inline]
+    pub fn get_or_insert_with(&self, f: impl FnOnce() -> u64) -> u64 {
+        cell::get_or_compute(&self.hash, f)
+    }
+
+    /// Returns the cached hash, if any.
+    #[inline]
+    pub fn get(&self) -> Option<u64> {
+        HashCell::load(&self.hash).map(NonZeroU64::get)
+    }
+
+    /// Empties the cache, so the next hash is computed afresh.
+    #[inline]
+    pub fn invalidate(&mut self) {
+        #[cfg(feature = "stats")]
+        crate::stats::invalidation();
+        self.hash.clear();
+    }
+}
+
+impl Clone for HashCache {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            hash: AtomicU64::new(self.hash.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl fmt::Debug for HashCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("HashCache").field(&self.get()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher, RandomState};
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+    use crate::HashMemo;
+
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    /// Counts how often it is hashed, across all instances.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct Counted(Vec<u8>);
+
+    impl Hash for Counted {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            COUNT.fetch_add(1, Ordering::Relaxed);
+            self.0.hash(state);
+        }
+    }
+
+    #[derive(Clone, Default, HashMemoize)]
+    struct Record {
+        name: String,
+        tags: Vec<u32>,
+        #[hashmemo(skip)]
+        hits: u64,
+        #[hashmemo(cache)]
+        cache: HashCache,
+    }
+
+    impl Record {
+        fn new(name: &str, tags: Vec<u32>) -> Self {
+            Self {
+                name: name.to_string(),
+                tags,
+                hits: 0,
+                cache: HashCache::new(),
+            }
+        }
+    }
+
+    #[derive(HashMemoize)]
+    struct Blob {
+        data: Counted,
+        #[hashmemo(cache)]
+        cache: HashCache,
+    }
+
+    #[derive(Clone, HashMemoize)]
+    struct Pair<A, B> {
+        a: A,
+        #[hashmemo(no_setter)]
+        b: B,
+        #[hashmemo(cache)]
+        cache: HashCache,
+    }
+
+    fn hash<T: Hash>(value: &T) -> u64 {
+        BuildHasherDefault::<DefaultHasher>::default().hash_one(value)
+    }
+
+    #[test]
+    fn hashes_like_a_memo_of_the_hashed_fields() {
+        let record = Record::new("a", vec![1, 2]);
+        let memo = HashMemo::new(("a".to_string(), vec![1u32, 2]));
+        assert_eq!(hash(&record), hash(&memo));
+        assert_eq!(record.cache.get(), Some(memo.memoized_hash()));
+    }
+
+    #[test]
+    fn hash_is_cached_and_only_calculated_once() {
+        let blob = Blob {
+            data: Counted(vec![7; 4096]),
+            cache: HashCache::new(),
+        };
+        let before = COUNT.load(Ordering::Relaxed);
+        for _ in 0..10 {
+            hash(&blob);
+        }
+        assert_eq!(COUNT.load(Ordering::Relaxed) - before, 1);
+    }
+
+    #[test]
+    fn hash_is_stable_after_clone() {
+        let record = Record::new("a", vec![1]);
+        let state = RandomState::new();
+        let hash = state.hash_one(&record);
+        let clone = record.clone();
+        assert_eq!(clone.cache.get(), record.cache.get());
+        assert_eq!(state.hash_one(&clone), hash);
+    }
+
+    #[test]
+    fn equality_and_debug_ignore_the_cache() {
+        let warm = Record::new("a", vec![1]);
+        hash(&warm);
+        let cold = Record::new("a", vec![1]);
+        assert!(warm == cold);
+        assert_eq!(format!("{warm:?}"), format!("{cold:?}"));
+        assert_eq!(
+            format!("{warm:?}"),
+            r#"Record { name: "a", tags: [1], hits: 0 }"#
+        );
+    }
+
+    #[test]
+    fn setters_invalidate_hashed_fields_only() {
+        let mut record = Record::new("a", vec![1]);
+        let before = hash(&record);
+
+        record.set_hits(10);
+        assert!(
+            record.cache.get().is_some(),
+            "skipped field keeps the cache"
+        );
+        assert_eq!(hash(&record), before);
+
+        record.tags_mut().push(2);
+        assert_eq!(record.cache.get(), None);
+        assert_eq!(hash(&record), hash(&Record::new("a", vec![1, 2])));
+
+        record.set_name("b".to_string());
+        assert_eq!(hash(&record), hash(&Record::new("b", vec![1, 2])));
+    }
+
+    #[test]
+    fn generic_structs_work_as_map_keys() {
+        let key = |a: &str, b: u8| Pair {
+            a: a.to_string(),
+            b,
+            cache: HashCache::new(),
+        };
+        let mut map = HashMap::new();
+        map.insert(key("x", 1), 1);
+        map.insert(key("y", 2), 2);
+        assert_eq!(map.get(&key("x", 1)), Some(&1));
+        assert_eq!(map.get(&key("x", 2)), None);
+
+        let mut pair = key("x", 1);
+        pair.set_a("y".to_string());
+        pair.b = 2;
+        assert_eq!(map.get(&pair), Some(&2));
+    }
+}
@@ -0,0 +1,525 @@
+//! Collision diagnostics for memoized hashes.
+//!
+//! Maps keyed by memos see only the memoized `u64`, so a weak inner hasher
+//! shows up as long probe sequences with no obvious culprit.
+//! [`find_collisions`] audits a set of memos for distinct values sharing a
+//! hash. A hook installed with [`set_collision_hook`] reports the
+//! collisions maps run into while probing: two memos compared unequal
+//! although both carry the same cached hash. [`hash_quality`] measures how
+//! evenly a collection's hashes spread, to tell a poorly mixing `Hash`
+//! impl or hasher from bad luck.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::{diagnostics, HashMemo};
+//!
+//! let memos: Vec<_> = (0..100).map(HashMemo::new).collect();
+//! for group in diagnostics::find_collisions(&memos) {
+//!     eprintln!("{:#018x} is shared by {:?}", group.hash, group.values);
+//! }
+//! ```
+
+use std::any::type_name;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::sync::atomic::Ordering;
+use std::sync::RwLock;
+
+use crate::HashMemo;
+
+/// Distinct values whose memoized hashes are equal, found by
+/// [`find_collisions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollisionGroup<'a, T> {
+    /// The hash the values share.
+    pub hash: u64,
+    /// The colliding values, pairwise unequal, in the order first seen.
+    pub values: Vec<&'a T>,
+}
+
+/// Groups the values of `items` whose memoized hashes are equal but which
+/// are not equal themselves.
+///
+/// Each hash with at least two distinct values yields one group; repeats
+/// of a value are reported once. Groups are sorted by hash. Cold memos are
+/// hashed, and so warmed, along the way.
+pub fn find_collisions<'a, T, H>(
+    items: impl IntoIterator<Item = &'a HashMemo<T, H>>,
+) -> Vec<CollisionGroup<'a, T>>
+where
+    T: Eq + Hash + 'a,
+    H: BuildHasher + 'a,
+{
+    let mut by_hash: HashMap<u64, Vec<&'a T>> = HashMap::new();
+    for memo in items {
+        let values = by_hash.entry(memo.memoized_hash()).or_default();
+        if !values.contains(&&memo.value) {
+            values.push(&memo.value);
+        }
+    }
+
+    let mut groups: Vec<_> = by_hash
+        .into_iter()
+        .filter(|(_, values)| values.len() > 1)
+        .map(|(hash, values)| CollisionGroup { hash, values })
+        .collect();
+    groups.sort_unstable_by_key(|group| group.hash);
+    groups
+}
+
+/// The table sizes [`hash_quality`] checks bucket occupancy for, when there
+/// are at least five values per bucket.
+const TABLE_SIZES: [usize; 4] = [16, 256, 4096, 65536];
+
+/// The fewest values per bucket a table size is checked with.
+const MIN_PER_BUCKET: usize = 5;
+
+/// Whether [`hash_quality`] found the hashes fit for hash tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// Every check passed.
+    Pass,
+    /// At least one check failed.
+    Warn,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Verdict::Pass => "pass",
+            Verdict::Warn => "warn",
+        })
+    }
+}
+
+/// The occupancy of a power-of-two table indexed by the low bits of each
+/// hash, as `std` and `hashbrown` maps index theirs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BucketCheck {
+    /// The number of buckets.
+    pub buckets: usize,
+    /// Pearson's chi-square statistic of the bucket counts against an even
+    /// spread, with `buckets - 1` degrees of freedom.
+    pub chi_square: f64,
+    /// The largest statistic that passes.
+    pub limit: f64,
+}
+
+impl BucketCheck {
+    /// Returns `true` if the statistic is within the limit.
+    #[inline]
+    pub fn passed(&self) -> bool {
+        self.chi_square <= self.limit
+    }
+}
+
+/// The distribution of a collection's hashes, computed by [`hash_quality`].
+///
+/// Three checks make up the verdict, each failing only on a deviation that
+/// good hashes of distinct values reach by chance with a probability of
+/// about one in ten thousand or less:
+///
+/// - Bucket occupancy: for each table size in 16, 256, 4096 and 65536 with
+///   at least five values per bucket, the chi-square statistic of the
+///   bucket counts must not exceed `df + 4·√(2·df)`, with `df` the number
+///   of buckets less one. Only excess clustering fails; a spread more even
+///   than chance, as sequential keys under an identity hash give, passes.
+/// - Bit bias: for each of the 64 bits, the fraction of hashes with the bit
+///   set, less one half. Every bias must be within `2.5/√n` of zero, five
+///   standard deviations for `n` values.
+/// - Collisions: distinct values sharing a hash, against the birthday
+///   expectation `E = n(n−1)/2^65` for 64-bit hashes. There must be no more
+///   than `E + 4·√E + 1`.
+///
+/// Small samples pass more easily: with fewer than 80 values no table size
+/// is checked, and a bit is only found biased once `n` exceeds 25.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityReport {
+    /// The number of distinct values analysed.
+    pub values: usize,
+    /// The bucket occupancy per table size, smallest first.
+    pub buckets: Vec<BucketCheck>,
+    /// The bias of each bit, from the least significant: the fraction of
+    /// hashes with the bit set, less `0.5`.
+    pub bit_bias: [f64; 64],
+    /// The largest absolute bias that passes.
+    pub bit_bias_limit: f64,
+    /// The number of distinct values whose hash an earlier distinct value
+    /// already had.
+    pub collisions: usize,
+    /// The birthday expectation of `collisions` for ideal hashes.
+    pub expected_collisions: f64,
+    /// The largest number of collisions that passes.
+    pub collision_limit: f64,
+    /// [`Verdict::Pass`] if every check passed.
+    pub verdict: Verdict,
+}
+
+impl QualityReport {
+    /// Returns the most biased bit and its bias.
+    pub fn max_bit_bias(&self) -> (u32, f64) {
+        (0..64)
+            .map(|bit| (bit, self.bit_bias[bit as usize]))
+            .fold((0, 0.0), |max, (bit, bias)| {
+                if bias.abs() > max.1.abs() {
+                    (bit, bias)
+                } else {
+                    max
+                }
+            })
+    }
+
+    /// Returns `true` if every table size passed.
+    pub fn buckets_passed(&self) -> bool {
+        self.buckets.iter().all(BucketCheck::passed)
+    }
+
+    /// Returns `true` if no bit is biased beyond the limit.
+    pub fn bits_passed(&self) -> bool {
+        self.max_bit_bias().1.abs() <= self.bit_bias_limit
+    }
+
+    /// Returns `true` if the collisions are within the limit.
+    pub fn collisions_passed(&self) -> bool {
+        self.collisions as f64 <= self.collision_limit
+    }
+}
+
+impl fmt::Display for QualityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mark = |passed: bool| if passed { "ok" } else { "FAIL" };
+        writeln!(f, "hash quality: {} ({} values)", self.verdict, self.values)?;
+        for check in &self.buckets {
+            writeln!(
+                f,
+                "  {} buckets: chi-square {:.1} (limit {:.1}) {}",
+                check.buckets,
+                check.chi_square,
+                check.limit,
+                mark(check.passed())
+            )?;
+        }
+        let (bit, bias) = self.max_bit_bias();
+        writeln!(
+            f,
+            "  bit bias: {bias:+.4} at bit {bit} (limit {:.4}) {}",
+            self.bit_bias_limit,
+            mark(self.bits_passed())
+        )?;
+        write!(
+            f,
+            "  collisions: {} (expected {:.2}, limit {:.2}) {}",
+            self.collisions,
+            self.expected_collisions,
+            self.collision_limit,
+            mark(self.collisions_passed())
+        )
+    }
+}
+
+/// Analyses the distribution of the memoized hashes of `items`, see
+/// [`QualityReport`] for the checks and their thresholds.
+///
+/// Repeats of a value are counted once, so they neither skew the
+/// distribution nor count as collisions. Cold memos are hashed, and so
+/// warmed, along the way.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::diagnostics::{hash_quality, Verdict};
+/// use hashmemo::HashMemo;
+///
+/// let memos: Vec<_> = (0..10_000u64).map(HashMemo::new_fixed).collect();
+/// let report = hash_quality(&memos);
+/// assert_eq!(report.verdict, Verdict::Pass);
+/// println!("{report}");
+/// ```
+pub fn hash_quality<'a, T, H>(items: impl IntoIterator<Item = &'a HashMemo<T, H>>) -> QualityReport
+where
+    T: Eq + Hash + 'a,
+    H: BuildHasher + 'a,
+{
+    let mut by_hash: HashMap<u64, Vec<&'a T>> = HashMap::new();
+    let mut hashes = Vec::new();
+    for memo in items {
+        let hash = memo.memoized_hash();
+        let values = by_hash.entry(hash).or_default();
+        if !values.contains(&&memo.value) {
+            values.push(&memo.value);
+            hashes.push(hash);
+        }
+    }
+
+    let n = hashes.len();
+    let buckets: Vec<_> = TABLE_SIZES
+        .into_iter()
+        .filter(|&size| n >= MIN_PER_BUCKET * size)
+        .map(|size| {
+            let mut counts = vec![0usize; size];
+            for &hash in &hashes {
+                counts[hash as usize & (size - 1)] += 1;
+            }
+            let expected = n as f64 / size as f64;
+            let chi_square = counts
+                .iter()
+                .map(|&count| (count as f64 - expected).powi(2) / expected)
+                .sum();
+            let df = (size - 1) as f64;
+            BucketCheck {
+                buckets: size,
+                chi_square,
+                limit: df + 4.0 * (2.0 * df).sqrt(),
+            }
+        })
+        .collect();
+
+    let mut bit_bias = [0.0; 64];
+    if n > 0 {
+        for (bit, bias) in bit_bias.iter_mut().enumerate() {
+            let ones = hashes.iter().filter(|&&hash| hash >> bit & 1 == 1).count();
+            *bias = ones as f64 / n as f64 - 0.5;
+        }
+    }
+
+    let expected_collisions = n as f64 * n.saturating_sub(1) as f64 / 2f64.powi(65);
+    let report = QualityReport {
+        values: n,
+        buckets,
+        bit_bias,
+        bit_bias_limit: 2.5 / (n as f64).sqrt(),
+        collisions: n - by_hash.len(),
+        expected_collisions,
+        collision_limit: expected_collisions + 4.0 * expected_collisions.sqrt() + 1.0,
+        verdict: Verdict::Pass,
+    };
+    let passed = report.buckets_passed() && report.bits_passed() && report.collisions_passed();
+    QualityReport {
+        verdict: if passed { Verdict::Pass } else { Verdict::Warn },
+        ..report
+    }
+}
+
+/// A collision seen while comparing memos, passed to the collision hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Collision {
+    /// The cached hash both memos carry.
+    pub hash: u64,
+    /// The type of the memoized values.
+    pub type_name: &'static str,
+}
+
+type Hook = Box<dyn Fn(&Collision) + Send + Sync>;
+
+static HOOK: RwLock<Option<Hook>> = RwLock::new(None);
+
+/// Installs `hook`, replacing any previous one, to be called whenever two
+/// memos with equal cached hashes compare unequal.
+///
+/// Maps compare keys only after their hashes match, so under the
+/// `diagnostics` feature every collision a lookup runs into between
+/// [`HashMemo`]s, including the wrappers built on it, reaches the hook.
+/// Only hashes already cached are compared; the check never hashes.
+pub fn set_collision_hook(hook: impl Fn(&Collision) + Send + Sync + 'static) {
+    *HOOK.write().unwrap_or_else(|e| e.into_inner()) = Some(Box::new(hook));
+}
+
+/// Removes the collision hook, if any.
+pub fn clear_collision_hook() {
+    *HOOK.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Reports `a` and `b`, which compared unequal, to the hook if their cached
+/// hashes are equal.
+#[cold]
+pub(crate) fn check_collision<T, H>(a: &HashMemo<T, H>, b: &HashMemo<T, H>)
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    let hash = a.hash.load(Ordering::Relaxed);
+    if hash == 0 || hash != b.hash.load(Ordering::Relaxed) {
+        return;
+    }
+
+    if let Some(hook) = &*HOOK.read().unwrap_or_else(|e| e.into_inner()) {
+        hook(&Collision {
+            hash,
+            type_name: type_name::<T>(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::hash::{BuildHasherDefault, DefaultHasher, Hasher};
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// Keeps two bits of `DefaultHasher`, so collisions are certain.
+    #[derive(Default)]
+    struct Truncating(DefaultHasher);
+
+    impl Hasher for Truncating {
+        fn finish(&self) -> u64 {
+            (self.0.finish() & 0b11) + 1
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            self.0.write(bytes);
+        }
+    }
+
+    type TruncatingState = BuildHasherDefault<Truncating>;
+
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    struct Key(u32);
+
+    #[test]
+    fn finds_every_collision() {
+        let memos: Vec<HashMemo<Key, TruncatingState>> = (0..20)
+            .chain(0..5)
+            .map(|i| HashMemo::with_hasher(Key(i), TruncatingState::default()))
+            .collect();
+        let groups = find_collisions(&memos);
+
+        let mut expected: HashMap<u64, Vec<u32>> = HashMap::new();
+        for i in 0..20 {
+            let hash = TruncatingState::default().hash_one(Key(i));
+            expected.entry(hash).or_default().push(i);
+        }
+        expected.retain(|_, values| values.len() > 1);
+
+        assert_eq!(groups.len(), expected.len());
+        assert!(groups.windows(2).all(|w| w[0].hash < w[1].hash));
+        for group in &groups {
+            let values: Vec<u32> = group.values.iter().map(|key| key.0).collect();
+            assert_eq!(values, expected[&group.hash]);
+        }
+        let reported: usize = groups.iter().map(|group| group.values.len()).sum();
+        assert_eq!(reported, 20);
+    }
+
+    #[test]
+    fn distinct_hashes_report_nothing() {
+        // Seeded, so the outcome does not hang on `DefaultHasher`'s output.
+        let memos: Vec<_> = (0..100).map(|i| HashMemo::deterministic(i, 1)).collect();
+        assert!(find_collisions(&memos).is_empty());
+        assert!(find_collisions(&memos[..0]).is_empty());
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn hook_sees_collisions_during_lookups() {
+        let seen = Arc::new(AtomicUsize::new(0));
+        let counter = seen.clone();
+        set_collision_hook(move |collision| {
+            if collision.type_name == type_name::<Key>() {
+                assert!((1..=4).contains(&collision.hash));
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+
+        let memo = |i| HashMemo::with_hasher(Key(i), TruncatingState::default());
+        let set: HashSet<_> = (0..8).map(memo).collect();
+        let before = seen.load(Ordering::Relaxed);
+        for i in 8..16 {
+            assert!(!set.contains(&memo(i)));
+        }
+        assert!(seen.load(Ordering::Relaxed) > before);
+
+        clear_collision_hook();
+        let after = seen.load(Ordering::Relaxed);
+        assert!(!set.contains(&memo(16)));
+        assert_eq!(seen.load(Ordering::Relaxed), after);
+    }
+
+    #[test]
+    fn well_mixed_hashes_pass() {
+        let memos: Vec<_> = (0..25_000u64)
+            .map(|i| HashMemo::deterministic(i, 7))
+            .collect();
+        let report = hash_quality(&memos);
+
+        assert_eq!(report.values, 25_000);
+        assert_eq!(
+            report
+                .buckets
+                .iter()
+                .map(|check| check.buckets)
+                .collect::<Vec<_>>(),
+            [16, 256, 4096]
+        );
+        assert!(report.buckets_passed() && report.bits_passed());
+        assert_eq!(report.collisions, 0);
+        assert_eq!(report.verdict, Verdict::Pass);
+    }
+
+    #[test]
+    fn identity_over_small_integers_fails_the_bit_check() {
+        // From 1, as a zero hash is cached as 1.
+        let memos: Vec<_> = (1..=10_000u64)
+            .map(|i| HashMemo::with_hasher(i, crate::PassthroughState))
+            .collect();
+        let report = hash_quality(&memos);
+
+        // Sequential keys fill the low buckets evenly, but the high bits
+        // are never set.
+        assert!(report.buckets_passed());
+        assert!(report.collisions_passed());
+        assert!(!report.bits_passed());
+        assert_eq!(report.bit_bias[63], -0.5);
+        assert_eq!(report.verdict, Verdict::Warn);
+    }
+
+    #[test]
+    fn truncating_hasher_fails_every_check() {
+        let memos: Vec<HashMemo<Key, TruncatingState>> = (0..2_000)
+            .map(|i| HashMemo::with_hasher(Key(i), TruncatingState::default()))
+            .collect();
+        let report = hash_quality(&memos);
+
+        assert!(report.buckets.iter().all(|check| !check.passed()));
+        assert!(!report.bits_passed());
+        assert_eq!(report.collisions, 2_000 - 4);
+        assert!(!report.collisions_passed());
+        assert_eq!(report.verdict, Verdict::Warn);
+    }
+
+    #[test]
+    fn repeats_are_counted_once() {
+        let memos: Vec<_> = (0..50u64)
+            .chain(0..50)
+            .map(|i| HashMemo::deterministic(i, 1))
+            .collect();
+        let report = hash_quality(&memos);
+        assert_eq!(report.values, 50);
+        assert_eq!(report.collisions, 0);
+        assert!(report.buckets.is_empty());
+
+        let empty = hash_quality(&memos[..0]);
+        assert_eq!((empty.values, empty.verdict), (0, Verdict::Pass));
+    }
+
+    #[test]
+    fn report_displays_every_check() {
+        let memos: Vec<_> = (1..=100u64)
+            .map(|i| HashMemo::with_hasher(i, crate::PassthroughState))
+            .collect();
+        let report = hash_quality(&memos).to_string();
+        assert_eq!(
+            report.lines().collect::<Vec<_>>(),
+            [
+                "hash quality: warn (100 values)",
+                "  16 buckets: chi-square 0.5 (limit 36.9) ok",
+                "  bit bias: -0.5000 at bit 7 (limit 0.2500) FAIL",
+                "  collisions: 0 (expected 0.00, limit 1.00) ok",
+            ]
+        );
+    }
+}
@@ -0,0 +1,248 @@
+//! [`digest`](::digest) support, so any `Digest` can back a memo.
+//!
+//! [`DigestBuildHasher<D>`] runs the digest `D` over the bytes a value's
+//! `Hash` impl writes and truncates the output to its first 8 bytes, read
+//! as a little-endian `u64`: the same reading as
+//! [`HashMemo::from_hashed_bytes`], so a memo built with
+//! [`HashMemo::new_digest`] caches the hash a memo seeded with the full
+//! digest of the same stream does. A `0` result is remapped to `1`, as for
+//! every memo.
+//!
+//! The bytes are fed portably: `write` passes its bytes through unchanged,
+//! integer writes feed their little-endian bytes, and `usize` and `isize`
+//! are widened to 64 bits first. A `str` is its bytes then `0xff`, and a
+//! `Vec<u8>` its length then its bytes, so the hash of a value is the same
+//! on every platform. Cryptographic digests are slow next to the hashers a
+//! `HashMap` usually uses; the memo pays for it once per value.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::HashMemo;
+//! use sha2::{Digest, Sha256};
+//!
+//! let memo = HashMemo::new_digest::<Sha256>(0x0102_0304u32);
+//! let digest = Sha256::digest(0x0102_0304u32.to_le_bytes());
+//! let truncated = u64::from_le_bytes(digest[..8].try_into().unwrap());
+//! assert_eq!(memo.hash_one_cached(), truncated);
+//! ```
+
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::marker::PhantomData;
+
+use ::digest::Digest;
+
+use crate::HashMemo;
+
+/// A [`HashMemo`] hashing with the digest `D`, truncated to 64 bits.
+pub type DigestMemo<T, D> = HashMemo<T, DigestBuildHasher<D>>;
+
+/// A `BuildHasher` running the digest `D` over a value's hashed bytes and
+/// keeping the first 8 bytes of the output, little-endian.
+///
+/// See the [module docs](crate::digest) for how the bytes are fed.
+pub struct DigestBuildHasher<D>(PhantomData<fn() -> D>);
+
+impl<D> DigestBuildHasher<D> {
+    /// Creates a `DigestBuildHasher`.
+    #[inline]
+    pub const fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<D> Default for DigestBuildHasher<D> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D> Clone for DigestBuildHasher<D> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<D> Copy for DigestBuildHasher<D> {}
+
+impl<D> PartialEq for DigestBuildHasher<D> {
+    #[inline]
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl<D> Eq for DigestBuildHasher<D> {}
+
+impl<D> fmt::Debug for DigestBuildHasher<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DigestBuildHasher")
+            .field(&std::any::type_name::<D>())
+            .finish()
+    }
+}
+
+impl<D> BuildHasher for DigestBuildHasher<D>
+where
+    D: Digest + Clone,
+{
+    type Hasher = DigestHasher<D>;
+
+    #[inline]
+    fn build_hasher(&self) -> DigestHasher<D> {
+        DigestHasher(D::new())
+    }
+}
+
+/// The `Hasher` of a [`DigestBuildHasher`].
+#[derive(Clone)]
+pub struct DigestHasher<D>(D);
+
+impl<D> fmt::Debug for DigestHasher<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DigestHasher")
+            .field(&std::any::type_name::<D>())
+            .finish()
+    }
+}
+
+impl<D> Hasher for DigestHasher<D>
+where
+    D: Digest + Clone,
+{
+    /// Finalizes a copy of the digest and reads its first 8 bytes as a
+    /// little-endian `u64`. A digest shorter than 8 bytes fills the low
+    /// bytes.
+    fn finish(&self) -> u64 {
+        let output = self.0.clone().finalize();
+        let mut prefix = [0; 8];
+        let len = output.len().min(8);
+        prefix[..len].copy_from_slice(&output[..len]);
+        u64::from_le_bytes(prefix)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.0.update([i]);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.0.update(i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.0.update(i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.0.update(i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.0.update(i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as i64 as u64);
+    }
+}
+
+impl<T> HashMemo<T>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new `HashMemo` hashing with the digest `D`, truncated to
+    /// its first 8 bytes, little-endian.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::{DigestMemo, HashMemo};
+    /// use sha2::Sha256;
+    ///
+    /// let memo: DigestMemo<&str, Sha256> = HashMemo::new_digest::<Sha256>("hello world");
+    /// ```
+    #[inline]
+    pub const fn new_digest<D>(value: T) -> HashMemo<T, DigestBuildHasher<D>>
+    where
+        D: Digest + Clone,
+    {
+        HashMemo::with_hasher(value, DigestBuildHasher::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use ::sha2::{Sha256, Sha512};
+
+    use super::*;
+    use crate::remap_zero;
+
+    /// Returns the first 8 bytes of the SHA-256 of `bytes`, little-endian.
+    fn truncated_sha256(bytes: &[u8]) -> u64 {
+        let digest = Sha256::digest(bytes);
+        u64::from_le_bytes(digest[..8].try_into().unwrap())
+    }
+
+    #[test]
+    fn caches_the_truncated_sha256_of_the_hashed_bytes() {
+        let memo = HashMemo::new_digest::<Sha256>("abc");
+        assert_eq!(
+            memo.memoized_hash(),
+            remap_zero(truncated_sha256(b"abc\xff"))
+        );
+
+        let bytes = vec![7u8; 1000];
+        let mut stream = 1000u64.to_le_bytes().to_vec();
+        stream.extend_from_slice(&bytes);
+        let memo = HashMemo::new_digest::<Sha256>(bytes);
+        assert_eq!(memo.memoized_hash(), remap_zero(truncated_sha256(&stream)));
+    }
+
+    #[test]
+    fn integers_are_fed_little_endian_and_usize_as_64_bits() {
+        let memo = HashMemo::new_digest::<Sha256>(0x0102_0304_0506_0708u64);
+        assert_eq!(
+            memo.memoized_hash(),
+            truncated_sha256(&0x0102_0304_0506_0708u64.to_le_bytes())
+        );
+
+        let memo = HashMemo::new_digest::<Sha256>(5usize);
+        assert_eq!(memo.memoized_hash(), truncated_sha256(&5u64.to_le_bytes()));
+    }
+
+    #[test]
+    fn agrees_with_memos_seeded_from_the_digest() {
+        let value = "seeded".to_string();
+        let digest = Sha512::digest(b"seeded\xff");
+        let seeded =
+            HashMemo::from_hashed_bytes(value.clone(), &digest, DigestBuildHasher::<Sha512>::new());
+        let computed = HashMemo::new_digest::<Sha512>(value);
+        assert_eq!(seeded, computed);
+        assert_eq!(seeded.memoized_hash(), computed.memoized_hash());
+    }
+
+    #[test]
+    fn works_as_a_hashmap_key() {
+        let mut map = HashMap::new();
+        for i in 0..100u32 {
+            map.insert(HashMemo::new_digest::<Sha256>(i.to_string()), i);
+        }
+        for i in 0..100u32 {
+            let key = HashMemo::new_digest::<Sha256>(i.to_string());
+            assert_eq!(map.get(&key), Some(&i));
+        }
+    }
+}
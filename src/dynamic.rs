@@ -0,0 +1,350 @@
+//! A hasher chosen at runtime, for memos whose hashing strategy comes from
+//! configuration.
+
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher, RandomState};
+use std::sync::OnceLock;
+
+use crate::{FixedHasher, FixedState, HashMemo};
+
+/// A [`HashMemo`] whose hasher is picked at runtime.
+pub type DynMemo<T> = HashMemo<T, DynState>;
+
+/// A source of hashers, as an object-safe counterpart of `BuildHasher`.
+///
+/// Implemented for the built-in strategies of [`DynState`]. Custom
+/// factories return a hasher wrapped by [`DynHasher::new`], which boxes
+/// it; the built-in ones do not allocate.
+pub trait DynHasherFactory: Send + Sync {
+    /// Returns a fresh hasher, as `BuildHasher::build_hasher` does.
+    fn build_dyn_hasher(&self) -> DynHasher;
+}
+
+impl DynHasherFactory for BuildHasherDefault<DefaultHasher> {
+    #[inline]
+    fn build_dyn_hasher(&self) -> DynHasher {
+        DynHasher(Inner::Std(DefaultHasher::new()))
+    }
+}
+
+impl DynHasherFactory for RandomState {
+    #[inline]
+    fn build_dyn_hasher(&self) -> DynHasher {
+        DynHasher(Inner::Std(self.build_hasher()))
+    }
+}
+
+impl DynHasherFactory for FixedState {
+    #[inline]
+    fn build_dyn_hasher(&self) -> DynHasher {
+        DynHasher(Inner::Fixed(FixedHasher::default()))
+    }
+}
+
+/// A `BuildHasher` dispatching to a [`DynHasherFactory`] chosen at
+/// runtime.
+///
+/// The state is a `'static` reference to the factory, so it is `Copy` and
+/// every memo holding it costs two words beyond the cache. Memos agree
+/// with each other when their states name the same strategy: the built-in
+/// constructors return the same factory on every call, including
+/// [`random`](Self::random), whose keys are drawn once per process.
+///
+/// `DynState::default()` is the process-wide default, which
+/// [`set_default_dyn_hasher`] picks once, before its first use.
+///
+/// Each hash costs a dynamic call to build the hasher and a branch per
+/// write, which the `Cold BigStruct memo by inner hasher` benchmark
+/// measures against the static hashers.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{DynMemo, DynState, HashMemo};
+/// use std::hash::BuildHasher;
+///
+/// let persist = true;
+/// let state = if persist { DynState::fixed() } else { DynState::random() };
+///
+/// let memo: DynMemo<&str> = HashMemo::with_hasher("key", state);
+/// assert_eq!(memo.hash_one_cached(), hashmemo::FixedState.hash_one("key"));
+/// ```
+#[derive(Clone, Copy)]
+pub struct DynState {
+    factory: &'static dyn DynHasherFactory,
+}
+
+static DEFAULT: OnceLock<DynState> = OnceLock::new();
+
+/// Sets the state returned by `DynState::default()` for the rest of the
+/// process.
+///
+/// The default can be set once, before any `DynState::default()` call has
+/// fixed it, so every default memo hashes alike. Otherwise `state` is
+/// returned unchanged as the error. Until it is set, the default is
+/// [`DynState::std`].
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{set_default_dyn_hasher, DynState, HashMemo};
+///
+/// set_default_dyn_hasher(DynState::random()).unwrap();
+/// assert!(set_default_dyn_hasher(DynState::fixed()).is_err());
+///
+/// let a = HashMemo::new_dyn("key");
+/// let b = HashMemo::new_dyn("key");
+/// assert_eq!(a.hash_one_cached(), b.hash_one_cached());
+/// ```
+pub fn set_default_dyn_hasher(state: DynState) -> Result<(), DynState> {
+    let mut state = Some(state);
+    DEFAULT.get_or_init(|| state.take().unwrap());
+    match state {
+        None => Ok(()),
+        Some(state) => Err(state),
+    }
+}
+
+impl DynState {
+    /// Creates a state dispatching to `factory`.
+    ///
+    /// A factory built at runtime can be made `'static` with `Box::leak`,
+    /// once, and the state copied from there.
+    #[inline]
+    pub const fn new(factory: &'static dyn DynHasherFactory) -> Self {
+        Self { factory }
+    }
+
+    /// Hashes like [`HashMemo::new`], with `DefaultHasher` under fixed
+    /// keys. Its output may change between Rust releases.
+    #[inline]
+    pub const fn std() -> Self {
+        static STD: BuildHasherDefault<DefaultHasher> = BuildHasherDefault::new();
+        Self::new(&STD)
+    }
+
+    /// Hashes with [`FixedState`], which is fast and stable across runs,
+    /// machines and releases, for hashes that are persisted. It is not
+    /// DoS-resistant.
+    #[inline]
+    pub const fn fixed() -> Self {
+        Self::new(&FixedState)
+    }
+
+    /// Hashes with a `RandomState` keyed once per process, which resists
+    /// inputs crafted to collide.
+    pub fn random() -> Self {
+        static RANDOM: OnceLock<RandomState> = OnceLock::new();
+        Self::new(RANDOM.get_or_init(RandomState::new))
+    }
+}
+
+impl Default for DynState {
+    /// Returns the process-wide default, fixing it if
+    /// [`set_default_dyn_hasher`] has not been called.
+    #[inline]
+    fn default() -> Self {
+        *DEFAULT.get_or_init(Self::std)
+    }
+}
+
+impl BuildHasher for DynState {
+    type Hasher = DynHasher;
+
+    #[inline]
+    fn build_hasher(&self) -> DynHasher {
+        self.factory.build_dyn_hasher()
+    }
+}
+
+impl fmt::Debug for DynState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynState").finish_non_exhaustive()
+    }
+}
+
+/// The `Hasher` built by [`DynState`].
+pub struct DynHasher(Inner);
+
+enum Inner {
+    Std(DefaultHasher),
+    Fixed(FixedHasher),
+    Boxed(Box<dyn Hasher + Send>),
+}
+
+impl DynHasher {
+    /// Wraps a custom hasher, for [`DynHasherFactory`] impls.
+    #[inline]
+    pub fn new<H: Hasher + Send + 'static>(hasher: H) -> Self {
+        DynHasher(Inner::Boxed(Box::new(hasher)))
+    }
+}
+
+/// Forwards a `Hasher` call to whichever hasher is inside.
+macro_rules! dispatch {
+    ($self:expr, $hasher:ident => $call:expr) => {
+        match $self {
+            Inner::Std($hasher) => $call,
+            Inner::Fixed($hasher) => $call,
+            Inner::Boxed($hasher) => $call,
+        }
+    };
+}
+
+macro_rules! forward_writes {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            #[inline]
+            fn $method(&mut self, i: $ty) {
+                dispatch!(&mut self.0, hasher => hasher.$method(i))
+            }
+        )*
+    };
+}
+
+impl Hasher for DynHasher {
+    #[inline]
+    fn finish(&self) -> u64 {
+        dispatch!(&self.0, hasher => hasher.finish())
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        dispatch!(&mut self.0, hasher => hasher.write(bytes))
+    }
+
+    forward_writes!(
+        write_u8(u8),
+        write_u16(u16),
+        write_u32(u32),
+        write_u64(u64),
+        write_u128(u128),
+        write_usize(usize),
+        write_i8(i8),
+        write_i16(i16),
+        write_i32(i32),
+        write_i64(i64),
+        write_i128(i128),
+        write_isize(isize),
+    );
+}
+
+impl fmt::Debug for DynHasher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynHasher").finish_non_exhaustive()
+    }
+}
+
+impl<T> HashMemo<T, DynState>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new `HashMemo` using the process-wide default
+    /// [`DynState`].
+    #[inline]
+    pub fn new_dyn(value: T) -> Self {
+        Self::with_hasher(value, DynState::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::remap_zero;
+
+    /// A custom strategy: FNV-1a, boxed by `DynHasher::new`.
+    struct Fnv;
+
+    struct FnvHasher(u64);
+
+    impl Hasher for FnvHasher {
+        fn finish(&self) -> u64 {
+            self.0
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            for &byte in bytes {
+                self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+    }
+
+    impl DynHasherFactory for Fnv {
+        fn build_dyn_hasher(&self) -> DynHasher {
+            DynHasher::new(FnvHasher(0xcbf2_9ce4_8422_2325))
+        }
+    }
+
+    impl BuildHasher for Fnv {
+        type Hasher = FnvHasher;
+
+        fn build_hasher(&self) -> FnvHasher {
+            FnvHasher(0xcbf2_9ce4_8422_2325)
+        }
+    }
+
+    fn values() -> Vec<(String, u64, Vec<u8>)> {
+        (0..100)
+            .map(|i| (i.to_string().repeat(i), i as u64, vec![i as u8; i]))
+            .collect()
+    }
+
+    /// Checks that `state` hashes every value like `expected`, and that
+    /// memos sharing it work as set keys.
+    fn check<S: BuildHasher>(state: DynState, expected: &S) {
+        let mut set = HashSet::new();
+        for value in values() {
+            let memo = HashMemo::with_hasher(value.clone(), state);
+            assert_eq!(
+                memo.hash_one_cached(),
+                remap_zero(expected.hash_one(&value))
+            );
+            set.insert(memo);
+        }
+        for value in values() {
+            assert!(set.contains(&HashMemo::with_hasher(value, state)));
+        }
+    }
+
+    #[test]
+    fn built_in_strategies_match_their_static_hashers() {
+        check(DynState::std(), &BuildHasherDefault::<DefaultHasher>::new());
+        check(DynState::fixed(), &FixedState);
+
+        let random = DynState::random();
+        let keys = RandomState::new();
+        assert_eq!(
+            random.hash_one("key"),
+            DynState::random().hash_one("key"),
+            "one set of keys per process"
+        );
+        assert_ne!(random.hash_one("key"), keys.hash_one("key"));
+        check(random, &random);
+    }
+
+    #[test]
+    fn custom_factories_are_boxed() {
+        static FNV: Fnv = Fnv;
+        check(DynState::new(&FNV), &Fnv);
+
+        let leaked: &'static Fnv = Box::leak(Box::new(Fnv));
+        check(DynState::new(leaked), &Fnv);
+    }
+
+    #[test]
+    fn the_default_is_fixed_on_first_use() {
+        let first = HashMemo::new_dyn("key".to_string());
+        assert!(set_default_dyn_hasher(DynState::fixed()).is_err());
+        assert_eq!(
+            first.hash_one_cached(),
+            HashMemo::new_dyn("key".to_string()).hash_one_cached()
+        );
+        assert_eq!(
+            first.hash_one_cached(),
+            HashMemo::new("key".to_string()).hash_one_cached(),
+            "std until set"
+        );
+    }
+}
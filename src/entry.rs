@@ -0,0 +1,309 @@
+//! Records hashed and compared by a memoized key.
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher};
+
+use crate::{HashMemo, MemoLookup, Probe};
+
+/// A key and a payload, hashed and compared by the key alone.
+///
+/// `Hash` feeds the key's memoized hash, and `Eq` and `Ord` look only at
+/// the key, so a `HashSet<MemoEntry<K, V>>` works as a map that stores
+/// whole records without a separate copy of their ids. The key cannot be
+/// changed in place; [`map_key`](Self::map_key) consumes the entry and
+/// builds a new one. The payload takes no part in hashing or equality and
+/// is freely mutable through [`value_mut`](Self::value_mut).
+///
+/// Entries borrow as `dyn MemoLookup<Q>`, so sets of them can be probed by
+/// a borrowed key; see [`MemoEntrySetExt`].
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{MemoEntry, MemoEntrySetExt};
+/// use std::collections::HashSet;
+///
+/// let mut users = HashSet::new();
+/// users.insert(MemoEntry::new("ada".to_string(), 36));
+/// users.insert(MemoEntry::new("alan".to_string(), 41));
+///
+/// assert_eq!(users.get_by_key("ada").map(MemoEntry::value), Some(&36));
+/// assert!(users.contains(&MemoEntry::new("alan".to_string(), 0)));
+/// ```
+pub struct MemoEntry<K, V, H = BuildHasherDefault<DefaultHasher>>
+where
+    K: Eq + Hash,
+    H: BuildHasher,
+{
+    key: HashMemo<K, H>,
+    value: V,
+}
+
+impl<K, V> MemoEntry<K, V>
+where
+    K: Eq + Hash,
+{
+    /// Creates a new `MemoEntry` with the default hasher.
+    #[inline]
+    pub const fn new(key: K, value: V) -> Self {
+        Self::from_memo(HashMemo::new(key), value)
+    }
+}
+
+impl<K, V, H> MemoEntry<K, V, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Creates a new `MemoEntry` whose key hashes with `hasher`.
+    #[inline]
+    pub const fn with_hasher(key: K, value: V, hasher: H) -> Self {
+        Self::from_memo(HashMemo::with_hasher(key, hasher), value)
+    }
+
+    /// Creates a new `MemoEntry` from a memoized key, keeping its cache.
+    #[inline]
+    pub const fn from_memo(key: HashMemo<K, H>, value: V) -> Self {
+        Self { key, value }
+    }
+
+    /// Returns the key.
+    #[inline]
+    pub fn key(&self) -> &K {
+        &self.key.value
+    }
+
+    /// Returns the memoized key.
+    #[inline]
+    pub fn memo_key(&self) -> &HashMemo<K, H> {
+        &self.key
+    }
+
+    /// Returns the payload.
+    #[inline]
+    pub fn value(&self) -> &V {
+        &self.value
+    }
+
+    /// Returns the payload mutably.
+    ///
+    /// The payload is not hashed, so this leaves the cached key hash and
+    /// the entry's place in any set untouched.
+    #[inline]
+    pub fn value_mut(&mut self) -> &mut V {
+        &mut self.value
+    }
+
+    /// Consumes the entry and returns the memoized key and the payload.
+    #[inline]
+    pub fn into_parts(self) -> (HashMemo<K, H>, V) {
+        (self.key, self.value)
+    }
+
+    /// Consumes the entry and returns it with its key replaced by `f(key)`.
+    ///
+    /// The new key starts with a cold cache and keeps the hasher.
+    pub fn map_key<K2, F>(self, f: F) -> MemoEntry<K2, V, H>
+    where
+        K2: Eq + Hash,
+        F: FnOnce(K) -> K2,
+    {
+        let HashMemo { value, hasher, .. } = self.key;
+        MemoEntry::with_hasher(f(value), self.value, hasher)
+    }
+}
+
+impl<'a, K, V, H, Q> Borrow<dyn MemoLookup<Q> + 'a> for MemoEntry<K, V, H>
+where
+    K: Eq + Hash + Borrow<Q> + 'a,
+    H: BuildHasher + 'a,
+    Q: ?Sized + 'a,
+{
+    #[inline]
+    fn borrow(&self) -> &(dyn MemoLookup<Q> + 'a) {
+        &self.key
+    }
+}
+
+impl<K, V, H> Hash for MemoEntry<K, V, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher,
+{
+    #[inline]
+    fn hash<H2: Hasher>(&self, state: &mut H2) {
+        self.key.hash(state);
+    }
+}
+
+impl<K, V, H> PartialEq for MemoEntry<K, V, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher,
+{
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<K, V, H> Eq for MemoEntry<K, V, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher,
+{
+}
+
+impl<K, V, H> PartialOrd for MemoEntry<K, V, H>
+where
+    K: Eq + Hash + Ord,
+    H: BuildHasher,
+{
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K, V, H> Ord for MemoEntry<K, V, H>
+where
+    K: Eq + Hash + Ord,
+    H: BuildHasher,
+{
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+impl<K, V, H> Clone for MemoEntry<K, V, H>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+    H: BuildHasher + Clone,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            key: self.key.clone(),
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl<K, V, H> fmt::Debug for MemoEntry<K, V, H>
+where
+    K: Eq + Hash + fmt::Debug,
+    V: fmt::Debug,
+    H: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoEntry")
+            .field("key", &self.key.value)
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+/// Lookups by borrowed key in a `HashSet` of [`MemoEntry`].
+///
+/// The probe is hashed with `H::default()`, which must agree with the
+/// hasher of the stored keys. Sets whose keys use a hasher without a
+/// `Default` can be probed with [`Probe::with_hasher`] directly, as in
+/// `set.get(probe.as_lookup())`.
+pub trait MemoEntrySetExt<K, V, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher + Default,
+{
+    /// Returns the entry whose key equals `key`, if any.
+    fn get_by_key<Q>(&self, key: &Q) -> Option<&MemoEntry<K, V, H>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash;
+
+    /// Removes and returns the entry whose key equals `key`, if any.
+    fn take_by_key<Q>(&mut self, key: &Q) -> Option<MemoEntry<K, V, H>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash;
+}
+
+impl<K, V, H, S> MemoEntrySetExt<K, V, H> for HashSet<MemoEntry<K, V, H>, S>
+where
+    K: Eq + Hash,
+    H: BuildHasher + Default,
+    S: BuildHasher,
+{
+    fn get_by_key<Q>(&self, key: &Q) -> Option<&MemoEntry<K, V, H>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.get(Probe::with_hasher(key, &H::default()).as_lookup())
+    }
+
+    fn take_by_key<Q>(&mut self, key: &Q) -> Option<MemoEntry<K, V, H>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.take(Probe::with_hasher(key, &H::default()).as_lookup())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_mutation_keeps_set_membership() {
+        let mut set = HashSet::new();
+        for (id, score) in [("a", 1), ("b", 2), ("c", 3)] {
+            set.insert(MemoEntry::new(id.to_string(), score));
+        }
+
+        let mut entry = set.take_by_key("b").unwrap();
+        let hash = entry.memo_key().memoized_hash();
+        *entry.value_mut() += 40;
+        assert_eq!(entry.memo_key().memoized_hash(), hash);
+        assert!(set.insert(entry));
+
+        assert!(set.contains(&MemoEntry::new("b".to_string(), 0)));
+        assert_eq!(set.get_by_key("b").map(MemoEntry::value), Some(&42));
+        assert!(!set.insert(MemoEntry::new("b".to_string(), 7)));
+        assert_eq!(set.len(), 3);
+    }
+
+    #[test]
+    fn lookups_go_by_key_only() {
+        let set: HashSet<_> = (0..100u32)
+            .map(|id| MemoEntry::new(id.to_string(), vec![id; 3]))
+            .collect();
+
+        for id in 0..100u32 {
+            let entry = set.get_by_key(id.to_string().as_str()).unwrap();
+            assert_eq!(entry.key(), &id.to_string());
+            assert_eq!(entry.value(), &vec![id; 3]);
+        }
+        assert!(set.get_by_key("100").is_none());
+        assert!(MemoEntry::new(1, "x") == MemoEntry::new(1, "y"));
+        assert!(MemoEntry::new(1, "z") < MemoEntry::new(2, "a"));
+    }
+
+    #[test]
+    fn map_key_rehashes_the_new_key() {
+        let entry = MemoEntry::new(7u32, "payload");
+        entry.memo_key().memoized_hash();
+
+        let mapped = entry.map_key(|id| id.to_string());
+        assert_eq!(
+            mapped.memo_key().memoized_hash(),
+            HashMemo::new("7".to_string()).memoized_hash()
+        );
+        assert_eq!(mapped.into_parts().1, "payload");
+    }
+}
@@ -0,0 +1,303 @@
+//! Memoization for values whose hash can fail to compute.
+
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher};
+use std::num::NonZeroU64;
+use std::ops::Deref;
+use std::sync::atomic::Ordering;
+
+use crate::cell::{self, HashCell};
+use crate::AtomicU64;
+
+/// A value that feeds a hasher from a source that may fail, such as a file
+/// checksum or a lazily decoded blob.
+///
+/// Implementations must be deterministic on success: values that compare
+/// equal must write the same data whenever they return `Ok`.
+pub trait TryHashSource {
+    /// The error reading the source can produce.
+    type Error;
+
+    /// Writes the value's hash input to `sink`, or fails.
+    ///
+    /// On failure `sink` may have received part of the input; it is
+    /// discarded.
+    fn try_hash_stream<S: Hasher>(&self, sink: &mut S) -> Result<(), Self::Error>;
+}
+
+/// A memo for a [`TryHashSource`], primed explicitly with
+/// [`try_prime`](Self::try_prime).
+///
+/// `try_prime` computes and caches the hash, returning the source's error
+/// instead of panicking; a failed attempt caches nothing, so it can simply
+/// be retried. Once primed, `Hash` writes the cached `u64` like
+/// [`HashMemo`](crate::HashMemo) does.
+///
+/// # Panics
+///
+/// `Hash` panics if the memo has not been primed, whether or not the
+/// source would succeed. Hashing an unprimed memo is a bug in the caller,
+/// and panicking every time, rather than only when the source fails,
+/// surfaces it on the first run instead of on the first bad read. A
+/// sentinel hash was not an option: it would change once the memo is
+/// primed and strand entries in any map that saw it. Prime memos before
+/// inserting them or looking them up.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{TryHashSource, TrySourceMemo};
+/// use std::collections::HashSet;
+/// use std::hash::Hasher;
+/// use std::io;
+///
+/// #[derive(PartialEq, Eq)]
+/// struct Checksum(&'static str);
+///
+/// impl TryHashSource for Checksum {
+///     type Error = io::Error;
+///
+///     fn try_hash_stream<S: Hasher>(&self, sink: &mut S) -> io::Result<()> {
+///         sink.write(self.0.as_bytes());
+///         Ok(())
+///     }
+/// }
+///
+/// let memo = TrySourceMemo::new(Checksum("blob"));
+/// memo.try_prime()?;
+///
+/// let mut set = HashSet::new();
+/// set.insert(memo);
+/// # Ok::<(), io::Error>(())
+/// ```
+pub struct TrySourceMemo<T, H = BuildHasherDefault<DefaultHasher>>
+where
+    T: TryHashSource,
+    H: BuildHasher,
+{
+    value: T,
+    hash: AtomicU64,
+    hasher: H,
+}
+
+impl<T> TrySourceMemo<T>
+where
+    T: TryHashSource,
+{
+    /// Creates a new, unprimed `TrySourceMemo` with the default hasher.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self::with_hasher(value, BuildHasherDefault::new())
+    }
+}
+
+impl<T, H> TrySourceMemo<T, H>
+where
+    T: TryHashSource,
+    H: BuildHasher,
+{
+    /// Creates a new, unprimed `TrySourceMemo` with a custom hasher.
+    #[inline]
+    pub const fn with_hasher(value: T, hasher: H) -> Self {
+        Self {
+            value,
+            hash: AtomicU64::new(0),
+            hasher,
+        }
+    }
+
+    /// Returns the cached hash, computing and caching it on first success.
+    ///
+    /// On error nothing is cached and a later call tries the source again.
+    pub fn try_prime(&self) -> Result<u64, T::Error> {
+        if let Some(hash) = HashCell::load(&self.hash) {
+            return Ok(hash.get());
+        }
+
+        let mut state = self.hasher.build_hasher();
+        self.value.try_hash_stream(&mut state)?;
+        let hash = cell::nonzero(state.finish());
+        self.hash.publish(hash);
+        Ok(hash.get())
+    }
+
+    /// Returns the cached hash, or `None` if the memo is unprimed.
+    #[inline]
+    pub fn primed_hash(&self) -> Option<u64> {
+        HashCell::load(&self.hash).map(NonZeroU64::get)
+    }
+
+    /// Returns the value mutably, clearing the cached hash.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.hash.clear();
+        &mut self.value
+    }
+
+    /// Consumes the memo and returns the value.
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T, H> Deref for TrySourceMemo<T, H>
+where
+    T: TryHashSource,
+    H: BuildHasher,
+{
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, H> Hash for TrySourceMemo<T, H>
+where
+    T: TryHashSource,
+    H: BuildHasher,
+{
+    #[inline]
+    fn hash<H2: Hasher>(&self, state: &mut H2) {
+        let hash = self
+            .primed_hash()
+            .expect("TrySourceMemo hashed before a successful try_prime");
+        state.write_u64(hash);
+    }
+}
+
+impl<T, H> PartialEq for TrySourceMemo<T, H>
+where
+    T: TryHashSource + Eq,
+    H: BuildHasher,
+{
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T, H> Eq for TrySourceMemo<T, H>
+where
+    T: TryHashSource + Eq,
+    H: BuildHasher,
+{
+}
+
+impl<T, H> Clone for TrySourceMemo<T, H>
+where
+    T: TryHashSource + Clone,
+    H: BuildHasher + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            hash: AtomicU64::new(self.hash.load(Ordering::Relaxed)),
+            hasher: self.hasher.clone(),
+        }
+    }
+}
+
+impl<T, H> fmt::Debug for TrySourceMemo<T, H>
+where
+    T: TryHashSource + fmt::Debug,
+    H: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::hash::RandomState;
+    use std::sync::atomic::AtomicU32;
+
+    use super::*;
+    use crate::remap_zero;
+
+    /// Reads that the next flaky sources fail, across all instances.
+    static OUTAGES: AtomicU32 = AtomicU32::new(0);
+
+    /// Hashes its bytes, failing halfway through while `OUTAGES` lasts if
+    /// it is flaky.
+    #[derive(Debug, PartialEq, Eq)]
+    struct Source {
+        bytes: Vec<u8>,
+        flaky: bool,
+    }
+
+    impl Source {
+        fn new(bytes: &[u8], flaky: bool) -> Self {
+            Self {
+                bytes: bytes.to_vec(),
+                flaky,
+            }
+        }
+    }
+
+    impl TryHashSource for Source {
+        type Error = &'static str;
+
+        fn try_hash_stream<S: Hasher>(&self, sink: &mut S) -> Result<(), &'static str> {
+            sink.write(&self.bytes[..1]);
+            if self.flaky
+                && OUTAGES
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+                    .is_ok()
+            {
+                return Err("read failed");
+            }
+            sink.write(&self.bytes[1..]);
+            Ok(())
+        }
+    }
+
+    fn direct(bytes: &[u8]) -> u64 {
+        let mut state = BuildHasherDefault::<DefaultHasher>::new().build_hasher();
+        state.write(bytes);
+        remap_zero(state.finish())
+    }
+
+    #[test]
+    fn priming_caches_the_hash() {
+        let memo = TrySourceMemo::new(Source::new(b"blob", false));
+        assert_eq!(memo.primed_hash(), None);
+        assert_eq!(memo.try_prime(), Ok(direct(b"blob")));
+        assert_eq!(memo.primed_hash(), Some(direct(b"blob")));
+
+        let state = RandomState::new();
+        assert_eq!(state.hash_one(&memo), {
+            let mut hasher = state.build_hasher();
+            hasher.write_u64(direct(b"blob"));
+            hasher.finish()
+        });
+    }
+
+    #[test]
+    fn failed_priming_caches_nothing_and_can_be_retried() {
+        let memo = TrySourceMemo::new(Source::new(b"blob", true));
+        OUTAGES.store(1, Ordering::Relaxed);
+        assert_eq!(memo.try_prime(), Err("read failed"));
+        assert_eq!(memo.primed_hash(), None);
+
+        assert_eq!(memo.try_prime(), Ok(direct(b"blob")));
+        let mut set = HashSet::new();
+        assert!(set.insert(memo));
+
+        let other = TrySourceMemo::new(Source::new(b"blob", true));
+        other.try_prime().unwrap();
+        assert!(set.contains(&other));
+    }
+
+    #[test]
+    #[should_panic(expected = "hashed before a successful try_prime")]
+    fn hashing_an_unprimed_memo_panics() {
+        let memo = TrySourceMemo::new(Source::new(b"blob", false));
+        RandomState::new().hash_one(&memo);
+    }
+}
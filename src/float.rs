@@ -0,0 +1,525 @@
+//! Memoized keys for data containing floats.
+//!
+//! `f32` and `f64` are neither `Eq` nor `Hash`: `NaN != NaN`, and
+//! `-0.0 == 0.0` although their bits differ. [`CanonicalKey`] keys a value
+//! through its [`CanonicalHash`] impl instead, which maps every float to a
+//! canonical bit pattern and compares, hashes and orders those bits:
+//!
+//! - Order follows `total_cmp` on the canonical value: negative NaNs, then
+//!   `-inf`, negative numbers, `-0.0`, `0.0`, positive numbers, `inf` and
+//!   positive NaNs.
+//! - Under the default [`Canonical`] policy, `-0.0` is folded to `0.0` and
+//!   every NaN, whatever its sign and payload, to the positive quiet NaN
+//!   `f64::NAN`, which sorts last. Floats equal under `==` are then equal
+//!   keys, and all NaNs are one key.
+//! - Under [`TotalOrder`], nothing is folded: keys are equal exactly when
+//!   their bits are, as `total_cmp` has it.
+//!
+//! Other policies implement [`FloatPolicy`] and pick either fold. Hashes
+//! depend on the policy, which is part of the key type, so keys under
+//! different policies never meet in one map.
+//!
+//! [`FloatKeyMemo`] memoizes the hash of the canonical bits, like any
+//! [`HashMemo`].
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::{CanonicalKey, FloatKeyMemo, HashMemo};
+//! use std::collections::HashMap;
+//!
+//! let mut cache: HashMap<FloatKeyMemo<[f64; 2]>, &str> = HashMap::new();
+//! cache.insert(HashMemo::new(CanonicalKey::new([0.0, f64::NAN])), "origin");
+//!
+//! let probe = HashMemo::new(CanonicalKey::new([-0.0, -f64::NAN]));
+//! assert_eq!(cache.get(&probe), Some(&"origin"));
+//! ```
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{BuildHasherDefault, DefaultHasher, Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use crate::HashMemo;
+
+/// How floats are canonicalized before they are compared and hashed.
+///
+/// See the [module docs](crate::float) for the resulting order.
+pub trait FloatPolicy {
+    /// Whether `-0.0` is folded to `0.0`.
+    const FOLD_NEGATIVE_ZERO: bool;
+    /// Whether every NaN is folded to the positive quiet NaN.
+    const FOLD_NAN: bool;
+}
+
+/// The default [`FloatPolicy`]: folds `-0.0` to `0.0` and all NaNs to one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Canonical;
+
+impl FloatPolicy for Canonical {
+    const FOLD_NEGATIVE_ZERO: bool = true;
+    const FOLD_NAN: bool = true;
+}
+
+/// A [`FloatPolicy`] folding nothing, keying floats by their exact bits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TotalOrder;
+
+impl FloatPolicy for TotalOrder {
+    const FOLD_NEGATIVE_ZERO: bool = false;
+    const FOLD_NAN: bool = false;
+}
+
+/// Equality, hashing and ordering over canonicalized floats.
+///
+/// Implemented for floats, for `Eq + Hash + Ord` scalars and strings, and
+/// for slices, arrays, `Vec`s, `Option`s, boxes, references and tuples of
+/// implementors. The three methods must agree with each other, as `Eq`,
+/// `Hash` and `Ord` must. A struct implements them field by field, most
+/// simply by delegating to a tuple of references to its fields:
+///
+/// ```rust
+/// use hashmemo::{CanonicalHash, CanonicalKey, FloatPolicy};
+/// use std::cmp::Ordering;
+/// use std::hash::Hasher;
+///
+/// struct Sample {
+///     sensor: String,
+///     reading: f64,
+/// }
+///
+/// impl Sample {
+///     fn fields(&self) -> (&String, &f64) {
+///         (&self.sensor, &self.reading)
+///     }
+/// }
+///
+/// impl CanonicalHash for Sample {
+///     fn canonical_hash<P: FloatPolicy, H: Hasher>(&self, state: &mut H) {
+///         self.fields().canonical_hash::<P, H>(state);
+///     }
+///
+///     fn canonical_eq<P: FloatPolicy>(&self, other: &Self) -> bool {
+///         self.fields().canonical_eq::<P>(&other.fields())
+///     }
+///
+///     fn canonical_cmp<P: FloatPolicy>(&self, other: &Self) -> Ordering {
+///         self.fields().canonical_cmp::<P>(&other.fields())
+///     }
+/// }
+///
+/// let a = CanonicalKey::<_>::new(Sample { sensor: "t".into(), reading: -0.0 });
+/// let b = CanonicalKey::<_>::new(Sample { sensor: "t".into(), reading: 0.0 });
+/// assert!(a == b);
+/// ```
+pub trait CanonicalHash {
+    /// Feeds the canonical form of `self` into `state`.
+    fn canonical_hash<P: FloatPolicy, H: Hasher>(&self, state: &mut H);
+
+    /// Returns `true` if `self` and `other` have the same canonical form.
+    fn canonical_eq<P: FloatPolicy>(&self, other: &Self) -> bool;
+
+    /// Orders `self` and `other` by their canonical forms.
+    fn canonical_cmp<P: FloatPolicy>(&self, other: &Self) -> Ordering;
+}
+
+macro_rules! float_impl {
+    ($float:ty, $write:ident) => {
+        impl CanonicalHash for $float {
+            #[inline]
+            fn canonical_hash<P: FloatPolicy, H: Hasher>(&self, state: &mut H) {
+                state.$write(canonical::<P, $float>(*self).to_bits());
+            }
+
+            #[inline]
+            fn canonical_eq<P: FloatPolicy>(&self, other: &Self) -> bool {
+                canonical::<P, $float>(*self).to_bits() == canonical::<P, $float>(*other).to_bits()
+            }
+
+            #[inline]
+            fn canonical_cmp<P: FloatPolicy>(&self, other: &Self) -> Ordering {
+                canonical::<P, $float>(*self).total_cmp(&canonical::<P, $float>(*other))
+            }
+        }
+
+        impl Float for $float {
+            const NAN: Self = <$float>::NAN;
+            const ZERO: Self = 0.0;
+
+            #[inline]
+            fn is_nan(self) -> bool {
+                <$float>::is_nan(self)
+            }
+        }
+    };
+}
+
+/// The float operations canonicalization needs.
+trait Float: Copy + PartialEq {
+    const NAN: Self;
+    const ZERO: Self;
+
+    fn is_nan(self) -> bool;
+}
+
+/// Applies the folds of `P` to `x`.
+#[inline]
+fn canonical<P: FloatPolicy, F: Float>(x: F) -> F {
+    if P::FOLD_NEGATIVE_ZERO && x == F::ZERO {
+        F::ZERO
+    } else if P::FOLD_NAN && x.is_nan() {
+        F::NAN
+    } else {
+        x
+    }
+}
+
+float_impl!(f32, write_u32);
+float_impl!(f64, write_u64);
+
+macro_rules! exact_impl {
+    ($($ty:ty),*) => {$(
+        impl CanonicalHash for $ty {
+            #[inline]
+            fn canonical_hash<P: FloatPolicy, H: Hasher>(&self, state: &mut H) {
+                self.hash(state);
+            }
+
+            #[inline]
+            fn canonical_eq<P: FloatPolicy>(&self, other: &Self) -> bool {
+                self == other
+            }
+
+            #[inline]
+            fn canonical_cmp<P: FloatPolicy>(&self, other: &Self) -> Ordering {
+                self.cmp(other)
+            }
+        }
+    )*};
+}
+
+exact_impl!(
+    (),
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    str,
+    String
+);
+
+impl<T: CanonicalHash> CanonicalHash for [T] {
+    fn canonical_hash<P: FloatPolicy, H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+        for item in self {
+            item.canonical_hash::<P, H>(state);
+        }
+    }
+
+    fn canonical_eq<P: FloatPolicy>(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().zip(other).all(|(a, b)| a.canonical_eq::<P>(b))
+    }
+
+    fn canonical_cmp<P: FloatPolicy>(&self, other: &Self) -> Ordering {
+        for (a, b) in self.iter().zip(other) {
+            match a.canonical_cmp::<P>(b) {
+                Ordering::Equal => {}
+                order => return order,
+            }
+        }
+        self.len().cmp(&other.len())
+    }
+}
+
+/// Delegates to the impl for `$target`, reached through `$via`.
+macro_rules! delegate_impl {
+    ([$($generics:tt)*] $ty:ty => $target:ty, $via:expr) => {
+        impl<$($generics)*> CanonicalHash for $ty {
+            #[inline]
+            fn canonical_hash<P: FloatPolicy, H: Hasher>(&self, state: &mut H) {
+                <$target>::canonical_hash::<P, H>($via(self), state);
+            }
+
+            #[inline]
+            fn canonical_eq<P: FloatPolicy>(&self, other: &Self) -> bool {
+                <$target>::canonical_eq::<P>($via(self), $via(other))
+            }
+
+            #[inline]
+            fn canonical_cmp<P: FloatPolicy>(&self, other: &Self) -> Ordering {
+                <$target>::canonical_cmp::<P>($via(self), $via(other))
+            }
+        }
+    };
+}
+
+delegate_impl!([T: CanonicalHash, const N: usize] [T; N] => [T], <[T; N]>::as_slice);
+delegate_impl!([T: CanonicalHash] Vec<T> => [T], Vec::as_slice);
+delegate_impl!([T: CanonicalHash + ?Sized] Box<T> => T, Deref::deref);
+delegate_impl!([T: CanonicalHash + ?Sized] &T => T, Deref::deref);
+
+impl<T: CanonicalHash> CanonicalHash for Option<T> {
+    fn canonical_hash<P: FloatPolicy, H: Hasher>(&self, state: &mut H) {
+        match self {
+            None => state.write_u8(0),
+            Some(value) => {
+                state.write_u8(1);
+                value.canonical_hash::<P, H>(state);
+            }
+        }
+    }
+
+    fn canonical_eq<P: FloatPolicy>(&self, other: &Self) -> bool {
+        match (self, other) {
+            (None, None) => true,
+            (Some(a), Some(b)) => a.canonical_eq::<P>(b),
+            _ => false,
+        }
+    }
+
+    fn canonical_cmp<P: FloatPolicy>(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Some(a), Some(b)) => a.canonical_cmp::<P>(b),
+            _ => self.is_some().cmp(&other.is_some()),
+        }
+    }
+}
+
+macro_rules! tuple_impl {
+    ($($name:ident $index:tt),+) => {
+        impl<$($name: CanonicalHash),+> CanonicalHash for ($($name,)+) {
+            fn canonical_hash<P: FloatPolicy, H: Hasher>(&self, state: &mut H) {
+                $(self.$index.canonical_hash::<P, H>(state);)+
+            }
+
+            fn canonical_eq<P: FloatPolicy>(&self, other: &Self) -> bool {
+                $(self.$index.canonical_eq::<P>(&other.$index))&&+
+            }
+
+            fn canonical_cmp<P: FloatPolicy>(&self, other: &Self) -> Ordering {
+                Ordering::Equal
+                    $(.then_with(|| self.$index.canonical_cmp::<P>(&other.$index)))+
+            }
+        }
+    };
+}
+
+tuple_impl!(A 0);
+tuple_impl!(A 0, B 1);
+tuple_impl!(A 0, B 1, C 2);
+tuple_impl!(A 0, B 1, C 2, D 3);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5);
+
+/// A value keyed by its [`CanonicalHash`] impl under the policy `P`, so
+/// that it is `Eq`, `Hash` and `Ord` even if it holds floats.
+pub struct CanonicalKey<T, P = Canonical> {
+    value: T,
+    policy: PhantomData<fn() -> P>,
+}
+
+/// A [`HashMemo`] over a value holding floats, keyed through
+/// [`CanonicalKey`].
+pub type FloatKeyMemo<T, P = Canonical, H = BuildHasherDefault<DefaultHasher>> =
+    HashMemo<CanonicalKey<T, P>, H>;
+
+impl<T, P> CanonicalKey<T, P> {
+    /// Wraps `value`.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self {
+            value,
+            policy: PhantomData,
+        }
+    }
+
+    /// Returns the value, as given: canonicalization only applies to
+    /// comparisons and hashing.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T, P> From<T> for CanonicalKey<T, P> {
+    #[inline]
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T, P> AsRef<T> for CanonicalKey<T, P> {
+    #[inline]
+    fn as_ref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: CanonicalHash, P: FloatPolicy> Hash for CanonicalKey<T, P> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.canonical_hash::<P, H>(state);
+    }
+}
+
+impl<T: CanonicalHash, P: FloatPolicy> PartialEq for CanonicalKey<T, P> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.value.canonical_eq::<P>(&other.value)
+    }
+}
+
+impl<T: CanonicalHash, P: FloatPolicy> Eq for CanonicalKey<T, P> {}
+
+impl<T: CanonicalHash, P: FloatPolicy> PartialOrd for CanonicalKey<T, P> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: CanonicalHash, P: FloatPolicy> Ord for CanonicalKey<T, P> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value.canonical_cmp::<P>(&other.value)
+    }
+}
+
+impl<T: Clone, P> Clone for CanonicalKey<T, P> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self::new(self.value.clone())
+    }
+}
+
+impl<T: Copy, P> Copy for CanonicalKey<T, P> {}
+
+impl<T: fmt::Debug, P> fmt::Debug for CanonicalKey<T, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeSet, HashMap};
+    use std::hash::BuildHasher;
+
+    use super::*;
+    use crate::FixedState;
+
+    fn key<T, P>(value: T) -> CanonicalKey<T, P> {
+        CanonicalKey::new(value)
+    }
+
+    fn hash<T: CanonicalHash, P: FloatPolicy>(value: T) -> u64 {
+        FixedState.hash_one(key::<T, P>(value))
+    }
+
+    const NANS: [f64; 4] = [
+        f64::NAN,
+        -f64::NAN,
+        f64::from_bits(0x7ff0_0000_0000_0001),
+        f64::from_bits(0xfff8_dead_beef_0000),
+    ];
+
+    #[test]
+    fn nans_fold_to_one_key_under_the_canonical_policy() {
+        for nan in NANS {
+            assert!(key::<_, Canonical>(nan) == key(f64::NAN));
+            assert_eq!(hash::<_, Canonical>(nan), hash::<_, Canonical>(f64::NAN));
+            assert!(key::<_, Canonical>(nan) > key(f64::INFINITY));
+            assert!(key::<_, Canonical>(nan as f32) == key(f32::NAN));
+        }
+
+        // Without folding, each NaN is its own key, ordered by total_cmp.
+        for (i, a) in NANS.iter().enumerate() {
+            for (j, b) in NANS.iter().enumerate() {
+                assert_eq!(key::<_, TotalOrder>(*a) == key(*b), i == j);
+            }
+        }
+        assert!(key::<_, TotalOrder>(-f64::NAN) < key(f64::NEG_INFINITY));
+        assert!(key::<_, TotalOrder>(f64::NAN) > key(f64::INFINITY));
+    }
+
+    #[test]
+    fn negative_zero_folds_under_the_canonical_policy() {
+        assert!(key::<_, Canonical>(-0.0f64) == key(0.0));
+        assert_eq!(hash::<_, Canonical>(-0.0f64), hash::<_, Canonical>(0.0f64));
+        assert!(key::<_, Canonical>(-0.0f32) == key(0.0));
+
+        assert!(key::<_, TotalOrder>(-0.0f64) != key(0.0));
+        assert!(key::<_, TotalOrder>(-0.0f64) < key(0.0));
+        assert_ne!(
+            hash::<_, TotalOrder>(-0.0f64),
+            hash::<_, TotalOrder>(0.0f64)
+        );
+
+        // The value is kept as given.
+        assert!(key::<f64, Canonical>(-0.0).into_inner().is_sign_negative());
+    }
+
+    #[test]
+    fn infinities_order_around_the_finite_floats() {
+        let mut set: BTreeSet<CanonicalKey<f64>> = BTreeSet::new();
+        for x in [
+            f64::INFINITY,
+            1.5,
+            f64::NAN,
+            -0.0,
+            f64::NEG_INFINITY,
+            -2.0,
+            0.0,
+        ] {
+            set.insert(key(x));
+        }
+        let sorted: Vec<u64> = set.into_iter().map(|k| k.into_inner().to_bits()).collect();
+        let expected = [f64::NEG_INFINITY, -2.0, -0.0, 1.5, f64::INFINITY, f64::NAN];
+        assert_eq!(sorted, expected.map(f64::to_bits));
+        assert!(key::<_, Canonical>(f64::INFINITY) != key(f64::NEG_INFINITY));
+        assert!(key::<_, Canonical>(f64::INFINITY) == key(f64::INFINITY));
+    }
+
+    #[test]
+    fn containers_round_trip_through_memo_keyed_maps() {
+        type Shape = (String, Vec<[f32; 2]>, Option<f64>);
+
+        let shape = |zero: f32, nan: f64| -> Shape {
+            ("tri".to_string(), vec![[zero, 1.0], [2.0, zero]], Some(nan))
+        };
+
+        let mut map: HashMap<FloatKeyMemo<Shape>, u32> = HashMap::new();
+        map.insert(HashMemo::new(key(shape(0.0, f64::NAN))), 1);
+        assert_eq!(
+            map.get(&HashMemo::new(key(shape(-0.0, -f64::NAN)))),
+            Some(&1)
+        );
+        assert_eq!(map.get(&HashMemo::new(key(shape(1.0, f64::NAN)))), None);
+
+        let mut exact: HashMap<FloatKeyMemo<Shape, TotalOrder>, u32> = HashMap::new();
+        exact.insert(HashMemo::new(key(shape(0.0, f64::NAN))), 1);
+        exact.insert(HashMemo::new(key(shape(-0.0, f64::NAN))), 2);
+        assert_eq!(exact.len(), 2);
+
+        let (memo, value) = map.into_iter().next().unwrap();
+        assert_eq!(value, 1);
+        let (name, points, _) = memo.into_inner().into_inner();
+        assert_eq!((name.as_str(), points.len()), ("tri", 2));
+
+        // Lengths are hashed, so nested sequences do not run together.
+        let split = |a: &[f64], b: &[f64]| (a.to_vec(), b.to_vec());
+        assert!(key::<_, Canonical>(split(&[1.0], &[2.0, 3.0])) != key(split(&[1.0, 2.0], &[3.0])));
+        assert!(key::<_, Canonical>(vec![1.0f64]) < key(vec![1.0, 0.0]));
+    }
+}
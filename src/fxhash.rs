@@ -0,0 +1,70 @@
+//! [`rustc-hash`](::rustc_hash) support, for maps of many small keys.
+//!
+//! [`FxHashMemo`] memoizes with `FxHasher`, the hasher rustc uses for its
+//! own tables. It is among the fastest for small keys, but it is not
+//! DoS-resistant: inputs can be crafted to collide, so keep it away from
+//! keys an attacker controls and use [`HashMemo::new_random`] there. Its
+//! output depends on the pointer width and may change between releases of
+//! `rustc-hash`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::HashMemo;
+//! use std::collections::HashSet;
+//!
+//! let set: HashSet<_> = (0..100u32).map(HashMemo::new_fx).collect();
+//! assert!(set.contains(&HashMemo::new_fx(42)));
+//! ```
+
+use std::hash::{BuildHasherDefault, Hash};
+
+use ::rustc_hash::FxHasher;
+
+use crate::HashMemo;
+
+/// A [`HashMemo`] hashing with `FxHasher`.
+pub type FxHashMemo<T> = HashMemo<T, BuildHasherDefault<FxHasher>>;
+
+impl<T> HashMemo<T, BuildHasherDefault<FxHasher>>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new `HashMemo` using `FxHasher`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::{FxHashMemo, HashMemo};
+    ///
+    /// let memo: FxHashMemo<u64> = HashMemo::new_fx(7);
+    /// ```
+    pub const fn new_fx(value: T) -> Self {
+        Self::with_hasher(value, BuildHasherDefault::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::hash::BuildHasher;
+
+    use super::*;
+    use crate::remap_zero;
+
+    #[test]
+    fn hashes_with_fxhasher_and_works_as_a_key() {
+        let memo = HashMemo::new_fx("small".to_string());
+        assert_eq!(
+            memo.memoized_hash(),
+            remap_zero(BuildHasherDefault::<FxHasher>::new().hash_one("small"))
+        );
+
+        let mut map = HashMap::new();
+        for i in 0..1000u32 {
+            map.insert(HashMemo::new_fx(i), i);
+        }
+        assert!((0..1000).all(|i| map.get(&HashMemo::new_fx(i)) == Some(&i)));
+        assert_eq!(map.get(&HashMemo::new_fx(1000)), None);
+    }
+}
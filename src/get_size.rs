@@ -0,0 +1,113 @@
+//! Heap-size accounting through [`get_size`](::get_size).
+//!
+//! With the `get-size` feature, memos and the crate's containers implement
+//! [`GetSize`](::get_size::GetSize), so a budgeter summing the sizes of its
+//! data structures sees into them instead of counting only their inline
+//! bytes.
+//!
+//! Allocations are attributed as follows:
+//!
+//! - A [`HashMemo`]'s inline size is its value, cache and hasher, and its
+//!   heap size is the value's. Hashers are taken to own no heap memory.
+//! - Hash tables count every slot at its entry's inline size, occupied or
+//!   not, plus the heap of each entry, as `get-size` counts a `HashMap`.
+//!   Control bytes are not counted.
+//! - [`MemoArena`] counts its values, its hash chains and its index.
+//! - [`MemoMultiMap`] counts its table, each key and each key's vector of
+//!   values.
+//! - [`MemoBiMap`] counts both indexes, and each pair once, with the heaps
+//!   of both of its values.
+//! - [`MemoCache`] counts its index, its entry slab with each key and value,
+//!   and its free list. A key is shared by the index and the slab, and is
+//!   counted with the slab.
+//! - Shared allocations count the value they hold, as `get-size` counts an
+//!   `Arc`; reference counts are not counted.
+//!
+//! [`MemoArena`]: crate::MemoArena
+//! [`MemoBiMap`]: crate::MemoBiMap
+//! [`MemoCache`]: crate::MemoCache
+//! [`MemoMultiMap`]: crate::MemoMultiMap
+//!
+//! # Examples
+//!
+//! ```rust
+//! use get_size::GetSize;
+//! use hashmemo::HashMemo;
+//!
+//! let memo = HashMemo::new(String::with_capacity(64));
+//! assert_eq!(memo.get_heap_size(), 64);
+//! assert_eq!(
+//!     memo.get_size(),
+//!     std::mem::size_of::<HashMemo<String>>() + 64
+//! );
+//! ```
+
+use std::hash::{BuildHasher, Hash};
+
+use ::get_size::GetSize;
+
+use crate::HashMemo;
+
+impl<T, H> GetSize for HashMemo<T, H>
+where
+    T: Eq + Hash + GetSize,
+    H: BuildHasher,
+{
+    #[inline]
+    fn get_heap_size(&self) -> usize {
+        self.value.get_heap_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::size_of;
+
+    use super::*;
+    use crate::{MemoArena, MemoBiMap, MemoCache};
+
+    #[test]
+    fn memo_counts_the_value_heap_and_its_own_size() {
+        let mut value = String::with_capacity(32);
+        value.push_str("key");
+        let memo = HashMemo::new(value);
+
+        assert_eq!(memo.get_heap_size(), 32);
+        assert_eq!(memo.get_size(), size_of::<HashMemo<String>>() + 32);
+        #[cfg(all(target_pointer_width = "64", not(feature = "disable-cache")))]
+        assert_eq!(memo.get_size(), 64);
+
+        assert_eq!(HashMemo::new(7u64).get_heap_size(), 0);
+        assert_eq!(HashMemo::new(vec![1u32; 4]).get_heap_size(), 16);
+    }
+
+    #[test]
+    fn arena_counts_values_chains_and_index() {
+        let mut arena: MemoArena<String> = MemoArena::new();
+        assert_eq!(arena.get_heap_size(), 0);
+
+        arena.intern(String::with_capacity(100));
+        assert!(arena.get_heap_size() >= size_of::<HashMemo<String>>() + 100);
+    }
+
+    #[test]
+    fn bimap_counts_each_pair_once() {
+        let mut map: MemoBiMap<String, u32> = MemoBiMap::new();
+        let empty = map.get_heap_size();
+        map.insert(String::with_capacity(1000), 1);
+
+        let heap = map.get_heap_size() - empty;
+        assert!(heap >= 1000);
+        assert!(heap < 2000, "the left value is counted once, not per index");
+    }
+
+    #[test]
+    fn cache_counts_each_key_once() {
+        let mut cache: MemoCache<String, Vec<u8>> = MemoCache::with_capacity(4);
+        cache.put(String::with_capacity(1000), Vec::with_capacity(500));
+
+        let heap = cache.get_heap_size();
+        assert!(heap >= 1500);
+        assert!(heap < 2500, "the key is counted once, not per index");
+    }
+}
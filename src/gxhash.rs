@@ -0,0 +1,107 @@
+//! [`gxhash`](::gxhash) support, for hardware-accelerated hashing of very
+//! large values.
+//!
+//! [`GxHashMemo`] memoizes with GxHash, which hashes long inputs with AES
+//! instructions and is among the fastest hashers for them. It needs those
+//! instructions at compile time: on `x86_64`, the `aes` and `sse2` target
+//! features, and on `aarch64`, `aes` and `neon`. Build with
+//! `RUSTFLAGS="-C target-cpu=native"` or name the features explicitly, as
+//! in `RUSTFLAGS="-C target-feature=+aes,+sse2"`. On targets without them
+//! the `gxhash` feature compiles to nothing rather than breaking the build,
+//! so code naming [`GxHashMemo`] must be gated the same way.
+//!
+//! Given a fixed seed the hash is deterministic within a build. Memos must
+//! share the seed to compare and look each other up, so do not give each
+//! memo its own `GxBuildHasher::default()`, which draws a random seed.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::HashMemo;
+//! use std::collections::HashMap;
+//!
+//! let mut map = HashMap::new();
+//! map.insert(HashMemo::new_gxhash(vec![7u8; 1 << 20]), "blob");
+//! assert_eq!(map.get(&HashMemo::new_gxhash(vec![7u8; 1 << 20])), Some(&"blob"));
+//! ```
+
+use std::hash::Hash;
+
+use ::gxhash::GxBuildHasher;
+
+use crate::HashMemo;
+
+/// A [`HashMemo`] hashing with GxHash, seeded or not.
+pub type GxHashMemo<T> = HashMemo<T, GxBuildHasher>;
+
+impl<T> HashMemo<T, GxBuildHasher>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new `HashMemo` using GxHash with a seed of `0`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::{GxHashMemo, HashMemo};
+    ///
+    /// let memo: GxHashMemo<&str> = HashMemo::new_gxhash("hello world");
+    /// ```
+    pub fn new_gxhash(value: T) -> Self {
+        Self::new_gxhash_with_seed(value, 0)
+    }
+
+    /// Creates a new `HashMemo` using GxHash with `seed`.
+    ///
+    /// Memos compared or looked up together must share the seed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let memo = HashMemo::new_gxhash_with_seed("hello world", 0x5eed);
+    /// ```
+    pub fn new_gxhash_with_seed(value: T, seed: i64) -> Self {
+        Self::with_hasher(value, GxBuildHasher::with_seed(seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::hash::BuildHasher;
+
+    use super::*;
+    use crate::remap_zero;
+
+    #[test]
+    fn works_as_a_hashmap_key() {
+        let mut map = HashMap::new();
+        for i in 0..100u32 {
+            map.insert(HashMemo::new_gxhash(i.to_string().repeat(100)), i);
+        }
+        for i in 0..100u32 {
+            let key = HashMemo::new_gxhash(i.to_string().repeat(100));
+            assert_eq!(map.get(&key), Some(&i));
+        }
+        assert_eq!(map.get(&HashMemo::new_gxhash("absent".repeat(100))), None);
+    }
+
+    #[test]
+    fn hashes_with_the_configured_seed() {
+        let value = "seeded".repeat(1000);
+        let unseeded = HashMemo::new_gxhash(value.clone());
+        let seeded = HashMemo::new_gxhash_with_seed(value.clone(), 42);
+
+        assert_eq!(
+            unseeded.memoized_hash(),
+            remap_zero(GxBuildHasher::with_seed(0).hash_one(&value))
+        );
+        assert_eq!(
+            seeded.memoized_hash(),
+            remap_zero(GxBuildHasher::with_seed(42).hash_one(&value))
+        );
+        assert_ne!(seeded.memoized_hash(), unseeded.memoized_hash());
+    }
+}
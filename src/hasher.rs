@@ -0,0 +1,639 @@
+//! `BuildHasher` implementations bundled with the crate.
+
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hasher};
+
+/// A `BuildHasher` that mixes the length of every byte write into the hash.
+///
+/// Hashers consume `write` calls as one continuous byte stream, so a value
+/// that writes `"ab"` then `"c"` hashes exactly like one that writes `"a"`
+/// then `"bc"`. `LengthPrefixed` feeds each chunk's length to the inner
+/// hasher ahead of the chunk itself, which separates such concatenations.
+/// Fixed-width integer writes are forwarded unchanged.
+///
+/// Hashes produced this way differ from those of the inner hasher alone, so
+/// a memo using `LengthPrefixed` only agrees with other memos using it; the
+/// `Borrow<T>` impl of `HashMemo` does not make it hash like `T`.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::HashMemo;
+///
+/// let memo = HashMemo::new_length_prefixed(vec![1u8, 2, 3]);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LengthPrefixed<S = BuildHasherDefault<DefaultHasher>>(pub(crate) S);
+
+impl<S> LengthPrefixed<S> {
+    /// Wraps `inner` so that every byte write is prefixed with its length.
+    #[inline]
+    pub const fn new(inner: S) -> Self {
+        Self(inner)
+    }
+}
+
+impl<S: BuildHasher> BuildHasher for LengthPrefixed<S> {
+    type Hasher = LengthPrefixedHasher<S::Hasher>;
+
+    #[inline]
+    fn build_hasher(&self) -> Self::Hasher {
+        LengthPrefixedHasher(self.0.build_hasher())
+    }
+}
+
+/// The `Hasher` built by [`LengthPrefixed`].
+#[derive(Debug, Clone, Default)]
+pub struct LengthPrefixedHasher<H>(H);
+
+impl<H: Hasher> Hasher for LengthPrefixedHasher<H> {
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write_usize(bytes.len());
+        self.0.write(bytes);
+    }
+
+    #[inline]
+    fn write_u8(&mut self, i: u8) {
+        self.0.write_u8(i);
+    }
+
+    #[inline]
+    fn write_u16(&mut self, i: u16) {
+        self.0.write_u16(i);
+    }
+
+    #[inline]
+    fn write_u32(&mut self, i: u32) {
+        self.0.write_u32(i);
+    }
+
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.0.write_u64(i);
+    }
+
+    #[inline]
+    fn write_u128(&mut self, i: u128) {
+        self.0.write_u128(i);
+    }
+
+    #[inline]
+    fn write_usize(&mut self, i: usize) {
+        self.0.write_usize(i);
+    }
+}
+
+/// A `BuildHasher` that runs the murmur3 `fmix64` finalizer over the
+/// inner hasher's output.
+///
+/// Under [`PassthroughState`] or `nohash_hasher`, a map picks buckets from
+/// the low bits of a memo's cached hash as is. Inner hashers with weak low
+/// bits, or keys with pinned hashes, then crowd into a few buckets and
+/// their probe sequences grow long. `Mixed` avalanches every bit of the
+/// inner hash into every other before it is cached.
+///
+/// The mix happens once, when the hash is computed: the cached value, the
+/// `u64` a memo writes to the outer hasher and [`Probe::with_hasher`]
+/// built with the same `Mixed` state all carry the mixed hash, so they
+/// agree with each other, and equal values still hash equally. A memo
+/// using `Mixed` only agrees with other memos using it, not with memos of
+/// the bare inner hasher. `fmix64` is a bijection, so mixing adds no
+/// collisions of the full 64-bit hash.
+///
+/// [`Probe::with_hasher`]: crate::Probe::with_hasher
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{HashMemo, Mixed, PassthroughState, Probe};
+/// use std::collections::HashSet;
+///
+/// let mut set: HashSet<_, PassthroughState> = HashSet::default();
+/// set.insert(HashMemo::new_mixed("key".to_string()));
+///
+/// let mixed: Mixed = Mixed::default();
+/// assert!(set.contains(Probe::with_hasher("key", &mixed).as_lookup()));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Mixed<S = BuildHasherDefault<DefaultHasher>>(S);
+
+impl<S> Mixed<S> {
+    /// Wraps `inner` so that its output is finalized with `fmix64`.
+    #[inline]
+    pub const fn new(inner: S) -> Self {
+        Self(inner)
+    }
+}
+
+impl<S: BuildHasher> BuildHasher for Mixed<S> {
+    type Hasher = MixedHasher<S::Hasher>;
+
+    #[inline]
+    fn build_hasher(&self) -> Self::Hasher {
+        MixedHasher(self.0.build_hasher())
+    }
+}
+
+/// The `Hasher` built by [`Mixed`].
+#[derive(Debug, Clone, Default)]
+pub struct MixedHasher<H>(H);
+
+impl<H: Hasher> Hasher for MixedHasher<H> {
+    #[inline]
+    fn finish(&self) -> u64 {
+        fmix64(self.0.finish())
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+    }
+
+    #[inline]
+    fn write_u8(&mut self, i: u8) {
+        self.0.write_u8(i);
+    }
+
+    #[inline]
+    fn write_u16(&mut self, i: u16) {
+        self.0.write_u16(i);
+    }
+
+    #[inline]
+    fn write_u32(&mut self, i: u32) {
+        self.0.write_u32(i);
+    }
+
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.0.write_u64(i);
+    }
+
+    #[inline]
+    fn write_u128(&mut self, i: u128) {
+        self.0.write_u128(i);
+    }
+
+    #[inline]
+    fn write_usize(&mut self, i: usize) {
+        self.0.write_usize(i);
+    }
+}
+
+/// A deterministic `BuildHasher` whose output is stable across runs,
+/// machines and releases of this crate.
+///
+/// `DefaultHasher` makes no promise across Rust versions and `RandomState`
+/// differs on every run, which rules both out for snapshot tests. The
+/// algorithm behind `FixedState` is versioned and frozen: a given sequence
+/// of `Hasher` calls always produces the same `u64`. Note that the calls a
+/// type makes in its `Hash` impl are up to that type.
+///
+/// The current version, v1, folds input into a 64-bit state starting at
+/// `0x243f_6a88_85a3_08d3`, mixing each word `w` as
+/// `state = (state.rotate_left(5) ^ w).wrapping_mul(0x517c_c1b7_2722_0a95)`.
+/// Byte writes are consumed as little-endian 8-byte words, then a 4-, 2-
+/// and 1-byte tail; integer writes mix their value as one word (`u128` as
+/// low then high word, `usize` widened to 64 bits), so results do not depend
+/// on endianness or pointer width. `finish` applies the murmur3 `fmix64`
+/// finalizer. It is not DoS-resistant.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::HashMemo;
+///
+/// let memo = HashMemo::new_fixed("snapshot key");
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FixedState;
+
+impl BuildHasher for FixedState {
+    type Hasher = FixedHasher;
+
+    #[inline]
+    fn build_hasher(&self) -> Self::Hasher {
+        FixedHasher::default()
+    }
+}
+
+/// The `Hasher` built by [`FixedState`].
+#[derive(Debug, Clone)]
+pub struct FixedHasher {
+    state: u64,
+}
+
+impl FixedState {
+    /// Hashes a string in a `const` context, exactly as
+    /// `FixedState.hash_one(s)` does at runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::FixedState;
+    /// use std::hash::BuildHasher;
+    ///
+    /// const HELLO: u64 = FixedState::hash_str("hello");
+    /// assert_eq!(HELLO, FixedState.hash_one("hello"));
+    /// ```
+    pub const fn hash_str(s: &str) -> u64 {
+        let mut hasher = FixedHasher::new();
+        hasher.write_bytes(s.as_bytes());
+        hasher.add_word(0xff);
+        fmix64(hasher.state)
+    }
+
+    /// Hashes a byte slice in a `const` context, exactly as
+    /// `FixedState.hash_one(bytes)` does at runtime.
+    pub const fn hash_bytes(bytes: &[u8]) -> u64 {
+        let mut hasher = FixedHasher::new();
+        hasher.add_word(bytes.len() as u64);
+        hasher.write_bytes(bytes);
+        fmix64(hasher.state)
+    }
+}
+
+impl FixedHasher {
+    const SEED: u64 = 0x243f_6a88_85a3_08d3;
+    const MULTIPLIER: u64 = 0x517c_c1b7_2722_0a95;
+
+    #[inline]
+    const fn new() -> Self {
+        Self { state: Self::SEED }
+    }
+
+    /// Starts from the v1 initial state XORed with `seed`, for `DetState`.
+    #[cfg(any(test, feature = "test-util"))]
+    #[inline]
+    pub(crate) const fn seeded(seed: u64) -> Self {
+        Self {
+            state: Self::SEED ^ seed,
+        }
+    }
+
+    #[inline]
+    const fn add_word(&mut self, word: u64) {
+        self.state = (self.state.rotate_left(5) ^ word).wrapping_mul(Self::MULTIPLIER);
+    }
+
+    #[inline]
+    const fn write_bytes(&mut self, mut bytes: &[u8]) {
+        while let Some((word, rest)) = bytes.split_first_chunk::<8>() {
+            self.add_word(u64::from_le_bytes(*word));
+            bytes = rest;
+        }
+        if let Some((word, rest)) = bytes.split_first_chunk::<4>() {
+            self.add_word(u32::from_le_bytes(*word) as u64);
+            bytes = rest;
+        }
+        if let Some((word, rest)) = bytes.split_first_chunk::<2>() {
+            self.add_word(u16::from_le_bytes(*word) as u64);
+            bytes = rest;
+        }
+        if let Some(&byte) = bytes.first() {
+            self.add_word(byte as u64);
+        }
+    }
+}
+
+impl Default for FixedHasher {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for FixedHasher {
+    #[inline]
+    fn finish(&self) -> u64 {
+        fmix64(self.state)
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        self.write_bytes(bytes);
+    }
+
+    #[inline]
+    fn write_u8(&mut self, i: u8) {
+        self.add_word(i as u64);
+    }
+
+    #[inline]
+    fn write_u16(&mut self, i: u16) {
+        self.add_word(i as u64);
+    }
+
+    #[inline]
+    fn write_u32(&mut self, i: u32) {
+        self.add_word(i as u64);
+    }
+
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.add_word(i);
+    }
+
+    #[inline]
+    fn write_u128(&mut self, i: u128) {
+        self.add_word(i as u64);
+        self.add_word((i >> 64) as u64);
+    }
+
+    #[inline]
+    fn write_usize(&mut self, i: usize) {
+        self.add_word(i as u64);
+    }
+}
+
+/// A `BuildHasher` for collections keyed by [`HashMemo`](crate::HashMemo),
+/// passing the memoized hash through unchanged.
+///
+/// A memo hashes by writing its cached `u64` once, which is already the
+/// output of a full hasher. Running it through a second hasher, as the
+/// collection's default `RandomState` does, is wasted work; with
+/// `PassthroughState` the collection uses the memoized value as-is.
+///
+/// Keys that write anything other than a single `u64` still hash correctly,
+/// with each write folded in FxHash-style, but without any DoS resistance.
+/// Use it only for memo keys whose inner hasher is of good quality.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{HashMemo, PassthroughState};
+/// use std::collections::HashMap;
+///
+/// let mut map: HashMap<HashMemo<String>, u32, PassthroughState> = HashMap::default();
+/// map.insert(HashMemo::new("key".to_string()), 1);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PassthroughState;
+
+impl BuildHasher for PassthroughState {
+    type Hasher = PassthroughHasher;
+
+    #[inline]
+    fn build_hasher(&self) -> Self::Hasher {
+        PassthroughHasher::default()
+    }
+}
+
+/// The `BuildHasher` to pair with memo keys: [`PassthroughState`] under the
+/// name collections keyed by [`HashMemo`](crate::HashMemo) reach for.
+///
+/// The memo's single `write_u64` of its cached hash is captured and
+/// returned by `finish` as is, the crate-provided counterpart of
+/// `nohash_hasher` for this write pattern.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{HashMemo, MemoBuildHasher};
+/// use std::collections::HashMap;
+///
+/// let mut map: HashMap<HashMemo<String>, u32, MemoBuildHasher> = HashMap::default();
+/// map.insert(HashMemo::new("key".to_string()), 1);
+/// ```
+pub type MemoBuildHasher = PassthroughState;
+
+/// The `Hasher` built by [`PassthroughState`].
+#[derive(Debug, Clone, Default)]
+pub struct PassthroughHasher {
+    hash: u64,
+    written: bool,
+}
+
+impl PassthroughHasher {
+    #[inline]
+    fn add_word(&mut self, word: u64) {
+        self.hash = if self.written {
+            (self.hash.rotate_left(5) ^ word).wrapping_mul(FixedHasher::MULTIPLIER)
+        } else {
+            word
+        };
+        self.written = true;
+    }
+}
+
+impl Hasher for PassthroughHasher {
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.add_word(byte as u64);
+        }
+    }
+
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.add_word(i);
+    }
+}
+
+/// The murmur3 64-bit finalizer.
+#[inline]
+const fn fmix64(mut h: u64) -> u64 {
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^= h >> 33;
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hash;
+
+    use super::*;
+    use crate::HashMemo;
+
+    /// Hashes its parts as one unseparated byte stream.
+    #[derive(PartialEq, Eq)]
+    struct Concat(&'static [&'static str]);
+
+    impl Hash for Concat {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            for part in self.0 {
+                state.write(part.as_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn length_prefix_separates_concatenations() {
+        let left = Concat(&["ab", "c"]);
+        let right = Concat(&["a", "bc"]);
+
+        let plain = BuildHasherDefault::<DefaultHasher>::default();
+        assert_eq!(plain.hash_one(&left), plain.hash_one(&right));
+
+        let left = HashMemo::new_length_prefixed(left);
+        let right = HashMemo::new_length_prefixed(right);
+        assert_ne!(left.memoized_hash(), right.memoized_hash());
+    }
+
+    #[test]
+    fn length_prefix_is_deterministic() {
+        let a = HashMemo::new_length_prefixed("same".to_string());
+        let b = HashMemo::new_length_prefixed("same".to_string());
+        assert_eq!(a.memoized_hash(), b.memoized_hash());
+    }
+
+    #[test]
+    fn fixed_state_output_is_pinned() {
+        assert_eq!(FixedState.hash_one("hello"), 0x2049_ca16_2112_b21f);
+        assert_eq!(FixedState.hash_one(42u64), 0x5e18_397c_240a_21ca);
+        assert_eq!(
+            FixedState.hash_one([1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]),
+            0x769d_10ef_f2a5_a872
+        );
+        assert_eq!(
+            HashMemo::new_fixed("hello").memoized_hash(),
+            FixedState.hash_one("hello")
+        );
+    }
+
+    #[test]
+    fn const_fixed_hashes_match_the_runtime_hasher() {
+        let text = "the quick brown fox jumps over the lazy dog";
+        for len in 0..=text.len() {
+            let s = &text[..len];
+            assert_eq!(FixedState::hash_str(s), FixedState.hash_one(s));
+            assert_eq!(
+                FixedState::hash_bytes(s.as_bytes()),
+                FixedState.hash_one(s.as_bytes())
+            );
+        }
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn memo_build_hasher_maps_hash_each_value_once() {
+        use std::collections::HashMap;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(PartialEq, Eq)]
+        struct Counted(u32);
+
+        impl Hash for Counted {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                COUNT.fetch_add(1, Ordering::Relaxed);
+                self.0.hash(state);
+            }
+        }
+
+        let keys: Vec<_> = (0..64).map(|i| HashMemo::new(Counted(i))).collect();
+        let mut map: HashMap<&HashMemo<Counted>, u32, MemoBuildHasher> = HashMap::default();
+        for (i, key) in keys.iter().enumerate() {
+            map.insert(key, i as u32);
+        }
+        map.shrink_to_fit();
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(map.get(key), Some(&(i as u32)));
+        }
+        assert_eq!(map.remove(&keys[3]), Some(3));
+        assert_eq!(map.get(&keys[3]), None);
+
+        assert_eq!(COUNT.load(Ordering::Relaxed), keys.len());
+        assert_eq!(
+            MemoBuildHasher::default().hash_one(&keys[0]),
+            keys[0].memoized_hash()
+        );
+    }
+
+    /// Hashes a `u64` to itself shifted left, leaving the low bits zero.
+    #[derive(Default)]
+    struct ShiftHasher(u64);
+
+    impl Hasher for ShiftHasher {
+        fn finish(&self) -> u64 {
+            self.0 << 20
+        }
+
+        fn write(&mut self, _: &[u8]) {
+            unimplemented!("only u64 keys are hashed")
+        }
+
+        fn write_u64(&mut self, i: u64) {
+            self.0 = i;
+        }
+    }
+
+    /// The chi-square statistic of `hashes` over `buckets` low-bit buckets,
+    /// against a uniform spread.
+    fn chi_square(hashes: impl Iterator<Item = u64>, buckets: usize) -> f64 {
+        let mut counts = vec![0usize; buckets];
+        let mut total = 0;
+        for hash in hashes {
+            counts[hash as usize & (buckets - 1)] += 1;
+            total += 1;
+        }
+        let expected = total as f64 / buckets as f64;
+        counts
+            .iter()
+            .map(|&count| (count as f64 - expected).powi(2) / expected)
+            .sum()
+    }
+
+    #[test]
+    fn mixing_spreads_low_entropy_hashes_over_buckets() {
+        const BUCKETS: usize = 256;
+        let keys = 0..16 * BUCKETS as u64;
+
+        let plain = BuildHasherDefault::<ShiftHasher>::default;
+        let plain_chi = chi_square(
+            keys.clone()
+                .map(|k| HashMemo::with_hasher(k, plain()).memoized_hash()),
+            BUCKETS,
+        );
+        let mixed_chi = chi_square(
+            keys.map(|k| HashMemo::with_hasher(k, Mixed::new(plain())).memoized_hash()),
+            BUCKETS,
+        );
+
+        // Every key lands in bucket 0 unmixed. Mixed, the statistic should
+        // sit near its 255 degrees of freedom; 400 is far in the tail.
+        assert!(plain_chi > 1000.0 * BUCKETS as f64, "{plain_chi}");
+        assert!(mixed_chi < 400.0, "{mixed_chi}");
+    }
+
+    #[test]
+    fn mixed_memos_agree_with_each_other_and_with_probes() {
+        let a = HashMemo::new_mixed("same".to_string());
+        let b = HashMemo::new_mixed("same".to_string());
+        assert_eq!(a.memoized_hash(), b.memoized_hash());
+        assert_eq!(a, b);
+        assert_eq!(
+            a.memoized_hash(),
+            fmix64(BuildHasherDefault::<DefaultHasher>::default().hash_one("same"))
+        );
+
+        let mut map = std::collections::HashMap::<_, _, PassthroughState>::default();
+        map.insert(a, 1);
+        let probe = crate::Probe::with_hasher(
+            "same",
+            &Mixed::<BuildHasherDefault<DefaultHasher>>::default(),
+        );
+        assert_eq!(map.get(probe.as_lookup()), Some(&1));
+        assert_eq!(map.get(crate::Probe::new("same").as_lookup()), None);
+    }
+
+    #[test]
+    fn passthrough_returns_the_memoized_hash() {
+        let memo = HashMemo::new("key".to_string());
+        assert_eq!(PassthroughState.hash_one(&memo), memo.memoized_hash());
+    }
+}
@@ -0,0 +1,234 @@
+//! A process-wide hook observing cold hash computations.
+//!
+//! A code path that rebuilds memos from scratch, or clones them without
+//! their hash, stays correct but hashes every value again, and nothing
+//! reports it. With the `hook` feature, [`set_recompute_hook`] installs a
+//! function called each time a memo or [`Memoized`] field computes its
+//! hash, with a [`RecomputeEvent`] naming the type, so an application can
+//! count, log or sample the recomputations it did not expect.
+//!
+//! A warm cache never reaches the hook. While no hook is installed, a cold
+//! computation costs one relaxed load more than without the feature. The
+//! hook runs on whichever thread computed the hash, possibly on many at
+//! once; any cold memo it hashes itself is reported to it too.
+//!
+//! For the computations of one memo rather than the whole process, see
+//! `HashMemo::with_stats` under the `stats` feature.
+//!
+//! [`Memoized`]: crate::Memoized
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::hook::{self, RecomputeEvent};
+//! use hashmemo::HashMemo;
+//! use std::sync::atomic::{AtomicU64, Ordering};
+//!
+//! static RECOMPUTED_BYTES: AtomicU64 = AtomicU64::new(0);
+//!
+//! fn observe(event: &RecomputeEvent) {
+//!     if event.type_name == "alloc::string::String" {
+//!         RECOMPUTED_BYTES.fetch_add(event.bytes as u64, Ordering::Relaxed);
+//!     }
+//! }
+//!
+//! hook::set_recompute_hook(observe);
+//! HashMemo::new("key".to_string()).hash_one_cached();
+//! assert!(RECOMPUTED_BYTES.load(Ordering::Relaxed) >= 4);
+//! hook::clear_recompute_hook();
+//! ```
+
+use std::any::type_name;
+use std::hash::{BuildHasher, Hash};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{PoisonError, RwLock};
+
+use crate::counting::hash_counted;
+
+/// The signature of a recompute hook.
+pub type RecomputeHook = fn(&RecomputeEvent);
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static HOOK: RwLock<Option<RecomputeHook>> = RwLock::new(None);
+
+/// A cold hash computation, passed to the installed hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RecomputeEvent {
+    /// The [`type_name`] of the hashed value.
+    pub type_name: &'static str,
+    /// The computed hash, as the memo caches it.
+    pub hash: u64,
+    /// The number of bytes fed to the hasher, an approximation of the size
+    /// of the value.
+    pub bytes: usize,
+}
+
+/// Installs `hook` for every cold computation in the process, returning
+/// the hook it replaces.
+pub fn set_recompute_hook(hook: RecomputeHook) -> Option<RecomputeHook> {
+    let mut slot = HOOK.write().unwrap_or_else(PoisonError::into_inner);
+    INSTALLED.store(true, Ordering::Relaxed);
+    slot.replace(hook)
+}
+
+/// Removes the installed hook, returning it.
+pub fn clear_recompute_hook() -> Option<RecomputeHook> {
+    let mut slot = HOOK.write().unwrap_or_else(PoisonError::into_inner);
+    INSTALLED.store(false, Ordering::Relaxed);
+    slot.take()
+}
+
+/// Returns the installed hook, if any.
+#[inline]
+fn installed() -> Option<RecomputeHook> {
+    if !INSTALLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    *HOOK.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Hashes `value` like `hasher.hash_one(value)`, remapping `0` to `1`, and
+/// reports the computation to the installed hook.
+pub(crate) fn hash_cold<T, H>(hasher: &H, value: &T) -> u64
+where
+    T: Hash + ?Sized,
+    H: BuildHasher,
+{
+    let Some(hook) = installed() else {
+        #[cfg(feature = "tracing")]
+        return crate::tracing::hash_cold(hasher, value);
+        #[cfg(not(feature = "tracing"))]
+        return crate::remap_zero(hasher.hash_one(value));
+    };
+
+    let (hash, bytes) = hash_counted(hasher, value);
+    #[cfg(feature = "tracing")]
+    crate::tracing::cold_event::<T>(hash, bytes);
+    hook(&RecomputeEvent {
+        type_name: type_name::<T>(),
+        hash,
+        bytes,
+    });
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::hash::RandomState;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use super::*;
+    use crate::{HashMemo, Memoized};
+
+    /// Serializes the tests, which share the process-wide hook.
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    /// The events seen by [`record`], by type name.
+    static EVENTS: Mutex<Option<HashMap<&'static str, Vec<RecomputeEvent>>>> = Mutex::new(None);
+
+    fn record(event: &RecomputeEvent) {
+        EVENTS
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .entry(event.type_name)
+            .or_default()
+            .push(*event);
+    }
+
+    /// Returns and forgets the events recorded for `T`, which each test
+    /// declares locally so that other threads' memos do not show up.
+    fn events_for<T>() -> Vec<RecomputeEvent> {
+        EVENTS
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .remove(type_name::<T>())
+            .unwrap_or_default()
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn counts_cold_computations_of_scripted_workloads() {
+        #[derive(Clone, PartialEq, Eq, Hash)]
+        struct Key(String);
+
+        let _serial = SERIAL.lock().unwrap_or_else(PoisonError::into_inner);
+        set_recompute_hook(record);
+        let state = RandomState::new();
+
+        // Ten keys hashed five times each: one computation per key.
+        let memos: Vec<_> = (0..10)
+            .map(|i| HashMemo::new(Key(i.to_string().repeat(100))))
+            .collect();
+        for _ in 0..5 {
+            memos.iter().for_each(|memo| {
+                state.hash_one(memo);
+            });
+        }
+        let events = events_for::<Key>();
+        assert_eq!(events.len(), 10);
+        for (event, memo) in events.iter().zip(&memos) {
+            assert_eq!(event.hash, memo.hash_one_cached());
+            // `str` hashing writes the bytes and a terminator.
+            assert_eq!(event.bytes, 101);
+        }
+
+        // The bug the hook is for: rebuilding memos on every pass.
+        for _ in 0..5 {
+            memos.iter().for_each(|memo| {
+                state.hash_one(memo.clone_without_hash());
+            });
+        }
+        assert_eq!(events_for::<Key>().len(), 50);
+
+        let mut field = Memoized::new(Key("field".to_string()));
+        state.hash_one(&field);
+        state.hash_one(&field);
+        field.set(Key("other".to_string()));
+        state.hash_one(&field);
+        assert_eq!(events_for::<Key>().len(), 2);
+
+        assert!(clear_recompute_hook().is_some());
+        HashMemo::new(Key("unobserved".to_string())).hash_one_cached();
+        assert!(events_for::<Key>().is_empty());
+        assert!(clear_recompute_hook().is_none());
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn runs_on_every_hashing_thread() {
+        #[derive(PartialEq, Eq, Hash)]
+        struct Shared(u64);
+
+        let _serial = SERIAL.lock().unwrap_or_else(PoisonError::into_inner);
+        set_recompute_hook(record);
+
+        let memos: Arc<Vec<_>> = Arc::new((0..1000).map(|i| HashMemo::new(Shared(i))).collect());
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                let memos = Arc::clone(&memos);
+                thread::spawn(move || {
+                    // Each thread primes its own quarter.
+                    for memo in memos.iter().skip(t).step_by(4) {
+                        memo.hash_one_cached();
+                    }
+                })
+            })
+            .collect();
+        threads.into_iter().for_each(|t| t.join().unwrap());
+        memos.iter().for_each(|memo| {
+            memo.hash_one_cached();
+        });
+
+        let mut hashes: Vec<u64> = events_for::<Shared>().iter().map(|e| e.hash).collect();
+        let mut expected: Vec<u64> = memos.iter().map(HashMemo::hash_one_cached).collect();
+        hashes.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(hashes, expected);
+        clear_recompute_hook();
+    }
+}
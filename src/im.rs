@@ -0,0 +1,92 @@
+//! [`im`](::im) support for persistent collections as memoized values.
+//!
+//! `im` collections share structure between clones, so cloning one is
+//! cheap, but their `Hash` impls still walk every element. Wrapping them in
+//! a memo caches that walk. A clone of a collection has the same contents,
+//! so memos over the original and the clone compare equal and share a hash;
+//! cloning the memo itself also carries the cached hash along.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::HashMemo;
+//! use std::collections::HashMap;
+//!
+//! let state: HashMemo<im::Vector<u32>> = HashMemo::from((0..1000).collect::<Vec<_>>());
+//! let mut seen = HashMap::new();
+//! seen.insert(state.clone(), "initial");
+//! assert_eq!(seen.get(&state), Some(&"initial"));
+//! ```
+
+use std::hash::Hash;
+
+use ::im::{OrdMap, Vector};
+
+use crate::HashMemo;
+
+impl<A> From<Vec<A>> for HashMemo<Vector<A>>
+where
+    A: Clone + Eq + Hash,
+{
+    fn from(items: Vec<A>) -> Self {
+        Self::new(Vector::from(items))
+    }
+}
+
+impl<K, V> From<Vec<(K, V)>> for HashMemo<OrdMap<K, V>>
+where
+    K: Ord + Clone + Hash,
+    V: Clone + Eq + Hash,
+{
+    fn from(entries: Vec<(K, V)>) -> Self {
+        Self::new(entries.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn vector_memos_work_as_map_keys() {
+        let mut map = HashMap::new();
+        let state: HashMemo<Vector<u32>> = HashMemo::from(vec![1, 2, 3]);
+        map.insert(state, "a");
+
+        let mut next = Vector::from(vec![1, 2, 3]);
+        assert_eq!(map.get(&HashMemo::new(next.clone())), Some(&"a"));
+        next.push_back(4);
+        assert_eq!(map.get(&HashMemo::new(next)), None);
+    }
+
+    #[test]
+    fn ord_map_memos_work_as_map_keys() {
+        let mut map = HashMap::new();
+        let state: HashMemo<OrdMap<String, u32>> =
+            HashMemo::from(vec![("b".to_string(), 2), ("a".to_string(), 1)]);
+        map.insert(state, 1);
+
+        let same: OrdMap<String, u32> = [("a".to_string(), 1u32), ("b".to_string(), 2)]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            map.get(&HashMemo::new(same.update("c".to_string(), 3))),
+            None
+        );
+        assert_eq!(map.get(&HashMemo::new(same)), Some(&1));
+    }
+
+    #[test]
+    fn structurally_shared_clones_agree() {
+        let original: Vector<u64> = (0..10_000).collect();
+        let shared = original.clone();
+
+        let a = HashMemo::new(original);
+        let b = HashMemo::new(shared);
+        assert!(a == b);
+        assert_eq!(a.memoized_hash(), b.memoized_hash());
+        assert_eq!(a.clone().memoized_hash(), a.memoized_hash());
+    }
+}
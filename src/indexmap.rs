@@ -0,0 +1,105 @@
+//! [`indexmap`](::indexmap) interop for insertion-ordered maps keyed by
+//! memoized values.
+//!
+//! `indexmap` resolves borrowed lookups through its `Equivalent` trait,
+//! which it implements for every `Q` that the key borrows as. Since
+//! `HashMemo` borrows as `dyn MemoLookup<Q>`, a [`Probe`](crate::Probe)
+//! works with `get`, `get_index_of`, `swap_remove`, `shift_remove` and the
+//! rest without further impls. A bare `&str` cannot be made to work: the
+//! map would hash its bytes rather than the memoized `u64`.
+//!
+//! The aliases use [`PassthroughState`] as the outer hasher, so the map
+//! consumes the memoized hash directly. Both collect from iterators of
+//! memoized keys:
+//!
+//! ```rust
+//! use hashmemo::{HashMemo, MemoIndexMap, Probe};
+//!
+//! let map: MemoIndexMap<String, u32> = [("a", 1), ("b", 2)]
+//!     .into_iter()
+//!     .map(|(k, v)| (HashMemo::new(k.to_string()), v))
+//!     .collect();
+//!
+//! assert_eq!(map.get(Probe::new("b").as_lookup()), Some(&2));
+//! assert_eq!(map.get_index_of(Probe::new("a").as_lookup()), Some(0));
+//! ```
+
+use std::hash::{BuildHasherDefault, DefaultHasher};
+
+use ::indexmap::{IndexMap, IndexSet};
+
+use crate::{HashMemo, PassthroughState};
+
+/// An `IndexMap` keyed by [`HashMemo`], using the memoized hash directly.
+///
+/// `H` is the hasher of the keys; lookups must use a
+/// [`Probe`](crate::Probe) built with the same hasher.
+pub type MemoIndexMap<K, V, H = BuildHasherDefault<DefaultHasher>> =
+    IndexMap<HashMemo<K, H>, V, PassthroughState>;
+
+/// An `IndexSet` of [`HashMemo`]s, using the memoized hash directly.
+///
+/// `H` is the hasher of the values; lookups must use a
+/// [`Probe`](crate::Probe) built with the same hasher.
+pub type MemoIndexSet<T, H = BuildHasherDefault<DefaultHasher>> =
+    IndexSet<HashMemo<T, H>, PassthroughState>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Probe;
+
+    fn map_of(keys: &[&str]) -> MemoIndexMap<String, usize> {
+        keys.iter()
+            .enumerate()
+            .map(|(i, k)| (HashMemo::new(k.to_string()), i))
+            .collect()
+    }
+
+    fn keys<V>(map: &MemoIndexMap<String, V>) -> Vec<&str> {
+        map.keys().map(|k| k.value.as_str()).collect()
+    }
+
+    #[test]
+    fn map_preserves_insertion_order() {
+        let mut map = map_of(&["c", "a", "b"]);
+        map.insert(HashMemo::new("a".to_string()), 10);
+        assert_eq!(keys(&map), ["c", "a", "b"]);
+        assert_eq!(map.get(Probe::new("a").as_lookup()), Some(&10));
+        assert_eq!(map.get_index_of(Probe::new("b").as_lookup()), Some(2));
+        assert_eq!(map.get(Probe::new("d").as_lookup()), None);
+    }
+
+    #[test]
+    fn swap_remove_moves_last_entry_into_the_gap() {
+        let mut map = map_of(&["a", "b", "c", "d"]);
+        assert_eq!(map.swap_remove(Probe::new("b").as_lookup()), Some(1));
+        assert_eq!(keys(&map), ["a", "d", "c"]);
+        assert_eq!(map.get_index_of(Probe::new("d").as_lookup()), Some(1));
+        assert_eq!(map.swap_remove(Probe::new("b").as_lookup()), None);
+    }
+
+    #[test]
+    fn shift_remove_keeps_remaining_order() {
+        let mut map = map_of(&["a", "b", "c", "d"]);
+        assert_eq!(map.shift_remove(Probe::new("b").as_lookup()), Some(1));
+        assert_eq!(keys(&map), ["a", "c", "d"]);
+        assert_eq!(map.get_index_of(Probe::new("d").as_lookup()), Some(2));
+        assert_eq!(map.get(Probe::new("c").as_lookup()), Some(&2));
+    }
+
+    #[test]
+    fn set_supports_borrowed_lookups() {
+        let mut set: MemoIndexSet<String> = ["x", "y", "z"]
+            .into_iter()
+            .map(|v| HashMemo::new(v.to_string()))
+            .collect();
+
+        assert!(set.contains(Probe::new("y").as_lookup()));
+        assert_eq!(set.get_index_of(Probe::new("z").as_lookup()), Some(2));
+        assert!(set.shift_remove(Probe::new("x").as_lookup()));
+        assert!(!set.contains(Probe::new("x").as_lookup()));
+        let values: Vec<_> = set.iter().map(|v| v.value.as_str()).collect();
+        assert_eq!(values, ["y", "z"]);
+    }
+}
@@ -0,0 +1,311 @@
+//! Keys that may or may not carry a memoized hash.
+
+use std::hash::{BuildHasher, Hash};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::{ArcMemo, HashMemo, Prehashed};
+
+/// A key whose hash a generic container can take from a cache, if it has
+/// one.
+///
+/// Code generic over `K: MemoKey` accepts plain keys such as `String` and
+/// memoized ones such as `HashMemo<String>` alike, and hashes each through
+/// [`key_hash`](Self::key_hash): plain keys are hashed with the container's
+/// state on every call, memoized keys return their cached hash.
+///
+/// A blanket impl for every `Hash + Eq` type would overlap with the impls
+/// for memos, which are `Hash + Eq` themselves, so plain keys are covered
+/// type by type: integers, `bool`, `char`, `str`, `String`, paths, and
+/// `Vec`, slices, `Option` and tuples of up to four `Hash + Eq` values.
+/// References, `Box`, `Rc` and `Arc` are keys when what they point to is,
+/// and keep its cache. Other plain types can be keyed through
+/// [`Prehashed`] or a memo, or implement the trait with `cached_hash`
+/// returning `None`.
+///
+/// One container must not mix hashes from different sources for equal
+/// keys. Every key of one type hashes the same way, either always through
+/// the container's state or always through its own cache, so this holds as
+/// long as a container holds keys of one type, as its type parameter
+/// ensures.
+///
+/// # Examples
+///
+/// A container generic over its keys, used with plain and memoized ones:
+///
+/// ```rust
+/// use hashmemo::{HashMemo, MemoKey};
+/// use std::hash::RandomState;
+///
+/// /// A fixed-size set, bucketed by `key_hash`.
+/// struct Buckets<K> {
+///     buckets: Vec<Vec<K>>,
+///     state: RandomState,
+/// }
+///
+/// impl<K: MemoKey> Buckets<K> {
+///     fn new() -> Self {
+///         Buckets { buckets: (0..64).map(|_| Vec::new()).collect(), state: RandomState::new() }
+///     }
+///
+///     fn bucket(&self, key: &K) -> usize {
+///         (key.key_hash(&self.state) % 64) as usize
+///     }
+///
+///     fn insert(&mut self, key: K) -> bool {
+///         let i = self.bucket(&key);
+///         if self.buckets[i].iter().any(|k| k.value() == key.value()) {
+///             return false;
+///         }
+///         self.buckets[i].push(key);
+///         true
+///     }
+///
+///     fn contains(&self, key: &K) -> bool {
+///         self.buckets[self.bucket(key)].iter().any(|k| k.value() == key.value())
+///     }
+/// }
+///
+/// let mut plain = Buckets::new();
+/// let mut memoized = Buckets::new();
+/// for word in ["a", "b", "a"] {
+///     let fresh = plain.insert(word.to_string());
+///     assert_eq!(memoized.insert(HashMemo::new(word.to_string())), fresh);
+/// }
+/// assert!(plain.contains(&"b".to_string()));
+/// assert!(memoized.contains(&HashMemo::new("b".to_string())));
+/// ```
+pub trait MemoKey {
+    /// The value compared and hashed, without any cache.
+    type Value: ?Sized + Hash + Eq;
+
+    /// Returns the value.
+    fn value(&self) -> &Self::Value;
+
+    /// Returns the key's own hash, if it carries one.
+    ///
+    /// Memos return their memoized hash, computing and caching it on first
+    /// use, so for a given key type this is either always `Some` or always
+    /// `None`. Under the `disable-cache` feature memos have no cache and
+    /// return `None`, so they are hashed like plain keys.
+    fn cached_hash(&self) -> Option<u64>;
+
+    /// Hashes the value with `state`, ignoring any cache.
+    #[inline]
+    fn hash_value_with<S: BuildHasher>(&self, state: &S) -> u64 {
+        state.hash_one(self.value())
+    }
+
+    /// Returns the key's own hash if it has one, and otherwise hashes the
+    /// value with `state`.
+    #[inline]
+    fn key_hash<S: BuildHasher>(&self, state: &S) -> u64 {
+        match self.cached_hash() {
+            Some(hash) => hash,
+            None => self.hash_value_with(state),
+        }
+    }
+}
+
+impl<T, H> MemoKey for HashMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    type Value = T;
+
+    #[inline]
+    fn value(&self) -> &T {
+        self.as_ref()
+    }
+
+    #[inline]
+    fn cached_hash(&self) -> Option<u64> {
+        #[cfg(feature = "disable-cache")]
+        return None;
+        #[cfg(not(feature = "disable-cache"))]
+        Some(self.memoized_hash())
+    }
+}
+
+impl<T, H> MemoKey for ArcMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    type Value = T;
+
+    #[inline]
+    fn value(&self) -> &T {
+        self.as_ref()
+    }
+
+    #[inline]
+    fn cached_hash(&self) -> Option<u64> {
+        #[cfg(feature = "disable-cache")]
+        return None;
+        #[cfg(not(feature = "disable-cache"))]
+        Some(self.memo().memoized_hash())
+    }
+}
+
+impl<T> MemoKey for Prehashed<T>
+where
+    T: Eq + Hash,
+{
+    type Value = T;
+
+    #[inline]
+    fn value(&self) -> &T {
+        self.as_ref()
+    }
+
+    #[inline]
+    fn cached_hash(&self) -> Option<u64> {
+        Some(self.stored_hash())
+    }
+}
+
+macro_rules! plain_impl {
+    ($([$($generics:tt)*] $ty:ty),* $(,)?) => {$(
+        impl<$($generics)*> MemoKey for $ty {
+            type Value = Self;
+
+            #[inline]
+            fn value(&self) -> &Self {
+                self
+            }
+
+            #[inline]
+            fn cached_hash(&self) -> Option<u64> {
+                None
+            }
+        }
+    )*};
+}
+
+plain_impl!(
+    [] u8, [] u16, [] u32, [] u64, [] u128, [] usize,
+    [] i8, [] i16, [] i32, [] i64, [] i128, [] isize,
+    [] bool, [] char, [] str, [] String, [] Path, [] PathBuf,
+    [T: Hash + Eq] [T],
+    [T: Hash + Eq] Vec<T>,
+    [T: Hash + Eq] Option<T>,
+    [A: Hash + Eq, B: Hash + Eq] (A, B),
+    [A: Hash + Eq, B: Hash + Eq, C: Hash + Eq] (A, B, C),
+    [A: Hash + Eq, B: Hash + Eq, C: Hash + Eq, D: Hash + Eq] (A, B, C, D),
+);
+
+macro_rules! pointer_impl {
+    ($([$($generics:tt)*] $ty:ty),* $(,)?) => {$(
+        impl<$($generics)* K: MemoKey + ?Sized> MemoKey for $ty {
+            type Value = K::Value;
+
+            #[inline]
+            fn value(&self) -> &K::Value {
+                (**self).value()
+            }
+
+            #[inline]
+            fn cached_hash(&self) -> Option<u64> {
+                (**self).cached_hash()
+            }
+        }
+    )*};
+}
+
+pointer_impl!(['a,] &'a K, [] Box<K>, [] Rc<K>, [] Arc<K>);
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::hash::RandomState;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// Counts keys by value, hashing each through `key_hash`.
+    struct Tally<K> {
+        counts: HashMap<u64, Vec<(K, usize)>>,
+        state: RandomState,
+    }
+
+    impl<K: MemoKey> Tally<K> {
+        fn new() -> Self {
+            Tally {
+                counts: HashMap::new(),
+                state: RandomState::new(),
+            }
+        }
+
+        fn add(&mut self, key: K) -> usize {
+            let bucket = self.counts.entry(key.key_hash(&self.state)).or_default();
+            match bucket.iter_mut().find(|(k, _)| k.value() == key.value()) {
+                Some((_, count)) => {
+                    *count += 1;
+                    *count
+                }
+                None => {
+                    bucket.push((key, 1));
+                    1
+                }
+            }
+        }
+    }
+
+    static HASHED: AtomicUsize = AtomicUsize::new(0);
+
+    /// Counts how often it is hashed, across all instances.
+    #[derive(PartialEq, Eq)]
+    struct Counted(String);
+
+    impl Hash for Counted {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            HASHED.fetch_add(1, Ordering::Relaxed);
+            self.0.hash(state);
+        }
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn plain_and_memoized_keys_behave_alike() {
+        let words = ["x", "y", "x", "z", "x", "y"];
+        let mut plain = Tally::new();
+        let mut memoized = Tally::new();
+        let mut shared = Tally::new();
+        for word in words {
+            let count = plain.add(word.to_string());
+            assert_eq!(memoized.add(HashMemo::new(word.to_string())), count);
+            assert_eq!(shared.add(Arc::new(ArcMemo::new(word.to_string()))), count);
+        }
+        assert_eq!(plain.add("x".to_string()), 4);
+
+        let memo = HashMemo::new("key".to_string());
+        assert_eq!(memo.cached_hash(), Some(memo.hash_one_cached()));
+        assert_eq!(
+            (&&memo).key_hash(&RandomState::new()),
+            memo.hash_one_cached()
+        );
+        assert_eq!("key".cached_hash(), None);
+        let state = RandomState::new();
+        assert_eq!(memo.hash_value_with(&state), "key".key_hash(&state));
+        assert_eq!(Prehashed::new(1u8, 7).key_hash(&state), 7);
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn memoized_keys_are_hashed_once() {
+        let mut tally = Tally::new();
+        let keys: Vec<_> = (0..10)
+            .map(|i| HashMemo::new(Counted(i.to_string())))
+            .collect();
+        let before = HASHED.load(Ordering::Relaxed);
+        for _ in 0..5 {
+            for key in &keys {
+                tally.add(key);
+            }
+        }
+        assert_eq!(HASHED.load(Ordering::Relaxed) - before, 10);
+    }
+}
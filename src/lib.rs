@@ -15,6 +15,94 @@
 //! - Minimal memory overhead with zero-sized hashers
 //! - Works with any `BuildHasher` implementation
 //!
+//! ## Optional Features
+//!
+//! - `arbitrary`: `Arbitrary` for fuzzing inputs containing memos, cold or primed
+//! - `bincode`: bincode 2 `Encode`/`Decode`, see the [`bincode`](crate::bincode) module
+//! - `bytes`: the [`MemoSharedBytes`] alias for memoized `bytes::Bytes` payloads
+//! - `compact_str`: the [`MemoCompactString`] alias, hashing like `String` memos
+//! - `crypto`: [`MacMemo`], memoizing a keyed BLAKE3 tag for cache keys that
+//!   must not be predictable or forgeable without a secret key
+//! - `dashmap`: the [`MemoDashMap`] alias for concurrent maps keyed by memos,
+//!   looked up through [`Probe`]
+//! - `derive`: `#[derive(HashMemoize)]` for structs embedding a [`HashCache`]
+//!   instead of being wrapped in a memo
+//! - `diagnostics`: finding and reporting values that share a memoized hash,
+//!   and measuring how evenly hashes spread, see the
+//!   [`diagnostics`](crate::diagnostics) module
+//! - `digest`: [`DigestBuildHasher`], hashing with any `digest::Digest`
+//!   truncated to 64 bits, and `new_digest`, see the
+//!   [`digest`](crate::digest) module
+//! - `disable-cache`: turns `HashMemo` into a plain wrapper that hashes its
+//!   value on every call and is no larger than the value and hasher, with
+//!   the same API; for measuring what memoization saves in an application.
+//!   Hashes supplied from outside, as by `with_hasher_const`, are dropped
+//!   too, so memos seeded with a hash other than their own hash differently
+//! - `fxhash`: the [`FxHashMemo`] alias and `new_fx`, hashing with
+//!   `rustc-hash`'s fast but not DoS-resistant `FxHasher`
+//! - `get-size`: `GetSize` for memos and the crate's containers, so heap
+//!   budgets can measure them, see the [`get_size`](crate::get_size) module
+//! - `gxhash`: the `GxHashMemo` alias and `new_gxhash` constructors, hashing
+//!   with AES-accelerated GxHash; needs AES target features at build time and
+//!   is empty without them
+//! - `hook`: a process-wide hook called on every cold hash computation, to
+//!   catch unexpected recomputation, see the [`hook`](crate::hook) module
+//! - `im`: conveniences for memos over `im` persistent collections
+//! - `indexmap`: the [`MemoIndexMap`] and [`MemoIndexSet`] aliases for
+//!   insertion-ordered collections keyed by memos, looked up through [`Probe`]
+//! - `lru`: the [`MemoLruCache`] alias and [`MemoLruExt`] for LRU caches keyed
+//!   by memos
+//! - `metrohash`: the [`MetroHashMemo`] alias and `new_metrohash`
+//!   constructors, hashing with seeded 64-bit MetroHash
+//! - `moka`: the [`MemoMokaCache`] and [`ArcMemoMokaCache`] aliases and
+//!   [`MemoMokaExt`] for concurrent `moka` caches keyed by memos
+//! - `portable-atomic`: keeps the cache in a `portable_atomic::AtomicU64`, for
+//!   targets without native 64-bit atomics; the public API is unchanged
+//! - `proptest`: strategies for memoized values, see the
+//!   [`proptest`](crate::proptest) module
+//! - `quickcheck`: `Arbitrary` for memos, cold or primed, shrinking the value
+//! - `rkyv`: zero-copy archiving that keeps the cached hash, see [`ArchivedHashMemo`]
+//! - `rolling`: [`RollingMemo`], a Rabin–Karp rolling hash over a sliding
+//!   window of bytes that freezes into memos, for chunk deduplication, see
+//!   the [`rolling`](crate::rolling) module
+//! - `seahash`: the [`SeaHashMemo`] alias and `new_seahash`, whose cached
+//!   hashes are stable across runs and machines
+//! - `serde`: transparent `Serialize`/`Deserialize`, plus the tagged
+//!   [`HashMemoTagged`], [`HashMemoVerified`] and [`HashMemoSeeded`] forms
+//!   and a columnar form for collections of memos, see the
+//!   [`serde`](crate::serde) module
+//! - `siphash`: the [`SipHashMemo`] alias and `new_siphash` constructors,
+//!   hashing with SipHash under a secret 128-bit key for adversarial inputs
+//! - `smallvec`: `SmallVec` and `ArrayVec` memos that hash like `Vec`
+//!   memos, looked up by slice through [`Probe`]
+//! - `smol_str`: the [`MemoSmolStr`] alias, hashing like `String` memos
+//! - `stable`: the [`StableMemo`] alias and `new_stable`, hashing with a
+//!   documented, portable SipHash-1-3 whose hashes are safe to persist, see
+//!   the [`stable`](crate::stable) module
+//! - `stats`: process-wide cache hit and computation counters, see the
+//!   [`stats`](crate::stats) module
+//! - `subtle`: constant-time equality for secret-bearing keys, see [`CtMemo`]
+//! - `t1ha`: the [`T1haMemo`] alias and `new_t1ha` constructors, hashing with
+//!   seeded t1ha0 for in-memory use; not DoS-resistant
+//! - `test-util`: [`DetState`], a seeded hasher with a documented, frozen
+//!   algorithm for reproducible tests, with the [`DetMemo`] alias and
+//!   `HashMemo::deterministic`, see the [`test_util`](crate::test_util)
+//!   module
+//! - `tokio`: [`ArcMemo::prime_async`] and [`ArcMemo::prime_all_async`],
+//!   computing cold hashes on the blocking pool instead of an executor thread
+//! - `tracing`: a `DEBUG` event for every cold hash computation, with the
+//!   value's type, hash and approximate size; warm hashes stay silent
+//! - `url`: [`MemoUrl`], a URL normalized on construction with a memoized
+//!   hash, for deduplicating crawled links
+//! - `validate`: debug-build checks that
+//!   [`with_mut_unchecked`](HashMemo::with_mut_unchecked) leaves the hash
+//!   unchanged
+//! - `wyhash`: the [`WyHashMemo`] alias and `new_wyhash` constructors, hashing
+//!   with seeded or unseeded wyhash
+//! - `xxhash`: the [`XxHashMemo`] alias and `new_xxh3` constructors, hashing
+//!   with seeded or unseeded XXH3
+//! - `zeroize`: `Zeroize` for memos wrapping secrets, scrubbing the cached hash too
+//!
 //! ## Examples
 //!
 //! ```rust
@@ -40,20 +128,323 @@
 //! - Complex nested data structures (Vec, HashMap, etc.)
 //! - Data that will be used as hash keys multiple times
 //! - Concurrent scenarios where the same data is hashed by multiple threads
+//!
+//! ## Unsafe code
+//!
+//! The crate is `#![forbid(unsafe_code)]` unless a feature listed here is
+//! enabled:
+//!
+//! - `rkyv`: an archived type must promise that its validation is sound,
+//!   through `unsafe impl`s of `Portable` and `Verify` and the derived
+//!   `CheckBytes`. The crate is then `#![deny(unsafe_code)]`, allowed only in
+//!   the `rkyv` module.
+
+#![cfg_attr(not(feature = "rkyv"), forbid(unsafe_code))]
+#![cfg_attr(feature = "rkyv", deny(unsafe_code))]
+// Tests of the cache itself are compiled out under `disable-cache`, leaving
+// some of their helpers unused.
+#![cfg_attr(all(test, feature = "disable-cache"), allow(dead_code, unused_imports))]
+
+use std::borrow::{Borrow, Cow};
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher, RandomState};
+#[cfg(not(feature = "portable-atomic"))]
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::OnceLock;
+
+#[cfg(feature = "portable-atomic")]
+use portable_atomic::AtomicU64;
 
-use std::borrow::Borrow;
-use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher};
-use std::num::NonZeroU64;
-use std::sync::atomic::{AtomicU64, Ordering};
+use crate::cell::{HashCell, MemoCell};
+
+#[cfg(not(any(target_has_atomic = "64", feature = "portable-atomic")))]
+compile_error!("this target lacks 64-bit atomics; enable the `portable-atomic` feature");
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+mod arc;
+mod arc_swap;
+mod arena;
+mod bimap;
+mod binary;
+#[cfg(feature = "bincode")]
+pub mod bincode;
+mod builder;
+#[cfg(feature = "bytes")]
+mod bytes;
+mod cache;
+mod cell;
+mod collect;
+#[cfg(feature = "compact_str")]
+mod compact_str;
+#[cfg(any(feature = "hook", feature = "tracing"))]
+mod counting;
+#[cfg(feature = "crypto")]
+mod crypto;
+#[cfg(feature = "dashmap")]
+mod dashmap;
+#[cfg(feature = "derive")]
+mod derive;
+#[cfg(feature = "diagnostics")]
+pub mod diagnostics;
+#[cfg(feature = "digest")]
+pub mod digest;
+mod dynamic;
+mod entry;
+mod fallible;
+pub mod float;
+#[cfg(feature = "fxhash")]
+mod fxhash;
+#[cfg(feature = "get-size")]
+pub mod get_size;
+#[cfg(all(
+    feature = "gxhash",
+    any(
+        all(
+            target_arch = "x86_64",
+            target_feature = "aes",
+            target_feature = "sse2"
+        ),
+        all(
+            target_arch = "aarch64",
+            target_feature = "aes",
+            target_feature = "neon"
+        ),
+    )
+))]
+mod gxhash;
+mod hasher;
+#[cfg(feature = "hook")]
+pub mod hook;
+#[cfg(feature = "im")]
+mod im;
+#[cfg(feature = "indexmap")]
+mod indexmap;
+mod key;
+mod lines;
+mod literal;
+mod lookup;
+#[cfg(feature = "lru")]
+mod lru;
+mod map_ext;
+mod memoized;
+#[cfg(feature = "metrohash")]
+mod metrohash;
+#[cfg(feature = "moka")]
+mod moka;
+mod multimap;
+mod overhead;
+mod path;
+mod prehashed;
+#[cfg(feature = "proptest")]
+pub mod proptest;
+#[cfg(feature = "quickcheck")]
+mod quickcheck;
+#[cfg(feature = "rkyv")]
+mod rkyv;
+#[cfg(feature = "rolling")]
+pub mod rolling;
+#[cfg(feature = "seahash")]
+mod seahash;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(feature = "siphash")]
+mod siphash;
+pub mod slice;
+#[cfg(feature = "smallvec")]
+mod smallvec;
+#[cfg(feature = "smol_str")]
+mod smol_str;
+mod sort_key;
+#[cfg(feature = "stable")]
+pub mod stable;
+#[cfg(feature = "stats")]
+pub mod stats;
+mod string;
+#[cfg(feature = "subtle")]
+mod subtle;
+#[cfg(feature = "t1ha")]
+mod t1ha;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+#[cfg(feature = "tokio")]
+mod tokio;
+#[cfg(feature = "tracing")]
+mod tracing;
+mod transparent;
+#[cfg(feature = "url")]
+mod url;
+mod vec;
+mod weak;
+mod word;
+mod writer;
+#[cfg(feature = "wyhash")]
+mod wyhash;
+#[cfg(feature = "xxhash")]
+mod xxhash;
+#[cfg(feature = "zeroize")]
+mod zeroize;
+
+pub use crate::arc::ArcMemo;
+pub use crate::arc_swap::ArcSwapMemo;
+pub use crate::arena::{Id, MemoArena};
+pub use crate::bimap::{MemoBiMap, Overwritten};
+pub use crate::binary::{FromHexError, MemoBytes};
+pub use crate::builder::{HashMemoBuilder, Width};
+#[cfg(feature = "bytes")]
+pub use crate::bytes::MemoSharedBytes;
+pub use crate::cache::MemoCache;
+pub use crate::collect::{prehash_map, MemoizeExt};
+#[cfg(feature = "compact_str")]
+pub use crate::compact_str::MemoCompactString;
+#[cfg(feature = "crypto")]
+pub use crate::crypto::MacMemo;
+#[cfg(feature = "dashmap")]
+pub use crate::dashmap::MemoDashMap;
+#[cfg(feature = "derive")]
+pub use crate::derive::{HashCache, HashMemoize};
+#[cfg(feature = "digest")]
+pub use crate::digest::{DigestBuildHasher, DigestHasher, DigestMemo};
+pub use crate::dynamic::{set_default_dyn_hasher, DynHasher, DynHasherFactory, DynMemo, DynState};
+pub use crate::entry::{MemoEntry, MemoEntrySetExt};
+pub use crate::fallible::{TryHashSource, TrySourceMemo};
+pub use crate::float::{
+    Canonical, CanonicalHash, CanonicalKey, FloatKeyMemo, FloatPolicy, TotalOrder,
+};
+#[cfg(feature = "fxhash")]
+pub use crate::fxhash::FxHashMemo;
+#[cfg(all(
+    feature = "gxhash",
+    any(
+        all(
+            target_arch = "x86_64",
+            target_feature = "aes",
+            target_feature = "sse2"
+        ),
+        all(
+            target_arch = "aarch64",
+            target_feature = "aes",
+            target_feature = "neon"
+        ),
+    )
+))]
+pub use crate::gxhash::GxHashMemo;
+pub use crate::hasher::{
+    FixedHasher, FixedState, LengthPrefixed, LengthPrefixedHasher, MemoBuildHasher, Mixed,
+    MixedHasher, PassthroughHasher, PassthroughState,
+};
+#[cfg(feature = "indexmap")]
+pub use crate::indexmap::{MemoIndexMap, MemoIndexSet};
+pub use crate::key::MemoKey;
+pub use crate::lines::{dedup_lines, write_deduped, DedupLines};
+#[doc(hidden)]
+pub use crate::literal::__Literal;
+pub use crate::lookup::{MemoLookup, Probe};
+#[cfg(feature = "lru")]
+pub use crate::lru::{MemoLruCache, MemoLruExt};
+pub use crate::map_ext::{MemoMapExt, VacantValueEntry, ValueEntry};
+pub use crate::memoized::Memoized;
+#[cfg(feature = "metrohash")]
+pub use crate::metrohash::{MetroHashMemo, MetroHashState};
+#[cfg(feature = "moka")]
+pub use crate::moka::{ArcMemoMokaCache, MemoMokaCache, MemoMokaExt};
+pub use crate::multimap::MemoMultiMap;
+pub use crate::overhead::CacheLayout;
+pub use crate::path::{MemoOsString, MemoPath};
+pub use crate::prehashed::Prehashed;
+#[cfg(feature = "rolling")]
+pub use crate::rolling::{RollingHasher, RollingMemo, RollingState};
+#[cfg(feature = "seahash")]
+pub use crate::seahash::SeaHashMemo;
+#[cfg(feature = "serde")]
+pub use crate::serde::{
+    ColumnarMemos, HashMemoSeeded, HashMemoTagged, HashMemoVerified, SeededState,
+};
+#[cfg(feature = "siphash")]
+pub use crate::siphash::{SipHash24Memo, SipHash24State, SipHashMemo, SipHashState};
+#[cfg(feature = "smol_str")]
+pub use crate::smol_str::MemoSmolStr;
+pub use crate::sort_key::{AsciiCaseInsensitive, Bytewise, SortKey, SortKeyMemo};
+#[cfg(feature = "stable")]
+pub use crate::stable::{StableMemo, StableState};
+#[cfg(feature = "stats")]
+pub use crate::stats::StatsState;
+pub use crate::string::MemoString;
+#[cfg(feature = "subtle")]
+pub use crate::subtle::CtMemo;
+#[cfg(feature = "t1ha")]
+pub use crate::t1ha::{T1haMemo, T1haState};
+#[cfg(any(test, feature = "test-util"))]
+pub use crate::test_util::{DetMemo, DetState};
+pub use crate::transparent::TransparentMemo;
+#[cfg(feature = "url")]
+pub use crate::url::{MemoUrl, UrlNormalization};
+pub use crate::vec::{dedup_memoized, unwrap_vec, wrap_vec, wrap_vec_eager, wrap_vec_eager_with};
+pub use crate::weak::WeakMemoSet;
+pub use crate::word::{HashMemo32, HashMemoUsize};
+pub use crate::writer::{HashMemoWriter, MemoWriter};
+#[cfg(feature = "wyhash")]
+pub use crate::wyhash::{WyHashMemo, WyHashState};
+#[cfg(feature = "xxhash")]
+pub use crate::xxhash::XxHashMemo;
+
+#[cfg(feature = "rkyv")]
+pub use crate::rkyv::ArchivedHashMemo;
 
 /// A wrapper that memoizes the hash value of its contained data.
+///
+/// # Memory
+///
+/// A memo is the value, a 64-bit atomic and the hasher. With the default
+/// zero-sized hasher that is 8 bytes more than `T` when `T`'s size is a
+/// multiple of 8, as for `u64` and, on 64-bit targets, `String` and
+/// `Box<str>`; other sizes are padded up to the atomic's alignment. The value's
+/// heap allocations are unchanged. The sizes are pinned by tests, and
+/// `cargo bench --bench memory` measures the footprint of a million memos.
+///
+/// # Panics
+///
+/// If `T`'s `Hash` impl or the hasher panics, the panic propagates out of
+/// whatever was hashing the memo and the cache stays empty: it is written
+/// once, with a complete hash, so no reader can observe a partial one. The
+/// value is untouched and the next hash retries from scratch. A memo is
+/// `UnwindSafe` and `RefUnwindSafe` whenever `T` and `H` are, through the
+/// auto impls, since the cache is a single atomic word.
+///
+/// # Ordering
+///
+/// `Ord`, `PartialOrd`, `Eq` and `PartialEq` all compare the values and
+/// ignore the cache, so the ordering is consistent with equality and a memo
+/// is a valid `BTreeMap` or `BTreeSet` key, ordered exactly like its value.
+/// Ordered collections never hash, so the cache goes unused there. They
+/// can be queried by the value, including `range` by `String` bounds for
+/// `String` memos:
+///
+/// ```rust
+/// use hashmemo::HashMemo;
+/// use std::collections::BTreeSet;
+///
+/// let set: BTreeSet<_> = ["apple", "banana", "cherry"]
+///     .map(|s| HashMemo::new(s.to_string()))
+///     .into();
+/// let hits: Vec<&String> = set.range::<String, _>("b".to_string().."c".to_string()).map(|m| m.as_ref()).collect();
+/// assert_eq!(hits, ["banana"]);
+/// ```
+///
+/// Hashed collections are different: a memo hashes as its cached `u64`, not
+/// as its value, so a `HashMap` keyed by memos must be probed with a memo or
+/// a [`Probe`], not with the borrowed value. For the same reason a `String`
+/// memo does not borrow as `str`: a `HashMap<HashMemo<String>, V>` looked
+/// up by `&str` would silently miss. Probe it with
+/// `Probe::new("key").as_lookup()` instead.
 #[derive(Debug)]
 pub struct HashMemo<T, H: BuildHasher = BuildHasherDefault<DefaultHasher>>
 where
     T: Eq + PartialEq + Hash,
 {
     value: T,
-    hash: AtomicU64,
+    hash: MemoCell,
     hasher: H,
 }
 
@@ -93,184 +484,1095 @@ where
     ///
     /// let memo = HashMemo::new("hello world");
     /// ```
-    pub fn new(value: T) -> Self {
-        Self::with_hasher(value, BuildHasherDefault::default())
+    ///
+    /// `new` is `const`, so memos can be built in `static` tables:
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// static KEYS: [HashMemo<&str>; 2] = [HashMemo::new("alpha"), HashMemo::new("beta")];
+    /// ```
+    pub const fn new(value: T) -> Self {
+        Self::with_hasher(value, BuildHasherDefault::new())
+    }
+
+    /// Creates a new `HashMemo` in a `const` context, seeded with a
+    /// precomputed hash.
+    ///
+    /// `hash` must be the hash the default hasher produces for `value`, for
+    /// example as emitted by a build script; otherwise the memo will not
+    /// agree with memos hashed at runtime, and debug builds take it for a
+    /// stale cache and panic when `==` finds it equal to a memo caching
+    /// another hash. A `0` hash is remapped to `1`, exactly as a computed
+    /// one would be.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// static KEYS: [HashMemo<&str>; 2] = [
+    ///     HashMemo::new_const("alpha", 0x0123_4567_89ab_cdef),
+    ///     HashMemo::new_const("beta", 0xfedc_ba98_7654_3210),
+    /// ];
+    /// ```
+    pub const fn new_const(value: T, hash: u64) -> Self {
+        Self::with_hasher_const(value, BuildHasherDefault::new(), hash)
     }
 }
 
-impl<T, H> HashMemo<T, H>
+impl<T> HashMemo<T, RandomState>
 where
     T: Eq + Hash,
-    H: BuildHasher,
 {
-    /// Creates a new `HashMemo` with a custom hasher.
+    /// Creates a new `HashMemo` using a randomly seeded hasher.
     ///
-    /// This allows you to specify a custom `BuildHasher` implementation for
-    /// controlling how hash values are computed.
+    /// The seed is drawn once per process and shared by every memo created
+    /// this way, so equal values still hash equally within a process, while
+    /// an attacker cannot predict the hashes to provoke collisions. The
+    /// flip side is that hashes are not reproducible across runs; use
+    /// [`HashMemo::new`] or a fixed hasher when they must be.
     ///
     /// # Examples
     ///
     /// ```rust
     /// use hashmemo::HashMemo;
-    /// use std::hash::BuildHasherDefault;
-    /// use std::collections::hash_map::DefaultHasher;
     ///
-    /// let memo = HashMemo::with_hasher("hello", BuildHasherDefault::<DefaultHasher>::default());
+    /// let memo = HashMemo::new_random("untrusted input");
     /// ```
-    pub const fn with_hasher(value: T, hasher: H) -> Self {
-        Self {
-            value,
-            hash: AtomicU64::new(u64::MIN),
-            hasher,
-        }
+    pub fn new_random(value: T) -> Self {
+        static STATE: OnceLock<RandomState> = OnceLock::new();
+        Self::with_hasher(value, STATE.get_or_init(RandomState::new).clone())
     }
+}
 
-    /// Consumes the `HashMemo` and returns the wrapped value.
+impl<T> HashMemo<T, FixedState>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new `HashMemo` using [`FixedState`], whose hashes are stable
+    /// across runs, machines and releases of this crate.
     ///
     /// # Examples
     ///
     /// ```rust
     /// use hashmemo::HashMemo;
     ///
-    /// let memo = HashMemo::new("hello");
-    /// let value = memo.into_inner();
-    /// assert_eq!(value, "hello");
+    /// let memo = HashMemo::new_fixed("hello world");
     /// ```
-    #[inline]
-    #[must_use]
-    pub fn into_inner(self) -> T {
-        self.value
+    pub const fn new_fixed(value: T) -> Self {
+        Self::with_hasher(value, FixedState)
     }
 }
 
-impl<T, H> PartialEq for HashMemo<T, H>
+impl<T> HashMemo<T, Mixed>
 where
     T: Eq + Hash,
-    H: BuildHasher,
 {
-    fn eq(&self, other: &Self) -> bool {
-        self.value == other.value
+    /// Creates a new `HashMemo` whose hash is the default hasher's, run
+    /// through the `fmix64` finalizer, see [`Mixed`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let memo = HashMemo::new_mixed(42u64);
+    /// ```
+    pub fn new_mixed(value: T) -> Self {
+        Self::with_hasher(value, Mixed::default())
     }
 }
 
-impl<T, H> Eq for HashMemo<T, H>
+impl<T> HashMemo<T, LengthPrefixed>
 where
     T: Eq + Hash,
-    H: BuildHasher,
 {
+    /// Creates a new `HashMemo` whose hash mixes in the length of every byte
+    /// write, see [`LengthPrefixed`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let memo = HashMemo::new_length_prefixed("hello world");
+    /// ```
+    pub fn new_length_prefixed(value: T) -> Self {
+        Self::with_hasher(value, LengthPrefixed::default())
+    }
 }
 
-impl<T, H> Hash for HashMemo<T, H>
+impl<T, H> HashMemo<T, H>
 where
     T: Eq + Hash,
     H: BuildHasher,
 {
-    fn hash<H2: Hasher>(&self, state: &mut H2) {
-        let hash = self.hash.load(Ordering::Relaxed);
-        if hash != 0 {
-            state.write_u64(hash);
-            return;
+    /// Creates a new `HashMemo` with a custom hasher.
+    ///
+    /// This allows you to specify a custom `BuildHasher` implementation for
+    /// controlling how hash values are computed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    /// use std::hash::BuildHasherDefault;
+    /// use std::collections::hash_map::DefaultHasher;
+    ///
+    /// let memo = HashMemo::with_hasher("hello", BuildHasherDefault::<DefaultHasher>::default());
+    /// ```
+    pub const fn with_hasher(value: T, hasher: H) -> Self {
+        Self {
+            value,
+            hash: MemoCell::new(u64::MIN),
+            hasher,
         }
+    }
 
-        let computed_hash = NonZeroU64::new(self.hasher.hash_one(&self.value))
-            .map(NonZeroU64::get)
-            .unwrap_or(u64::MIN | 1);
+    /// Like [`with_hasher`](Self::with_hasher), seeded with a precomputed
+    /// hash, as [`new_const`](HashMemo::new_const) does for the default
+    /// hasher.
+    ///
+    /// Pair it with a hasher whose output is stable, such as [`FixedState`],
+    /// when the hashes come from a build script. As with `new_const`,
+    /// `hash` must be the hash `hasher` produces for `value`, or debug
+    /// builds panic when `==` finds the memo equal to one caching another
+    /// hash.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::{FixedState, HashMemo};
+    /// use std::hash::BuildHasher;
+    ///
+    /// static KEY: HashMemo<&str, FixedState> =
+    ///     HashMemo::with_hasher_const("hello", FixedState, 0x2049_ca16_2112_b21f);
+    ///
+    /// assert!(KEY.hash_matches(FixedState.hash_one("hello")));
+    /// ```
+    pub const fn with_hasher_const(value: T, hasher: H, hash: u64) -> Self {
+        Self {
+            value,
+            hash: MemoCell::new(remap_zero(hash)),
+            hasher,
+        }
+    }
 
-        let _ = self.hash.compare_exchange(
-            u64::MIN,
-            computed_hash,
-            Ordering::Relaxed,
-            Ordering::Relaxed,
-        );
-        state.write_u64(computed_hash);
+    /// Like [`with_hasher_const`](Self::with_hasher_const), seeded with the
+    /// first 8 bytes of an externally computed digest, read as a
+    /// little-endian `u64`.
+    ///
+    /// A digest shorter than 8 bytes fills the low bytes and leaves the
+    /// rest `0`; bytes past the eighth are ignored. A `0` result is
+    /// remapped to `1`, as a computed hash would be.
+    ///
+    /// Hashing `value` with `hasher` will in general not give the digest,
+    /// so these memos agree only with memos seeded from the same digests:
+    /// keep them apart from memos that compute their own hash, and do not
+    /// [`forget_hash`](Self::forget_hash) them. When `==` finds two values
+    /// equal but their warm caches differ, debug builds check each cache
+    /// against its hasher, and panic unless the hasher reproduces the
+    /// digest, as the `digest` feature's `DigestBuildHasher` does.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::{FixedState, HashMemo};
+    ///
+    /// let digest = [0xef, 0xbe, 0xad, 0xde, 0, 0, 0, 0, 0xff, 0xff];
+    /// let memo = HashMemo::from_hashed_bytes(vec![0u8; 1 << 20], &digest, FixedState);
+    /// # #[cfg(not(feature = "disable-cache"))]
+    /// assert_eq!(memo.hash_one_cached(), 0xdead_beef);
+    /// ```
+    pub fn from_hashed_bytes(value: T, digest: &[u8], hasher: H) -> Self {
+        let mut prefix = [0; 8];
+        let len = digest.len().min(8);
+        prefix[..len].copy_from_slice(&digest[..len]);
+        Self::with_hasher_const(value, hasher, u64::from_le_bytes(prefix))
     }
-}
 
-impl<T, H> AsRef<T> for HashMemo<T, H>
-where
-    T: Eq + Hash,
-    H: BuildHasher,
-{
-    fn as_ref(&self) -> &T {
-        &self.value
+    /// Consumes the `HashMemo` and returns the wrapped value.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let memo = HashMemo::new("hello");
+    /// let value = memo.into_inner();
+    /// assert_eq!(value, "hello");
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> T {
+        self.value
     }
-}
 
-impl<T, H> Borrow<T> for HashMemo<T, H>
-where
-    T: Eq + Hash,
-    H: BuildHasher,
-{
-    fn borrow(&self) -> &T {
-        &self.value
+    /// Moves the value into a memo using another hasher, with a cold cache.
+    ///
+    /// This is the conversion a blanket `From<HashMemo<T, H1>> for
+    /// HashMemo<T, H2>` would provide; such an impl would overlap with the
+    /// reflexive `From<T> for T` when `H1 == H2`, so it is a method instead.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::{FixedState, HashMemo};
+    ///
+    /// let memo = HashMemo::new("key".to_string());
+    /// let fixed: HashMemo<String, FixedState> = memo.rehash();
+    /// ```
+    #[inline]
+    pub fn rehash<H2>(self) -> HashMemo<T, H2>
+    where
+        H2: BuildHasher + Default,
+    {
+        self.rehash_with(H2::default())
     }
-}
 
-impl<T, H> From<T> for HashMemo<T, BuildHasherDefault<H>>
-where
-    T: Eq + Hash,
-    H: Hasher + Default,
-{
-    fn from(value: T) -> Self {
-        Self::with_hasher(value, BuildHasherDefault::<H>::default())
+    /// Like [`rehash`](Self::rehash), with an explicit hasher.
+    #[inline]
+    pub fn rehash_with<H2>(self, hasher: H2) -> HashMemo<T, H2>
+    where
+        H2: BuildHasher,
+    {
+        HashMemo::with_hasher(self.value, hasher)
     }
-}
 
-impl<T, H> Clone for HashMemo<T, H>
-where
-    T: Eq + Hash + Clone,
-    H: BuildHasher + Clone,
-{
-    fn clone(&self) -> Self {
+    /// Clones the value, hasher and cached hash; `Clone` delegates here.
+    ///
+    /// Carrying the cache over is correct because the clone's value and
+    /// hasher are clones too, so they would compute the same hash. If that
+    /// does not hold, for example a `Clone` that changes how the value
+    /// hashes, use [`clone_without_hash`](Self::clone_without_hash).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let memo = HashMemo::new("key".to_string());
+    /// let hash = memo.hash_one_cached();
+    ///
+    /// let clone = memo.clone_with_hash();
+    /// assert_eq!(clone.hash_one_cached(), hash);
+    /// ```
+    #[inline]
+    pub fn clone_with_hash(&self) -> Self
+    where
+        T: Clone,
+        H: Clone,
+    {
         Self {
             value: self.value.clone(),
-            hash: AtomicU64::new(self.hash.load(Ordering::Relaxed)),
+            hash: MemoCell::new(self.hash.load(Ordering::Relaxed)),
             hasher: self.hasher.clone(),
         }
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
-    use std::sync::atomic::{AtomicBool, Ordering};
-    use std::sync::Arc;
-
-    use super::*;
 
-    fn calculate_hash<T: Hash>(t: &T) -> u64 {
-        calculate_hash_with_hasher::<T, DefaultHasher>(t)
+    /// Clones the value and hasher into a memo whose cache starts cold.
+    ///
+    /// [`clone_with_hash`](Self::clone_with_hash), and so `Clone`, copy the
+    /// cached hash; use this instead when the clone will be hashed in a
+    /// context where that hash would be stale, so it recomputes on first
+    /// use.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let memo = HashMemo::new("key".to_string());
+    /// memo.precompute();
+    ///
+    /// let clone = memo.clone_without_hash();
+    /// assert_eq!(clone, memo);
+    /// ```
+    #[inline]
+    pub fn clone_without_hash(&self) -> Self
+    where
+        T: Clone,
+        H: Clone,
+    {
+        Self::with_hasher(self.value.clone(), self.hasher.clone())
     }
 
-    fn calculate_hash_with_hasher<T: Hash, H: Hasher + Default>(t: &T) -> u64 {
-        let mut s = H::default();
-        t.hash(&mut s);
-        s.finish()
+    /// Clones the value into a memo with a fresh `H::default()` hasher,
+    /// whose cache starts cold.
+    ///
+    /// This clones memos whose hasher is not `Clone`, such as one holding a
+    /// handle. A blanket `Clone` for `H: Default` would overlap with the
+    /// `Clone` for `H: Clone`, so it is a method instead. The new hasher
+    /// may hash differently, as `RandomState`'s does, so the cached hash is
+    /// not carried over.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    /// use std::hash::{BuildHasher, DefaultHasher};
+    ///
+    /// // Not `Clone`.
+    /// #[derive(Default)]
+    /// struct HandleState;
+    ///
+    /// impl BuildHasher for HandleState {
+    ///     type Hasher = DefaultHasher;
+    ///
+    ///     fn build_hasher(&self) -> DefaultHasher {
+    ///         DefaultHasher::new()
+    ///     }
+    /// }
+    ///
+    /// let memo = HashMemo::with_hasher("key".to_string(), HandleState);
+    /// let clone = memo.clone_with_default_hasher();
+    /// assert_eq!(clone.hash_one_cached(), memo.hash_one_cached());
+    /// ```
+    #[inline]
+    pub fn clone_with_default_hasher(&self) -> Self
+    where
+        T: Clone,
+        H: Default,
+    {
+        Self::with_hasher(self.value.clone(), H::default())
     }
 
-    #[test]
-    fn hash_is_stable_after_clone() {
-        let foo = HashMemo::new("foo".to_string());
-        let hash = calculate_hash(&foo);
-
-        let foo2 = foo.clone();
-        let hash2 = calculate_hash(&foo2);
-
-        assert_eq!(hash, hash2, "Hash should remain the same after cloning");
+    /// Derives a memo of `f(&value)` with a cold cache and a clone of the
+    /// hasher, leaving this memo untouched.
+    ///
+    /// `U` may borrow from the value, as in `memo.map_ref(|v| &v.name)`,
+    /// giving a view that lives no longer than this memo.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let memo = HashMemo::new(("key".to_string(), 7u64));
+    /// let id = memo.map_ref(|(_, id)| *id);
+    /// let name = memo.map_ref(|(name, _)| name.as_str());
+    /// assert_eq!((*id.as_ref(), *name.as_ref()), (7, "key"));
+    /// ```
+    #[inline]
+    pub fn map_ref<'a, U, F>(&'a self, f: F) -> HashMemo<U, H>
+    where
+        U: Eq + Hash,
+        H: Clone,
+        F: FnOnce(&'a T) -> U,
+    {
+        HashMemo::with_hasher(f(&self.value), self.hasher.clone())
     }
 
-    #[test]
-    fn hash_is_consistent_on_reuse() {
-        let foo = HashMemo::new("foo".to_string());
-        let hash1 = calculate_hash(&foo);
-        let hash2 = calculate_hash(&foo);
-        let hash3 = calculate_hash(&foo);
-
-        assert_eq!(hash1, hash2);
-        assert_eq!(hash2, hash3);
+    /// Runs `f` on the value mutably, then clears the cached hash once.
+    ///
+    /// Any number of changes made inside `f`, such as a loop of indexed
+    /// writes into a `Vec`, cost a single invalidation, and the hash is
+    /// computed again on next use.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let mut memo = HashMemo::new(vec![0u32; 1024]);
+    /// memo.precompute();
+    /// memo.with_mut(|values| {
+    ///     for (i, value) in values.iter_mut().enumerate() {
+    ///         *value = i as u32;
+    ///     }
+    /// });
+    /// assert_eq!(memo.as_ref()[7], 7);
+    /// ```
+    pub fn with_mut<R>(&mut self, f: impl FnOnce(&mut T) -> R) -> R {
+        let result = f(&mut self.value);
+        #[cfg(feature = "stats")]
+        crate::stats::invalidation();
+        self.hash.clear();
+        result
     }
 
+    /// Runs `f` on the value mutably, keeping the cached hash.
+    ///
+    /// This is for values that carry state left out of their `Hash` and
+    /// `Eq` impls, such as access timestamps or lazily filled buffers, which
+    /// can then be updated without discarding the cache. `f` must not change
+    /// anything that `Hash` or `Eq` observe: the memo would go on reporting
+    /// the old hash, and maps holding it would misbehave. As with mutating a
+    /// `HashMap` key through interior mutability, this is a logic error
+    /// rather than undefined behavior, so the method is safe to call. To
+    /// change hashed state, use [`with_mut`](Self::with_mut) instead.
+    ///
+    /// With the `validate` feature, debug builds rehash the value after `f`
+    /// whenever the cache was warm, and panic if the hash changed. Changes
+    /// that only `Eq` observes still go unnoticed.
+    ///
+    /// Independently, any debug build panics when `==` finds two equal
+    /// values whose caches disagree and one of them no longer matches its
+    /// own hasher, which is how a stale cache shows up in practice.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    /// use std::hash::{Hash, Hasher};
+    ///
+    /// #[derive(PartialEq, Eq)]
+    /// struct Entry {
+    ///     key: String,
+    ///     hits: u64,
+    /// }
+    ///
+    /// impl Hash for Entry {
+    ///     fn hash<H: Hasher>(&self, state: &mut H) {
+    ///         self.key.hash(state);
+    ///     }
+    /// }
+    ///
+    /// let mut memo = HashMemo::new(Entry { key: "key".to_string(), hits: 0 });
+    /// memo.precompute();
+    /// memo.with_mut_unchecked(|entry| entry.hits += 1);
+    /// ```
+    pub fn with_mut_unchecked<R>(&mut self, f: impl FnOnce(&mut T) -> R) -> R {
+        let result = f(&mut self.value);
+
+        #[cfg(all(feature = "validate", debug_assertions))]
+        {
+            let cached = self.hash.load(Ordering::Relaxed);
+            assert!(
+                cached == 0 || cached == remap_zero(self.hasher.hash_one(&self.value)),
+                "with_mut_unchecked changed the hash of the value"
+            );
+        }
+
+        result
+    }
+
+    /// Computes and caches the hash now, so later hashing is a plain load.
+    ///
+    /// Useful to move the cost of hashing a large value off a latency
+    /// sensitive path. Does nothing if the cache is already warm.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let memo = HashMemo::new("a".repeat(10000));
+    /// memo.precompute();
+    /// ```
+    #[inline]
+    pub fn precompute(&self) {
+        self.memoized_hash();
+    }
+
+    /// Like [`precompute`](Self::precompute), but returns `self` so warming
+    /// composes in expressions.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    /// use std::collections::HashMap;
+    ///
+    /// let memo = HashMemo::new("key".to_string());
+    /// let mut map = HashMap::new();
+    /// map.insert(memo.compute_and_cache().clone(), 1);
+    /// ```
+    #[inline]
+    pub fn compute_and_cache(&self) -> &Self {
+        self.precompute();
+        self
+    }
+
+    /// Empties the cache through a shared reference, so the next hash is
+    /// computed afresh.
+    ///
+    /// The value is untouched; use this to re-hash after something outside
+    /// the memo, such as the state behind its hasher, has changed. It is
+    /// sound while the memo is shared: recomputing is deterministic and
+    /// idempotent, so a concurrent reader either sees the old hash or
+    /// computes and stores the same one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let memo = HashMemo::new("key");
+    /// let hash = memo.hash_one_cached();
+    /// memo.forget_hash();
+    /// assert_eq!(memo.hash_one_cached(), hash);
+    /// ```
+    #[inline]
+    pub fn forget_hash(&self) {
+        #[cfg(feature = "stats")]
+        crate::stats::invalidation();
+        self.hash.store(0, Ordering::Relaxed);
+    }
+
+    /// Returns the memoized hash, computing and caching it on first use.
+    ///
+    /// This is the memoized counterpart of `hasher.hash_one(&value)` and
+    /// returns the same `u64`, except that a genuine `0` is reported as `1`
+    /// (see [`is_zero_remapped`](Self::is_zero_remapped)).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::{FixedState, HashMemo};
+    /// use std::hash::BuildHasher;
+    ///
+    /// let memo = HashMemo::new_fixed("key");
+    /// assert_eq!(memo.hash_one_cached(), FixedState.hash_one("key"));
+    /// ```
+    #[inline]
+    pub fn hash_one_cached(&self) -> u64 {
+        self.memoized_hash()
+    }
+
+    /// Returns `true` if the memoized hash equals `hash`.
+    ///
+    /// The hash is computed and cached on demand if the cache is cold. Since
+    /// a genuine `0` hash is remapped to `1`, compare against remapped
+    /// values: a raw `0` never matches.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::{FixedState, HashMemo};
+    /// use std::hash::BuildHasher;
+    ///
+    /// let memo = HashMemo::new_fixed("shard-key");
+    /// assert!(memo.hash_matches(FixedState.hash_one("shard-key")));
+    /// ```
+    #[inline]
+    pub fn hash_matches(&self, hash: u64) -> bool {
+        self.memoized_hash() == hash
+    }
+
+    /// Compares like `==`, but lets the cached hashes prove inequality.
+    ///
+    /// When both caches are warm and hold different hashes the values
+    /// cannot be equal, and `false` is returned without reading them. `T`'s
+    /// `eq` runs only when the hashes match or either cache is cold, so
+    /// negative comparisons of large values cost two loads. Neither cache is
+    /// computed by the call.
+    ///
+    /// This holds only when both memos hash with the same hasher type *and*
+    /// seed, as memos of the default hasher, [`FixedState`] or
+    /// [`new_random`](HashMemo::new_random) do. Memos seeded with separate
+    /// `RandomState`s, or built by [`new_const`](HashMemo::new_const) with a
+    /// hash that does not belong to the value, can compare unequal although
+    /// their values are equal. Debug builds assert that the two hashers
+    /// agree, by comparing their fingerprints, whenever the hashes decide
+    /// the result.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let a = HashMemo::new(vec![0u8; 1 << 20]);
+    /// let b = HashMemo::new(vec![1u8; 1 << 20]);
+    /// assert!(!a.fast_eq(&b), "cold caches fall back to the values");
+    ///
+    /// a.precompute();
+    /// b.precompute();
+    /// assert!(!a.fast_eq(&b), "settled by the hashes alone");
+    /// ```
+    #[inline]
+    pub fn fast_eq(&self, other: &Self) -> bool {
+        let (a, b) = (
+            self.hash.load(Ordering::Relaxed),
+            other.hash.load(Ordering::Relaxed),
+        );
+        if a != 0 && b != 0 && a != b {
+            #[cfg(debug_assertions)]
+            assert!(
+                hasher_tag(&self.hasher) == hasher_tag(&other.hasher),
+                "fast_eq on memos whose hashers disagree"
+            );
+            return false;
+        }
+        self == other
+    }
+
+    /// Returns `true` if the value equals `other`, without building a memo
+    /// of it.
+    ///
+    /// Only the values are compared and the cache is left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let memo = HashMemo::new(vec![1, 2, 3]);
+    /// assert!(memo.eq_value(&vec![1, 2, 3]));
+    /// assert!(!memo.eq_value(&vec![3, 2, 1]));
+    /// ```
+    #[inline]
+    pub fn eq_value(&self, other: &T) -> bool {
+        self.value == *other
+    }
+
+    /// Returns `true` if the hasher genuinely produced `0` for this value,
+    /// which the memo reports as `1` instead.
+    ///
+    /// No flag is kept for this, so the hash is recomputed unless the cache
+    /// is warm with a value other than `1`; in the worst case this costs a
+    /// full hash of the value per call. The cache is left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let memo = HashMemo::new("key");
+    /// assert!(!memo.is_zero_remapped());
+    /// ```
+    pub fn is_zero_remapped(&self) -> bool {
+        match self.hash.load(Ordering::Relaxed) {
+            0 | 1 => self.hasher.hash_one(&self.value) == 0,
+            _ => false,
+        }
+    }
+
+    /// Returns the cached hash, computing and publishing it on first use.
+    ///
+    /// A warm cache is returned after a single load, without hashing the
+    /// value or storing again, so defensive `precompute` calls are cheap.
+    fn memoized_hash(&self) -> u64 {
+        // The value and hasher are immutable behind `&self`, so racing
+        // threads compute the same hash and a plain store cannot publish a
+        // wrong one; a compare-exchange would only add a read-modify-write.
+        // A racing `forget_hash` may be undone by this store, which leaves
+        // the correct hash cached. Anything that changes the value takes
+        // `&mut self` and so cannot race with it. `tests/loom.rs` models
+        // this protocol.
+        cell::get_or_compute(&self.hash, || hash_cold(&self.hasher, &self.value))
+    }
+
+    /// Panics if either of `self` and `other`, whose values are equal,
+    /// caches a hash its own hasher does not produce, which is how a stale
+    /// cache shows up in practice.
+    ///
+    /// Each cache is checked against its own hasher, never against the
+    /// other cache: memos hashing with differently seeded hashers
+    /// legitimately cache different hashes. Matching caches are taken as
+    /// fresh, so equal memos sharing a hasher are not rehashed.
+    #[cfg(debug_assertions)]
+    fn debug_assert_fresh(&self, other: &Self) {
+        let (a, b) = (
+            self.hash.load(Ordering::Relaxed),
+            other.hash.load(Ordering::Relaxed),
+        );
+        if a == 0 || b == 0 || a == b {
+            return;
+        }
+        for (memo, cached) in [(self, a), (other, b)] {
+            assert!(
+                remap_zero(memo.hasher.hash_one(&memo.value)) == cached,
+                "stale cached hash: the value changed without emptying the cache"
+            );
+        }
+    }
+}
+
+impl<H> HashMemo<String, H>
+where
+    H: BuildHasher,
+{
+    /// Converts into a `HashMemo<Box<str>>`, dropping excess capacity and
+    /// keeping the cached hash, since `String` and `Box<str>` hash alike.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let memo = HashMemo::new(String::from("key"));
+    /// let hash = memo.hash_one_cached();
+    ///
+    /// let boxed = memo.into_boxed_str();
+    /// assert_eq!(boxed.hash_one_cached(), hash);
+    /// ```
+    #[inline]
+    pub fn into_boxed_str(self) -> HashMemo<Box<str>, H> {
+        HashMemo {
+            value: self.value.into_boxed_str(),
+            hash: MemoCell::new(self.hash.into_inner()),
+            hasher: self.hasher,
+        }
+    }
+}
+
+impl<H> HashMemo<Box<str>, H>
+where
+    H: BuildHasher,
+{
+    /// Converts into a `HashMemo<String>` without copying the string,
+    /// keeping the cached hash.
+    #[inline]
+    pub fn into_string(self) -> HashMemo<String, H> {
+        HashMemo {
+            value: self.value.into_string(),
+            hash: MemoCell::new(self.hash.into_inner()),
+            hasher: self.hasher,
+        }
+    }
+}
+
+/// Swaps the values of two memos together with their cached hashes, so
+/// neither is recomputed, as [`mem::swap`](std::mem::swap) does for plain
+/// values.
+///
+/// The hashers are swapped too: a cached hash is only valid for the hasher
+/// that computed it, and two hashers of one type may differ, as two
+/// `RandomState`s do.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::HashMemo;
+///
+/// let mut a = HashMemo::new("a");
+/// let mut b = HashMemo::new("b");
+/// let (hash_a, hash_b) = (a.hash_one_cached(), b.hash_one_cached());
+///
+/// hashmemo::swap(&mut a, &mut b);
+/// assert_eq!((*a.as_ref(), *b.as_ref()), ("b", "a"));
+/// assert_eq!((a.hash_one_cached(), b.hash_one_cached()), (hash_b, hash_a));
+/// ```
+#[inline]
+pub fn swap<T, H>(a: &mut HashMemo<T, H>, b: &mut HashMemo<T, H>)
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    std::mem::swap(a, b);
+}
+
+/// Fingerprints `hasher`, so persisted hashes written with one hasher are
+/// not trusted by another.
+///
+/// Debug builds also use it to catch [`HashMemo::fast_eq`] on memos whose
+/// hashers disagree.
+#[cfg(any(
+    debug_assertions,
+    feature = "bincode",
+    feature = "rkyv",
+    feature = "serde"
+))]
+fn hasher_tag<H: BuildHasher>(hasher: &H) -> u64 {
+    hasher.hash_one(u64::from_le_bytes(*b"hashmemo"))
+}
+
+/// Hashes `value` with `hasher` on a cold cache, reporting it to the
+/// `tracing` and `hook` features when they are enabled.
+#[inline]
+fn hash_cold<T, H>(hasher: &H, value: &T) -> u64
+where
+    T: Hash + ?Sized,
+    H: BuildHasher,
+{
+    #[cfg(feature = "hook")]
+    return crate::hook::hash_cold(hasher, value);
+    #[cfg(all(feature = "tracing", not(feature = "hook")))]
+    return crate::tracing::hash_cold(hasher, value);
+    #[cfg(not(any(feature = "hook", feature = "tracing")))]
+    hasher.hash_one(value)
+}
+
+/// Maps a genuine `0` hash to `1`, since `0` marks an empty cache.
+#[inline]
+const fn remap_zero(hash: u64) -> u64 {
+    cell::nonzero(hash).get()
+}
+
+// Equality delegates to `T`, so byte-backed values such as `Vec<u8>` and
+// `String` already compare through std's vectorized `memcmp` slice path, and
+// `Arc`s of `Eq` types check `ptr_eq` before their contents. Cached hashes
+// are not consulted, since memos with differently seeded hashers may hold
+// equal values; `HashMemo::fast_eq` opts into that.
+impl<T, H> PartialEq for HashMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    fn eq(&self, other: &Self) -> bool {
+        let eq = self.value == other.value;
+        #[cfg(feature = "diagnostics")]
+        if !eq {
+            crate::diagnostics::check_collision(self, other);
+        }
+        #[cfg(debug_assertions)]
+        if eq {
+            self.debug_assert_fresh(other);
+        }
+        eq
+    }
+}
+
+impl<T, H> Eq for HashMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+}
+
+impl<H> PartialEq<Cow<'_, str>> for HashMemo<String, H>
+where
+    H: BuildHasher,
+{
+    fn eq(&self, other: &Cow<'_, str>) -> bool {
+        self.value.as_bytes() == other.as_bytes()
+    }
+}
+
+impl<H> PartialEq<Cow<'_, [u8]>> for HashMemo<Vec<u8>, H>
+where
+    H: BuildHasher,
+{
+    fn eq(&self, other: &Cow<'_, [u8]>) -> bool {
+        self.value.as_slice() == other.as_ref()
+    }
+}
+
+impl<T, H> Hash for HashMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    fn hash<H2: Hasher>(&self, state: &mut H2) {
+        state.write_u64(self.memoized_hash());
+    }
+}
+
+impl<T, H> fmt::Display for HashMemo<T, H>
+where
+    T: Eq + Hash + fmt::Display,
+    H: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl<T, H> AsRef<T> for HashMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    fn as_ref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, H> Borrow<T> for HashMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    fn borrow(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, H> From<T> for HashMemo<T, BuildHasherDefault<H>>
+where
+    T: Eq + Hash,
+    H: Hasher + Default,
+{
+    fn from(value: T) -> Self {
+        Self::with_hasher(value, BuildHasherDefault::<H>::default())
+    }
+}
+
+impl<T, H> Clone for HashMemo<T, H>
+where
+    T: Eq + Hash + Clone,
+    H: BuildHasher + Clone,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        self.clone_with_hash()
+    }
+
+    /// Clones `source` into `self` through `T::clone_from` and
+    /// `H::clone_from`, reusing their allocations, and copies the cached
+    /// hash as [`clone`](Clone::clone) does.
+    #[inline]
+    fn clone_from(&mut self, source: &Self) {
+        self.value.clone_from(&source.value);
+        self.hasher.clone_from(&source.hasher);
+        self.hash
+            .store(source.hash.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+}
+
+impl<T, H> Default for HashMemo<T, H>
+where
+    T: Eq + Hash + Default,
+    H: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(T::default(), H::default())
+    }
+}
+
+impl<T, H> HashMemo<T, H>
+where
+    T: Eq + Hash + Default,
+    H: BuildHasher,
+{
+    /// Returns `true` if the value equals `T::default()`.
+    ///
+    /// Compares values only and leaves the cache untouched. With the `serde`
+    /// feature, this can drop default fields from the output via
+    /// `#[serde(default, skip_serializing_if = "HashMemo::is_default")]`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// assert!(HashMemo::<String>::default().is_default());
+    /// assert!(!HashMemo::new("key".to_string()).is_default());
+    /// ```
+    pub fn is_default(&self) -> bool {
+        self.value == T::default()
+    }
+
+    /// Replaces the value with `T::default()` and returns the old one,
+    /// clearing the cached hash, as [`mem::take`](std::mem::take) does for
+    /// a plain value.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let mut memo = HashMemo::new(vec![1, 2, 3]);
+    /// memo.hash_one_cached();
+    ///
+    /// assert_eq!(memo.take(), [1, 2, 3]);
+    /// assert!(memo.is_default());
+    /// assert_eq!(memo, HashMemo::<Vec<i32>>::default());
+    /// ```
+    pub fn take(&mut self) -> T {
+        #[cfg(feature = "stats")]
+        crate::stats::invalidation();
+        self.hash.clear();
+        std::mem::take(&mut self.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use nohash_hasher::NoHashHasher;
+
+    use super::*;
+
+    /// Hashes to `H` under `NoHashHasher`.
+    struct PinHash<const H: u64>();
+    impl<const H: u64> Eq for PinHash<H> {}
+    impl<const H: u64> PartialEq for PinHash<H> {
+        fn eq(&self, _: &Self) -> bool {
+            true
+        }
+    }
+    impl<const H: u64> Hash for PinHash<H> {
+        fn hash<HS: Hasher>(&self, state: &mut HS) {
+            state.write_u64(H);
+        }
+    }
+
+    fn calculate_hash<T: Hash>(t: &T) -> u64 {
+        calculate_hash_with_hasher::<T, DefaultHasher>(t)
+    }
+
+    fn calculate_hash_with_hasher<T: Hash, H: Hasher + Default>(t: &T) -> u64 {
+        let mut s = H::default();
+        t.hash(&mut s);
+        s.finish()
+    }
+
+    // Growing the memo is a breaking change for users budgeting memory by
+    // these sizes, so it should fail here rather than slip in.
+    const CACHE: usize = if cfg!(feature = "disable-cache") {
+        0
+    } else {
+        8
+    };
+
+    const _: () = {
+        use std::mem::size_of;
+
+        assert!(size_of::<BuildHasherDefault<DefaultHasher>>() == 0);
+        assert!(size_of::<FixedState>() == 0);
+        assert!(size_of::<HashMemo<u64>>() == size_of::<u64>() + CACHE);
+        assert!(size_of::<HashMemo<u64, FixedState>>() == size_of::<HashMemo<u64>>());
+    };
+
+    #[cfg(target_pointer_width = "64")]
+    const _: () = {
+        use std::mem::size_of;
+
+        assert!(size_of::<HashMemo<u64>>() == 8 + CACHE);
+        assert!(size_of::<HashMemo<String>>() == 24 + CACHE);
+        assert!(size_of::<HashMemo<Box<str>>>() == 16 + CACHE);
+    };
+
+    #[test]
+    fn hash_is_stable_after_clone() {
+        let foo = HashMemo::new("foo".to_string());
+        let hash = calculate_hash(&foo);
+
+        let foo2 = foo.clone();
+        let hash2 = calculate_hash(&foo2);
+
+        assert_eq!(hash, hash2, "Hash should remain the same after cloning");
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn clone_with_hash_carries_the_cache_over() {
+        use crate::test_util::CountingState;
+
+        let state = CountingState::default();
+        let foo = HashMemo::with_hasher("foo".to_string(), state.clone());
+        let hash = calculate_hash(&foo);
+
+        let explicit = foo.clone_with_hash();
+        let implicit = foo.clone();
+        assert_eq!(calculate_hash(&explicit), hash);
+        assert_eq!(calculate_hash(&implicit), hash);
+        assert_eq!(
+            explicit.hash.load(Ordering::Relaxed),
+            foo.hash.load(Ordering::Relaxed)
+        );
+        assert_eq!(state.count(), 1);
+    }
+
+    #[test]
+    fn hash_is_consistent_on_reuse() {
+        let foo = HashMemo::new("foo".to_string());
+        let hash1 = calculate_hash(&foo);
+        let hash2 = calculate_hash(&foo);
+        let hash3 = calculate_hash(&foo);
+
+        assert_eq!(hash1, hash2);
+        assert_eq!(hash2, hash3);
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
     #[test]
     fn hash_is_cached_and_only_calculated_once() {
         struct HashOnce {
@@ -301,6 +1603,25 @@ mod tests {
         }
     }
 
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn warm_cache_is_neither_recomputed_nor_restored() {
+        use crate::test_util::CountingState;
+
+        let state = CountingState::default();
+        let memo = HashMemo::with_hasher("warm".to_string(), state.clone());
+        for _ in 0..10 {
+            memo.precompute();
+        }
+        assert_eq!(state.count(), 1);
+
+        // A seeded hash that the hasher would not produce survives, so
+        // nothing was stored over it.
+        let seeded = HashMemo::new_const("warm".to_string(), 42);
+        seeded.precompute();
+        assert_eq!(seeded.hash.load(Ordering::Relaxed), 42);
+    }
+
     #[test]
     fn into_inner_returns_original_value() {
         let foo = HashMemo::new("foo".to_string());
@@ -309,31 +1630,366 @@ mod tests {
     }
 
     #[test]
-    fn struct_is_not_significantly_larger_than_wrapped_value() {
-        assert!(
-            std::mem::size_of::<HashMemo<String>>()
-                <= std::mem::size_of::<String>() + std::mem::size_of::<u64>(),
-            "HashMemo should have minimal overhead"
+    #[cfg(all(feature = "portable-atomic", not(feature = "disable-cache")))]
+    fn portable_atomic_cache_is_shared_across_threads() {
+        let memo = HashMemo::new("portable".repeat(64));
+        let hashes: Vec<u64> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..4).map(|_| s.spawn(|| memo.memoized_hash())).collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        assert!(hashes.iter().all(|&h| h == hashes[0]));
+        assert_eq!(memo.hash.load(Ordering::Relaxed), hashes[0]);
+        assert_eq!(
+            hashes[0],
+            HashMemo::new("portable".repeat(64)).memoized_hash()
         );
     }
 
+    #[cfg(not(feature = "disable-cache"))]
     #[test]
-    fn zero_hash_is_remapped_to_nonzero_in_cache() {
-        use nohash_hasher::NoHashHasher;
+    fn compute_and_cache_warms_and_returns_self() {
+        let memo = HashMemo::new("warm".to_string());
+        assert_eq!(memo.hash.load(Ordering::Relaxed), 0);
 
-        struct PinHash<const H: u64>();
-        impl<const H: u64> Eq for PinHash<H> {}
-        impl<const H: u64> PartialEq for PinHash<H> {
-            fn eq(&self, _: &Self) -> bool {
-                true
+        let returned = memo.compute_and_cache();
+        assert!(std::ptr::eq(returned, &memo));
+        assert_eq!(
+            memo.hash.load(Ordering::Relaxed),
+            calculate_hash(&"warm".to_string())
+        );
+
+        let cold = HashMemo::new("warm".to_string());
+        cold.precompute();
+        assert_eq!(
+            cold.hash.load(Ordering::Relaxed),
+            memo.hash.load(Ordering::Relaxed)
+        );
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn forget_hash_recomputes_on_next_use() {
+        use crate::test_util::CountingState;
+
+        let state = CountingState::default();
+        let memo = HashMemo::with_hasher("forget".to_string(), state.clone());
+        let shared = &memo;
+        let hash = shared.hash_one_cached();
+        shared.hash_one_cached();
+        assert_eq!(state.count(), 1);
+
+        shared.forget_hash();
+        assert_eq!(memo.hash.load(Ordering::Relaxed), 0);
+        assert_eq!(shared.hash_one_cached(), hash);
+        shared.hash_one_cached();
+        assert_eq!(state.count(), 2);
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn nested_memo_reuses_the_inner_cache() {
+        use std::sync::atomic::AtomicUsize;
+
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(PartialEq, Eq, Clone)]
+        struct Counted(String);
+
+        impl Hash for Counted {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                COUNT.fetch_add(1, Ordering::Relaxed);
+                self.0.hash(state);
+            }
+        }
+
+        let inner = HashMemo::new(Counted("nested".to_string()));
+        inner.precompute();
+        let outer = HashMemo::new(inner);
+
+        let first = calculate_hash(&outer);
+        for _ in 0..10 {
+            assert_eq!(calculate_hash(&outer), first);
+        }
+        assert_eq!(
+            HashMemo::new(outer.clone()).memoized_hash(),
+            HashMemo::new(outer).memoized_hash()
+        );
+        assert_eq!(COUNT.load(Ordering::Relaxed), 1);
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn map_ref_derives_a_cold_memo_and_leaves_the_source() {
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        struct Record {
+            id: u64,
+            name: String,
+        }
+
+        let record = Record {
+            id: 42,
+            name: "answer".to_string(),
+        };
+        let memo = HashMemo::new(record.clone());
+        let hash = memo.memoized_hash();
+
+        let id = memo.map_ref(|r| r.id);
+        assert_eq!(id.hash.load(Ordering::Relaxed), 0);
+        assert_eq!(id.memoized_hash(), HashMemo::new(42u64).memoized_hash());
+
+        let name = memo.map_ref(|r| r.name.as_str());
+        assert_eq!(name.value, "answer");
+        assert_eq!(
+            name.memoized_hash(),
+            HashMemo::new("answer").memoized_hash()
+        );
+
+        assert_eq!(memo.value, record);
+        assert_eq!(memo.hash.load(Ordering::Relaxed), hash);
+    }
+
+    #[test]
+    fn panicking_first_hash_leaves_the_memo_usable() {
+        use std::panic::{self, RefUnwindSafe, UnwindSafe};
+        use std::sync::atomic::AtomicBool;
+
+        static ARMED: AtomicBool = AtomicBool::new(true);
+
+        /// Panics the first time it is hashed.
+        #[derive(Debug, PartialEq, Eq)]
+        struct Fragile(u32);
+        impl Hash for Fragile {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                state.write_u32(self.0);
+                assert!(!ARMED.swap(false, Ordering::Relaxed), "invariant violated");
             }
         }
-        impl<const H: u64> Hash for PinHash<H> {
-            fn hash<HS: Hasher>(&self, state: &mut HS) {
-                state.write_u64(H);
+
+        fn unwind_safe<T: UnwindSafe + RefUnwindSafe>(_: &T) {}
+
+        let memo = HashMemo::new(Fragile(7));
+        unwind_safe(&memo);
+        let panicked = panic::catch_unwind(|| memo.precompute());
+        assert!(panicked.is_err());
+        assert_eq!(memo.hash.load(Ordering::Relaxed), 0);
+        assert_eq!(memo.value, Fragile(7));
+
+        assert_eq!(
+            memo.memoized_hash(),
+            HashMemo::new(Fragile(7)).memoized_hash()
+        );
+    }
+
+    /// Hashes and compares by `key` only.
+    #[derive(Debug)]
+    struct Tracked {
+        key: String,
+        hits: u64,
+    }
+
+    impl PartialEq for Tracked {
+        fn eq(&self, other: &Self) -> bool {
+            self.key == other.key
+        }
+    }
+
+    impl Eq for Tracked {}
+
+    impl Hash for Tracked {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.key.hash(state);
+        }
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn with_mut_unchecked_keeps_the_cache() {
+        let mut memo = HashMemo::new(Tracked {
+            key: "key".to_string(),
+            hits: 0,
+        });
+        let hash = memo.memoized_hash();
+
+        let hits = memo.with_mut_unchecked(|tracked| {
+            tracked.hits += 2;
+            tracked.hits
+        });
+        assert_eq!(hits, 2);
+        assert_eq!(memo.value.hits, 2);
+        assert_eq!(memo.hash.load(Ordering::Relaxed), hash);
+
+        let fresh = HashMemo::new(Tracked {
+            key: "key".to_string(),
+            hits: 0,
+        });
+        assert_eq!(fresh.memoized_hash(), hash);
+        assert!(memo == fresh);
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[cfg(all(feature = "validate", debug_assertions))]
+    #[test]
+    #[should_panic = "changed the hash"]
+    fn validate_catches_hash_changes_through_with_mut_unchecked() {
+        let mut memo = HashMemo::new(Tracked {
+            key: "key".to_string(),
+            hits: 0,
+        });
+        memo.precompute();
+        memo.with_mut_unchecked(|tracked| tracked.key.push('!'));
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn clone_without_hash_starts_cold() {
+        let memo = HashMemo::new("cold".to_string());
+        memo.precompute();
+        let warm = memo.hash.load(Ordering::Relaxed);
+
+        assert_eq!(memo.clone().hash.load(Ordering::Relaxed), warm);
+        let clone = memo.clone_without_hash();
+        assert_eq!(clone.hash.load(Ordering::Relaxed), 0);
+        assert_eq!(clone.value, "cold");
+        assert_eq!(clone.memoized_hash(), warm);
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn clone_with_default_hasher_rebuilds_the_hasher_and_starts_cold() {
+        // Holds a non-`Clone` handle, so memos using it are not `Clone`.
+        #[derive(Default)]
+        struct HandleState(#[allow(dead_code)] Option<std::fs::File>);
+
+        impl BuildHasher for HandleState {
+            type Hasher = DefaultHasher;
+
+            fn build_hasher(&self) -> DefaultHasher {
+                DefaultHasher::new()
             }
         }
 
+        let memo = HashMemo::with_hasher("handle".to_string(), HandleState::default());
+        memo.precompute();
+        let warm = memo.hash.load(Ordering::Relaxed);
+
+        let clone = memo.clone_with_default_hasher();
+        assert_eq!(
+            clone.hash.load(Ordering::Relaxed),
+            0,
+            "the cache is cleared"
+        );
+        assert_eq!(clone.value, "handle");
+        assert_eq!(clone.memoized_hash(), warm);
+
+        // A `Clone` hasher keeps the clone's cache warm.
+        let memo = HashMemo::new("handle".to_string());
+        memo.precompute();
+        let warm = memo.hash.load(Ordering::Relaxed);
+        assert_eq!(memo.clone().hash.load(Ordering::Relaxed), warm);
+        assert_eq!(
+            memo.clone_with_default_hasher()
+                .hash
+                .load(Ordering::Relaxed),
+            0
+        );
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn boxed_str_conversions_keep_the_cache() {
+        let mut value = String::with_capacity(64);
+        value.push_str("compact");
+        let fresh = HashMemo::new(value.clone()).memoized_hash();
+
+        // A seeded hash the hasher would not produce survives both ways,
+        // so neither conversion recomputes.
+        let seeded = HashMemo::new_const(value.clone(), 42).into_boxed_str();
+        assert_eq!(seeded.hash.load(Ordering::Relaxed), 42);
+        assert_eq!(seeded.into_string().hash.load(Ordering::Relaxed), 42);
+
+        let memo = HashMemo::new(value);
+        memo.precompute();
+        let boxed = memo.into_boxed_str();
+        assert_eq!(&*boxed.value, "compact");
+        assert_eq!(boxed.hash.load(Ordering::Relaxed), fresh);
+        assert_eq!(
+            boxed.memoized_hash(),
+            HashMemo::new(Box::<str>::from("compact")).memoized_hash()
+        );
+
+        let back = boxed.into_string();
+        assert_eq!(back.hash.load(Ordering::Relaxed), fresh);
+        assert_eq!(back.value, "compact");
+    }
+
+    #[test]
+    fn rehash_moves_the_value_into_a_cold_memo() {
+        let memo = HashMemo::new("migrate".to_string());
+        memo.precompute();
+
+        let rehashed: HashMemo<String, ahash::RandomState> = memo.rehash();
+        assert_eq!(rehashed.hash.load(Ordering::Relaxed), 0);
+        assert_eq!(rehashed.value, "migrate");
+        assert_eq!(
+            rehashed.memoized_hash(),
+            remap_zero(rehashed.hasher.hash_one("migrate"))
+        );
+
+        let state = RandomState::new();
+        let back = rehashed.rehash_with(state.clone());
+        assert_eq!(back.memoized_hash(), remap_zero(state.hash_one("migrate")));
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn hash_matches_computes_on_demand() {
+        let memo = HashMemo::new("route".to_string());
+        let hash = calculate_hash(&"route".to_string());
+        assert!(memo.hash_matches(hash));
+        assert_eq!(memo.hash.load(Ordering::Relaxed), hash);
+        assert!(!memo.hash_matches(hash ^ 1));
+        assert!(!memo.hash_matches(0));
+
+        let zero = HashMemo::new_const("zero", 0);
+        assert!(zero.hash_matches(1));
+        assert!(!zero.hash_matches(0));
+    }
+
+    #[test]
+    fn racing_threads_publish_a_consistent_hash() {
+        for _ in 0..50 {
+            let memo = HashMemo::new("race".repeat(256));
+            let barrier = std::sync::Barrier::new(8);
+            let hashes: Vec<u64> = std::thread::scope(|s| {
+                let handles: Vec<_> = (0..8)
+                    .map(|_| {
+                        s.spawn(|| {
+                            barrier.wait();
+                            calculate_hash(&memo)
+                        })
+                    })
+                    .collect();
+                handles.into_iter().map(|h| h.join().unwrap()).collect()
+            });
+
+            assert!(hashes.iter().all(|&h| h == hashes[0]));
+            assert_eq!(calculate_hash(&memo), hashes[0]);
+        }
+    }
+
+    #[test]
+    fn struct_is_not_significantly_larger_than_wrapped_value() {
+        assert!(
+            std::mem::size_of::<HashMemo<String>>()
+                <= std::mem::size_of::<String>() + std::mem::size_of::<u64>(),
+            "HashMemo should have minimal overhead"
+        );
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn zero_hash_is_remapped_to_nonzero_in_cache() {
         // Sanity check: hash value of FixedHash<0> using dummy hasher is 0
         assert_eq!(
             calculate_hash_with_hasher::<PinHash<0>, NoHashHasher<u64>>(&PinHash::<0>()),
@@ -350,4 +2006,381 @@ mod tests {
         let cached = memo.hash.load(Ordering::Relaxed);
         assert_ne!(cached, 0, "Cached hash must not be zero");
     }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn hash_one_cached_matches_the_hasher() {
+        let state = RandomState::new();
+        let memo = HashMemo::with_hasher(vec![1u8, 2, 3], state.clone());
+        assert_eq!(memo.hash_one_cached(), state.hash_one(vec![1u8, 2, 3]));
+        assert_eq!(memo.hash_one_cached(), memo.hash.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn is_zero_remapped_detects_a_genuine_zero_hash() {
+        let zero = HashMemo::with_hasher(
+            PinHash::<0>(),
+            BuildHasherDefault::<NoHashHasher<u64>>::default(),
+        );
+        assert!(zero.is_zero_remapped());
+        zero.precompute();
+        assert!(zero.is_zero_remapped());
+
+        // Caches the same `1` as the remapped zero, but genuinely.
+        let one = HashMemo::with_hasher(
+            PinHash::<1>(),
+            BuildHasherDefault::<NoHashHasher<u64>>::default(),
+        );
+        one.precompute();
+        assert!(!one.is_zero_remapped());
+
+        assert!(!HashMemo::new("foo".to_string()).is_zero_remapped());
+    }
+
+    #[test]
+    fn btree_map_keys_order_and_range_by_the_value() {
+        use std::collections::BTreeMap;
+        use std::ops::Bound::{Excluded, Included};
+
+        let words = ["delta", "alpha", "echo", "charlie", "bravo"];
+        let map: BTreeMap<_, _> = words
+            .iter()
+            .map(|w| (HashMemo::new(w.to_string()), w.len()))
+            .collect();
+
+        let mut sorted = words;
+        sorted.sort();
+        assert!(map.keys().map(|k| k.value.as_str()).eq(sorted));
+        for pair in map.keys().collect::<Vec<_>>().windows(2) {
+            assert_eq!(pair[0].cmp(pair[1]), pair[0].value.cmp(&pair[1].value));
+            assert_eq!(pair[0] == pair[1], pair[0].cmp(pair[1]).is_eq());
+        }
+
+        let charlie = "charlie".to_string();
+        assert_eq!(map.get(&charlie), Some(&7));
+        assert_eq!(map.get(&HashMemo::new("echo".to_string())), Some(&4));
+        let hits: Vec<_> = map
+            .range::<String, _>((Included("b".to_string()), Excluded("d".to_string())))
+            .map(|(k, _)| k.value.as_str())
+            .collect();
+        assert_eq!(hits, ["bravo", "charlie"]);
+        assert_eq!(map.range::<String, _>("d".to_string()..).count(), 2);
+    }
+
+    #[test]
+    fn eq_against_borrowed_and_owned_cow() {
+        let memo = HashMemo::new("foo".to_string());
+        assert!(memo == Cow::Borrowed("foo"));
+        assert!(memo == Cow::<str>::Owned("foo".to_string()));
+        assert!(memo != Cow::Borrowed("bar"));
+        assert!(memo != Cow::<str>::Owned("fo".to_string()));
+
+        let memo = HashMemo::new(b"foo".to_vec());
+        assert!(memo == Cow::Borrowed(&b"foo"[..]));
+        assert!(memo == Cow::<[u8]>::Owned(b"foo".to_vec()));
+        assert!(memo != Cow::Borrowed(&b"bar"[..]));
+        assert!(memo != Cow::<[u8]>::Owned(Vec::new()));
+    }
+
+    #[test]
+    fn long_byte_values_compare_by_content() {
+        let a = HashMemo::new(vec![7u8; 1 << 20]);
+        let b = HashMemo::new(vec![7u8; 1 << 20]);
+        assert!(a == b);
+
+        let mut last_differs = vec![7u8; 1 << 20];
+        *last_differs.last_mut().unwrap() = 8;
+        assert!(a != HashMemo::new(last_differs));
+        assert!(a != HashMemo::new(vec![7u8; (1 << 20) - 1]));
+
+        let s = HashMemo::new("x".repeat(1 << 20));
+        assert!(s == HashMemo::new("x".repeat(1 << 20)));
+        assert!(s != HashMemo::new(format!("{}y", "x".repeat((1 << 20) - 1))));
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn fast_eq_falls_back_to_the_values_unless_the_hashes_differ() {
+        thread_local! {
+            static EQS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+        }
+
+        /// Hashes as its bucket, so equal buckets collide.
+        struct Key {
+            id: u32,
+            bucket: u64,
+        }
+        impl PartialEq for Key {
+            fn eq(&self, other: &Self) -> bool {
+                EQS.with(|c| c.set(c.get() + 1));
+                self.id == other.id
+            }
+        }
+        impl Eq for Key {}
+        impl Hash for Key {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                state.write_u64(self.bucket);
+            }
+        }
+
+        let key = |id, bucket| HashMemo::new(Key { id, bucket });
+        let eqs = || EQS.with(std::cell::Cell::get);
+        let (a, collides, apart) = (key(1, 0), key(2, 0), key(3, 1));
+
+        assert!(!a.fast_eq(&apart), "cold");
+        assert_eq!(eqs(), 1);
+        for memo in [&a, &collides, &apart] {
+            memo.precompute();
+        }
+
+        assert!(!a.fast_eq(&apart));
+        assert_eq!(eqs(), 1, "different hashes decide");
+        assert!(!a.fast_eq(&collides));
+        assert_eq!(eqs(), 2, "a collision still compares the values");
+        assert!(a.fast_eq(&key(1, 0)));
+        assert_eq!(eqs(), 3);
+        assert!(a != apart);
+        assert_eq!(eqs(), 4, "`==` always compares the values");
+    }
+
+    #[test]
+    fn equality_ignores_differently_seeded_caches() {
+        let a = HashMemo::with_hasher("foo".to_string(), RandomState::new());
+        let b = HashMemo::with_hasher("foo".to_string(), RandomState::new());
+        a.precompute();
+        b.precompute();
+        assert!(a == b);
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic = "stale cached hash"]
+    fn eq_asserts_on_a_stale_cache() {
+        let a = HashMemo::new("foo".to_string());
+        let b = HashMemo::new("foo".to_string());
+        a.precompute();
+        b.precompute();
+        a.hash
+            .store(remap_zero(a.hash_one_cached() ^ 1), Ordering::Relaxed);
+        let _ = a == b;
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic = "hashers disagree"]
+    fn fast_eq_asserts_on_differently_seeded_caches() {
+        let a = HashMemo::with_hasher("foo".to_string(), RandomState::new());
+        let b = HashMemo::with_hasher("bar".to_string(), RandomState::new());
+        a.precompute();
+        b.precompute();
+        a.fast_eq(&b);
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn seeded_memos_compare_by_value() {
+        let seeded = HashMemo::new_const("foo".to_string(), Probe::new("foo").hash());
+        let computed = HashMemo::new("foo".to_string());
+        let other = HashMemo::new_const("bar".to_string(), 0xfedc_ba98_7654_3210);
+        computed.precompute();
+
+        assert!(seeded == computed);
+        assert!(seeded != other, "unequal values are not checked");
+        assert!(!seeded.fast_eq(&other));
+        assert!(seeded.fast_eq(&computed));
+    }
+
+    #[test]
+    fn random_memos_share_a_seed_that_differs_from_other_states() {
+        let a = HashMemo::new_random("foo".to_string());
+        let b = HashMemo::new_random("foo".to_string());
+        assert_eq!(a.memoized_hash(), b.memoized_hash());
+
+        let other = HashMemo::with_hasher("foo".to_string(), RandomState::new());
+        assert_ne!(a.memoized_hash(), other.memoized_hash());
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn const_memos_use_their_precomputed_hashes() {
+        static KEYS: [HashMemo<&str>; 3] = [
+            HashMemo::new_const("alpha", 11),
+            HashMemo::new_const("beta", 22),
+            HashMemo::new_const("gamma", 0),
+        ];
+
+        assert_eq!(KEYS[0].memoized_hash(), 11);
+        assert_eq!(KEYS[1].memoized_hash(), 22);
+        assert_eq!(KEYS[2].memoized_hash(), 1, "zero is remapped");
+
+        let map: std::collections::HashMap<_, _> =
+            KEYS.iter().enumerate().map(|(i, key)| (key, i)).collect();
+        assert_eq!(map.get(&&HashMemo::new_const("beta", 22)), Some(&1));
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn const_constructed_statics_are_ordinary_map_keys() {
+        static COLD: [HashMemo<&str>; 2] = [HashMemo::new("alpha"), HashMemo::new("beta")];
+        static FIXED: HashMemo<&str, FixedState> =
+            HashMemo::with_hasher_const("gamma", FixedState, 0x2f4d_0a4e_3a9d_4b0c);
+        static NARROW: HashMemo32<&str> = HashMemo32::new("delta");
+
+        let mut map = std::collections::HashMap::new();
+        for (i, key) in COLD.iter().enumerate() {
+            map.insert(key, i);
+        }
+        assert_eq!(map.get(&HashMemo::new("beta")), Some(&1));
+        assert_eq!(map.get(&HashMemo::new("gamma")), None);
+
+        assert_eq!(FIXED.memoized_hash(), 0x2f4d_0a4e_3a9d_4b0c);
+        assert_eq!(NARROW, HashMemo32::new("delta"));
+        assert_eq!(
+            calculate_hash(&NARROW),
+            calculate_hash(&HashMemo32::new("delta"))
+        );
+    }
+
+    #[test]
+    fn take_returns_the_value_and_leaves_a_cold_default() {
+        let mut memo = HashMemo::new("drained".to_string());
+        memo.precompute();
+
+        assert_eq!(memo.take(), "drained");
+        assert_eq!(memo.as_ref(), "");
+        assert_eq!(memo.hash.load(Ordering::Relaxed), 0);
+        assert_eq!(
+            memo.hash_one_cached(),
+            HashMemo::new(String::new()).hash_one_cached()
+        );
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn swap_exchanges_values_and_warm_caches() {
+        use crate::test_util::CountingState;
+
+        let state = CountingState::default();
+        let mut a = HashMemo::with_hasher("left".to_string(), state.clone());
+        let mut b = HashMemo::with_hasher("right".to_string(), state.clone());
+        let (hash_a, hash_b) = (a.hash_one_cached(), b.hash_one_cached());
+        assert_eq!(state.count(), 2);
+
+        swap(&mut a, &mut b);
+        assert_eq!(
+            (a.as_ref().as_str(), b.as_ref().as_str()),
+            ("right", "left")
+        );
+        assert_eq!(a.hash_one_cached(), hash_b);
+        assert_eq!(b.hash_one_cached(), hash_a);
+        assert_eq!(state.count(), 2, "nothing was rehashed");
+
+        assert_eq!(a, HashMemo::with_hasher("right".to_string(), state.clone()));
+        assert_eq!(b, HashMemo::with_hasher("left".to_string(), state));
+    }
+
+    #[test]
+    fn eq_value_compares_against_raw_values() {
+        #[derive(PartialEq, Eq, Hash)]
+        struct BigStruct {
+            name: String,
+            data: [u64; 64],
+        }
+
+        let big = |name: &str, fill| BigStruct {
+            name: name.to_string(),
+            data: [fill; 64],
+        };
+        let memo = HashMemo::new(big("a", 1));
+        assert!(memo.eq_value(&big("a", 1)));
+        assert!(!memo.eq_value(&big("a", 2)));
+        assert!(!memo.eq_value(&big("b", 1)));
+        assert_eq!(memo.hash.load(Ordering::Relaxed), 0, "the cache stays cold");
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn with_mut_batches_writes_into_one_recompute() {
+        use crate::test_util::CountingState;
+
+        let state = CountingState::default();
+        let mut memo = HashMemo::with_hasher(vec![0u64; 256], state.clone());
+        memo.precompute();
+
+        let indices = [3, 0, 255, 128, 7, 64];
+        let written = memo.with_mut(|values| {
+            for i in indices {
+                values[i] = i as u64 * 3;
+            }
+            indices.len()
+        });
+        assert_eq!(written, 6);
+        assert_eq!(memo.hash.load(Ordering::Relaxed), 0);
+
+        let mut expected = vec![0u64; 256];
+        for i in indices {
+            expected[i] = i as u64 * 3;
+        }
+        let hash = memo.hash_one_cached();
+        memo.hash_one_cached();
+        assert_eq!(state.count(), 2, "one hash before, one after");
+        assert_eq!(
+            hash,
+            HashMemo::with_hasher(expected, state).hash_one_cached()
+        );
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn from_hashed_bytes_reads_a_little_endian_prefix() {
+        let memo = |digest: &[u8]| {
+            HashMemo::from_hashed_bytes("blob", digest, FixedState)
+                .hash
+                .into_inner()
+        };
+        let digest: Vec<u8> = (1..=32).collect();
+
+        assert_eq!(memo(&digest), 0x0807_0605_0403_0201, "a full digest");
+        assert_eq!(memo(&digest[..8]), 0x0807_0605_0403_0201);
+        assert_eq!(memo(&digest[..3]), 0x03_0201, "a short digest");
+        assert_eq!(memo(&digest[..1]), 0x01);
+        assert_eq!(memo(&[]), 1, "an empty digest reads as 0, remapped");
+        assert_eq!(memo(&[0; 32]), 1, "zero is remapped");
+
+        let a = HashMemo::from_hashed_bytes("blob", &digest, FixedState);
+        let b = HashMemo::from_hashed_bytes("blob", &digest, FixedState);
+        assert_eq!(calculate_hash(&a), calculate_hash(&b));
+        assert_eq!(a, b);
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn clone_from_reuses_the_allocation_and_copies_the_cache() {
+        let template = HashMemo::new("template".repeat(8));
+        let hash = template.hash_one_cached();
+
+        let mut existing = HashMemo::new(String::with_capacity(1024));
+        let buffer = existing.as_ref().as_ptr();
+        existing.precompute();
+        for _ in 0..3 {
+            existing.clone_from(&template);
+            assert_eq!(existing.as_ref().as_ptr(), buffer, "no reallocation");
+            assert_eq!(existing.as_ref().capacity(), 1024);
+            assert_eq!(existing.hash.load(Ordering::Relaxed), hash);
+        }
+
+        let cloned = template.clone();
+        assert_eq!(existing, cloned);
+        assert_eq!(existing.hash_one_cached(), cloned.hash_one_cached());
+        assert_eq!(calculate_hash(&existing), calculate_hash(&cloned));
+
+        // A cold source leaves a cold copy, as `clone` does.
+        let cold = HashMemo::new("cold".to_string());
+        existing.clone_from(&cold);
+        assert_eq!(existing.hash.load(Ordering::Relaxed), 0);
+        assert_eq!(cold.clone().hash.load(Ordering::Relaxed), 0);
+        assert_eq!(existing.hash_one_cached(), cold.hash_one_cached());
+    }
 }
@@ -0,0 +1,270 @@
+//! Streaming deduplication of the lines of a reader.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use crate::{HashMemo, PassthroughState};
+
+/// Returns the lines of `reader` with repeats removed, keeping the first
+/// occurrence of each in order.
+///
+/// Lines are read one at a time, so only the distinct lines are held in
+/// memory, not the input. Each line is hashed once, into a memo kept in a
+/// [`PassthroughState`] set, which reuses the cached hashes as it grows.
+///
+/// By default a line ends at `\n`, which is dropped along with a `\r`
+/// before it, so `a\r\n` and `a\n` are the same line; a last line without
+/// a newline is a line too. A line that is not valid UTF-8 is an
+/// [`InvalidData`](io::ErrorKind::InvalidData) error. The returned
+/// [`DedupLines`] changes these and can bound the number of distinct lines.
+/// Iteration stops after the first error.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::dedup_lines;
+///
+/// let input = "b\na\r\nb\nc\na";
+/// let lines: Vec<String> = dedup_lines(input.as_bytes())
+///     .collect::<std::io::Result<_>>()
+///     .unwrap();
+/// assert_eq!(lines, ["b", "a", "c"]);
+/// ```
+pub fn dedup_lines<R: BufRead>(reader: R) -> DedupLines<R> {
+    DedupLines {
+        reader,
+        buf: Vec::new(),
+        seen: HashSet::with_hasher(PassthroughState),
+        trim_cr: true,
+        lossy: false,
+        max_distinct: None,
+        done: false,
+    }
+}
+
+/// Writes the lines of `reader` to `writer` with repeats removed, each
+/// followed by `\n`, and returns the number of lines written.
+///
+/// This is [`dedup_lines`] with its defaults, then
+/// [`write_to`](DedupLines::write_to).
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::write_deduped;
+///
+/// let mut out = Vec::new();
+/// assert_eq!(write_deduped("x\ny\nx\n".as_bytes(), &mut out).unwrap(), 2);
+/// assert_eq!(out, b"x\ny\n");
+/// ```
+pub fn write_deduped<R: BufRead, W: Write>(reader: R, writer: W) -> io::Result<u64> {
+    dedup_lines(reader).write_to(writer)
+}
+
+/// An iterator over the distinct lines of a reader, returned by
+/// [`dedup_lines`].
+///
+/// The options are set before iterating.
+pub struct DedupLines<R> {
+    reader: R,
+    /// The bytes of the line being read, reused across duplicates.
+    buf: Vec<u8>,
+    seen: HashSet<HashMemo<String>, PassthroughState>,
+    trim_cr: bool,
+    lossy: bool,
+    max_distinct: Option<usize>,
+    done: bool,
+}
+
+impl<R: BufRead> DedupLines<R> {
+    /// Sets whether a `\r` before the `\n` is dropped, `true` by default.
+    ///
+    /// With `false`, `a\r\n` and `a\n` are different lines, and the `\r`
+    /// is part of the first.
+    pub fn trim_cr(mut self, trim_cr: bool) -> Self {
+        self.trim_cr = trim_cr;
+        self
+    }
+
+    /// Sets whether invalid UTF-8 is replaced with `U+FFFD`, rather than
+    /// being an error, `false` by default.
+    ///
+    /// Lines that differ only in their invalid bytes can then be the same
+    /// line.
+    pub fn lossy(mut self, lossy: bool) -> Self {
+        self.lossy = lossy;
+        self
+    }
+
+    /// Bounds the number of distinct lines, and so the memory held.
+    ///
+    /// A distinct line beyond the first `max` is an
+    /// [`OutOfMemory`](io::ErrorKind::OutOfMemory) error instead; repeats
+    /// of the first `max` are still skipped until then.
+    pub fn max_distinct(mut self, max: usize) -> Self {
+        self.max_distinct = Some(max);
+        self
+    }
+
+    /// Returns the number of distinct lines yielded so far.
+    #[inline]
+    pub fn distinct(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// Writes the remaining distinct lines to `writer`, each followed by
+    /// `\n`, and returns the number of lines written.
+    pub fn write_to<W: Write>(self, mut writer: W) -> io::Result<u64> {
+        let mut written = 0;
+        for line in self {
+            let line = line?;
+            writer.write_all(line.as_bytes())?;
+            writer.write_all(b"\n")?;
+            written += 1;
+        }
+        writer.flush()?;
+        Ok(written)
+    }
+
+    /// Reads the next line into `buf`, without its terminator. Returns
+    /// `false` at the end of the input.
+    fn read_line(&mut self) -> io::Result<bool> {
+        self.buf.clear();
+        if self.reader.read_until(b'\n', &mut self.buf)? == 0 {
+            return Ok(false);
+        }
+        if self.buf.last() == Some(&b'\n') {
+            self.buf.pop();
+            if self.trim_cr && self.buf.last() == Some(&b'\r') {
+                self.buf.pop();
+            }
+        }
+        Ok(true)
+    }
+
+    fn decode(&mut self) -> io::Result<String> {
+        let bytes = std::mem::take(&mut self.buf);
+        match String::from_utf8(bytes) {
+            Ok(line) => Ok(line),
+            Err(err) if self.lossy => Ok(String::from_utf8_lossy(err.as_bytes()).into_owned()),
+            Err(err) => Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+        }
+    }
+
+    fn next_distinct(&mut self) -> io::Result<Option<String>> {
+        while self.read_line()? {
+            let memo = HashMemo::new(self.decode()?);
+            if self.seen.contains(&memo) {
+                self.buf = memo.into_inner().into_bytes();
+                continue;
+            }
+            if self.max_distinct == Some(self.seen.len()) {
+                return Err(io::Error::new(
+                    io::ErrorKind::OutOfMemory,
+                    format!("more than {} distinct lines", self.seen.len()),
+                ));
+            }
+            self.seen.insert(memo.clone());
+            return Ok(Some(memo.into_inner()));
+        }
+        Ok(None)
+    }
+}
+
+impl<R: BufRead> Iterator for DedupLines<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<io::Result<String>> {
+        if self.done {
+            return None;
+        }
+        let next = self.next_distinct().transpose();
+        self.done = !matches!(next, Some(Ok(_)));
+        next
+    }
+}
+
+impl<R> fmt::Debug for DedupLines<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DedupLines")
+            .field("distinct", &self.seen.len())
+            .field("trim_cr", &self.trim_cr)
+            .field("lossy", &self.lossy)
+            .field("max_distinct", &self.max_distinct)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dedup(input: &[u8]) -> Vec<String> {
+        dedup_lines(input).collect::<io::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn keeps_first_occurrences_across_line_endings() {
+        assert_eq!(dedup(b"b\na\nb\nc\na\n"), ["b", "a", "c"]);
+        assert_eq!(dedup(b"a\nb\na"), ["a", "b"], "last line without newline");
+        assert_eq!(dedup(b"a\r\nb\na\nb\r\n"), ["a", "b"]);
+        assert_eq!(dedup(b"\n\na\n\n"), ["", "a"]);
+        assert!(dedup(b"").is_empty());
+
+        let kept: Vec<String> = dedup_lines(&b"a\r\nb\na\n"[..])
+            .trim_cr(false)
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(kept, ["a\r", "b", "a"]);
+    }
+
+    #[test]
+    fn invalid_utf8_is_an_error_unless_lossy() {
+        let input = b"ok\nbad \xff\nbad \xfe\nok\n";
+
+        let mut strict = dedup_lines(&input[..]);
+        assert_eq!(strict.next().unwrap().unwrap(), "ok");
+        let err = strict.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(strict.next().is_none());
+
+        let lossy: Vec<String> = dedup_lines(&input[..])
+            .lossy(true)
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(lossy, ["ok", "bad \u{fffd}"]);
+    }
+
+    #[test]
+    fn max_distinct_errors_instead_of_growing() {
+        let mut lines = dedup_lines(&b"a\nb\na\nb\nc\na\n"[..]).max_distinct(2);
+        assert_eq!(lines.next().unwrap().unwrap(), "a");
+        assert_eq!(lines.next().unwrap().unwrap(), "b");
+        let err = lines.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::OutOfMemory);
+        assert!(lines.next().is_none());
+        assert_eq!(lines.distinct(), 2);
+
+        let mut out = Vec::new();
+        let err = dedup_lines(&b"a\nb\n"[..])
+            .max_distinct(1)
+            .write_to(&mut out)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::OutOfMemory);
+        assert_eq!(out, b"a\n");
+    }
+
+    #[test]
+    fn large_input_smoke() {
+        let mut input = String::new();
+        for i in 0..200_000 {
+            input.push_str(&format!("{:0>64}\n", i % 10_000));
+        }
+        let mut out = Vec::new();
+        let written = write_deduped(input.as_bytes(), &mut out).unwrap();
+        assert_eq!(written, 10_000);
+        assert_eq!(out.len(), 10_000 * 65);
+        assert!(out.starts_with(format!("{:0>64}\n{:0>64}\n", 0, 1).as_bytes()));
+    }
+}
@@ -0,0 +1,87 @@
+//! The [`memo!`](crate::memo) macro for memos hashed at compile time.
+
+use crate::FixedState;
+
+/// Creates a [`HashMemo`](crate::HashMemo) for a string or byte string
+/// literal, hashed at compile time with [`FixedState`](crate::FixedState).
+///
+/// The result is a `HashMemo<&str, FixedState>` or a
+/// `HashMemo<&[u8; N], FixedState>` whose cache is already primed, so it can
+/// initialize a `static` and never hashes at runtime. It is equal to, and
+/// hashes like, the same literal wrapped by
+/// [`new_fixed`](crate::HashMemo::new_fixed).
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{memo, FixedState, HashMemo};
+///
+/// static GET: HashMemo<&str, FixedState> = memo!("GET");
+/// static MAGIC: HashMemo<&[u8; 4], FixedState> = memo!(b"\x7fELF");
+///
+/// assert_eq!(GET, HashMemo::new_fixed("GET"));
+/// assert_eq!(MAGIC, HashMemo::new_fixed(b"\x7fELF"));
+/// ```
+#[macro_export]
+macro_rules! memo {
+    ($literal:literal) => {
+        $crate::HashMemo::with_hasher_const(
+            $literal,
+            $crate::FixedState,
+            $crate::__Literal($literal).fixed_hash(),
+        )
+    };
+}
+
+/// Picks the `const` hash function for the type of a [`memo!`] literal.
+#[doc(hidden)]
+pub struct __Literal<T>(pub T);
+
+impl __Literal<&str> {
+    pub const fn fixed_hash(self) -> u64 {
+        FixedState::hash_str(self.0)
+    }
+}
+
+impl<const N: usize> __Literal<&[u8; N]> {
+    pub const fn fixed_hash(self) -> u64 {
+        FixedState::hash_bytes(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use crate::{FixedState, HashMemo};
+
+    #[test]
+    fn memo_matches_the_runtime_path() {
+        let runtime = HashMemo::with_hasher("x", FixedState);
+        assert_eq!(memo!("x").memoized_hash(), runtime.memoized_hash());
+        assert_eq!(memo!("x"), runtime);
+
+        let runtime = HashMemo::with_hasher(b"bytes", FixedState);
+        assert_eq!(memo!(b"bytes").memoized_hash(), runtime.memoized_hash());
+
+        let runtime = HashMemo::with_hasher("", FixedState);
+        assert_eq!(memo!("").memoized_hash(), runtime.memoized_hash());
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn memo_is_primed_in_statics() {
+        static KEYS: [HashMemo<&str, FixedState>; 3] = [
+            memo!("alpha"),
+            memo!("beta"),
+            memo!("a longer key spanning words"),
+        ];
+
+        let map: std::collections::HashMap<_, _> =
+            KEYS.iter().enumerate().map(|(i, key)| (key, i)).collect();
+        for (i, key) in KEYS.iter().enumerate() {
+            assert_ne!(key.hash.load(Ordering::Relaxed), 0);
+            assert_eq!(map.get(&&HashMemo::new_fixed(*key.as_ref())), Some(&i));
+        }
+    }
+}
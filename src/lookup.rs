@@ -0,0 +1,275 @@
+//! Borrowed lookups against collections keyed by [`HashMemo`].
+//!
+//! `HashMemo<T>` implements `Borrow<T>`, but it hashes as the memoized `u64`
+//! while `T` hashes its contents, so probing a `HashMap<HashMemo<String>, V>`
+//! with a `&str` through `Borrow` looks in the wrong bucket. This module
+//! provides lookups that agree with the stored keys instead.
+//!
+//! Every `HashMemo<T, H>` also borrows as `dyn MemoLookup<Q>` for any `Q`
+//! that `T` borrows as. That trait object hashes like the memo, so a
+//! [`Probe`] built from a `&Q` and the same hasher finds the stored key in
+//! any map that looks keys up through `Borrow`: `std`'s `HashMap` and
+//! `HashSet`, `hashbrown`, `dashmap`, `indexmap` and friends. Any outer
+//! `BuildHasher` works, since both sides feed it the same single `u64`.
+//!
+//! # Nested memos
+//!
+//! A `HashMemo<HashMemo<T>>` hashes the inner memo's `u64`, so a probe for
+//! it carries one more layer of hashing: [`Probe::nested`] adds it. Such a
+//! key borrows as `dyn MemoLookup<Q>` for what the inner memo borrows as,
+//! that is the inner memo itself and `T`, so it is found by `T` through
+//! `Probe::new(&value).nested()`. It goes no further down: reaching a `Q`
+//! that only `T` borrows as, such as `str` for `String`, would take a
+//! blanket `Borrow<Q>` impl on `HashMemo`, which overlaps the reflexive
+//! `Borrow<T> for T`. Probe with an owned `T` in that case.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::{HashMemo, Probe};
+//! use std::collections::HashMap;
+//!
+//! let mut map = HashMap::new();
+//! map.insert(HashMemo::new("key".to_string()), 1);
+//!
+//! assert_eq!(map.get(Probe::new("key").as_lookup()), Some(&1));
+//! ```
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher};
+
+use crate::{remap_zero, HashMemo};
+
+/// A borrowed form of a memoized key, used to probe collections keyed by
+/// [`HashMemo`].
+///
+/// `dyn MemoLookup<Q>` hashes as [`memo_hash`](Self::memo_hash) and compares
+/// by [`memo_value`](Self::memo_value). Implementations must return the hash
+/// the matching `HashMemo` caches for a value equal to `memo_value()`.
+pub trait MemoLookup<Q: ?Sized> {
+    /// Returns the memoized hash of the value.
+    fn memo_hash(&self) -> u64;
+
+    /// Returns the value compared for equality.
+    fn memo_value(&self) -> &Q;
+}
+
+impl<Q: ?Sized + PartialEq> PartialEq for dyn MemoLookup<Q> + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        self.memo_value() == other.memo_value()
+    }
+}
+
+impl<Q: ?Sized + Eq> Eq for dyn MemoLookup<Q> + '_ {}
+
+impl<Q: ?Sized> Hash for dyn MemoLookup<Q> + '_ {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.memo_hash());
+    }
+}
+
+impl<Q: ?Sized + fmt::Debug> fmt::Debug for dyn MemoLookup<Q> + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.memo_value().fmt(f)
+    }
+}
+
+impl<T, H, Q> MemoLookup<Q> for HashMemo<T, H>
+where
+    T: Eq + Hash + Borrow<Q>,
+    H: BuildHasher,
+    Q: ?Sized,
+{
+    #[inline]
+    fn memo_hash(&self) -> u64 {
+        self.memoized_hash()
+    }
+
+    #[inline]
+    fn memo_value(&self) -> &Q {
+        self.value.borrow()
+    }
+}
+
+impl<'a, T, H, Q> Borrow<dyn MemoLookup<Q> + 'a> for HashMemo<T, H>
+where
+    T: Eq + Hash + Borrow<Q> + 'a,
+    H: BuildHasher + 'a,
+    Q: ?Sized + 'a,
+{
+    #[inline]
+    fn borrow(&self) -> &(dyn MemoLookup<Q> + 'a) {
+        self
+    }
+}
+
+/// A borrowed value paired with its memoized hash, for probing collections
+/// keyed by [`HashMemo`].
+///
+/// The probe must be built with a hasher equivalent to the one the stored
+/// memos use; [`Probe::new`] matches [`HashMemo::new`].
+pub struct Probe<'a, Q: ?Sized> {
+    value: &'a Q,
+    hash: u64,
+}
+
+impl<'a, Q: ?Sized + Hash> Probe<'a, Q> {
+    /// Creates a probe matching memos built with [`HashMemo::new`].
+    #[inline]
+    pub fn new(value: &'a Q) -> Self {
+        Self::with_hasher(value, &BuildHasherDefault::<DefaultHasher>::default())
+    }
+
+    /// Creates a probe matching memos built with `hasher`.
+    #[inline]
+    pub fn with_hasher<H: BuildHasher>(value: &'a Q, hasher: &H) -> Self {
+        Self {
+            value,
+            hash: remap_zero(hasher.hash_one(value)),
+        }
+    }
+}
+
+impl<'a, Q: ?Sized> Probe<'a, Q> {
+    /// Returns the probe as the key type that memo-keyed collections borrow
+    /// their keys as.
+    #[inline]
+    pub fn as_lookup(&self) -> &(dyn MemoLookup<Q> + 'a) {
+        self
+    }
+
+    /// Returns the probe for one more layer of memo, matching a
+    /// `HashMemo::new(HashMemo::new(value))` key.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::{HashMemo, Probe};
+    /// use std::collections::HashSet;
+    ///
+    /// let mut set = HashSet::new();
+    /// set.insert(HashMemo::new(HashMemo::new("key".to_string())));
+    ///
+    /// let key = "key".to_string();
+    /// assert!(set.contains(Probe::new(&key).nested().as_lookup()));
+    /// ```
+    #[inline]
+    pub fn nested(self) -> Self {
+        self.nested_with_hasher(&BuildHasherDefault::<DefaultHasher>::default())
+    }
+
+    /// Like [`nested`](Self::nested), for an outer memo built with
+    /// `hasher`.
+    #[inline]
+    pub fn nested_with_hasher<H: BuildHasher>(self, hasher: &H) -> Self {
+        Self {
+            value: self.value,
+            hash: remap_zero(hasher.hash_one(self.hash)),
+        }
+    }
+
+    /// Returns the probed value.
+    #[inline]
+    pub fn value(&self) -> &'a Q {
+        self.value
+    }
+
+    /// Returns the memoized hash the probe will match.
+    #[inline]
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+}
+
+impl<Q: ?Sized> MemoLookup<Q> for Probe<'_, Q> {
+    #[inline]
+    fn memo_hash(&self) -> u64 {
+        self.hash
+    }
+
+    #[inline]
+    fn memo_value(&self) -> &Q {
+        self.value
+    }
+}
+
+impl<Q: ?Sized> Clone for Probe<'_, Q> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Q: ?Sized> Copy for Probe<'_, Q> {}
+
+impl<Q: ?Sized + fmt::Debug> fmt::Debug for Probe<'_, Q> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Probe")
+            .field("value", &self.value)
+            .field("hash", &self.hash)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use std::hash::RandomState;
+
+    use super::*;
+    use crate::FixedState;
+
+    #[test]
+    fn probe_finds_keys_by_borrowed_value() {
+        let mut map = HashMap::new();
+        map.insert(HashMemo::new("alpha".to_string()), 1);
+        map.insert(HashMemo::new("beta".to_string()), 2);
+
+        assert_eq!(map.get(Probe::new("alpha").as_lookup()), Some(&1));
+        assert_eq!(map.get(Probe::new("beta").as_lookup()), Some(&2));
+        assert_eq!(map.get(Probe::new("gamma").as_lookup()), None);
+        assert_eq!(map.remove(Probe::new("alpha").as_lookup()), Some(1));
+        assert!(!map.contains_key(Probe::new("alpha").as_lookup()));
+    }
+
+    #[test]
+    fn probe_matches_memo_hasher() {
+        let mut set: HashSet<_, RandomState> = HashSet::default();
+        set.insert(HashMemo::new_fixed(vec![1u8, 2, 3]));
+
+        let probe = Probe::with_hasher(&[1u8, 2, 3][..], &FixedState);
+        assert_eq!(
+            probe.hash(),
+            HashMemo::new_fixed(vec![1u8, 2, 3]).memoized_hash()
+        );
+        assert!(set.contains(probe.as_lookup()));
+    }
+
+    #[test]
+    fn nested_memos_are_found_by_the_innermost_value() {
+        let mut map = HashMap::new();
+        for (i, word) in ["alpha", "beta"].iter().enumerate() {
+            map.insert(HashMemo::new(HashMemo::new(word.to_string())), i);
+        }
+
+        let alpha = "alpha".to_string();
+        let probe = Probe::new(&alpha).nested();
+        assert_eq!(
+            probe.hash(),
+            HashMemo::new(HashMemo::new(alpha.clone())).memoized_hash()
+        );
+        assert_eq!(map.get(probe.as_lookup()), Some(&0));
+        assert_eq!(map.get(Probe::new(&alpha).as_lookup()), None);
+
+        let inner = HashMemo::new("beta".to_string());
+        assert_eq!(map.get(Probe::new(&inner).as_lookup()), Some(&1));
+        let beta = "beta".to_string();
+        assert_eq!(map.get(Probe::new(&beta).nested().as_lookup()), Some(&1));
+
+        let mut fixed = HashSet::new();
+        fixed.insert(HashMemo::new_fixed(HashMemo::new("gamma".to_string())));
+        let gamma = "gamma".to_string();
+        let probe = Probe::new(&gamma).nested_with_hasher(&FixedState);
+        assert!(fixed.contains(probe.as_lookup()));
+    }
+}
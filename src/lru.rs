@@ -0,0 +1,175 @@
+//! [`lru`](::lru) interop for caches keyed by memoized values.
+//!
+//! `LruCache` looks keys up through `Borrow`, so a plain `&str` does not
+//! find a `HashMemo<String>` key. [`MemoLruExt`] adds lookups by borrowed
+//! value that build a [`Probe`] with the key hasher, and a `put` that
+//! memoizes the key itself:
+//!
+//! ```rust
+//! use hashmemo::{MemoLruCache, MemoLruExt};
+//! use std::num::NonZeroUsize;
+//!
+//! let mut cache: MemoLruCache<String, u32> = MemoLruCache::new_memo(NonZeroUsize::new(2).unwrap());
+//! cache.put_value("a".to_string(), 1);
+//! assert_eq!(cache.get_by_value("a"), Some(&1));
+//! ```
+//!
+//! Caches whose keys use a hasher without a `Default` can be probed with
+//! [`Probe::with_hasher`] directly, as in `cache.get(probe.as_lookup())`.
+
+use std::borrow::Borrow;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash};
+use std::num::NonZeroUsize;
+
+use ::lru::LruCache;
+
+use crate::{HashMemo, PassthroughState, Probe};
+
+/// An `LruCache` keyed by [`HashMemo`], using the memoized hash directly.
+///
+/// `H` is the hasher of the keys; see [`MemoLruExt`] for lookups.
+pub type MemoLruCache<K, V, H = BuildHasherDefault<DefaultHasher>> =
+    LruCache<HashMemo<K, H>, V, PassthroughState>;
+
+/// Construction and by-value access for a [`MemoLruCache`].
+///
+/// The by-value methods hash the probe with `H::default()`, which must
+/// agree with the hasher of the stored keys.
+pub trait MemoLruExt<K, V, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher + Default,
+{
+    /// Creates a cache holding at most `cap` entries.
+    fn new_memo(cap: NonZeroUsize) -> Self;
+
+    /// Creates a cache with no capacity limit.
+    fn unbounded_memo() -> Self;
+
+    /// Memoizes `key` and inserts it, returning the previous value if any.
+    fn put_value(&mut self, key: K, value: V) -> Option<V>;
+
+    /// Like `get`, marking the entry as most recently used.
+    fn get_by_value<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash;
+
+    /// Like `get_mut`, marking the entry as most recently used.
+    fn get_mut_by_value<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash;
+
+    /// Like `peek`, leaving the recency order untouched.
+    fn peek_by_value<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash;
+
+    /// Like `pop`, removing the entry.
+    fn pop_by_value<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash;
+}
+
+impl<K, V, H> MemoLruExt<K, V, H> for MemoLruCache<K, V, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher + Default,
+{
+    fn new_memo(cap: NonZeroUsize) -> Self {
+        LruCache::with_hasher(cap, PassthroughState)
+    }
+
+    fn unbounded_memo() -> Self {
+        LruCache::unbounded_with_hasher(PassthroughState)
+    }
+
+    fn put_value(&mut self, key: K, value: V) -> Option<V> {
+        self.put(HashMemo::with_hasher(key, H::default()), value)
+    }
+
+    fn get_by_value<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.get(Probe::with_hasher(key, &H::default()).as_lookup())
+    }
+
+    fn get_mut_by_value<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.get_mut(Probe::with_hasher(key, &H::default()).as_lookup())
+    }
+
+    fn peek_by_value<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.peek(Probe::with_hasher(key, &H::default()).as_lookup())
+    }
+
+    fn pop_by_value<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.pop(Probe::with_hasher(key, &H::default()).as_lookup())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FixedState;
+
+    fn cache(cap: usize) -> MemoLruCache<String, u32> {
+        MemoLruCache::new_memo(NonZeroUsize::new(cap).unwrap())
+    }
+
+    #[test]
+    fn borrowed_probes_find_memoized_keys() {
+        let mut cache = cache(4);
+        cache.put_value("alpha".to_string(), 1);
+        cache.put(HashMemo::new("beta".to_string()), 2);
+
+        assert_eq!(cache.get_by_value("alpha"), Some(&1));
+        assert_eq!(cache.peek_by_value("beta"), Some(&2));
+        assert_eq!(cache.get_by_value("gamma"), None);
+        *cache.get_mut_by_value("beta").unwrap() += 10;
+        assert_eq!(cache.pop_by_value("beta"), Some(12));
+        assert_eq!(cache.peek_by_value("beta"), None);
+        assert_eq!(cache.get(Probe::new("alpha").as_lookup()), Some(&1));
+    }
+
+    #[test]
+    fn eviction_follows_recency_of_memoized_keys() {
+        let mut cache = cache(2);
+        cache.put_value("a".to_string(), 1);
+        cache.put_value("b".to_string(), 2);
+        assert_eq!(cache.get_by_value("a"), Some(&1));
+
+        cache.put_value("c".to_string(), 3);
+        assert_eq!(cache.peek_by_value("b"), None, "b was least recently used");
+        assert_eq!(cache.peek_by_value("a"), Some(&1));
+
+        assert_eq!(cache.peek_by_value("a"), Some(&1));
+        cache.put_value("d".to_string(), 4);
+        assert_eq!(cache.peek_by_value("a"), None, "peek does not promote");
+        assert_eq!(cache.peek_by_value("c"), Some(&3));
+        assert_eq!(cache.peek_by_value("d"), Some(&4));
+    }
+
+    #[test]
+    fn custom_key_hasher() {
+        let mut cache: MemoLruCache<Vec<u8>, u32, FixedState> = MemoLruCache::unbounded_memo();
+        cache.put(HashMemo::new_fixed(vec![1, 2, 3]), 7);
+        assert_eq!(cache.get_by_value(&[1u8, 2, 3][..]), Some(&7));
+    }
+}
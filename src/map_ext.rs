@@ -0,0 +1,295 @@
+//! Memo-aware lookups on a plain `std` `HashMap` keyed by [`HashMemo`].
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash};
+
+use crate::{HashMemo, Probe};
+
+/// By-value access to a `HashMap<HashMemo<K, H>, V, S>`.
+///
+/// Looking such a map up through `Borrow<K>` hashes the wrong thing: the
+/// stored keys hash as their memoized `u64`, a borrowed `&K` as its
+/// contents. These methods build a [`Probe`] with `H::default()` instead,
+/// which hashes the borrowed key exactly as the stored memos cached it, and
+/// look it up through `Borrow<dyn MemoLookup<Q>>`.
+///
+/// # Supported configurations
+///
+/// The outer `S` can be any `BuildHasher`: the default `RandomState`,
+/// [`PassthroughState`](crate::PassthroughState) or any other, since the
+/// stored keys and the probe both feed it the same single `u64`. The memo
+/// hasher `H` must build the same hasher from `H::default()` every time,
+/// and every stored key must have been built with that hasher, as
+/// [`insert_value`](Self::insert_value) does. `BuildHasherDefault`,
+/// [`FixedState`](crate::FixedState) and the seedless states of the hasher
+/// features qualify; `RandomState` does not, nor do keys built with a
+/// seeded hasher, which can be probed with [`Probe::with_hasher`] directly.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{HashMemo, MemoMapExt};
+/// use std::collections::HashMap;
+///
+/// let mut map: HashMap<HashMemo<String>, u32> = HashMap::new();
+/// map.insert_value("ada".to_string(), 36);
+///
+/// assert_eq!(map.get_value("ada"), Some(&36));
+/// *map.entry_value("alan").or_insert(0) += 41;
+/// assert_eq!(map.remove_value("alan"), Some(41));
+/// ```
+pub trait MemoMapExt<K, V, S, H = BuildHasherDefault<DefaultHasher>>
+where
+    K: Eq + Hash,
+    H: BuildHasher + Default,
+{
+    /// Memoizes `key` and inserts it, returning the previous value if any.
+    fn insert_value(&mut self, key: K, value: V) -> Option<V>;
+
+    /// Like `get`, by a borrowed key.
+    fn get_value<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash;
+
+    /// Like `get_mut`, by a borrowed key.
+    fn get_value_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash;
+
+    /// Like `contains_key`, by a borrowed key.
+    fn contains_value<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash;
+
+    /// Like `remove`, by a borrowed key.
+    fn remove_value<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash;
+
+    /// Like `entry`, by a borrowed key.
+    ///
+    /// The key is cloned into an owned `K` only if the entry is vacant and
+    /// a value is inserted, and its hash is computed once either way.
+    fn entry_value<'a, 'q, Q>(&'a mut self, key: &'q Q) -> ValueEntry<'a, 'q, K, V, S, H, Q>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash + ToOwned<Owned = K>;
+}
+
+impl<K, V, S, H> MemoMapExt<K, V, S, H> for HashMap<HashMemo<K, H>, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    H: BuildHasher + Default,
+{
+    fn insert_value(&mut self, key: K, value: V) -> Option<V> {
+        self.insert(HashMemo::with_hasher(key, H::default()), value)
+    }
+
+    fn get_value<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.get(Probe::with_hasher(key, &H::default()).as_lookup())
+    }
+
+    fn get_value_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.get_mut(Probe::with_hasher(key, &H::default()).as_lookup())
+    }
+
+    fn contains_value<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.contains_key(Probe::with_hasher(key, &H::default()).as_lookup())
+    }
+
+    fn remove_value<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.remove(Probe::with_hasher(key, &H::default()).as_lookup())
+    }
+
+    fn entry_value<'a, 'q, Q>(&'a mut self, key: &'q Q) -> ValueEntry<'a, 'q, K, V, S, H, Q>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash + ToOwned<Owned = K>,
+    {
+        let probe = Probe::with_hasher(key, &H::default());
+        if self.contains_key(probe.as_lookup()) {
+            ValueEntry::Occupied(self.get_mut(probe.as_lookup()).unwrap())
+        } else {
+            ValueEntry::Vacant(VacantValueEntry {
+                map: self,
+                key,
+                hash: probe.hash(),
+            })
+        }
+    }
+}
+
+/// An entry of a memo-keyed `HashMap`, from
+/// [`MemoMapExt::entry_value`].
+pub enum ValueEntry<'a, 'q, K, V, S, H, Q>
+where
+    K: Eq + Hash,
+    H: BuildHasher,
+    Q: ?Sized,
+{
+    /// The key is present; holds its value.
+    Occupied(&'a mut V),
+    /// The key is absent.
+    Vacant(VacantValueEntry<'a, 'q, K, V, S, H, Q>),
+}
+
+/// A vacant [`ValueEntry`], holding the borrowed key and its hash.
+pub struct VacantValueEntry<'a, 'q, K, V, S, H, Q>
+where
+    K: Eq + Hash,
+    H: BuildHasher,
+    Q: ?Sized,
+{
+    map: &'a mut HashMap<HashMemo<K, H>, V, S>,
+    key: &'q Q,
+    hash: u64,
+}
+
+impl<'a, K, V, S, H, Q> VacantValueEntry<'a, '_, K, V, S, H, Q>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    H: BuildHasher + Default,
+    Q: ?Sized + ToOwned<Owned = K>,
+{
+    /// Returns the borrowed key.
+    pub fn key(&self) -> &Q {
+        self.key
+    }
+
+    /// Clones the key into a memo primed with the probe's hash and inserts
+    /// it with `value`.
+    pub fn insert(self, value: V) -> &'a mut V {
+        let memo = HashMemo::with_hasher_const(self.key.to_owned(), H::default(), self.hash);
+        self.map.entry(memo).or_insert(value)
+    }
+}
+
+impl<'a, K, V, S, H, Q> ValueEntry<'a, '_, K, V, S, H, Q>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+    H: BuildHasher + Default,
+    Q: ?Sized + ToOwned<Owned = K>,
+{
+    /// Returns the value, inserting `default` if the entry is vacant.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    /// Returns the value, inserting the result of `default` if the entry is
+    /// vacant.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            ValueEntry::Occupied(value) => value,
+            ValueEntry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Returns the value, inserting `V::default()` if the entry is vacant.
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Calls `f` on the value if the entry is occupied.
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let ValueEntry::Occupied(value) = &mut self {
+            f(value);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::RandomState;
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+    use crate::{FixedState, PassthroughState};
+
+    fn exercise<S: BuildHasher, H: BuildHasher + Default>(
+        map: &mut HashMap<HashMemo<String, H>, u32, S>,
+    ) {
+        for i in 0..100u32 {
+            assert_eq!(map.insert_value(i.to_string(), i), None);
+        }
+        assert_eq!(map.insert_value("7".to_string(), 70), Some(7));
+
+        for i in 0..100u32 {
+            let key = i.to_string();
+            assert!(map.contains_value(key.as_str()));
+            assert!(map.get_value(key.as_str()).is_some());
+        }
+        assert_eq!(map.get_value("7"), Some(&70));
+        assert!(!map.contains_value("100"));
+
+        *map.get_value_mut("8").unwrap() += 1;
+        assert_eq!(map.remove_value("8"), Some(9));
+        assert_eq!(map.remove_value("8"), None);
+        assert_eq!(map.len(), 99);
+    }
+
+    #[test]
+    fn lookups_agree_with_stored_keys_under_any_outer_state() {
+        exercise(&mut HashMap::<HashMemo<String>, u32, RandomState>::new());
+        exercise(&mut HashMap::<HashMemo<String>, u32, PassthroughState>::default());
+        exercise(&mut HashMap::<HashMemo<String, FixedState>, u32>::new());
+
+        // Keys built elsewhere with the same hasher are found too.
+        let mut map = HashMap::<_, _, PassthroughState>::default();
+        map.insert(HashMemo::new("key".to_string()), 1);
+        assert_eq!(map.get_value("key"), Some(&1));
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn entry_clones_the_key_only_when_vacant() {
+        let mut map: HashMap<HashMemo<String>, u32> = HashMap::new();
+        *map.entry_value("a").or_insert(0) += 1;
+        *map.entry_value("a").or_insert(0) += 1;
+        map.entry_value("b").and_modify(|n| *n += 10).or_default();
+        map.entry_value("b").and_modify(|n| *n += 10).or_default();
+
+        assert!(matches!(map.entry_value("a"), ValueEntry::Occupied(&mut 2)));
+        match map.entry_value("c") {
+            ValueEntry::Vacant(entry) => assert_eq!(entry.key(), "c"),
+            ValueEntry::Occupied(_) => panic!("`c` was never inserted"),
+        }
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_value("b"), Some(&10));
+
+        // The inserted memo is primed with the probe's hash.
+        let (memo, _) = map.get_key_value(Probe::new("a").as_lookup()).unwrap();
+        assert_ne!(memo.hash.load(Ordering::Relaxed), 0);
+        assert_eq!(
+            memo.memoized_hash(),
+            HashMemo::new("a".to_string()).memoized_hash()
+        );
+    }
+}
@@ -0,0 +1,299 @@
+//! A memoized field, for structs that are cheap to hash except for one part.
+
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher};
+use std::ops::Deref;
+use std::sync::atomic::Ordering;
+
+use crate::cell::{self, HashCell};
+use crate::AtomicU64;
+
+/// A field wrapper that caches the hash of just that field.
+///
+/// Where [`HashMemo`](crate::HashMemo) wraps a whole key, `Memoized` wraps
+/// one expensive field inside a struct whose `Hash` is derived or written by
+/// hand: its `Hash` impl writes the memoized `u64`, so the parent's hash
+/// costs one word for the field after the first time. Everything else
+/// behaves as if the wrapper were not there: it derefs to `T`, and
+/// `PartialEq`, `Eq`, `Clone`, `Debug` and, under the `serde` feature,
+/// serialization are those of `T`.
+///
+/// Unlike `HashMemo` it does not implement `Borrow`, since its hash is not
+/// `T`'s and it is not meant to be a map key on its own. Mutate through
+/// [`set`](Self::set) or [`get_mut`](Self::get_mut), which clear the cache.
+/// `get_mut` clears it before handing out the reference, so a panic while
+/// mutating leaves the cache cold and the next hash sees the value as the
+/// unwinding left it. Panics while hashing behave as for `HashMemo`.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::Memoized;
+/// use std::collections::HashSet;
+///
+/// #[derive(PartialEq, Eq, Hash)]
+/// struct Chunk {
+///     id: u32,
+///     blob: Memoized<Vec<u8>>,
+/// }
+///
+/// let mut chunk = Chunk { id: 1, blob: Memoized::new(vec![0; 1 << 20]) };
+/// chunk.blob.get_mut()[0] = 1;
+///
+/// let mut chunks = HashSet::new();
+/// chunks.insert(chunk);
+/// ```
+pub struct Memoized<T, H = BuildHasherDefault<DefaultHasher>>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    value: T,
+    hash: AtomicU64,
+    hasher: H,
+}
+
+impl<T> Memoized<T, BuildHasherDefault<DefaultHasher>>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new `Memoized` with the default hasher.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self::with_hasher(value, BuildHasherDefault::new())
+    }
+}
+
+impl<T, H> Memoized<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Creates a new `Memoized` with a custom hasher.
+    #[inline]
+    pub const fn with_hasher(value: T, hasher: H) -> Self {
+        Self {
+            value,
+            hash: AtomicU64::new(0),
+            hasher,
+        }
+    }
+
+    /// Replaces the value, clearing the cached hash.
+    #[inline]
+    pub fn set(&mut self, value: T) {
+        self.value = value;
+        #[cfg(feature = "stats")]
+        crate::stats::invalidation();
+        self.hash.clear();
+    }
+
+    /// Returns the value mutably, clearing the cached hash.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        #[cfg(feature = "stats")]
+        crate::stats::invalidation();
+        self.hash.clear();
+        &mut self.value
+    }
+
+    /// Consumes the wrapper and returns the value.
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// Returns the cached hash, computing and publishing it on first use.
+    pub(crate) fn memoized_hash(&self) -> u64 {
+        cell::get_or_compute(&self.hash, || crate::hash_cold(&self.hasher, &self.value))
+    }
+}
+
+impl<T, H> Deref for Memoized<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, H> PartialEq for Memoized<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T, H> Eq for Memoized<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+}
+
+impl<T, H> Hash for Memoized<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    #[inline]
+    fn hash<H2: Hasher>(&self, state: &mut H2) {
+        state.write_u64(self.memoized_hash());
+    }
+}
+
+impl<T, H> fmt::Debug for Memoized<T, H>
+where
+    T: Eq + Hash + fmt::Debug,
+    H: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl<T, H> Clone for Memoized<T, H>
+where
+    T: Eq + Hash + Clone,
+    H: BuildHasher + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            hash: AtomicU64::new(self.hash.load(Ordering::Relaxed)),
+            hasher: self.hasher.clone(),
+        }
+    }
+}
+
+impl<T, H> Default for Memoized<T, H>
+where
+    T: Eq + Hash + Default,
+    H: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(T::default(), H::default())
+    }
+}
+
+impl<T, H> From<T> for Memoized<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher + Default,
+{
+    fn from(value: T) -> Self {
+        Self::with_hasher(value, H::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::hash::RandomState;
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    /// Counts how often it is hashed, across all instances.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct Blob(Vec<u8>);
+
+    impl Hash for Blob {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            COUNT.fetch_add(1, Ordering::Relaxed);
+            self.0.hash(state);
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+    struct Chunk {
+        id: u32,
+        blob: Memoized<Blob>,
+    }
+
+    #[test]
+    fn parent_hashes_the_blob_once_across_map_operations() {
+        let chunk = Chunk {
+            id: 7,
+            blob: Memoized::new(Blob(vec![1; 4096])),
+        };
+        let before = COUNT.load(Ordering::Relaxed);
+
+        let mut map = HashMap::new();
+        map.insert(&chunk, 1);
+        for _ in 0..10 {
+            assert_eq!(map.get(&chunk), Some(&1));
+        }
+        map.remove(&chunk);
+        map.insert(&chunk, 2);
+
+        assert_eq!(COUNT.load(Ordering::Relaxed) - before, 1);
+    }
+
+    #[test]
+    fn panicking_mutation_still_clears_the_cache() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let mut field = Memoized::new(vec![1u8, 2, 3]);
+        field.memoized_hash();
+
+        let panicked = panic::catch_unwind(AssertUnwindSafe(|| {
+            let value = field.get_mut();
+            value.push(4);
+            panic!("mutation failed halfway");
+        }));
+        assert!(panicked.is_err());
+
+        assert_eq!(field.hash.load(Ordering::Relaxed), 0);
+        assert_eq!(*field, [1, 2, 3, 4]);
+        assert_eq!(
+            field.memoized_hash(),
+            Memoized::new(vec![1u8, 2, 3, 4]).memoized_hash()
+        );
+    }
+
+    #[test]
+    fn mutation_changes_the_parent_hash() {
+        let state = RandomState::new();
+        let mut chunk = Chunk {
+            id: 7,
+            blob: Memoized::new(Blob(vec![1, 2, 3])),
+        };
+        let before = state.hash_one(&chunk);
+
+        chunk.blob.get_mut().0.push(4);
+        let pushed = state.hash_one(&chunk);
+        assert_ne!(pushed, before);
+
+        chunk.blob.set(Blob(vec![1, 2, 3]));
+        assert_eq!(state.hash_one(&chunk), before);
+
+        let fresh = Chunk {
+            id: 7,
+            blob: Memoized::new(Blob(vec![1, 2, 3, 4])),
+        };
+        assert_eq!(state.hash_one(&fresh), pushed);
+    }
+
+    #[test]
+    fn behaves_like_the_wrapped_value() {
+        let a = Memoized::new(vec![1u8, 2]);
+        let b = a.clone();
+        assert_eq!(a, b);
+        assert_eq!(a.len(), 2);
+        assert_eq!(format!("{a:?}"), "[1, 2]");
+        assert_eq!(b.into_inner(), vec![1, 2]);
+    }
+}
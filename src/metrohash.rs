@@ -0,0 +1,129 @@
+//! [`metrohash`](::metrohash) support, for fast seeded hashing.
+//!
+//! [`MetroHashMemo`] memoizes with 64-bit MetroHash through
+//! [`MetroHashState`], which carries the seed the `metrohash` crate's own
+//! `BuildHasher` lacks. For a given seed the hash is deterministic: equal
+//! values hash equally across runs and processes of the same build, and
+//! different seeds give unrelated hashes. `MetroHash64` reads byte input in
+//! native byte order, so values hash differently on targets of the other
+//! endianness, as do values hashing `usize`s across pointer widths. It is
+//! not DoS-resistant, even with a secret seed.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::HashMemo;
+//! use std::collections::HashMap;
+//!
+//! let mut map = HashMap::new();
+//! map.insert(HashMemo::new_metrohash(vec![7u8; 1 << 20]), "blob");
+//! assert_eq!(map.get(&HashMemo::new_metrohash(vec![7u8; 1 << 20])), Some(&"blob"));
+//! ```
+
+use std::hash::{BuildHasher, Hash};
+
+use ::metrohash::MetroHash64;
+
+use crate::HashMemo;
+
+/// A [`HashMemo`] hashing with 64-bit MetroHash, seeded or not.
+pub type MetroHashMemo<T> = HashMemo<T, MetroHashState>;
+
+/// A `BuildHasher` for `MetroHash64` with a fixed seed.
+///
+/// The default seed is `0`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetroHashState {
+    seed: u64,
+}
+
+impl MetroHashState {
+    /// Creates a `MetroHashState` building hashers seeded with `seed`.
+    #[inline]
+    pub const fn with_seed(seed: u64) -> Self {
+        Self { seed }
+    }
+}
+
+impl BuildHasher for MetroHashState {
+    type Hasher = MetroHash64;
+
+    #[inline]
+    fn build_hasher(&self) -> MetroHash64 {
+        MetroHash64::with_seed(self.seed)
+    }
+}
+
+impl<T> HashMemo<T, MetroHashState>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new `HashMemo` using MetroHash with a seed of `0`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::{HashMemo, MetroHashMemo};
+    ///
+    /// let memo: MetroHashMemo<&str> = HashMemo::new_metrohash("hello world");
+    /// ```
+    pub const fn new_metrohash(value: T) -> Self {
+        Self::new_metrohash_with_seed(value, 0)
+    }
+
+    /// Creates a new `HashMemo` using MetroHash with `seed`.
+    ///
+    /// Memos compared or looked up together must share the seed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let memo = HashMemo::new_metrohash_with_seed("hello world", 0x5eed);
+    /// ```
+    pub const fn new_metrohash_with_seed(value: T, seed: u64) -> Self {
+        Self::with_hasher(value, MetroHashState::with_seed(seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::hash::Hasher;
+
+    use super::*;
+    use crate::remap_zero;
+
+    #[test]
+    fn works_as_a_hashmap_key() {
+        let mut map = HashMap::new();
+        for i in 0..100u32 {
+            map.insert(HashMemo::new_metrohash(i.to_string().repeat(100)), i);
+        }
+        for i in 0..100u32 {
+            let key = HashMemo::new_metrohash(i.to_string().repeat(100));
+            assert_eq!(map.get(&key), Some(&i));
+        }
+        assert_eq!(
+            map.get(&HashMemo::new_metrohash("absent".repeat(100))),
+            None
+        );
+    }
+
+    #[test]
+    fn hashes_with_the_configured_seed() {
+        let value = "seeded".to_string();
+        let unseeded = HashMemo::new_metrohash(value.clone());
+        let seeded = HashMemo::new_metrohash_with_seed(value.clone(), 42);
+
+        let mut hasher = MetroHash64::with_seed(42);
+        value.hash(&mut hasher);
+        assert_eq!(seeded.memoized_hash(), remap_zero(hasher.finish()));
+        assert_eq!(
+            unseeded.memoized_hash(),
+            remap_zero(MetroHashState::default().hash_one(&value))
+        );
+        assert_ne!(seeded.memoized_hash(), unseeded.memoized_hash());
+    }
+}
@@ -0,0 +1,292 @@
+//! [`moka`](::moka) interop for concurrent caches keyed by memoized values.
+//!
+//! A `moka::sync::Cache` hashes its key on every `get`, `insert` and
+//! `invalidate`, and keeps the hash alongside the entry for its eviction
+//! bookkeeping. Keyed by memos and built with [`PassthroughState`], each of
+//! those hashes is a read of the cached hash, and a key's value is hashed
+//! once, when the memo is first hashed.
+//!
+//! Two key types suit it:
+//!
+//! - [`MemoMokaCache`], keyed by [`HashMemo`], for keys that are cheap to
+//!   clone or are not cloned.
+//! - [`ArcMemoMokaCache`], keyed by [`ArcMemo`], for large keys. moka
+//!   stores each key in an `Arc` of its own, but callers that keep the key
+//!   after inserting it, or look entries up by key, clone it; an `ArcMemo`
+//!   clone is a reference count, and shares the warm cache.
+//!
+//! Like `HashMap`, moka looks keys up through `Borrow`, so a plain `&str`
+//! does not find a `HashMemo<String>` key. [`MemoMokaExt`] adds lookups by
+//! borrowed value that build a [`Probe`] with the key hasher, or probe with
+//! [`Probe::as_lookup`] directly:
+//!
+//! ```rust
+//! use hashmemo::{ArcMemo, ArcMemoMokaCache, MemoMokaExt, Probe};
+//!
+//! let cache: ArcMemoMokaCache<String, u32> = ArcMemoMokaCache::new_memo(1_000);
+//! let key = ArcMemo::new("a long key".repeat(100));
+//! cache.insert(key.clone(), 1);
+//!
+//! assert_eq!(cache.get(&key), Some(1));
+//! let value = "a long key".repeat(100);
+//! assert_eq!(cache.get_by_value(value.as_str()), Some(1));
+//! let probe = Probe::new(value.as_str());
+//! assert!(cache.contains_key(probe.as_lookup()));
+//! ```
+//!
+//! `PassthroughState` uses the memoized hash as is, for bucket and segment
+//! selection as well as moka's frequency sketch, which is sound as long as
+//! the key hasher `H` spreads its output over all 64 bits, as the default
+//! `DefaultHasher` does. With a weaker key hasher, build the cache with
+//! `moka::sync::Cache::builder().build_with_hasher(RandomState::new())`;
+//! memo keys then hash one `u64` instead of their whole value.
+
+use std::borrow::Borrow;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash};
+
+use ::moka::sync::Cache;
+
+use crate::{ArcMemo, HashMemo, PassthroughState, Probe};
+
+/// A `moka::sync::Cache` keyed by [`HashMemo`], using the memoized hash
+/// directly.
+///
+/// `H` is the hasher of the keys; see [`MemoMokaExt`] for lookups.
+pub type MemoMokaCache<K, V, H = BuildHasherDefault<DefaultHasher>> =
+    Cache<HashMemo<K, H>, V, PassthroughState>;
+
+/// A `moka::sync::Cache` keyed by [`ArcMemo`], using the memoized hash
+/// directly.
+///
+/// `H` is the hasher of the keys; see [`MemoMokaExt`] for lookups.
+pub type ArcMemoMokaCache<K, V, H = BuildHasherDefault<DefaultHasher>> =
+    Cache<ArcMemo<K, H>, V, PassthroughState>;
+
+/// Construction and by-value access for a [`MemoMokaCache`] or an
+/// [`ArcMemoMokaCache`].
+///
+/// The by-value methods hash the probe with `H::default()`, which must
+/// agree with the hasher of the stored keys.
+pub trait MemoMokaExt<K, V, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher + Default,
+{
+    /// Creates a cache holding at most `max_capacity` entries.
+    fn new_memo(max_capacity: u64) -> Self;
+
+    /// Memoizes `key` and inserts it, replacing any previous value.
+    fn insert_value(&self, key: K, value: V);
+
+    /// Like `get`, returning a clone of the value.
+    fn get_by_value<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash;
+
+    /// Like `contains_key`.
+    fn contains_value<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash;
+
+    /// Like `remove`, returning the removed value.
+    fn remove_by_value<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash;
+}
+
+/// Implements [`MemoMokaExt`] for a cache keyed by `$key`, built from a
+/// value with `$wrap`.
+macro_rules! memo_moka_ext {
+    ($key:ident, $wrap:path) => {
+        impl<K, V, H> MemoMokaExt<K, V, H> for Cache<$key<K, H>, V, PassthroughState>
+        where
+            K: Eq + Hash + Send + Sync + 'static,
+            V: Clone + Send + Sync + 'static,
+            H: BuildHasher + Default + Send + Sync + 'static,
+        {
+            fn new_memo(max_capacity: u64) -> Self {
+                Cache::builder()
+                    .max_capacity(max_capacity)
+                    .build_with_hasher(PassthroughState)
+            }
+
+            fn insert_value(&self, key: K, value: V) {
+                self.insert($wrap(key, H::default()), value);
+            }
+
+            fn get_by_value<Q>(&self, key: &Q) -> Option<V>
+            where
+                K: Borrow<Q>,
+                Q: ?Sized + Eq + Hash,
+            {
+                self.get(Probe::with_hasher(key, &H::default()).as_lookup())
+            }
+
+            fn contains_value<Q>(&self, key: &Q) -> bool
+            where
+                K: Borrow<Q>,
+                Q: ?Sized + Eq + Hash,
+            {
+                self.contains_key(Probe::with_hasher(key, &H::default()).as_lookup())
+            }
+
+            fn remove_by_value<Q>(&self, key: &Q) -> Option<V>
+            where
+                K: Borrow<Q>,
+                Q: ?Sized + Eq + Hash,
+            {
+                self.remove(Probe::with_hasher(key, &H::default()).as_lookup())
+            }
+        }
+    };
+}
+
+memo_moka_ext!(HashMemo, HashMemo::with_hasher);
+memo_moka_ext!(ArcMemo, ArcMemo::with_hasher);
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    static COUNTS: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+
+    /// Counts the hashers it builds, one per hash computed, in
+    /// `COUNTS[N]`, so tests running in parallel keep separate counts.
+    #[derive(Clone, Default)]
+    struct CountingState<const N: usize>;
+
+    impl<const N: usize> BuildHasher for CountingState<N> {
+        type Hasher = DefaultHasher;
+
+        fn build_hasher(&self) -> DefaultHasher {
+            COUNTS[N].fetch_add(1, Ordering::Relaxed);
+            DefaultHasher::new()
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    struct BigStruct {
+        name: String,
+        data: [u64; 64],
+        payload: Vec<u8>,
+    }
+
+    impl BigStruct {
+        fn new(i: usize) -> Self {
+            Self {
+                name: format!("big-{i}"),
+                data: [i as u64; 64],
+                payload: vec![7; 1024],
+            }
+        }
+    }
+
+    #[test]
+    fn borrowed_lookups_find_memoized_keys() {
+        let cache: MemoMokaCache<String, u32> = MemoMokaCache::new_memo(100);
+        cache.insert_value("alpha".to_string(), 1);
+        cache.insert(HashMemo::new("beta".to_string()), 2);
+
+        assert_eq!(cache.get_by_value("alpha"), Some(1));
+        assert_eq!(cache.get(&HashMemo::new("beta".to_string())), Some(2));
+        assert_eq!(cache.get_by_value("gamma"), None);
+        assert!(cache.contains_value("beta"));
+        assert_eq!(cache.remove_by_value("alpha"), Some(1));
+        assert!(!cache.contains_value("alpha"));
+    }
+
+    // These count hashes of the keys' values, which `disable-cache` makes
+    // on every lookup.
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn resident_string_keys_are_not_rehashed() {
+        let cache: MemoMokaCache<String, usize, CountingState<0>> = MemoMokaCache::new_memo(64);
+        let keys: Vec<_> = (0..64)
+            .map(|i| HashMemo::with_hasher(format!("key-{i}"), CountingState::<0>))
+            .collect();
+        let before = COUNTS[0].load(Ordering::Relaxed);
+
+        // Warm the keys before cloning them, so the clones share the hash.
+        for key in &keys {
+            key.hash_one_cached();
+        }
+        for (i, key) in keys.iter().enumerate() {
+            cache.insert(key.clone(), i);
+        }
+        cache.run_pending_tasks();
+        assert_eq!(
+            COUNTS[0].load(Ordering::Relaxed) - before,
+            64,
+            "once per key"
+        );
+
+        for _ in 0..10 {
+            for (i, key) in keys.iter().enumerate() {
+                assert_eq!(cache.get(key), Some(i));
+            }
+        }
+        cache.run_pending_tasks();
+        assert_eq!(COUNTS[0].load(Ordering::Relaxed) - before, 64);
+
+        // Overflowing the capacity evicts entries without hashing them.
+        for i in 64..256 {
+            cache.insert(
+                HashMemo::with_hasher(format!("key-{i}"), CountingState::<0>),
+                i,
+            );
+        }
+        cache.run_pending_tasks();
+        assert!(cache.entry_count() <= 64);
+        assert_eq!(COUNTS[0].load(Ordering::Relaxed) - before, 256);
+
+        // A lookup by value hashes the probe once.
+        cache.get_by_value("key-255");
+        assert_eq!(COUNTS[0].load(Ordering::Relaxed) - before, 257);
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn arc_memo_keys_share_one_hash() {
+        let cache: ArcMemoMokaCache<BigStruct, usize, CountingState<1>> =
+            ArcMemoMokaCache::new_memo(16);
+        let keys: Vec<_> = (0..32)
+            .map(|i| ArcMemo::with_hasher(BigStruct::new(i), CountingState::<1>))
+            .collect();
+        let before = COUNTS[1].load(Ordering::Relaxed);
+
+        for (i, key) in keys.iter().enumerate() {
+            cache.insert(key.clone(), i);
+            assert_eq!(cache.get(key), Some(i));
+        }
+        cache.run_pending_tasks();
+        assert!(cache.entry_count() <= 16);
+        assert_eq!(
+            COUNTS[1].load(Ordering::Relaxed) - before,
+            32,
+            "once per key"
+        );
+
+        let resident: Vec<_> = keys.iter().filter(|key| cache.contains_key(*key)).collect();
+        assert!(!resident.is_empty());
+        for key in &resident {
+            assert!(cache.get(*key).is_some());
+            cache.invalidate(*key);
+        }
+        cache.run_pending_tasks();
+        assert_eq!(cache.entry_count(), 0);
+        assert_eq!(COUNTS[1].load(Ordering::Relaxed) - before, 32);
+    }
+
+    #[test]
+    fn arc_memo_keys_are_found_by_value() {
+        let cache: ArcMemoMokaCache<BigStruct, usize> = ArcMemoMokaCache::new_memo(16);
+        cache.insert_value(BigStruct::new(1), 1);
+        assert_eq!(cache.get_by_value(&BigStruct::new(1)), Some(1));
+        assert!(!cache.contains_value(&BigStruct::new(2)));
+    }
+}
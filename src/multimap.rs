@@ -0,0 +1,385 @@
+//! A multimap keyed by memoized values.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash};
+
+#[cfg(feature = "get-size")]
+use ::get_size::GetSize;
+
+use crate::{HashMemo, PassthroughState, Probe};
+
+/// A map from each key to the values inserted under it, in insertion order.
+///
+/// Keys are stored as [`HashMemo`]s in a map using [`PassthroughState`],
+/// so a key is hashed once, when it is first inserted: growing the map and
+/// every later operation reuse the cached hash. Each call hashes its key
+/// argument once, and [`insert_ref`](Self::insert_ref) reuses that hash for
+/// the stored key, cloning the key only when it is new.
+///
+/// Lookups take any `&Q` the key borrows as and build a [`Probe`] with the
+/// map's hasher. A key whose last value is removed is removed with it, so
+/// every key present has at least one value.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::MemoMultiMap;
+///
+/// let mut tags: MemoMultiMap<String, &str> = MemoMultiMap::new();
+/// tags.insert_ref("rust", "lang");
+/// tags.insert_ref("rust", "systems");
+/// tags.insert_ref("tea", "drink");
+///
+/// assert_eq!(tags.get("rust"), ["lang", "systems"]);
+/// assert!(tags.remove_value("tea", &"drink"));
+/// assert!(!tags.contains_key("tea"));
+/// assert_eq!((tags.len_keys(), tags.len_values()), (1, 2));
+/// ```
+pub struct MemoMultiMap<K, V, H = BuildHasherDefault<DefaultHasher>>
+where
+    K: Eq + Hash,
+    H: BuildHasher,
+{
+    map: HashMap<HashMemo<K, H>, Vec<V>, PassthroughState>,
+    values: usize,
+    hasher: H,
+}
+
+impl<K, V> MemoMultiMap<K, V>
+where
+    K: Eq + Hash,
+{
+    /// Creates an empty multimap whose keys use the default hasher.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_hasher(BuildHasherDefault::new())
+    }
+}
+
+impl<K, V, H> MemoMultiMap<K, V, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher + Clone,
+{
+    /// Creates an empty multimap whose keys hash with `hasher`.
+    #[inline]
+    pub fn with_hasher(hasher: H) -> Self {
+        Self {
+            map: HashMap::default(),
+            values: 0,
+            hasher,
+        }
+    }
+
+    /// Appends `value` to the values of `key`.
+    pub fn insert(&mut self, key: K, value: V) {
+        let key = HashMemo::with_hasher(key, self.hasher.clone());
+        self.map.entry(key).or_default().push(value);
+        self.values += 1;
+    }
+
+    /// Appends `value` to the values of `key`, cloning the key only if it is
+    /// not present yet.
+    pub fn insert_ref<Q>(&mut self, key: &Q, value: V)
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash + ToOwned<Owned = K>,
+    {
+        let probe = Probe::with_hasher(key, &self.hasher);
+        match self.map.get_mut(probe.as_lookup()) {
+            Some(values) => values.push(value),
+            None => {
+                let key =
+                    HashMemo::with_hasher_const(key.to_owned(), self.hasher.clone(), probe.hash());
+                self.map.insert(key, vec![value]);
+            }
+        }
+        self.values += 1;
+    }
+
+    /// Returns the values of `key` in insertion order, empty if it has none.
+    pub fn get<Q>(&self, key: &Q) -> &[V]
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.map
+            .get(Probe::with_hasher(key, &self.hasher).as_lookup())
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns `true` if `key` has any values.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.map
+            .contains_key(Probe::with_hasher(key, &self.hasher).as_lookup())
+    }
+
+    /// Removes `key` and returns its values, empty if it had none.
+    pub fn remove_key<Q>(&mut self, key: &Q) -> Vec<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let values = self
+            .map
+            .remove(Probe::with_hasher(key, &self.hasher).as_lookup())
+            .unwrap_or_default();
+        self.values -= values.len();
+        values
+    }
+
+    /// Removes the first value of `key` equal to `value`, and `key` itself
+    /// if that was its last value. Returns `true` if a value was removed.
+    pub fn remove_value<Q>(&mut self, key: &Q, value: &V) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+        V: PartialEq,
+    {
+        let probe = Probe::with_hasher(key, &self.hasher);
+        let Some(values) = self.map.get_mut(probe.as_lookup()) else {
+            return false;
+        };
+        let Some(index) = values.iter().position(|v| v == value) else {
+            return false;
+        };
+        values.remove(index);
+        if values.is_empty() {
+            self.map.remove(probe.as_lookup());
+        }
+        self.values -= 1;
+        true
+    }
+}
+
+impl<K, V, H> MemoMultiMap<K, V, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Returns the keys, in arbitrary order.
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.map.keys().map(|key| &key.value)
+    }
+
+    /// Returns every key and value pair, with the values of each key in
+    /// insertion order and the keys in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.map
+            .iter()
+            .flat_map(|(key, values)| values.iter().map(move |value| (&key.value, value)))
+    }
+
+    /// Returns the number of distinct keys.
+    #[inline]
+    pub fn len_keys(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns the number of values across all keys.
+    #[inline]
+    pub fn len_values(&self) -> usize {
+        self.values
+    }
+
+    /// Returns `true` if the multimap holds no values.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Removes every key and value, keeping the allocated capacity.
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.values = 0;
+    }
+}
+
+impl<K, V, H> Extend<(K, V)> for MemoMultiMap<K, V, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher + Clone,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V, H> FromIterator<(K, V)> for MemoMultiMap<K, V, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher + Clone + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::default();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K, V, H> Default for MemoMultiMap<K, V, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher + Clone + Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+impl<K, V, H> Clone for MemoMultiMap<K, V, H>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+    H: BuildHasher + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+            values: self.values,
+            hasher: self.hasher.clone(),
+        }
+    }
+}
+
+impl<K, V, H> fmt::Debug for MemoMultiMap<K, V, H>
+where
+    K: Eq + Hash + fmt::Debug,
+    V: fmt::Debug,
+    H: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.map.iter().map(|(key, values)| (&key.value, values)))
+            .finish()
+    }
+}
+
+#[cfg(feature = "get-size")]
+impl<K, V, H> GetSize for MemoMultiMap<K, V, H>
+where
+    K: Eq + Hash + GetSize,
+    V: GetSize,
+    H: BuildHasher,
+{
+    fn get_heap_size(&self) -> usize {
+        let slot = HashMemo::<K, H>::get_stack_size() + Vec::<V>::get_stack_size();
+        self.map
+            .iter()
+            .fold(self.map.capacity() * slot, |size, (key, values)| {
+                size + key.get_heap_size() + values.get_heap_size()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hasher;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    /// Counts how often it is hashed, across all instances.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct Counted(u32);
+
+    impl Hash for Counted {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            COUNT.fetch_add(1, Ordering::Relaxed);
+            self.0.hash(state);
+        }
+    }
+
+    #[test]
+    fn duplicate_heavy_insertion_groups_values() {
+        let mut map = MemoMultiMap::new();
+        for i in 0..10_000u32 {
+            map.insert(i % 10, i);
+        }
+
+        assert_eq!(map.len_keys(), 10);
+        assert_eq!(map.len_values(), 10_000);
+        for key in 0..10u32 {
+            let values = map.get(&key);
+            assert_eq!(values.len(), 1000);
+            assert!(values.iter().all(|v| v % 10 == key));
+            assert!(values.windows(2).all(|w| w[0] < w[1]));
+        }
+        assert_eq!(map.iter().count(), 10_000);
+        assert!(map.get(&10).is_empty());
+    }
+
+    #[test]
+    fn removing_the_last_value_removes_the_key() {
+        let mut map: MemoMultiMap<String, u32> = [("a", 1), ("a", 2), ("b", 3)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+
+        assert!(map.remove_value("a", &1));
+        assert!(!map.remove_value("a", &1));
+        assert_eq!(map.get("a"), [2]);
+        assert!(map.remove_value("a", &2));
+        assert!(!map.contains_key("a"));
+        assert_eq!((map.len_keys(), map.len_values()), (1, 1));
+
+        assert_eq!(map.remove_key("b"), [3]);
+        assert!(map.remove_key("b").is_empty());
+        assert!(map.is_empty());
+        assert_eq!(map.len_values(), 0);
+        assert_eq!(map.keys().count(), 0);
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn each_call_hashes_its_key_once() {
+        let keys: Vec<Counted> = (0..64).map(Counted).collect();
+        let mut map = MemoMultiMap::new();
+        let before = COUNT.load(Ordering::Relaxed);
+
+        for round in 0..16u32 {
+            for key in &keys {
+                map.insert_ref(key, round);
+            }
+        }
+        map.extend(keys.iter().cloned().map(|key| (key, 16)));
+        assert_eq!(COUNT.load(Ordering::Relaxed) - before, 17 * 64);
+
+        for key in &keys {
+            assert_eq!(map.get(key).len(), 17);
+        }
+        assert_eq!(COUNT.load(Ordering::Relaxed) - before, 18 * 64);
+    }
+
+    #[cfg(feature = "get-size")]
+    #[test]
+    fn get_size_counts_the_table_keys_and_values() {
+        let mut key = String::with_capacity(16);
+        key.push_str("key");
+        let mut map: MemoMultiMap<String, u64> = MemoMultiMap::new();
+        map.insert(key, 1);
+        map.insert_ref("key", 2);
+
+        let (capacity, values) = (map.map.capacity(), map.get("key").len());
+        assert_eq!((capacity, values), (3, 2));
+        let slot = std::mem::size_of::<(HashMemo<String>, Vec<u64>)>();
+        // Each of the 3 slots, the key's 16 bytes and room for 4 values.
+        assert_eq!(map.get_heap_size(), 3 * slot + 16 + 4 * 8);
+        #[cfg(all(target_pointer_width = "64", not(feature = "disable-cache")))]
+        assert_eq!(map.get_heap_size(), 216);
+        assert_eq!(
+            map.get_size(),
+            std::mem::size_of::<MemoMultiMap<String, u64>>() + map.get_heap_size()
+        );
+    }
+}
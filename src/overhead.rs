@@ -0,0 +1,191 @@
+//! What a memo costs beside its value: a compile-time check, and the
+//! numbers at runtime.
+
+use std::hash::{BuildHasher, Hash};
+use std::mem::size_of;
+
+use crate::cell::MemoCell;
+use crate::HashMemo;
+
+/// The memory layout of a [`HashMemo`] instantiation, returned by
+/// [`HashMemo::cache_layout`].
+///
+/// All sizes are in bytes, as `size_of` reports them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct CacheLayout {
+    /// The size of the value.
+    pub value_size: usize,
+    /// The size of the cached hash, `0` under the `disable-cache` feature.
+    pub cache_size: usize,
+    /// The size of the whole memo, including the hasher and padding.
+    pub total_size: usize,
+    /// What the memo adds to the value: `total_size - value_size`.
+    pub overhead: usize,
+}
+
+impl<T, H> HashMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Returns the sizes of this memo type and of the value it wraps, for
+    /// reporting the memory a collection of memos costs beyond its keys.
+    ///
+    /// The overhead is the cached hash, the hasher and any padding. See
+    /// [`assert_hashmemo_zero_overhead!`] to require at compile time that
+    /// it is no more than the hash.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    /// use std::hash::RandomState;
+    ///
+    /// let layout = HashMemo::<String>::cache_layout();
+    /// assert_eq!(layout.overhead, layout.cache_size);
+    ///
+    /// let keyed = HashMemo::<String, RandomState>::cache_layout();
+    /// assert!(keyed.overhead > layout.overhead);
+    /// ```
+    pub const fn cache_layout() -> CacheLayout {
+        let value_size = size_of::<T>();
+        let total_size = size_of::<Self>();
+        CacheLayout {
+            value_size,
+            cache_size: size_of::<MemoCell>(),
+            total_size,
+            overhead: total_size - value_size,
+        }
+    }
+}
+
+/// Fails the build unless a [`HashMemo`](crate::HashMemo) of the given
+/// value type is laid out like the value next to a `u64`.
+///
+/// This holds when the hasher is zero-sized, as the default hasher and
+/// [`FixedState`](crate::FixedState) are, and fails for hashers that carry
+/// state, such as `RandomState`. The comparison is against `(T, u64)`
+/// rather than the sum of the two sizes, so padding that any value stored
+/// beside a `u64` would need, as a `u8` does, is not counted as overhead.
+/// The hasher defaults to that of [`HashMemo::new`](crate::HashMemo::new)
+/// and can be named as a second argument.
+///
+/// Under the `disable-cache` feature a memo has no cache at all and is as
+/// large as the value and hasher alone, which passes the check too.
+///
+/// The macro expands to an anonymous `const` item, so it can be placed at
+/// module level or inside a function.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{assert_hashmemo_zero_overhead, FixedState};
+///
+/// assert_hashmemo_zero_overhead!(String);
+/// assert_hashmemo_zero_overhead!(Vec<u8>, FixedState);
+/// ```
+///
+/// ```rust,compile_fail
+/// use hashmemo::assert_hashmemo_zero_overhead;
+/// use std::hash::RandomState;
+///
+/// assert_hashmemo_zero_overhead!(String, RandomState);
+/// ```
+#[macro_export]
+macro_rules! assert_hashmemo_zero_overhead {
+    ($value:ty $(,)?) => {
+        const _: () = ::core::assert!(
+            ::core::mem::size_of::<$crate::HashMemo<$value>>()
+                <= ::core::mem::size_of::<($value, u64)>(),
+            ::core::concat!(
+                "HashMemo<",
+                ::core::stringify!($value),
+                "> is larger than the value and a u64"
+            ),
+        );
+    };
+    ($value:ty, $hasher:ty $(,)?) => {
+        const _: () = ::core::assert!(
+            ::core::mem::size_of::<$crate::HashMemo<$value, $hasher>>()
+                <= ::core::mem::size_of::<($value, u64)>(),
+            ::core::concat!(
+                "HashMemo<",
+                ::core::stringify!($value),
+                ", ",
+                ::core::stringify!($hasher),
+                "> is larger than the value and a u64"
+            ),
+        );
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::RandomState;
+
+    use super::*;
+    use crate::{FixedState, LengthPrefixed, Mixed, PassthroughState};
+
+    #[derive(PartialEq, Eq, Hash)]
+    struct BigStruct {
+        name: String,
+        data: [u64; 64],
+        payload: Vec<u8>,
+    }
+
+    assert_hashmemo_zero_overhead!(String);
+    assert_hashmemo_zero_overhead!(BigStruct);
+    assert_hashmemo_zero_overhead!(u8);
+    assert_hashmemo_zero_overhead!(&'static str, FixedState);
+    assert_hashmemo_zero_overhead!(BigStruct, PassthroughState);
+    assert_hashmemo_zero_overhead!(Vec<u8>, Mixed);
+    assert_hashmemo_zero_overhead!(Vec<u8>, LengthPrefixed);
+
+    #[test]
+    fn expands_inside_functions() {
+        assert_hashmemo_zero_overhead!((u32, String));
+        #[cfg(not(feature = "disable-cache"))]
+        assert_eq!(
+            std::mem::size_of::<crate::HashMemo<u8>>(),
+            std::mem::size_of::<(u8, u64)>()
+        );
+    }
+
+    #[test]
+    fn cache_layout_matches_size_of() {
+        let layout = HashMemo::<String>::cache_layout();
+        assert_eq!(layout.value_size, size_of::<String>());
+        assert_eq!(layout.cache_size, size_of::<MemoCell>());
+        assert_eq!(layout.total_size, size_of::<HashMemo<String>>());
+        assert_eq!(layout.overhead, layout.cache_size);
+
+        let keyed = HashMemo::<u8, RandomState>::cache_layout();
+        assert_eq!(keyed.value_size, 1);
+        assert_eq!(keyed.total_size, size_of::<HashMemo<u8, RandomState>>());
+        assert_eq!(
+            keyed.overhead,
+            size_of::<HashMemo<u8, RandomState>>() - 1,
+            "the hasher and padding count as overhead"
+        );
+        assert!(keyed.overhead >= keyed.cache_size + size_of::<RandomState>());
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn the_cache_is_one_word() {
+        assert_eq!(
+            HashMemo::<String>::cache_layout().cache_size,
+            size_of::<u64>()
+        );
+    }
+
+    #[cfg(feature = "disable-cache")]
+    #[test]
+    fn disabled_caches_take_no_space() {
+        assert_eq!(size_of::<HashMemo<String>>(), size_of::<String>());
+        assert_eq!(size_of::<HashMemo<u8>>(), 1);
+        assert_eq!(size_of::<HashMemo<BigStruct>>(), size_of::<BigStruct>());
+        assert_eq!(HashMemo::<String>::cache_layout().overhead, 0);
+    }
+}
@@ -0,0 +1,212 @@
+//! Path and OS string keys.
+//!
+//! `PathBuf` hashes like `Path`, which hashes its components rather than its
+//! raw bytes, so `a/b`, `a//b` and `a/b/` are one key, as `Path`'s `Eq`
+//! already says. [`MemoPath`] keeps that behavior and maps keyed by it can
+//! be probed with [`Probe::path`], which accepts anything that converts to a
+//! `&Path`, including `&str`. [`MemoOsString`] does the same for `OsString`
+//! keys through [`Probe::os_str`].
+//!
+//! # Examples
+//!
+//! Deduplicating the files seen while walking a directory tree:
+//!
+//! ```rust
+//! use hashmemo::{MemoPath, Probe};
+//! use std::collections::HashSet;
+//! use std::path::Path;
+//!
+//! fn walk(dir: &Path, seen: &mut HashSet<MemoPath>) -> std::io::Result<()> {
+//!     for entry in std::fs::read_dir(dir)? {
+//!         let path = entry?.path().canonicalize()?;
+//!         if path.is_dir() {
+//!             walk(&path, seen)?;
+//!         } else {
+//!             seen.insert(MemoPath::new(path));
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//!
+//! let mut seen = HashSet::new();
+//! walk(Path::new("src"), &mut seen).unwrap();
+//! let files = seen.len();
+//! walk(Path::new("src"), &mut seen).unwrap();
+//! assert_eq!(seen.len(), files);
+//!
+//! let lib = Path::new("src/lib.rs").canonicalize().unwrap();
+//! assert!(seen.contains(Probe::path(&lib).as_lookup()));
+//! ```
+
+use std::ffi::{OsStr, OsString};
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher};
+use std::path::{Path, PathBuf};
+
+use crate::{HashMemo, Probe};
+
+/// A [`HashMemo`] over a `PathBuf`.
+pub type MemoPath<H = BuildHasherDefault<DefaultHasher>> = HashMemo<PathBuf, H>;
+
+/// A [`HashMemo`] over an `OsString`.
+pub type MemoOsString<H = BuildHasherDefault<DefaultHasher>> = HashMemo<OsString, H>;
+
+impl From<&Path> for HashMemo<PathBuf> {
+    fn from(path: &Path) -> Self {
+        Self::new(path.to_path_buf())
+    }
+}
+
+impl From<&str> for HashMemo<PathBuf> {
+    fn from(path: &str) -> Self {
+        Self::new(PathBuf::from(path))
+    }
+}
+
+impl<H> AsRef<Path> for HashMemo<PathBuf, H>
+where
+    H: BuildHasher,
+{
+    fn as_ref(&self) -> &Path {
+        &self.value
+    }
+}
+
+impl From<&OsStr> for HashMemo<OsString> {
+    fn from(value: &OsStr) -> Self {
+        Self::new(value.to_os_string())
+    }
+}
+
+impl From<&str> for HashMemo<OsString> {
+    fn from(value: &str) -> Self {
+        Self::new(OsString::from(value))
+    }
+}
+
+impl<H> AsRef<OsStr> for HashMemo<OsString, H>
+where
+    H: BuildHasher,
+{
+    fn as_ref(&self) -> &OsStr {
+        &self.value
+    }
+}
+
+impl<'a> Probe<'a, Path> {
+    /// Creates a probe for [`MemoPath`] keys from anything that converts to
+    /// a `&Path`, such as a `&str`.
+    ///
+    /// The probe hashes as a `Path`; probing with `Probe::new` on a `&str`
+    /// would hash the string bytes instead and miss.
+    #[inline]
+    pub fn path<P: AsRef<Path> + ?Sized>(path: &'a P) -> Self {
+        Self::new(path.as_ref())
+    }
+}
+
+impl<'a> Probe<'a, OsStr> {
+    /// Creates a probe for [`MemoOsString`] keys from anything that converts
+    /// to an `&OsStr`, such as a `&str`.
+    #[inline]
+    pub fn os_str<S: AsRef<OsStr> + ?Sized>(value: &'a S) -> Self {
+        Self::new(value.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn paths_are_probed_by_path_and_str() {
+        let mut map = HashMap::new();
+        map.insert(MemoPath::from("/var/log/syslog"), 1);
+        map.insert(MemoPath::from(Path::new("/etc/hosts")), 2);
+
+        assert_eq!(
+            map.get(Probe::path("/var/log/syslog").as_lookup()),
+            Some(&1)
+        );
+        assert_eq!(
+            map.get(Probe::path(Path::new("/etc/hosts")).as_lookup()),
+            Some(&2)
+        );
+        assert_eq!(
+            map.get(Probe::path(&PathBuf::from("/etc/hosts")).as_lookup()),
+            Some(&2)
+        );
+        assert_eq!(map.get(Probe::path("/etc").as_lookup()), None);
+        assert_eq!(
+            AsRef::<Path>::as_ref(&MemoPath::from("/etc")),
+            Path::new("/etc")
+        );
+    }
+
+    #[test]
+    fn equivalent_spellings_share_a_key() {
+        let memo = MemoPath::from("a/b");
+        for spelling in ["a//b", "a/b/", "a/./b"] {
+            let other = MemoPath::from(spelling);
+            assert!(memo == other, "{spelling}");
+            assert_eq!(memo.memoized_hash(), other.memoized_hash(), "{spelling}");
+            assert_eq!(Probe::path(spelling).hash(), memo.memoized_hash());
+        }
+        assert!(memo != MemoPath::from("a/../a/b"));
+    }
+
+    #[test]
+    fn os_strings_are_probed_by_os_str_and_str() {
+        let mut env = HashMap::new();
+        env.insert(MemoOsString::from("PATH"), "/usr/bin");
+        env.insert(MemoOsString::from(OsStr::new("HOME")), "/root");
+
+        assert_eq!(
+            env.get(Probe::os_str("PATH").as_lookup()),
+            Some(&"/usr/bin")
+        );
+        assert_eq!(
+            env.get(Probe::os_str(OsStr::new("HOME")).as_lookup()),
+            Some(&"/root")
+        );
+        assert_eq!(env.get(Probe::os_str("home").as_lookup()), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_unix_names_round_trip() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let name = OsStr::from_bytes(b"caf\xe9");
+        let mut map = HashMap::new();
+        map.insert(MemoOsString::from(name), 1);
+        map.insert(
+            MemoPath::from(Path::new(name))
+                .into_inner()
+                .into_os_string()
+                .into(),
+            2,
+        );
+
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get(Probe::os_str(name).as_lookup()), Some(&2));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_separators_and_unpaired_surrogates() {
+        use std::os::windows::ffi::OsStringExt;
+
+        let memo = MemoPath::from(r"C:\Users\me");
+        assert!(memo == MemoPath::from("C:/Users/me"));
+        assert_eq!(Probe::path("C:/Users/me").hash(), memo.memoized_hash());
+
+        // An unpaired surrogate is valid WTF-8 but not UTF-8.
+        let name = OsString::from_wide(&[0x0066, 0xD800, 0x006F]);
+        let mut map = HashMap::new();
+        map.insert(MemoOsString::new(name.clone()), 1);
+        assert_eq!(map.get(Probe::os_str(&name).as_lookup()), Some(&1));
+        assert_eq!(map.get(Probe::os_str("f\u{FFFD}o").as_lookup()), None);
+    }
+}
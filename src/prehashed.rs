@@ -0,0 +1,181 @@
+//! A value paired with a hash supplied from outside.
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// A value and a precomputed hash, with no hasher of its own.
+///
+/// Where a [`HashMemo`](crate::HashMemo) computes its hash lazily with the
+/// `BuildHasher` it owns, a `Prehashed` takes the hash at construction,
+/// from a digest pipeline, a persisted index or another process, and
+/// carries nothing else. `Hash` writes the stored `u64` alone, so under a
+/// pass-through outer hasher such as
+/// [`PassthroughState`](crate::PassthroughState) or `nohash_hasher`, map
+/// operations never look at the value to hash it.
+///
+/// `Eq` and `Ord` compare the values. The caller must supply equal hashes
+/// for equal values, as with any `Hash` impl; two equal values with
+/// different hashes land in different buckets and are never matched.
+///
+/// `Borrow<T>` serves APIs that need a `&T`, such as ordered maps. Hashed
+/// maps looked up by a plain `&T` hash the value, not the stored hash, and
+/// miss, as with `HashMemo`; probe them with a `Prehashed` instead.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{PassthroughState, Prehashed};
+/// use std::collections::HashMap;
+///
+/// let mut map: HashMap<Prehashed<String>, u32, PassthroughState> = HashMap::default();
+/// map.insert(Prehashed::new("blob".to_string(), 0x5eed), 1);
+///
+/// let probe = Prehashed::new("blob".to_string(), 0x5eed);
+/// assert_eq!(map.get(&probe), Some(&1));
+/// ```
+#[derive(Clone, Copy, Default)]
+pub struct Prehashed<T> {
+    value: T,
+    hash: u64,
+}
+
+impl<T> Prehashed<T> {
+    /// Pairs `value` with `hash`, which must be equal for equal values.
+    #[inline]
+    pub const fn new(value: T, hash: u64) -> Self {
+        Self { value, hash }
+    }
+
+    /// Returns the stored hash.
+    #[inline]
+    pub const fn stored_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Returns the value.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// Returns the value and the stored hash.
+    #[inline]
+    pub fn into_parts(self) -> (T, u64) {
+        (self.value, self.hash)
+    }
+}
+
+impl<T> AsRef<T> for Prehashed<T> {
+    #[inline]
+    fn as_ref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> Borrow<T> for Prehashed<T> {
+    #[inline]
+    fn borrow(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> Hash for Prehashed<T> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+impl<T: PartialEq> PartialEq for Prehashed<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T: Eq> Eq for Prehashed<T> {}
+
+impl<T: Ord> PartialOrd for Prehashed<T> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> Ord for Prehashed<T> {
+    /// Orders by value alone, agreeing with `Ord for T`.
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value.cmp(&other.value)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Prehashed<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Prehashed")
+            .field("value", &self.value)
+            .field("hash", &format_args!("{:#018x}", self.hash))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeSet, HashMap};
+    use std::hash::BuildHasherDefault;
+
+    use nohash_hasher::NoHashHasher;
+
+    use super::*;
+
+    type NoHashMap<K, V> = HashMap<K, V, BuildHasherDefault<NoHashHasher<u64>>>;
+
+    fn digest(value: &str) -> u64 {
+        // Any function of the value will do; FNV-1a stands in for an
+        // external digest.
+        value.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
+
+    fn key(value: &str) -> Prehashed<String> {
+        Prehashed::new(value.to_string(), digest(value))
+    }
+
+    #[test]
+    fn works_as_a_nohash_map_key() {
+        let mut map = NoHashMap::default();
+        for i in 0..1000 {
+            map.insert(key(&i.to_string()), i);
+        }
+        for i in 0..1000 {
+            assert_eq!(map.get(&key(&i.to_string())), Some(&i));
+        }
+        assert_eq!(map.get(&key("1000")), None);
+        assert_eq!(map.insert(key("7"), 70), Some(7));
+        assert_eq!(map.remove(&key("7")), Some(70));
+        assert_eq!(map.len(), 999);
+    }
+
+    #[test]
+    fn colliding_hashes_fall_back_to_the_value() {
+        let mut map = NoHashMap::default();
+        map.insert(Prehashed::new("a", 1), 'a');
+        map.insert(Prehashed::new("b", 1), 'b');
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&Prehashed::new("b", 1)), Some(&'b'));
+        assert_eq!(map.get(&Prehashed::new("c", 1)), None);
+
+        let ordered: BTreeSet<_> = ["b", "a"].map(|s| Prehashed::new(s, 1)).into();
+        assert!(ordered.contains(&"a"));
+        assert_eq!(
+            ordered
+                .into_iter()
+                .map(Prehashed::into_parts)
+                .collect::<Vec<_>>(),
+            [("a", 1), ("b", 1)]
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! [`proptest`](::proptest) strategies for memoized values.
+
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use ::proptest::arbitrary::any;
+use ::proptest::strategy::Strategy;
+
+use crate::HashMemo;
+
+/// Wraps the values of `inner` in memos, primed or cold at random.
+///
+/// Shrinking delegates to `inner`, so a failing case shrinks to the
+/// smallest value that still fails, in either cache state.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::proptest::memo_strategy;
+/// use proptest::prelude::*;
+///
+/// proptest!(|(memo in memo_strategy(any::<String>()))| {
+///     prop_assert!(memo.clone() == memo);
+/// });
+/// ```
+pub fn memo_strategy<T>(inner: impl Strategy<Value = T>) -> impl Strategy<Value = HashMemo<T>>
+where
+    T: Eq + Hash + Debug,
+{
+    (any::<bool>(), inner).prop_map(|(primed, value)| {
+        let memo = HashMemo::new(value);
+        if primed {
+            memo.precompute();
+        }
+        memo
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::hash::{BuildHasher, RandomState};
+
+    use ::proptest::prelude::*;
+
+    use super::*;
+    use crate::Probe;
+
+    proptest! {
+        #[test]
+        fn equal_values_have_equal_memo_hashes(value in any::<Vec<u16>>(), primed in any::<bool>()) {
+            let a = HashMemo::new(value.clone());
+            if primed {
+                a.precompute();
+            }
+            let b = HashMemo::new(value);
+            prop_assert!(a == b);
+            prop_assert_eq!(a.memoized_hash(), b.memoized_hash());
+        }
+
+        #[test]
+        fn clone_preserves_behavior(memo in memo_strategy(any::<String>())) {
+            let clone = memo.clone();
+            let state = RandomState::new();
+            prop_assert!(clone == memo);
+            prop_assert_eq!(state.hash_one(&clone), state.hash_one(&memo));
+            prop_assert_eq!(clone.into_inner(), memo.into_inner());
+        }
+
+        #[test]
+        fn map_lookups_by_fresh_memo_and_probe_succeed(
+            keys in proptest::collection::vec(memo_strategy(any::<String>()), 1..32),
+        ) {
+            let mut map = HashMap::new();
+            for (i, key) in keys.iter().enumerate() {
+                map.insert(key.clone(), i);
+            }
+            for key in &keys {
+                let expected = map.get(key).copied();
+                prop_assert!(expected.is_some());
+                prop_assert_eq!(map.get(&HashMemo::new(key.value.clone())).copied(), expected);
+                prop_assert_eq!(map.get(Probe::new(key.value.as_str()).as_lookup()).copied(), expected);
+            }
+        }
+
+        #[test]
+        fn into_inner_round_trips(value in any::<(u64, String)>()) {
+            prop_assert_eq!(HashMemo::new(value.clone()).into_inner(), value);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! [`quickcheck`](::quickcheck) support for property tests over memos.
+//!
+//! A generated memo wraps an arbitrary value and is primed about half of the
+//! time. Shrunk candidates are always cold, so a failing case is reported
+//! with the smallest value that still fails regardless of cache state.
+
+use std::hash::{BuildHasher, Hash};
+
+use ::quickcheck::{Arbitrary, Gen};
+
+use crate::HashMemo;
+
+impl<T, H> Arbitrary for HashMemo<T, H>
+where
+    T: Eq + Hash + Arbitrary,
+    H: BuildHasher + Default + Clone + 'static,
+{
+    fn arbitrary(g: &mut Gen) -> Self {
+        let primed = bool::arbitrary(g);
+        let memo = Self::with_hasher(T::arbitrary(g), H::default());
+        if primed {
+            memo.precompute();
+        }
+        memo
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        Box::new(
+            self.value
+                .shrink()
+                .map(|value| Self::with_hasher(value, H::default())),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::hash::RandomState;
+    use std::sync::atomic::Ordering;
+
+    use ::quickcheck::quickcheck;
+
+    use super::*;
+    use crate::Probe;
+
+    quickcheck! {
+        fn clone_keeps_hash_stable(memo: HashMemo<String>) -> bool {
+            let clone = memo.clone();
+            let state = RandomState::new();
+            clone == memo && state.hash_one(&clone) == state.hash_one(&memo)
+        }
+
+        fn map_insert_then_lookup_round_trips(keys: Vec<HashMemo<String>>) -> bool {
+            let map: HashMap<_, _> = keys.iter().cloned().zip(0..).collect();
+            keys.iter().all(|key| {
+                let expected = map.get(key);
+                expected.is_some()
+                    && map.get(&HashMemo::new(key.value.clone())) == expected
+                    && map.get(Probe::new(key.value.as_str()).as_lookup()) == expected
+            })
+        }
+
+        fn shrinks_to_cold_memos_of_shrunk_values(memo: HashMemo<Vec<u8>>) -> bool {
+            memo.shrink()
+                .zip(memo.value.shrink())
+                .all(|(shrunk, value)| shrunk.hash.load(Ordering::Relaxed) == 0 && shrunk.value == value)
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! Zero-copy archiving with [`rkyv`](::rkyv).
+//!
+//! A memo is archived together with its hash, so tables reloaded from an
+//! archive never rehash their keys. The archive also records a tag derived
+//! from the hasher that produced the hash; deserializing with a hasher that
+//! produces a different tag discards the archived hash instead of trusting it.
+//!
+//! This is the one module allowed `unsafe` code, for the `unsafe impl`s an
+//! rkyv archived type needs.
+
+#![allow(unsafe_code)]
+
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use ::rkyv::bytecheck::{CheckBytes, Verify};
+use ::rkyv::rancor::{Fallible, Source};
+use ::rkyv::{munge::munge, Archive, Archived, Deserialize, Place, Portable, Serialize};
+
+use crate::{hasher_tag, HashMemo};
+
+/// An archived [`HashMemo`].
+///
+/// The archived hash is always computed: serializing a memo whose cache is
+/// cold computes the hash first. `Hash` writes that hash, exactly like the
+/// live memo does, so archived and live memos can be probed against each
+/// other in place.
+#[derive(Portable, CheckBytes)]
+#[rkyv(crate = ::rkyv)]
+#[bytecheck(crate = ::rkyv::bytecheck, verify)]
+#[repr(C)]
+pub struct ArchivedHashMemo<T> {
+    value: T,
+    hash: Archived<u64>,
+    hasher_tag: Archived<u64>,
+}
+
+impl<T> ArchivedHashMemo<T> {
+    /// Returns the archived value.
+    #[inline]
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Returns the archived hash.
+    #[inline]
+    pub fn cached_hash(&self) -> u64 {
+        self.hash.to_native()
+    }
+
+    /// Returns `true` if the archived hash was produced by a hasher
+    /// equivalent to `hasher`.
+    #[inline]
+    pub fn matches_hasher<H: BuildHasher>(&self, hasher: &H) -> bool {
+        self.hasher_tag.to_native() == hasher_tag(hasher)
+    }
+}
+
+// SAFETY: `verify` only rejects archives; it never asserts more than the
+// derived field checks already established.
+unsafe impl<T, C> Verify<C> for ArchivedHashMemo<T>
+where
+    C: Fallible + ?Sized,
+    C::Error: Source,
+{
+    fn verify(&self, _: &mut C) -> Result<(), C::Error> {
+        if self.hash.to_native() == 0 {
+            return Err(C::Error::new(EmptyHashError));
+        }
+        Ok(())
+    }
+}
+
+/// The error returned when an archived memo carries the empty-cache marker.
+#[derive(Debug)]
+struct EmptyHashError;
+
+impl fmt::Display for EmptyHashError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("archived HashMemo has an empty hash")
+    }
+}
+
+impl std::error::Error for EmptyHashError {}
+
+impl<T: fmt::Debug> fmt::Debug for ArchivedHashMemo<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArchivedHashMemo")
+            .field("value", &self.value)
+            .field("hash", &self.cached_hash())
+            .finish()
+    }
+}
+
+impl<T> Hash for ArchivedHashMemo<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.cached_hash());
+    }
+}
+
+impl<T: PartialEq> PartialEq for ArchivedHashMemo<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T: Eq> Eq for ArchivedHashMemo<T> {}
+
+impl<T, U, H> PartialEq<HashMemo<U, H>> for ArchivedHashMemo<T>
+where
+    T: PartialEq<U>,
+    U: Eq + Hash,
+    H: BuildHasher,
+{
+    fn eq(&self, other: &HashMemo<U, H>) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T, U, H> PartialEq<ArchivedHashMemo<U>> for HashMemo<T, H>
+where
+    T: Eq + Hash,
+    U: PartialEq<T>,
+    H: BuildHasher,
+{
+    fn eq(&self, other: &ArchivedHashMemo<U>) -> bool {
+        other.value == self.value
+    }
+}
+
+impl<T, H> Archive for HashMemo<T, H>
+where
+    T: Eq + Hash + Archive,
+    H: BuildHasher,
+{
+    type Archived = ArchivedHashMemo<T::Archived>;
+    type Resolver = T::Resolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        munge!(let ArchivedHashMemo { value, hash, hasher_tag: tag } = out);
+        self.value.resolve(resolver, value);
+        self.memoized_hash().resolve((), hash);
+        hasher_tag(&self.hasher).resolve((), tag);
+    }
+}
+
+impl<T, H, S> Serialize<S> for HashMemo<T, H>
+where
+    T: Eq + Hash + Serialize<S>,
+    H: BuildHasher,
+    S: Fallible + ?Sized,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        self.value.serialize(serializer)
+    }
+}
+
+impl<T, H, D> Deserialize<HashMemo<T, H>, D> for ArchivedHashMemo<T::Archived>
+where
+    T: Eq + Hash + Archive,
+    T::Archived: Deserialize<T, D>,
+    H: BuildHasher + Default,
+    D: Fallible + ?Sized,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<HashMemo<T, H>, D::Error> {
+        let value = self.value.deserialize(deserializer)?;
+        let memo = HashMemo::with_hasher(value, H::default());
+        if self.matches_hasher(&memo.hasher) {
+            memo.hash
+                .store(self.cached_hash(), std::sync::atomic::Ordering::Relaxed);
+        }
+        Ok(memo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::{BuildHasherDefault, DefaultHasher, RandomState};
+    use std::sync::atomic::Ordering;
+
+    use ::rkyv::rancor::Error;
+    use ::rkyv::string::ArchivedString;
+
+    use super::*;
+
+    type Memo = HashMemo<String>;
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn round_trip_preserves_value_and_hash() {
+        let memo = Memo::new("foo".repeat(100));
+        let bytes = ::rkyv::to_bytes::<Error>(&memo).unwrap();
+
+        let archived = ::rkyv::access::<ArchivedHashMemo<ArchivedString>, Error>(&bytes).unwrap();
+        assert_eq!(archived.cached_hash(), memo.hash.load(Ordering::Relaxed));
+
+        let restored: Memo = ::rkyv::deserialize::<Memo, Error>(archived).unwrap();
+        assert_eq!(restored, memo);
+        assert_eq!(
+            restored.hash.load(Ordering::Relaxed),
+            memo.hash.load(Ordering::Relaxed),
+            "deserializing with the same hasher should keep the archived hash"
+        );
+    }
+
+    #[test]
+    fn archiving_a_cold_memo_computes_its_hash() {
+        let memo = Memo::new("cold".to_string());
+        assert_eq!(memo.hash.load(Ordering::Relaxed), 0);
+
+        let bytes = ::rkyv::to_bytes::<Error>(&memo).unwrap();
+        let archived = ::rkyv::access::<ArchivedHashMemo<ArchivedString>, Error>(&bytes).unwrap();
+        assert_ne!(archived.cached_hash(), 0);
+        assert_eq!(archived.cached_hash(), memo.memoized_hash());
+    }
+
+    #[test]
+    fn archived_vec_can_be_probed_in_place() {
+        let memos: Vec<Memo> = (0..16).map(|i| Memo::new(i.to_string())).collect();
+        let bytes = ::rkyv::to_bytes::<Error>(&memos).unwrap();
+        let archived = ::rkyv::access::<
+            ::rkyv::vec::ArchivedVec<ArchivedHashMemo<ArchivedString>>,
+            Error,
+        >(&bytes)
+        .unwrap();
+
+        let probe = Memo::new("11".to_string());
+        let position = archived
+            .iter()
+            .position(|entry| entry.cached_hash() == probe.memoized_hash() && *entry == probe);
+        assert_eq!(position, Some(11));
+
+        let hasher = BuildHasherDefault::<DefaultHasher>::default();
+        assert_eq!(hasher.hash_one(&archived[11]), hasher.hash_one(&probe));
+        assert!(probe == archived[11]);
+        assert!(archived[3] != probe);
+    }
+
+    #[test]
+    fn mismatched_hasher_discards_archived_hash() {
+        let memo = Memo::new("seeded".to_string());
+        let bytes = ::rkyv::to_bytes::<Error>(&memo).unwrap();
+        let archived = ::rkyv::access::<ArchivedHashMemo<ArchivedString>, Error>(&bytes).unwrap();
+
+        assert!(archived.matches_hasher(&BuildHasherDefault::<DefaultHasher>::default()));
+        assert!(!archived.matches_hasher(&RandomState::new()));
+
+        let restored: HashMemo<String, RandomState> =
+            ::rkyv::deserialize::<_, Error>(archived).unwrap();
+        assert_eq!(restored.value, "seeded");
+        assert_eq!(restored.hash.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn validation_rejects_an_empty_hash() {
+        let memo = Memo::new("x".to_string());
+        let mut bytes = ::rkyv::to_bytes::<Error>(&memo).unwrap().to_vec();
+
+        let hash = memo.memoized_hash().to_le_bytes();
+        let offset = bytes
+            .windows(8)
+            .position(|window| window == hash)
+            .expect("archived hash should be present");
+        bytes[offset..offset + 8].fill(0);
+
+        let mut aligned = ::rkyv::util::AlignedVec::<16>::new();
+        aligned.extend_from_slice(&bytes);
+        assert!(::rkyv::access::<ArchivedHashMemo<ArchivedString>, Error>(&aligned).is_err());
+    }
+}
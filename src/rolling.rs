@@ -0,0 +1,350 @@
+//! Rolling hashes over a sliding window of bytes, for chunk deduplication.
+//!
+//! [`RollingMemo`] keeps the Rabin–Karp hash of the last `window_size`
+//! bytes pushed into it, updated in O(1) per byte, so scanning a stream for
+//! known chunks costs one update per position instead of a full hash of
+//! each window. [`freeze`](RollingMemo::freeze) captures the window as a
+//! memo whose cache already holds the rolling value, so checking it
+//! against a set of known chunks hashes nothing.
+//!
+//! The hash of bytes `b_1 .. b_n` is
+//! `((b_1 + 1)·B^(n-1) + (b_2 + 1)·B^(n-2) + … + (b_n + 1)) mod (2^61 − 1)`
+//! with the fixed base `B = 0x1f35_a7bd_3c4e_2d69`. Bytes are offset by one
+//! so that leading zero bytes still count.
+//!
+//! # Collisions
+//!
+//! Modulo the prime `2^61 − 1`, two distinct windows of `n` bytes collide
+//! for at most `n − 1` of the possible bases, so for inputs chosen without
+//! knowledge of `B` a collision has a chance of about `n / 2^61`. The base
+//! is public and fixed, though, so colliding inputs are easy to construct
+//! on purpose: this is not DoS-resistant, and a hash match should be
+//! confirmed by comparing bytes, as sets of memos do. Hashes are below
+//! `2^61`, so they use 61 of the 64 bits.
+
+use std::collections::VecDeque;
+use std::hash::{BuildHasher, Hasher};
+
+use crate::HashMemo;
+
+/// The Mersenne prime `2^61 − 1` the polynomial is reduced by.
+const MODULUS: u64 = (1 << 61) - 1;
+
+/// The base of the polynomial.
+const BASE: u64 = 0x1f35_a7bd_3c4e_2d69;
+
+/// Returns `a · b mod (2^61 − 1)`, for `a` and `b` already reduced.
+#[inline]
+const fn mul_mod(a: u64, b: u64) -> u64 {
+    let product = a as u128 * b as u128;
+    let folded = (product as u64 & MODULUS) + (product >> 61) as u64;
+    reduce(folded)
+}
+
+/// Reduces a value below `2^62` into `0..MODULUS`.
+#[inline]
+const fn reduce(value: u64) -> u64 {
+    let folded = (value & MODULUS) + (value >> 61);
+    if folded >= MODULUS {
+        folded - MODULUS
+    } else {
+        folded
+    }
+}
+
+/// Appends `byte` to the window hashed as `hash`.
+#[inline]
+const fn append(hash: u64, byte: u8) -> u64 {
+    reduce(mul_mod(hash, BASE) + byte as u64 + 1)
+}
+
+/// A `BuildHasher` for the polynomial of the [`rolling`](crate::rolling)
+/// module, the hasher of memos frozen from a [`RollingMemo`].
+///
+/// Byte slices, `Vec<u8>`s and byte arrays hash to the rolling hash of
+/// their bytes: `write` folds its bytes into the polynomial, and
+/// `write_usize`, with which slices write their length, is ignored. Other
+/// integer writes fold in their native-endian bytes.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{RollingMemo, RollingState};
+/// use std::hash::BuildHasher;
+///
+/// let mut rolling = RollingMemo::new(4);
+/// rolling.push_slice(b"abcdef");
+/// assert_eq!(rolling.current_hash(), RollingState.hash_one(b"cdef".to_vec()));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RollingState;
+
+impl BuildHasher for RollingState {
+    type Hasher = RollingHasher;
+
+    #[inline]
+    fn build_hasher(&self) -> Self::Hasher {
+        RollingHasher::default()
+    }
+}
+
+/// The `Hasher` built by [`RollingState`].
+#[derive(Debug, Clone, Default)]
+pub struct RollingHasher {
+    hash: u64,
+}
+
+impl Hasher for RollingHasher {
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        self.hash = bytes
+            .iter()
+            .fold(self.hash, |hash, &byte| append(hash, byte));
+    }
+
+    #[inline]
+    fn write_usize(&mut self, _len: usize) {}
+}
+
+/// A sliding window over a byte stream with its rolling hash.
+///
+/// Pushing a byte appends it to the window, dropping the oldest byte once
+/// the window holds `window_size` of them, and updates the hash in O(1).
+/// Until the window fills, the hash is that of the bytes pushed so far. A
+/// window of size `0` holds nothing and hashes to `0`.
+///
+/// See the [`rolling`](crate::rolling) module for the hash and its
+/// collision characteristics.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::RollingMemo;
+/// use std::collections::HashSet;
+///
+/// let mut rolling = RollingMemo::new(4);
+/// rolling.push_slice(b"know");
+/// let known = HashSet::from([rolling.freeze()]);
+///
+/// let mut rolling = RollingMemo::new(4);
+/// let mut hits = Vec::new();
+/// for (i, &byte) in b"I know you know".iter().enumerate() {
+///     rolling.push(byte);
+///     if rolling.is_full() && known.contains(&rolling.freeze()) {
+///         hits.push(i + 1 - 4);
+///     }
+/// }
+/// assert_eq!(hits, [2, 11]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RollingMemo {
+    window: VecDeque<u8>,
+    window_size: usize,
+    hash: u64,
+    /// `BASE^(window_size - 1)`, the weight of the oldest byte of a full
+    /// window.
+    leading: u64,
+}
+
+impl RollingMemo {
+    /// Creates an empty window of `window_size` bytes.
+    pub fn new(window_size: usize) -> Self {
+        let mut leading = 1;
+        for _ in 1..window_size {
+            leading = mul_mod(leading, BASE);
+        }
+        Self {
+            window: VecDeque::with_capacity(window_size),
+            window_size,
+            hash: 0,
+            leading,
+        }
+    }
+
+    /// Pushes `byte` into the window, evicting the oldest byte if the
+    /// window is full.
+    #[inline]
+    pub fn push(&mut self, byte: u8) {
+        if self.window_size == 0 {
+            return;
+        }
+        if self.window.len() == self.window_size {
+            let oldest = self.window.pop_front().expect("a full window is not empty");
+            let weight = mul_mod(oldest as u64 + 1, self.leading);
+            self.hash = reduce(self.hash + MODULUS - weight);
+        }
+        self.window.push_back(byte);
+        self.hash = append(self.hash, byte);
+    }
+
+    /// Pushes every byte of `bytes`, in order.
+    pub fn push_slice(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.push(byte);
+        }
+    }
+
+    /// Returns the hash of the bytes in the window.
+    #[inline]
+    pub fn current_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Returns the size the window fills up to.
+    #[inline]
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// Returns the number of bytes in the window.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.window.len()
+    }
+
+    /// Returns `true` if no byte is in the window.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.window.is_empty()
+    }
+
+    /// Returns `true` if the window holds `window_size` bytes.
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.window.len() == self.window_size
+    }
+
+    /// Empties the window.
+    pub fn clear(&mut self) {
+        self.window.clear();
+        self.hash = 0;
+    }
+
+    /// Copies the window into a memo whose cache holds the rolling hash.
+    ///
+    /// The memo hashes with [`RollingState`], which computes the same hash
+    /// should the cache be cleared. As for any memo, a hash of `0`, that of
+    /// the empty window, is cached as `1`.
+    pub fn freeze(&self) -> HashMemo<Vec<u8>, RollingState> {
+        HashMemo::with_hasher_const(
+            self.window.iter().copied().collect(),
+            RollingState,
+            self.hash,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    /// Deterministic xorshift bytes.
+    fn random_bytes(len: usize) -> Vec<u8> {
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    /// Hashes `bytes` from scratch, with exact arithmetic.
+    fn from_scratch(bytes: &[u8]) -> u64 {
+        let modulus = MODULUS as u128;
+        bytes.iter().fold(0u128, |hash, &byte| {
+            (hash * BASE as u128 + byte as u128 + 1) % modulus
+        }) as u64
+    }
+
+    #[test]
+    fn rolling_hash_matches_from_scratch_at_every_offset() {
+        let data = random_bytes(2048);
+        for window_size in [1, 2, 7, 8, 64, 100] {
+            let mut rolling = RollingMemo::new(window_size);
+            for end in 1..=data.len() {
+                rolling.push(data[end - 1]);
+                let window = &data[end.saturating_sub(window_size)..end];
+                assert_eq!(rolling.current_hash(), from_scratch(window));
+                assert_eq!(rolling.len(), window.len());
+            }
+        }
+    }
+
+    #[test]
+    fn window_size_edge_cases() {
+        let mut empty = RollingMemo::new(0);
+        empty.push_slice(b"ignored");
+        assert!(empty.is_empty() && empty.is_full());
+        assert_eq!(empty.current_hash(), 0);
+
+        let mut one = RollingMemo::new(1);
+        one.push_slice(b"xyz");
+        assert_eq!(one.current_hash(), from_scratch(b"z"));
+        assert_eq!(one.freeze().into_inner(), b"z");
+
+        let mut long = RollingMemo::new(64);
+        long.push_slice(b"short input");
+        assert!(!long.is_full());
+        assert_eq!(long.current_hash(), from_scratch(b"short input"));
+        long.clear();
+        assert_eq!(long.current_hash(), 0);
+        long.push_slice(b"short");
+        assert_eq!(long.current_hash(), from_scratch(b"short"));
+    }
+
+    #[test]
+    fn leading_zero_bytes_change_the_hash() {
+        assert_ne!(from_scratch(b"\0a"), from_scratch(b"a"));
+        assert_ne!(
+            RollingState.hash_one(b"\0\0".to_vec()),
+            RollingState.hash_one(b"\0".to_vec())
+        );
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn frozen_windows_carry_the_rolling_hash() {
+        let data = random_bytes(512);
+        let mut rolling = RollingMemo::new(32);
+        rolling.push_slice(&data);
+
+        let frozen = rolling.freeze();
+        assert_eq!(frozen.as_ref()[..], data[data.len() - 32..]);
+        assert_eq!(frozen.hash_one_cached(), rolling.current_hash());
+        frozen.forget_hash();
+        assert_eq!(frozen.hash_one_cached(), rolling.current_hash());
+    }
+
+    #[test]
+    fn frozen_windows_find_known_chunks() {
+        let data = random_bytes(4096);
+        let known: HashSet<_> = [100, 1000, 3000]
+            .into_iter()
+            .map(|start| {
+                let mut rolling = RollingMemo::new(48);
+                rolling.push_slice(&data[start..start + 48]);
+                rolling.freeze()
+            })
+            .collect();
+
+        let mut rolling = RollingMemo::new(48);
+        let mut found = Vec::new();
+        for (i, &byte) in data.iter().enumerate() {
+            rolling.push(byte);
+            if rolling.is_full() && known.contains(&rolling.freeze()) {
+                found.push(i + 1 - 48);
+            }
+        }
+        assert_eq!(found, [100, 1000, 3000]);
+    }
+}
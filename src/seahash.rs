@@ -0,0 +1,73 @@
+//! [`seahash`](::seahash) support, for reproducible hashing.
+//!
+//! SeaHash is unseeded and fully specified, so the cached hash of a
+//! [`SeaHashMemo`] is stable across runs, processes and machines, and can
+//! be persisted or compared between them. Values hashing `usize`s or other
+//! platform-sized integers still hash differently across pointer widths,
+//! and the calls a type makes in its `Hash` impl are up to that type.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::HashMemo;
+//! use std::collections::HashSet;
+//!
+//! let mut set = HashSet::new();
+//! set.insert(HashMemo::new_seahash("stable".to_string()));
+//! assert!(set.contains(&HashMemo::new_seahash("stable".to_string())));
+//! ```
+
+use std::hash::{BuildHasherDefault, Hash};
+
+use ::seahash::SeaHasher;
+
+use crate::HashMemo;
+
+/// A [`HashMemo`] hashing with SeaHash.
+pub type SeaHashMemo<T> = HashMemo<T, BuildHasherDefault<SeaHasher>>;
+
+impl<T> HashMemo<T, BuildHasherDefault<SeaHasher>>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new `HashMemo` using SeaHash.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::{HashMemo, SeaHashMemo};
+    ///
+    /// let memo: SeaHashMemo<&str> = HashMemo::new_seahash("hello world");
+    /// ```
+    pub const fn new_seahash(value: T) -> Self {
+        Self::with_hasher(value, BuildHasherDefault::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    /// SeaHash of `"to be or not to be"`, hashed as a `str`.
+    const PINNED: u64 = 0x7f34_d8d2_0816_e43b;
+
+    #[test]
+    fn hashes_are_pinned() {
+        let memo = HashMemo::new_seahash("to be or not to be");
+        assert_eq!(memo.memoized_hash(), PINNED);
+    }
+
+    #[test]
+    fn persisted_hashes_can_be_reused() {
+        let restored =
+            HashMemo::with_hasher_const("to be or not to be", BuildHasherDefault::new(), PINNED);
+        let fresh: HashSet<SeaHashMemo<&str>> = ["to be or not to be", "that is the question"]
+            .into_iter()
+            .map(HashMemo::new_seahash)
+            .collect();
+        assert!(fresh.contains(&restored));
+        assert!(restored == HashMemo::new_seahash("to be or not to be"));
+    }
+}
@@ -0,0 +1,1179 @@
+//! [`serde`](::serde) support.
+//!
+//! `HashMemo` itself serializes transparently, exactly as the wrapped value
+//! would, and deserializes into a memo with a cold cache and a hasher built
+//! via `Default`; so do [`Memoized`], [`MemoString`] and [`MemoBytes`].
+//! This is the right choice for most formats, and the only sensible one for
+//! non-self-describing formats such as bincode or postcard, where the memo
+//! should be invisible on the wire.
+//!
+//! [`HashMemoTagged`] instead serializes as a struct,
+//! `{ "value": ..., "hash": ..., "hasher": ... }`. Use it with
+//! self-describing formats such as JSON, YAML or MessagePack when readers
+//! benefit from an explicit wrapper, or when the cached hash should survive
+//! a round trip. [`HashMemoVerified`] reads and writes the same shape, but
+//! recomputes the hash on deserialize and rejects input whose persisted hash
+//! does not match.
+//!
+//! [`HashMemoSeeded`] persists the hasher's seed next to the value, hash and
+//! fingerprint, `{ "value": ..., "hash": ..., "seed": ..., "hasher": ... }`,
+//! for hashers implementing [`SeededState`]. Deserializing rebuilds an
+//! identically seeded hasher and trusts the persisted hash only if the
+//! rebuilt hasher has the same fingerprint, so even seeded hashers keep
+//! their hash across a round trip.
+//!
+//! Collections of memos can instead be written column by column, all values
+//! then all cached hashes, with the [`columnar`] module as a
+//! `#[serde(with = ...)]` helper or through the [`ColumnarMemos`] wrapper.
+//!
+//! Memo fields that usually hold their default can be left out of the
+//! output with [`HashMemo::is_default`]:
+//!
+//! ```rust
+//! use hashmemo::HashMemo;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Entry {
+//!     name: HashMemo<String>,
+//!     #[serde(default, skip_serializing_if = "HashMemo::is_default")]
+//!     alias: HashMemo<String>,
+//! }
+//! ```
+
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash};
+use std::sync::atomic::Ordering;
+
+use ::serde::de::Error as _;
+use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use ::serde::de::DeserializeOwned;
+
+use crate::{
+    hasher_tag, remap_zero, FixedState, HashMemo, LengthPrefixed, MemoBytes, MemoEntry, MemoString,
+    Memoized, PassthroughState,
+};
+
+impl<T, H> Serialize for HashMemo<T, H>
+where
+    T: Eq + Hash + Serialize,
+    H: BuildHasher,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(serializer)
+    }
+}
+
+impl<'de, T, H> Deserialize<'de> for HashMemo<T, H>
+where
+    T: Eq + Hash + Deserialize<'de>,
+    H: BuildHasher + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(|value| Self::with_hasher(value, H::default()))
+    }
+}
+
+impl<T, H> Serialize for Memoized<T, H>
+where
+    T: Eq + Hash + Serialize,
+    H: BuildHasher,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+impl<'de, T, H> Deserialize<'de> for Memoized<T, H>
+where
+    T: Eq + Hash + Deserialize<'de>,
+    H: BuildHasher + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Memoized::from)
+    }
+}
+
+impl<H> Serialize for MemoString<H>
+where
+    H: BuildHasher,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_str().serialize(serializer)
+    }
+}
+
+impl<'de, H> Deserialize<'de> for MemoString<H>
+where
+    H: BuildHasher + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(MemoString::from)
+    }
+}
+
+impl<H> Serialize for MemoBytes<H>
+where
+    H: BuildHasher,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.memo().serialize(serializer)
+    }
+}
+
+impl<'de, H> Deserialize<'de> for MemoBytes<H>
+where
+    H: BuildHasher + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<u8>::deserialize(deserializer).map(MemoBytes::from)
+    }
+}
+
+/// Serializes a [`HashMemo`] as a tagged struct carrying its cached hash.
+///
+/// The `hash` field holds the memoized hash, computed first if the cache is
+/// cold, and `hasher` a fingerprint of the hasher. Both are optional on
+/// deserialize, so `{ "value": ... }` alone is accepted. The cache is seeded
+/// only when the fingerprint matches the decoded hasher; otherwise the memo
+/// reloads cold rather than with a wrong hash.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{HashMemo, HashMemoTagged};
+///
+/// let tagged = HashMemoTagged(HashMemo::new("key".to_string()));
+/// let json = serde_json::to_string(&tagged).unwrap();
+/// assert!(json.starts_with(r#"{"value":"key","hash":"#));
+///
+/// let HashMemoTagged(memo): HashMemoTagged<String> =
+///     serde_json::from_str(r#"{ "value": "key" }"#).unwrap();
+/// assert_eq!(memo.into_inner(), "key");
+/// ```
+#[derive(Clone)]
+pub struct HashMemoTagged<T, H = BuildHasherDefault<DefaultHasher>>(pub HashMemo<T, H>)
+where
+    T: Eq + Hash,
+    H: BuildHasher;
+
+impl<T, H> fmt::Debug for HashMemoTagged<T, H>
+where
+    T: Eq + Hash + fmt::Debug,
+    H: BuildHasher + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("HashMemoTagged").field(&self.0).finish()
+    }
+}
+
+impl<T, H> From<HashMemo<T, H>> for HashMemoTagged<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    fn from(memo: HashMemo<T, H>) -> Self {
+        Self(memo)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "::serde", rename = "MemoEntry")]
+struct EntryRef<'a, K, V> {
+    key: &'a K,
+    value: &'a V,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "::serde", rename = "MemoEntry")]
+struct EntryOwned<K, V> {
+    key: K,
+    value: V,
+}
+
+impl<K, V, H> Serialize for MemoEntry<K, V, H>
+where
+    K: Eq + Hash + Serialize,
+    V: Serialize,
+    H: BuildHasher,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        EntryRef {
+            key: self.key(),
+            value: self.value(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, K, V, H> Deserialize<'de> for MemoEntry<K, V, H>
+where
+    K: Eq + Hash + Deserialize<'de>,
+    V: Deserialize<'de>,
+    H: BuildHasher + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let entry = EntryOwned::<K, V>::deserialize(deserializer)?;
+        Ok(Self::with_hasher(entry.key, entry.value, H::default()))
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "::serde", rename = "HashMemo")]
+struct TaggedRef<'a, T> {
+    value: &'a T,
+    hash: u64,
+    hasher: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "::serde", rename = "HashMemo")]
+struct TaggedOwned<T> {
+    value: T,
+    #[serde(default)]
+    hash: Option<u64>,
+    #[serde(default)]
+    hasher: Option<u64>,
+}
+
+impl<T, H> Serialize for HashMemoTagged<T, H>
+where
+    T: Eq + Hash + Serialize,
+    H: BuildHasher,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        TaggedRef {
+            value: &self.0.value,
+            hash: self.0.memoized_hash(),
+            hasher: hasher_tag(&self.0.hasher),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T, H> Deserialize<'de> for HashMemoTagged<T, H>
+where
+    T: Eq + Hash + Deserialize<'de>,
+    H: BuildHasher + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let tagged = TaggedOwned::<T>::deserialize(deserializer)?;
+        let memo = HashMemo::with_hasher(tagged.value, H::default());
+        if let (Some(hash), Some(tag)) = (tagged.hash, tagged.hasher) {
+            if hash != 0 && tag == hasher_tag(&memo.hasher) {
+                memo.hash.store(hash, Ordering::Relaxed);
+            }
+        }
+        Ok(Self(memo))
+    }
+}
+
+/// Like [`HashMemoTagged`], but verifies the persisted hash on deserialize.
+///
+/// Deserializing recomputes the hash from the value and fails with a serde
+/// error unless it equals the persisted `hash` field, which is required.
+/// This catches corrupted input as well as data written with a different
+/// hasher, so `H` must build the same hasher that was used on serialize;
+/// with a randomly seeded hasher such as `RandomState`, verification fails
+/// across processes by design. The `hasher` fingerprint is ignored.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{HashMemo, HashMemoTagged, HashMemoVerified};
+///
+/// let json = serde_json::to_string(&HashMemoTagged(HashMemo::new(7u32))).unwrap();
+/// let HashMemoVerified(memo): HashMemoVerified<u32> = serde_json::from_str(&json).unwrap();
+///
+/// let corrupted = r#"{ "value": 8, "hash": 1 }"#;
+/// assert!(serde_json::from_str::<HashMemoVerified<u32>>(corrupted).is_err());
+/// ```
+#[derive(Clone)]
+pub struct HashMemoVerified<T, H = BuildHasherDefault<DefaultHasher>>(pub HashMemo<T, H>)
+where
+    T: Eq + Hash,
+    H: BuildHasher;
+
+impl<T, H> fmt::Debug for HashMemoVerified<T, H>
+where
+    T: Eq + Hash + fmt::Debug,
+    H: BuildHasher + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("HashMemoVerified").field(&self.0).finish()
+    }
+}
+
+impl<T, H> From<HashMemo<T, H>> for HashMemoVerified<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    fn from(memo: HashMemo<T, H>) -> Self {
+        Self(memo)
+    }
+}
+
+impl<T, H> Serialize for HashMemoVerified<T, H>
+where
+    T: Eq + Hash + Serialize,
+    H: BuildHasher,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        TaggedRef {
+            value: &self.0.value,
+            hash: self.0.memoized_hash(),
+            hasher: hasher_tag(&self.0.hasher),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T, H> Deserialize<'de> for HashMemoVerified<T, H>
+where
+    T: Eq + Hash + Deserialize<'de>,
+    H: BuildHasher + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let tagged = TaggedOwned::<T>::deserialize(deserializer)?;
+        let persisted = tagged.hash.ok_or_else(|| D::Error::missing_field("hash"))?;
+        let memo = HashMemo::with_hasher(tagged.value, H::default());
+        let computed = remap_zero(memo.hasher.hash_one(&memo.value));
+        if computed != persisted {
+            return Err(D::Error::custom(format_args!(
+                "hash mismatch: persisted {persisted:#018x}, computed {computed:#018x}"
+            )));
+        }
+        memo.hash.store(computed, Ordering::Relaxed);
+        Ok(Self(memo))
+    }
+}
+
+/// A `BuildHasher` that can be persisted as a seed and rebuilt from it.
+///
+/// Implemented by the unseeded hashers, whose seed is `()`:
+/// `BuildHasherDefault`, [`FixedState`] and [`PassthroughState`], and by
+/// [`LengthPrefixed`] over any `SeededState`. Hashers that do not expose
+/// their keys, such as std's `RandomState` or `ahash::RandomState`, cannot
+/// implement it directly; wrap them together with the seed they were built
+/// from instead.
+///
+/// `from_seed(state.seed())` must build a hasher that produces the same
+/// hashes as `state`.
+pub trait SeededState: BuildHasher {
+    /// The serializable seed.
+    type Seed: Serialize + DeserializeOwned;
+
+    /// Returns the seed this hasher was built from.
+    fn seed(&self) -> Self::Seed;
+
+    /// Rebuilds the hasher from a seed returned by [`seed`](Self::seed).
+    fn from_seed(seed: Self::Seed) -> Self;
+}
+
+impl<H: std::hash::Hasher + Default> SeededState for BuildHasherDefault<H> {
+    type Seed = ();
+
+    fn seed(&self) {}
+
+    fn from_seed((): ()) -> Self {
+        Self::default()
+    }
+}
+
+impl SeededState for FixedState {
+    type Seed = ();
+
+    fn seed(&self) {}
+
+    fn from_seed((): ()) -> Self {
+        FixedState
+    }
+}
+
+impl SeededState for PassthroughState {
+    type Seed = ();
+
+    fn seed(&self) {}
+
+    fn from_seed((): ()) -> Self {
+        PassthroughState
+    }
+}
+
+impl<S: SeededState> SeededState for LengthPrefixed<S> {
+    type Seed = S::Seed;
+
+    fn seed(&self) -> S::Seed {
+        self.0.seed()
+    }
+
+    fn from_seed(seed: S::Seed) -> Self {
+        Self::new(S::from_seed(seed))
+    }
+}
+
+/// Serializes a [`HashMemo`] with its cached hash and its hasher's seed.
+///
+/// Deserializing rebuilds the hasher with [`SeededState::from_seed`]. The
+/// `seed` field is required, since without it the hasher cannot be rebuilt.
+/// As with [`HashMemoTagged`], the `hash` and `hasher` fields are optional
+/// and the cache is seeded only when the `hasher` fingerprint matches the
+/// rebuilt hasher, so a `SeededState` whose seed does not capture its whole
+/// state, or a hasher whose algorithm changed between builds, reloads the
+/// memo cold rather than with a wrong hash.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{FixedState, HashMemo, HashMemoSeeded};
+///
+/// let seeded = HashMemoSeeded(HashMemo::new_fixed("key".to_string()));
+/// let json = serde_json::to_string(&seeded).unwrap();
+///
+/// let HashMemoSeeded(memo): HashMemoSeeded<String, FixedState> =
+///     serde_json::from_str(&json).unwrap();
+/// assert_eq!(memo.into_inner(), "key");
+/// ```
+#[derive(Clone)]
+pub struct HashMemoSeeded<T, H>(pub HashMemo<T, H>)
+where
+    T: Eq + Hash,
+    H: SeededState;
+
+impl<T, H> fmt::Debug for HashMemoSeeded<T, H>
+where
+    T: Eq + Hash + fmt::Debug,
+    H: SeededState + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("HashMemoSeeded").field(&self.0).finish()
+    }
+}
+
+impl<T, H> From<HashMemo<T, H>> for HashMemoSeeded<T, H>
+where
+    T: Eq + Hash,
+    H: SeededState,
+{
+    fn from(memo: HashMemo<T, H>) -> Self {
+        Self(memo)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "::serde", rename = "HashMemo")]
+struct SeededRef<'a, T, S> {
+    value: &'a T,
+    hash: u64,
+    seed: S,
+    hasher: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "::serde", rename = "HashMemo")]
+struct SeededOwned<T, S> {
+    value: T,
+    #[serde(default)]
+    hash: Option<u64>,
+    seed: S,
+    #[serde(default)]
+    hasher: Option<u64>,
+}
+
+impl<T, H> Serialize for HashMemoSeeded<T, H>
+where
+    T: Eq + Hash + Serialize,
+    H: SeededState,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SeededRef {
+            value: &self.0.value,
+            hash: self.0.memoized_hash(),
+            seed: self.0.hasher.seed(),
+            hasher: hasher_tag(&self.0.hasher),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T, H> Deserialize<'de> for HashMemoSeeded<T, H>
+where
+    T: Eq + Hash + Deserialize<'de>,
+    H: SeededState,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let seeded = SeededOwned::<T, H::Seed>::deserialize(deserializer)?;
+        let memo = HashMemo::with_hasher(seeded.value, H::from_seed(seeded.seed));
+        if let (Some(hash), Some(tag)) = (seeded.hash, seeded.hasher) {
+            if hash != 0 && tag == hasher_tag(&memo.hasher) {
+                memo.hash.store(hash, Ordering::Relaxed);
+            }
+        }
+        Ok(Self(memo))
+    }
+}
+
+/// A `Vec` of memos serialized column by column, with the [`columnar`]
+/// format: all values, then the cached hashes of primed memos.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{ColumnarMemos, HashMemo};
+///
+/// let memos = ColumnarMemos(vec![HashMemo::new(1u32), HashMemo::new(2)]);
+/// let json = serde_json::to_string(&memos).unwrap();
+/// assert!(json.starts_with(r#"{"values":[1,2],"hashes":[],"primed":[0],"#));
+///
+/// let ColumnarMemos(memos): ColumnarMemos<u32> = serde_json::from_str(&json).unwrap();
+/// assert_eq!(memos.len(), 2);
+/// ```
+#[derive(Clone)]
+pub struct ColumnarMemos<T, H = BuildHasherDefault<DefaultHasher>>(pub Vec<HashMemo<T, H>>)
+where
+    T: Eq + Hash,
+    H: BuildHasher;
+
+impl<T, H> fmt::Debug for ColumnarMemos<T, H>
+where
+    T: Eq + Hash + fmt::Debug,
+    H: BuildHasher + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ColumnarMemos").field(&self.0).finish()
+    }
+}
+
+impl<T, H> From<Vec<HashMemo<T, H>>> for ColumnarMemos<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    fn from(memos: Vec<HashMemo<T, H>>) -> Self {
+        Self(memos)
+    }
+}
+
+impl<T, H> Serialize for ColumnarMemos<T, H>
+where
+    T: Eq + Hash + Serialize,
+    H: BuildHasher,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        columnar::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de, T, H> Deserialize<'de> for ColumnarMemos<T, H>
+where
+    T: Eq + Hash + Deserialize<'de>,
+    H: BuildHasher + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        columnar::deserialize(deserializer).map(Self)
+    }
+}
+
+pub mod columnar {
+    //! Columnar serialization of collections of memos, for
+    //! `#[serde(with = "hashmemo::serde::columnar")]`.
+    //!
+    //! A collection of memos is written as one struct of parallel arrays
+    //! rather than as a sequence of per-memo records:
+    //!
+    //! ```json
+    //! { "values": [...], "hashes": [...], "primed": [...], "hasher": 123 }
+    //! ```
+    //!
+    //! - `values` holds every value, in iteration order.
+    //! - `primed` is a bitmap of the entries whose hash is cached, as `u64`
+    //!   words: entry `i` is bit `i % 64`, counting from the least significant
+    //!   bit, of word `i / 64`. It has exactly `ceil(len / 64)` words, and bits
+    //!   past the last entry are clear.
+    //! - `hashes` holds the cached hash of each primed entry, in entry order, so
+    //!   it has one element per set bit. Cold memos are written as they are;
+    //!   serializing never computes a hash.
+    //! - `hasher` is the fingerprint of the first memo's hasher, as in
+    //!   [`HashMemoTagged`](crate::HashMemoTagged), or `0` for an empty
+    //!   collection. Memos whose hasher has a different fingerprint are written
+    //!   as cold.
+    //!
+    //! Deserializing builds each hasher via `Default` and seeds the caches of
+    //! primed entries when the decoded hasher's fingerprint matches. Input
+    //! whose bitmap does not fit the number of values or the number of hashes
+    //! is rejected.
+    //!
+    //! Any collection whose references iterate over memos, and which can be
+    //! collected from them, works: `Vec`, `Box<[_]>`, `VecDeque`, and `HashSet`
+    //! or `BTreeSet` of memos. [`ColumnarMemos`](crate::ColumnarMemos)
+    //! wraps a `Vec` for use outside of a derive.
+    //!
+    //! # Examples
+    //!
+    //! ```rust
+    //! use hashmemo::HashMemo;
+    //! use serde::{Deserialize, Serialize};
+    //!
+    //! #[derive(Serialize, Deserialize)]
+    //! struct Index {
+    //!     #[serde(with = "hashmemo::serde::columnar")]
+    //!     keys: Vec<HashMemo<String>>,
+    //! }
+    //!
+    //! let index = Index {
+    //!     keys: vec![HashMemo::new("a".to_string()), HashMemo::new("b".to_string())],
+    //! };
+    //! index.keys[1].precompute();
+    //! let json = serde_json::to_string(&index).unwrap();
+    //!
+    //! let decoded: Index = serde_json::from_str(&json).unwrap();
+    //! assert_eq!(decoded.keys, index.keys);
+    //! ```
+
+    use std::hash::{BuildHasher, Hash};
+    use std::sync::atomic::Ordering;
+
+    use ::serde::de::Error as _;
+    use ::serde::ser::SerializeStruct;
+    use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::{hasher_tag, HashMemo};
+
+    /// The values of a collection of memos, serialized as a sequence.
+    struct Values<'a, C>(&'a C);
+
+    impl<'a, C, T, H> Serialize for Values<'a, C>
+    where
+        &'a C: IntoIterator<Item = &'a HashMemo<T, H>>,
+        T: Eq + Hash + Serialize + 'a,
+        H: BuildHasher + 'a,
+    {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.0.into_iter().map(|memo| &memo.value))
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(crate = "::serde", rename = "ColumnarMemos")]
+    struct ColumnsOwned<T> {
+        values: Vec<T>,
+        hashes: Vec<u64>,
+        primed: Vec<u64>,
+        hasher: u64,
+    }
+
+    /// Serializes the memos of `memos` as parallel arrays.
+    pub fn serialize<'a, C, T, H, S>(memos: &'a C, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        &'a C: IntoIterator<Item = &'a HashMemo<T, H>>,
+        T: Eq + Hash + Serialize + 'a,
+        H: BuildHasher + 'a,
+        S: Serializer,
+    {
+        let tag = memos
+            .into_iter()
+            .next()
+            .map_or(0, |memo| hasher_tag(&memo.hasher));
+
+        let mut hashes = Vec::new();
+        let mut primed = Vec::<u64>::new();
+        for (i, memo) in memos.into_iter().enumerate() {
+            if i % 64 == 0 {
+                primed.push(0);
+            }
+            let hash = memo.hash.load(Ordering::Relaxed);
+            if hash != 0 && hasher_tag(&memo.hasher) == tag {
+                hashes.push(hash);
+                primed[i / 64] |= 1 << (i % 64);
+            }
+        }
+
+        let mut columns = serializer.serialize_struct("ColumnarMemos", 4)?;
+        columns.serialize_field("values", &Values(memos))?;
+        columns.serialize_field("hashes", &hashes)?;
+        columns.serialize_field("primed", &primed)?;
+        columns.serialize_field("hasher", &tag)?;
+        columns.end()
+    }
+
+    /// Deserializes parallel arrays into a collection of memos, seeding the
+    /// caches of primed entries.
+    pub fn deserialize<'de, C, T, H, D>(deserializer: D) -> Result<C, D::Error>
+    where
+        C: FromIterator<HashMemo<T, H>>,
+        T: Eq + Hash + Deserialize<'de>,
+        H: BuildHasher + Default,
+        D: Deserializer<'de>,
+    {
+        let columns = ColumnsOwned::<T>::deserialize(deserializer)?;
+        let len = columns.values.len();
+
+        let words = len.div_ceil(64);
+        if columns.primed.len() != words {
+            return Err(D::Error::custom(format_args!(
+                "primed bitmap has {} words, but {len} values need {words}",
+                columns.primed.len()
+            )));
+        }
+        if let Some(&last) = columns.primed.last() {
+            if len % 64 != 0 && last >> (len % 64) != 0 {
+                return Err(D::Error::custom(format_args!(
+                    "primed bitmap marks entries past the {len} values"
+                )));
+            }
+        }
+        let marked: usize = columns
+            .primed
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum();
+        if marked != columns.hashes.len() {
+            return Err(D::Error::custom(format_args!(
+                "primed bitmap marks {marked} entries, but there are {} hashes",
+                columns.hashes.len()
+            )));
+        }
+
+        let mut hashes = columns.hashes.into_iter();
+        Ok(columns
+            .values
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let memo = HashMemo::with_hasher(value, H::default());
+                if columns.primed[i / 64] >> (i % 64) & 1 == 1 {
+                    let hash = hashes.next().expect("hashes match the bitmap");
+                    if hash != 0 && hasher_tag(&memo.hasher) == columns.hasher {
+                        memo.hash.store(hash, Ordering::Relaxed);
+                    }
+                }
+                memo
+            })
+            .collect())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::collections::{BTreeSet, HashSet};
+        use std::hash::{BuildHasher, RandomState};
+        use std::sync::atomic::Ordering;
+
+        use ::serde::{Deserialize, Serialize};
+
+        use crate::{ColumnarMemos, FixedState, HashMemo, MemoBuildHasher, PassthroughState};
+
+        #[derive(Serialize, Deserialize)]
+        #[serde(crate = "::serde")]
+        struct Collections {
+            #[serde(with = "super")]
+            vec: Vec<HashMemo<String, FixedState>>,
+            #[serde(with = "super")]
+            boxed: Box<[HashMemo<u32, FixedState>]>,
+            #[serde(with = "super")]
+            set: HashSet<HashMemo<String, FixedState>, MemoBuildHasher>,
+            #[serde(with = "super")]
+            ordered: BTreeSet<HashMemo<u64, FixedState>>,
+        }
+
+        fn strings(n: usize) -> Vec<HashMemo<String, FixedState>> {
+            (0..n)
+                .map(|i| HashMemo::new_fixed(format!("value-{i}")))
+                .collect()
+        }
+
+        /// Returns the cached hashes, `0` for cold memos.
+        fn cached<'a, T: Eq + std::hash::Hash + 'a>(
+            memos: impl IntoIterator<Item = &'a HashMemo<T, FixedState>>,
+        ) -> Vec<u64> {
+            memos
+                .into_iter()
+                .map(|memo| memo.hash.load(Ordering::Relaxed))
+                .collect()
+        }
+
+        #[test]
+        fn collections_round_trip() {
+            let vec = strings(150);
+            for memo in vec.iter().step_by(3) {
+                memo.precompute();
+            }
+            let boxed: Box<[_]> = (0..5u32).map(HashMemo::new_fixed).collect();
+            boxed[4].precompute();
+            let set: HashSet<_, MemoBuildHasher> = strings(10).into_iter().collect();
+            let ordered: BTreeSet<_> = (0..3u64).map(HashMemo::new_fixed).collect();
+            let collections = Collections {
+                vec,
+                boxed,
+                set,
+                ordered,
+            };
+
+            let json = serde_json::to_string(&collections).unwrap();
+            let decoded: Collections = serde_json::from_str(&json).unwrap();
+            assert_eq!(decoded.vec, collections.vec);
+            assert_eq!(decoded.boxed, collections.boxed);
+            assert_eq!(decoded.set, collections.set);
+            assert_eq!(decoded.ordered, collections.ordered);
+
+            // Primed entries come back primed, cold entries cold.
+            assert_eq!(cached(&decoded.vec), cached(&collections.vec));
+            assert_eq!(cached(&*decoded.boxed), cached(&*collections.boxed));
+            for memo in &decoded.vec {
+                assert_eq!(memo.memoized_hash(), FixedState.hash_one(memo.as_ref()));
+            }
+        }
+
+        #[test]
+        fn wrapper_round_trips() {
+            let memos = ColumnarMemos(strings(3));
+            memos.0[0].precompute();
+            let json = serde_json::to_string(&memos).unwrap();
+            let decoded: ColumnarMemos<String, FixedState> = serde_json::from_str(&json).unwrap();
+            assert_eq!(decoded.0, memos.0);
+            assert_eq!(cached(&decoded.0), cached(&memos.0));
+
+            let empty: ColumnarMemos<String> = ColumnarMemos(Vec::new());
+            let json = serde_json::to_string(&empty).unwrap();
+            assert_eq!(json, r#"{"values":[],"hashes":[],"primed":[],"hasher":0}"#);
+            let decoded: ColumnarMemos<String> = serde_json::from_str(&json).unwrap();
+            assert!(decoded.0.is_empty());
+        }
+
+        #[cfg(not(feature = "disable-cache"))]
+        #[test]
+        fn json_shape_is_stable() {
+            // A passthrough hasher hashes a `u64` to itself, so the hashes are
+            // the values, and the fingerprint is `b"hashmemo"` read as a
+            // little-endian `u64`.
+            let memos: Vec<HashMemo<u64, PassthroughState>> = (7..10)
+                .map(|i| HashMemo::with_hasher(i, PassthroughState))
+                .collect();
+            memos[0].precompute();
+            memos[2].precompute();
+
+            let json = serde_json::to_string(&ColumnarMemos(memos)).unwrap();
+            assert_eq!(
+                json,
+                r#"{"values":[7,8,9],"hashes":[7,9],"primed":[5],"hasher":8029185231249695080}"#
+            );
+        }
+
+        #[cfg(not(feature = "disable-cache"))]
+        #[test]
+        fn bitmap_spans_words() {
+            let memos = strings(130);
+            memos[0].precompute();
+            memos[64].precompute();
+            memos[129].precompute();
+
+            let json: serde_json::Value =
+                serde_json::to_value(ColumnarMemos(memos.clone())).unwrap();
+            assert_eq!(json["primed"], serde_json::json!([1, 1, 2]));
+            assert_eq!(json["hashes"].as_array().unwrap().len(), 3);
+
+            let decoded: ColumnarMemos<String, FixedState> = serde_json::from_value(json).unwrap();
+            assert_eq!(cached(&decoded.0), cached(&memos));
+        }
+
+        #[test]
+        fn mismatched_columns_are_rejected() {
+            let error = |json: &str| {
+                serde_json::from_str::<ColumnarMemos<u64>>(json)
+                    .unwrap_err()
+                    .to_string()
+            };
+
+            assert!(
+                error(r#"{"values":[1,2],"hashes":[],"primed":[],"hasher":0}"#)
+                    .contains("primed bitmap has 0 words, but 2 values need 1")
+            );
+            assert!(
+                error(r#"{"values":[1,2],"hashes":[3],"primed":[4],"hasher":0}"#)
+                    .contains("marks entries past the 2 values")
+            );
+            assert!(
+                error(r#"{"values":[1,2],"hashes":[3],"primed":[3],"hasher":0}"#)
+                    .contains("marks 2 entries, but there are 1 hashes")
+            );
+            assert!(
+                error(r#"{"values":[1],"hashes":[],"primed":[],"hasher":0}"#).contains("0 words")
+            );
+        }
+
+        #[cfg(not(feature = "disable-cache"))]
+        #[test]
+        fn foreign_hashers_load_cold() {
+            let memos: Vec<_> = (0..4u64)
+                .map(|i| HashMemo::with_hasher(i, RandomState::new()))
+                .collect();
+            for memo in &memos {
+                memo.precompute();
+            }
+            let json = serde_json::to_value(ColumnarMemos(memos)).unwrap();
+            // Each `RandomState` has its own fingerprint, so only the first
+            // memo's hash is written, and a fresh `RandomState` matches none.
+            assert_eq!(json["hashes"].as_array().unwrap().len(), 1);
+
+            let decoded: ColumnarMemos<u64, RandomState> = serde_json::from_value(json).unwrap();
+            for memo in &decoded.0 {
+                assert_eq!(memo.hash.load(Ordering::Relaxed), 0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::RandomState;
+
+    use super::*;
+    use crate::FixedState;
+
+    #[test]
+    fn transparent_json_matches_inner_value() {
+        let memo = HashMemo::new(vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(serde_json::to_string(&memo).unwrap(), r#"["a","b"]"#);
+
+        let decoded: HashMemo<Vec<String>> = serde_json::from_str(r#"["a","b"]"#).unwrap();
+        assert_eq!(decoded.hash.load(Ordering::Relaxed), 0);
+        assert_eq!(decoded, memo);
+        assert_eq!(decoded.memoized_hash(), memo.memoized_hash());
+    }
+
+    #[test]
+    fn entries_serialize_key_and_value() {
+        let entry = MemoEntry::new("ada".to_string(), vec![1, 2]);
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(json, r#"{"key":"ada","value":[1,2]}"#);
+
+        let decoded: MemoEntry<String, Vec<u32>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, entry);
+        assert_eq!(decoded.value(), &vec![1, 2]);
+        assert_eq!(
+            decoded.memo_key().memoized_hash(),
+            entry.memo_key().memoized_hash()
+        );
+    }
+
+    #[test]
+    fn default_fields_can_be_skipped() {
+        #[derive(Serialize, Deserialize)]
+        struct Entry {
+            name: HashMemo<String>,
+            #[serde(default, skip_serializing_if = "HashMemo::is_default")]
+            tags: HashMemo<Vec<String>>,
+        }
+
+        let entry = Entry {
+            name: HashMemo::new("key".to_string()),
+            tags: HashMemo::default(),
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(json, r#"{"name":"key"}"#);
+
+        let decoded: Entry = serde_json::from_str(&json).unwrap();
+        assert!(decoded.tags.is_default());
+        assert_eq!(decoded.tags, entry.tags);
+
+        let tagged = Entry {
+            tags: HashMemo::new(vec!["a".to_string()]),
+            ..entry
+        };
+        assert_eq!(
+            serde_json::to_string(&tagged).unwrap(),
+            r#"{"name":"key","tags":["a"]}"#
+        );
+    }
+
+    #[test]
+    fn memoized_json_matches_inner_value() {
+        let memo = Memoized::new(vec!["a".to_string()]);
+        let json = serde_json::to_string(&memo).unwrap();
+        assert_eq!(json, r#"["a"]"#);
+
+        let decoded: Memoized<Vec<String>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, memo);
+    }
+
+    #[test]
+    fn memo_string_json_is_a_plain_string() {
+        let key: MemoString = "key".into();
+        assert_eq!(serde_json::to_string(&key).unwrap(), r#""key""#);
+
+        let decoded: MemoString = serde_json::from_str(r#""key""#).unwrap();
+        assert_eq!(decoded, key);
+    }
+
+    #[test]
+    fn memo_bytes_json_matches_a_vec() {
+        let key = MemoBytes::new(vec![1, 2]);
+        assert_eq!(serde_json::to_string(&key).unwrap(), "[1,2]");
+
+        let decoded: MemoBytes = serde_json::from_str("[1,2]").unwrap();
+        assert_eq!(decoded, key);
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn tagged_json_carries_value_hash_and_hasher() {
+        let memo = HashMemo::new("key".to_string());
+        let hash = memo.memoized_hash();
+        let json = serde_json::to_value(HashMemoTagged(memo)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "value": "key",
+                "hash": hash,
+                "hasher": hasher_tag(&BuildHasherDefault::<DefaultHasher>::default()),
+            })
+        );
+
+        let HashMemoTagged(decoded): HashMemoTagged<String> = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.value, "key");
+        assert_eq!(decoded.hash.load(Ordering::Relaxed), hash);
+    }
+
+    #[test]
+    fn tagged_json_accepts_value_only() {
+        let HashMemoTagged(decoded): HashMemoTagged<u32> =
+            serde_json::from_str(r#"{ "value": 7 }"#).unwrap();
+        assert_eq!(decoded.value, 7);
+        assert_eq!(decoded.hash.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn tagged_json_from_a_different_hasher_reloads_cold() {
+        let json = serde_json::to_string(&HashMemoTagged(HashMemo::new(7u32))).unwrap();
+        let HashMemoTagged(decoded): HashMemoTagged<u32, RandomState> =
+            serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.value, 7);
+        assert_eq!(decoded.hash.load(Ordering::Relaxed), 0);
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn verified_accepts_a_matching_hash() {
+        let memo = HashMemo::new("key".to_string());
+        let hash = memo.memoized_hash();
+        let json = serde_json::to_string(&HashMemoVerified(memo)).unwrap();
+
+        let HashMemoVerified(decoded): HashMemoVerified<String> =
+            serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.value, "key");
+        assert_eq!(decoded.hash.load(Ordering::Relaxed), hash);
+    }
+
+    #[test]
+    fn verified_rejects_a_mismatched_hash() {
+        let json = serde_json::to_value(HashMemoTagged(HashMemo::new("key".to_string()))).unwrap();
+
+        let mut corrupted = json.clone();
+        corrupted["value"] = "kez".into();
+        let err = serde_json::from_value::<HashMemoVerified<String>>(corrupted).unwrap_err();
+        assert!(err.to_string().contains("hash mismatch"), "{err}");
+
+        let other_hasher = serde_json::from_value::<HashMemoVerified<String, FixedState>>(json);
+        assert!(other_hasher.is_err());
+
+        let missing = serde_json::from_str::<HashMemoVerified<String>>(r#"{ "value": "key" }"#);
+        assert!(missing
+            .unwrap_err()
+            .to_string()
+            .contains("missing field `hash`"));
+    }
+
+    /// An `ahash::RandomState` that remembers the keys it was built from.
+    #[derive(Clone, Debug)]
+    struct SeededAHash {
+        keys: [u64; 4],
+        state: ahash::RandomState,
+    }
+
+    impl SeededAHash {
+        fn new(keys: [u64; 4]) -> Self {
+            let [k0, k1, k2, k3] = keys;
+            let state = ahash::RandomState::with_seeds(k0, k1, k2, k3);
+            Self { keys, state }
+        }
+    }
+
+    impl BuildHasher for SeededAHash {
+        type Hasher = ahash::AHasher;
+
+        fn build_hasher(&self) -> ahash::AHasher {
+            self.state.build_hasher()
+        }
+    }
+
+    impl SeededState for SeededAHash {
+        type Seed = [u64; 4];
+
+        fn seed(&self) -> [u64; 4] {
+            self.keys
+        }
+
+        fn from_seed(seed: [u64; 4]) -> Self {
+            Self::new(seed)
+        }
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn seeded_round_trip_rebuilds_the_hasher_and_trusts_the_hash() {
+        let keys = [1, 2, 3, 0x5eed];
+        let memo = HashMemo::with_hasher("key".to_string(), SeededAHash::new(keys));
+        let hash = memo.memoized_hash();
+
+        let tag = hasher_tag(&memo.hasher);
+        let json = serde_json::to_value(HashMemoSeeded(memo)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "value": "key", "hash": hash, "seed": keys, "hasher": tag })
+        );
+
+        let HashMemoSeeded(decoded): HashMemoSeeded<String, SeededAHash> =
+            serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.hasher.keys, keys);
+        assert_eq!(decoded.hash.load(Ordering::Relaxed), hash);
+        assert_eq!(
+            remap_zero(decoded.hasher.hash_one(&decoded.value)),
+            hash,
+            "the rebuilt hasher agrees with the persisted hash"
+        );
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn seeded_trusts_the_hash_only_with_a_matching_fingerprint() {
+        let memo = HashMemo::with_hasher(7u32, SeededAHash::new([1, 2, 3, 4]));
+        let mut json = serde_json::to_value(HashMemoSeeded(memo)).unwrap();
+
+        // A seed that no longer rebuilds the same hasher.
+        json["seed"] = serde_json::json!([5, 6, 7, 8]);
+        let HashMemoSeeded(decoded): HashMemoSeeded<u32, SeededAHash> =
+            serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(decoded.hash.load(Ordering::Relaxed), 0);
+
+        // Data written without a fingerprint is not trusted either.
+        json["seed"] = serde_json::json!([1, 2, 3, 4]);
+        json.as_object_mut().unwrap().remove("hasher");
+        let HashMemoSeeded(decoded): HashMemoSeeded<u32, SeededAHash> =
+            serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.hash.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn seeded_requires_the_seed() {
+        let HashMemoSeeded(decoded): HashMemoSeeded<u32, LengthPrefixed<FixedState>> =
+            serde_json::from_str(r#"{ "value": 7, "seed": null }"#).unwrap();
+        assert_eq!(decoded.hash.load(Ordering::Relaxed), 0);
+
+        let missing = serde_json::from_str::<HashMemoSeeded<u32, SeededAHash>>(r#"{ "value": 7 }"#);
+        assert!(missing
+            .unwrap_err()
+            .to_string()
+            .contains("missing field `seed`"));
+    }
+}
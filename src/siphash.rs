@@ -0,0 +1,171 @@
+//! [`siphasher`](::siphasher) support, for keyed hashing of adversarial
+//! inputs.
+//!
+//! `DefaultHasher::new()` is SipHash with a fixed, public key, so anyone can
+//! precompute values that collide under it. [`SipHashMemo`] memoizes with
+//! SipHash-1-3 under a caller-supplied 128-bit key, through [`SipHashState`];
+//! [`SipHash24Memo`] uses the more conservative SipHash-2-4 through
+//! [`SipHash24State`].
+//!
+//! SipHash is a keyed pseudorandom function: without the key, an attacker
+//! cannot predict the hash of a value, so cannot feed a map keys chosen to
+//! land in the same bucket and degrade its lookups to linear scans. That
+//! only holds while the key stays secret. Draw it from a random source,
+//! never from a constant or a timestamp, and do not expose cached hashes to
+//! untrusted parties, as through the `serde` tagged forms. Memos compared or
+//! looked up together must share the key.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::HashMemo;
+//! use std::collections::HashMap;
+//!
+//! let (k0, k1) = (0x0706_0504_0302_0100, 0x0f0e_0d0c_0b0a_0908);
+//! let mut map = HashMap::new();
+//! map.insert(HashMemo::new_siphash("untrusted".to_string(), k0, k1), 1);
+//! assert_eq!(map.get(&HashMemo::new_siphash("untrusted".to_string(), k0, k1)), Some(&1));
+//! ```
+
+use std::hash::{BuildHasher, Hash};
+
+use ::siphasher::sip::{SipHasher13, SipHasher24};
+
+use crate::HashMemo;
+
+/// A [`HashMemo`] hashing with keyed SipHash-1-3.
+pub type SipHashMemo<T> = HashMemo<T, SipHashState>;
+
+/// A [`HashMemo`] hashing with keyed SipHash-2-4.
+pub type SipHash24Memo<T> = HashMemo<T, SipHash24State>;
+
+/// A `BuildHasher` for SipHash-1-3 with a fixed 128-bit key.
+///
+/// The default key is all zeroes, which gives no DoS resistance.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SipHashState {
+    k0: u64,
+    k1: u64,
+}
+
+impl SipHashState {
+    /// Creates a `SipHashState` building hashers keyed with `k0` and `k1`.
+    #[inline]
+    pub const fn with_keys(k0: u64, k1: u64) -> Self {
+        Self { k0, k1 }
+    }
+}
+
+impl BuildHasher for SipHashState {
+    type Hasher = SipHasher13;
+
+    #[inline]
+    fn build_hasher(&self) -> SipHasher13 {
+        SipHasher13::new_with_keys(self.k0, self.k1)
+    }
+}
+
+/// A `BuildHasher` for SipHash-2-4 with a fixed 128-bit key.
+///
+/// The default key is all zeroes, which gives no DoS resistance.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SipHash24State {
+    k0: u64,
+    k1: u64,
+}
+
+impl SipHash24State {
+    /// Creates a `SipHash24State` building hashers keyed with `k0` and `k1`.
+    #[inline]
+    pub const fn with_keys(k0: u64, k1: u64) -> Self {
+        Self { k0, k1 }
+    }
+}
+
+impl BuildHasher for SipHash24State {
+    type Hasher = SipHasher24;
+
+    #[inline]
+    fn build_hasher(&self) -> SipHasher24 {
+        SipHasher24::new_with_keys(self.k0, self.k1)
+    }
+}
+
+impl<T> HashMemo<T, SipHashState>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new `HashMemo` using SipHash-1-3 keyed with `k0` and `k1`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::{HashMemo, SipHashMemo};
+    ///
+    /// let memo: SipHashMemo<&str> = HashMemo::new_siphash("hello world", 0x5eed, 0xc0ffee);
+    /// ```
+    pub const fn new_siphash(value: T, k0: u64, k1: u64) -> Self {
+        Self::with_hasher(value, SipHashState::with_keys(k0, k1))
+    }
+}
+
+impl<T> HashMemo<T, SipHash24State>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new `HashMemo` using SipHash-2-4 keyed with `k0` and `k1`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::{HashMemo, SipHash24Memo};
+    ///
+    /// let memo: SipHash24Memo<&str> = HashMemo::new_siphash24("hello world", 0x5eed, 0xc0ffee);
+    /// ```
+    pub const fn new_siphash24(value: T, k0: u64, k1: u64) -> Self {
+        Self::with_hasher(value, SipHash24State::with_keys(k0, k1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::remap_zero;
+
+    #[test]
+    fn works_as_a_hashmap_key() {
+        let mut map = HashMap::new();
+        for i in 0..100u32 {
+            map.insert(HashMemo::new_siphash(i.to_string().repeat(100), 1, 2), i);
+        }
+        for i in 0..100u32 {
+            let key = HashMemo::new_siphash(i.to_string().repeat(100), 1, 2);
+            assert_eq!(map.get(&key), Some(&i));
+        }
+        assert_eq!(
+            map.get(&HashMemo::new_siphash("absent".repeat(100), 1, 2)),
+            None
+        );
+    }
+
+    #[test]
+    fn different_keys_give_different_hashes() {
+        let value = "adversarial".to_string();
+        let a = HashMemo::new_siphash(value.clone(), 1, 2);
+        let b = HashMemo::new_siphash(value.clone(), 2, 1);
+        let a24 = HashMemo::new_siphash24(value.clone(), 1, 2);
+
+        assert_eq!(
+            a.memoized_hash(),
+            remap_zero(SipHashState::with_keys(1, 2).hash_one(&value))
+        );
+        assert_eq!(
+            a24.memoized_hash(),
+            remap_zero(SipHash24State::with_keys(1, 2).hash_one(&value))
+        );
+        assert_ne!(a.memoized_hash(), b.memoized_hash());
+        assert_ne!(a.memoized_hash(), a24.memoized_hash());
+    }
+}
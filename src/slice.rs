@@ -0,0 +1,154 @@
+//! Utilities over slices of memos.
+//!
+//! Warmup, persistence and monitoring code handles memos in bulk: priming a
+//! whole table before serving from it, saving the cached hashes alongside
+//! the values, or reporting how much of a table is still cold. The
+//! functions here do one pass over a slice each, sequentially.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::{slice, HashMemo};
+//!
+//! let memos: Vec<_> = (0..100).map(HashMemo::new).collect();
+//! assert_eq!(slice::cache_summary(&memos).cold, 100);
+//!
+//! slice::prime_all(&memos);
+//! # #[cfg(not(feature = "disable-cache"))]
+//! assert_eq!(slice::cache_summary(&memos).primed, 100);
+//! assert_eq!(slice::distinct_hash_count(&memos), 100);
+//! ```
+
+use std::collections::HashSet;
+use std::hash::{BuildHasher, Hash};
+use std::num::NonZeroU64;
+use std::sync::atomic::Ordering;
+
+use crate::HashMemo;
+
+/// How many memos of a slice have their hash cached, from
+/// [`cache_summary`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheSummary {
+    /// Memos whose hash is cached.
+    pub primed: usize,
+    /// Memos whose hash is yet to be computed.
+    pub cold: usize,
+}
+
+/// Computes and caches the hash of every cold memo in `items`.
+pub fn prime_all<T, H>(items: &[HashMemo<T, H>])
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    for memo in items {
+        memo.precompute();
+    }
+}
+
+/// Returns the cached hash of every memo in `items`, in order, or `None` for
+/// a cold memo.
+///
+/// Leaves the caches untouched. Cached hashes are never `0`, which is what
+/// an empty cache holds, hence the `NonZeroU64`.
+pub fn cached_hashes<T, H>(items: &[HashMemo<T, H>]) -> Vec<Option<NonZeroU64>>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    items
+        .iter()
+        .map(|memo| NonZeroU64::new(memo.hash.load(Ordering::Relaxed)))
+        .collect()
+}
+
+/// Counts the primed and cold memos in `items`, leaving the caches
+/// untouched.
+///
+/// Other threads may prime memos of a shared slice while it is counted, so
+/// the summary is a snapshot.
+pub fn cache_summary<T, H>(items: &[HashMemo<T, H>]) -> CacheSummary
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    let primed = items
+        .iter()
+        .filter(|memo| memo.hash.load(Ordering::Relaxed) != 0)
+        .count();
+    CacheSummary {
+        primed,
+        cold: items.len() - primed,
+    }
+}
+
+/// Returns the number of distinct memoized hashes in `items`, hashing, and
+/// so priming, any cold memos.
+///
+/// Equal values share a hash, so this estimates the number of distinct
+/// values, undercounting only by the values whose hashes collide.
+pub fn distinct_hash_count<T, H>(items: &[HashMemo<T, H>]) -> usize
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    items
+        .iter()
+        .map(HashMemo::memoized_hash)
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FixedState;
+
+    fn memos() -> Vec<HashMemo<String, FixedState>> {
+        (0..10)
+            .map(|i| HashMemo::new_fixed((i % 4).to_string().repeat(50)))
+            .collect()
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn summarizes_mixed_primed_and_cold_slices() {
+        let items = memos();
+        for memo in items.iter().step_by(3) {
+            memo.precompute();
+        }
+
+        assert_eq!(cache_summary(&items), CacheSummary { primed: 4, cold: 6 });
+        let hashes = cached_hashes(&items);
+        for (i, hash) in hashes.iter().enumerate() {
+            assert_eq!(hash.is_some(), i % 3 == 0);
+        }
+        assert_eq!(cache_summary(&items).cold, 6);
+        assert_eq!(
+            cache_summary::<String, FixedState>(&[]),
+            CacheSummary::default()
+        );
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn prime_all_matches_individual_hashing() {
+        let items = memos();
+        prime_all(&items);
+
+        assert_eq!(cache_summary(&items).cold, 0);
+        for (memo, hash) in items.iter().zip(cached_hashes(&items)) {
+            let fresh = memo.clone_without_hash();
+            assert_eq!(hash.map(NonZeroU64::get), Some(fresh.hash_one_cached()));
+        }
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn distinct_hash_count_counts_distinct_values() {
+        let items = memos();
+        assert_eq!(distinct_hash_count(&items), 4);
+        assert_eq!(cache_summary(&items).primed, 10);
+    }
+}
@@ -0,0 +1,117 @@
+//! [`smallvec`](::smallvec) and [`arrayvec`](::arrayvec) support for short
+//! sequence keys.
+//!
+//! `SmallVec` and `ArrayVec` hash exactly like the slice they hold, as does
+//! `Vec`, so memos over any of them with equal contents and the same hasher
+//! share a memoized hash, and `eq_vec` compares them with a memo over a
+//! `Vec`. Maps keyed by them can be probed with a `&[T]` through
+//! [`Probe`](crate::Probe).
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::{HashMemo, Probe};
+//! use smallvec::SmallVec;
+//! use std::collections::HashMap;
+//!
+//! let mut map = HashMap::new();
+//! map.insert(HashMemo::<SmallVec<[u32; 4]>>::from(&[1, 2, 3][..]), "short");
+//! assert_eq!(map.get(Probe::new(&[1u32, 2, 3][..]).as_lookup()), Some(&"short"));
+//! ```
+
+use std::hash::{BuildHasher, Hash};
+
+use ::arrayvec::ArrayVec;
+use ::smallvec::{Array, SmallVec};
+
+use crate::HashMemo;
+
+impl<A> From<&[A::Item]> for HashMemo<SmallVec<A>>
+where
+    A: Array,
+    A::Item: Eq + Hash + Clone,
+{
+    fn from(items: &[A::Item]) -> Self {
+        Self::new(SmallVec::from(items))
+    }
+}
+
+impl<A, H> HashMemo<SmallVec<A>, H>
+where
+    A: Array,
+    A::Item: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Returns whether this memo holds the same items as a memo over a
+    /// `Vec`.
+    ///
+    /// This stands in for `==`, which compares memos of one type only, so
+    /// that comparisons between memos infer their types.
+    pub fn eq_vec(&self, other: &HashMemo<Vec<A::Item>, H>) -> bool {
+        self.value.as_slice() == other.value.as_slice()
+    }
+}
+
+impl<T, H, const CAP: usize> HashMemo<ArrayVec<T, CAP>, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Returns whether this memo holds the same items as a memo over a
+    /// `Vec`.
+    ///
+    /// This stands in for `==`, which compares memos of one type only, so
+    /// that comparisons between memos infer their types.
+    pub fn eq_vec(&self, other: &HashMemo<Vec<T>, H>) -> bool {
+        self.value.as_slice() == other.value.as_slice()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::Probe;
+
+    #[test]
+    fn smallvec_and_vec_memos_agree() {
+        for ids in [&[1u32, 2][..], &[1, 2, 3, 4, 5, 6, 7, 8]] {
+            let small = HashMemo::<SmallVec<[u32; 4]>>::from(ids);
+            let vec = HashMemo::new(ids.to_vec());
+            assert!(small.eq_vec(&vec));
+            assert_eq!(small.memoized_hash(), vec.memoized_hash());
+
+            let small = HashMemo::new_fixed(SmallVec::<[u32; 4]>::from(ids));
+            let vec = HashMemo::new_fixed(ids.to_vec());
+            assert!(small.eq_vec(&vec));
+            assert_eq!(small.memoized_hash(), vec.memoized_hash());
+        }
+        assert!(!HashMemo::<SmallVec<[u32; 4]>>::from(&[1, 2][..])
+            .eq_vec(&HashMemo::new(vec![2u32, 1])));
+    }
+
+    #[test]
+    fn arrayvec_and_vec_memos_agree() {
+        let array: ArrayVec<u32, 4> = [7, 8, 9].into_iter().collect();
+        let array = HashMemo::new(array);
+        let vec = HashMemo::new(vec![7u32, 8, 9]);
+        assert!(array.eq_vec(&vec));
+        assert_eq!(array.memoized_hash(), vec.memoized_hash());
+    }
+
+    #[test]
+    fn slice_lookups_find_inline_and_spilled_keys() {
+        let mut set = HashSet::new();
+        set.insert(HashMemo::<SmallVec<[u32; 4]>>::from(&[1, 2][..]));
+        set.insert(HashMemo::<SmallVec<[u32; 4]>>::from(&[1, 2, 3, 4, 5][..]));
+
+        assert!(set.contains(Probe::new(&[1u32, 2][..]).as_lookup()));
+        assert!(set.contains(Probe::new(&[1u32, 2, 3, 4, 5][..]).as_lookup()));
+        assert!(!set.contains(Probe::new(&[1u32][..]).as_lookup()));
+
+        let mut set = HashSet::new();
+        set.insert(HashMemo::new(ArrayVec::from([3u8, 4])));
+        assert!(set.contains(Probe::new(&[3u8, 4][..]).as_lookup()));
+    }
+}
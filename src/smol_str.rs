@@ -0,0 +1,80 @@
+//! [`smol_str`](::smol_str) support for small-string keys.
+//!
+//! `SmolStr` hashes exactly like the `str` it holds, so a [`MemoSmolStr`]
+//! has the same memoized hash as a `HashMemo<String>` or `HashMemo<&str>`
+//! with the same text and the same hasher. Maps keyed by it can be probed
+//! with a `&str` through [`Probe`](crate::Probe).
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::{MemoSmolStr, Probe};
+//! use std::collections::HashMap;
+//!
+//! let mut map = HashMap::new();
+//! map.insert(MemoSmolStr::from("short"), 1);
+//! assert_eq!(map.get(Probe::new("short").as_lookup()), Some(&1));
+//! ```
+
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher};
+
+use ::smol_str::SmolStr;
+
+use crate::HashMemo;
+
+/// A [`HashMemo`] over a `smol_str::SmolStr`.
+pub type MemoSmolStr<H = BuildHasherDefault<DefaultHasher>> = HashMemo<SmolStr, H>;
+
+impl From<&str> for HashMemo<SmolStr> {
+    fn from(value: &str) -> Self {
+        Self::new(SmolStr::new(value))
+    }
+}
+
+impl From<String> for HashMemo<SmolStr> {
+    fn from(value: String) -> Self {
+        Self::new(SmolStr::from(value))
+    }
+}
+
+impl<H> AsRef<str> for HashMemo<SmolStr, H>
+where
+    H: BuildHasher,
+{
+    fn as_ref(&self) -> &str {
+        &self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::Probe;
+
+    #[test]
+    fn hashes_like_string_memos_with_the_same_text() {
+        for text in ["inline", "a key long enough to spill onto the heap"] {
+            let smol = MemoSmolStr::from(text);
+            assert_eq!(
+                smol.memoized_hash(),
+                HashMemo::new(text.to_string()).memoized_hash()
+            );
+            assert_eq!(smol.memoized_hash(), HashMemo::new(text).memoized_hash());
+            assert_eq!(AsRef::<str>::as_ref(&smol), text);
+            assert_eq!(smol.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn lookups_by_str() {
+        let mut set = HashSet::new();
+        set.insert(MemoSmolStr::from("inline"));
+        set.insert(MemoSmolStr::from("x".repeat(64)));
+
+        assert!(set.contains(Probe::new("inline").as_lookup()));
+        assert!(set.contains(Probe::new("x".repeat(64).as_str()).as_lookup()));
+        assert!(!set.contains(Probe::new("missing").as_lookup()));
+    }
+}
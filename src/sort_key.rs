@@ -0,0 +1,389 @@
+//! Memoized comparison keys, for values that are expensive to order.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher};
+use std::sync::atomic::Ordering as AtomicOrdering;
+use std::sync::OnceLock;
+
+use crate::cell;
+use crate::AtomicU64;
+
+/// Derives the key a [`SortKeyMemo`] compares, hashes and orders its value
+/// by.
+///
+/// Implemented by [`AsciiCaseInsensitive`] and [`Bytewise`], and by every
+/// `Fn(&T) -> K`, so a collation hook such as an ICU collator producing
+/// sort keys can be passed as a closure, or as a type holding the collator.
+/// Equal values must produce equal keys, and the key should be a pure
+/// function of the value: it is computed once and never refreshed.
+pub trait SortKey<T: ?Sized> {
+    /// The key values are ordered by.
+    type Key: Ord + Hash;
+
+    /// Returns the key of `value`.
+    fn sort_key(&self, value: &T) -> Self::Key;
+}
+
+impl<T, K, F> SortKey<T> for F
+where
+    T: ?Sized,
+    K: Ord + Hash,
+    F: Fn(&T) -> K,
+{
+    type Key = K;
+
+    #[inline]
+    fn sort_key(&self, value: &T) -> K {
+        self(value)
+    }
+}
+
+/// A [`SortKey`] ordering byte strings as their ASCII-lowercased bytes, so
+/// `"Apple"` and `"apple"` are equal and sort before `"banana"`. Non-ASCII
+/// bytes are compared as they are.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AsciiCaseInsensitive;
+
+impl<T> SortKey<T> for AsciiCaseInsensitive
+where
+    T: ?Sized + AsRef<[u8]>,
+{
+    type Key = Box<[u8]>;
+
+    #[inline]
+    fn sort_key(&self, value: &T) -> Box<[u8]> {
+        value.as_ref().to_ascii_lowercase().into_boxed_slice()
+    }
+}
+
+/// A [`SortKey`] ordering values as their bytes, for types whose own order
+/// is not bytewise or that have none.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Bytewise;
+
+impl<T> SortKey<T> for Bytewise
+where
+    T: ?Sized + AsRef<[u8]>,
+{
+    type Key = Box<[u8]>;
+
+    #[inline]
+    fn sort_key(&self, value: &T) -> Box<[u8]> {
+        value.as_ref().into()
+    }
+}
+
+/// A value with a memoized comparison key and a memoized hash of that key.
+///
+/// The key is derived by `S` the first time the memo is compared, ordered
+/// or hashed, and cached; so is its hash. Sorting `n` memos computes `n`
+/// keys instead of deriving two per comparison, and repeated sorts, range
+/// queries and lookups derive none.
+///
+/// `Eq`, `Ord` and `Hash` all go through the key, so two memos are equal
+/// when their keys are, even if their values differ, and the memo is a
+/// consistent `BTreeMap` and `HashMap` key under those semantics. Memos
+/// that meet in one collection must use equivalent policies. There is no
+/// `Borrow` impl, since the value's own `Eq` and `Hash` disagree with the
+/// key's: look entries up with a memo of the query.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{AsciiCaseInsensitive, SortKeyMemo};
+/// use std::collections::BTreeMap;
+///
+/// let mut headers = BTreeMap::new();
+/// headers.insert(SortKeyMemo::new("Content-Type", AsciiCaseInsensitive), "text/plain");
+/// headers.insert(SortKeyMemo::new("accept", AsciiCaseInsensitive), "*/*");
+///
+/// let query = SortKeyMemo::new("content-type", AsciiCaseInsensitive);
+/// assert_eq!(headers.get(&query), Some(&"text/plain"));
+/// let names: Vec<_> = headers.keys().map(|name| *name.as_ref()).collect();
+/// assert_eq!(names, ["accept", "Content-Type"]);
+/// ```
+///
+/// A caller-provided collation is any function from the value to an `Ord`
+/// key:
+///
+/// ```rust
+/// use hashmemo::SortKeyMemo;
+///
+/// let by_length = |word: &&str| (word.len(), word.to_string());
+/// let mut words: Vec<_> = ["ccc", "a", "bb"]
+///     .into_iter()
+///     .map(|word| SortKeyMemo::new(word, by_length))
+///     .collect();
+/// words.sort();
+/// assert_eq!(words.iter().map(|w| *w.as_ref()).collect::<Vec<_>>(), ["a", "bb", "ccc"]);
+/// ```
+pub struct SortKeyMemo<T, S, H = BuildHasherDefault<DefaultHasher>>
+where
+    S: SortKey<T>,
+    H: BuildHasher,
+{
+    value: T,
+    key: OnceLock<S::Key>,
+    hash: AtomicU64,
+    policy: S,
+    hasher: H,
+}
+
+impl<T, S> SortKeyMemo<T, S>
+where
+    S: SortKey<T>,
+{
+    /// Creates a memo keyed by `policy`, hashing its key with the default
+    /// hasher.
+    #[inline]
+    pub const fn new(value: T, policy: S) -> Self {
+        Self::with_hasher(value, policy, BuildHasherDefault::new())
+    }
+}
+
+impl<T, S, H> SortKeyMemo<T, S, H>
+where
+    S: SortKey<T>,
+    H: BuildHasher,
+{
+    /// Creates a memo keyed by `policy`, hashing its key with `hasher`.
+    #[inline]
+    pub const fn with_hasher(value: T, policy: S, hasher: H) -> Self {
+        Self {
+            value,
+            key: OnceLock::new(),
+            hash: AtomicU64::new(0),
+            policy,
+            hasher,
+        }
+    }
+
+    /// Returns the comparison key, computing and caching it on first use.
+    #[inline]
+    pub fn key(&self) -> &S::Key {
+        self.key.get_or_init(|| self.policy.sort_key(&self.value))
+    }
+
+    /// Returns the policy deriving the key.
+    #[inline]
+    pub fn policy(&self) -> &S {
+        &self.policy
+    }
+
+    /// Returns the value, dropping the key.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// Returns the hash of the key, computing and caching both on first
+    /// use.
+    fn memoized_hash(&self) -> u64 {
+        cell::get_or_compute(&self.hash, || self.hasher.hash_one(self.key()))
+    }
+}
+
+impl<T, S, H> AsRef<T> for SortKeyMemo<T, S, H>
+where
+    S: SortKey<T>,
+    H: BuildHasher,
+{
+    #[inline]
+    fn as_ref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, S, H> PartialEq for SortKeyMemo<T, S, H>
+where
+    S: SortKey<T>,
+    H: BuildHasher,
+{
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl<T, S, H> Eq for SortKeyMemo<T, S, H>
+where
+    S: SortKey<T>,
+    H: BuildHasher,
+{
+}
+
+impl<T, S, H> PartialOrd for SortKeyMemo<T, S, H>
+where
+    S: SortKey<T>,
+    H: BuildHasher,
+{
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, S, H> Ord for SortKeyMemo<T, S, H>
+where
+    S: SortKey<T>,
+    H: BuildHasher,
+{
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(other.key())
+    }
+}
+
+impl<T, S, H> Hash for SortKeyMemo<T, S, H>
+where
+    S: SortKey<T>,
+    H: BuildHasher,
+{
+    #[inline]
+    fn hash<H2: Hasher>(&self, state: &mut H2) {
+        state.write_u64(self.memoized_hash());
+    }
+}
+
+impl<T, S, H> Clone for SortKeyMemo<T, S, H>
+where
+    T: Clone,
+    S: SortKey<T> + Clone,
+    S::Key: Clone,
+    H: BuildHasher + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            key: self.key.clone(),
+            hash: AtomicU64::new(self.hash.load(AtomicOrdering::Relaxed)),
+            policy: self.policy.clone(),
+            hasher: self.hasher.clone(),
+        }
+    }
+}
+
+impl<T, S, H> fmt::Debug for SortKeyMemo<T, S, H>
+where
+    T: fmt::Debug,
+    S: SortKey<T>,
+    H: BuildHasher,
+{
+    /// Shows the value and whether the key is cached.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SortKeyMemo")
+            .field("value", &self.value)
+            .field("keyed", &self.key.get().is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap, HashSet};
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    const WORDS: [&str; 12] = [
+        "banana", "Apple", "cherry", "apple", "BANANA", "date", "Éclair", "éclair", "fig", "",
+        "Fig", "apples",
+    ];
+
+    #[test]
+    fn sort_order_matches_a_from_scratch_comparator() {
+        let mut memos: Vec<_> = WORDS
+            .iter()
+            .map(|word| SortKeyMemo::new(*word, AsciiCaseInsensitive))
+            .collect();
+        memos.sort();
+        let sorted: Vec<&str> = memos.iter().map(|memo| *memo.as_ref()).collect();
+
+        let mut expected = WORDS;
+        expected.sort_by(|a, b| {
+            a.bytes()
+                .map(|byte| byte.to_ascii_lowercase())
+                .cmp(b.bytes().map(|byte| byte.to_ascii_lowercase()))
+        });
+        assert_eq!(sorted, expected);
+
+        let mut bytewise: Vec<_> = WORDS
+            .iter()
+            .map(|word| SortKeyMemo::new(word.to_string(), Bytewise))
+            .collect();
+        bytewise.sort();
+        let mut expected = WORDS;
+        expected.sort();
+        let sorted: Vec<&str> = bytewise.iter().map(|memo| memo.as_ref().as_str()).collect();
+        assert_eq!(sorted, expected);
+    }
+
+    #[test]
+    fn each_key_is_computed_once() {
+        let computed = AtomicUsize::new(0);
+        let counting = |word: &String| {
+            computed.fetch_add(1, AtomicOrdering::Relaxed);
+            word.to_lowercase()
+        };
+        let mut memos: Vec<_> = (0..200)
+            .map(|i| SortKeyMemo::new(format!("Key-{}", (i * 37) % 200), counting))
+            .collect();
+
+        memos.sort();
+        assert_eq!(computed.load(AtomicOrdering::Relaxed), 200);
+        memos.reverse();
+        memos.sort();
+        let set: HashSet<_> = memos.iter().collect();
+        let map: BTreeMap<_, _> = memos.iter().map(|memo| (memo, ())).collect();
+        assert_eq!((set.len(), map.len()), (200, 200));
+        assert_eq!(computed.load(AtomicOrdering::Relaxed), 200);
+
+        let clone = memos[0].clone();
+        assert!(clone == memos[0]);
+        assert_eq!(computed.load(AtomicOrdering::Relaxed), 200);
+    }
+
+    #[test]
+    fn ord_eq_and_hash_agree() {
+        let memos: Vec<_> = WORDS
+            .iter()
+            .map(|word| SortKeyMemo::new(*word, AsciiCaseInsensitive))
+            .collect();
+        let hash = |memo: &SortKeyMemo<&str, AsciiCaseInsensitive>| {
+            BuildHasherDefault::<DefaultHasher>::default().hash_one(memo)
+        };
+
+        for a in &memos {
+            assert_eq!(a.cmp(a), Ordering::Equal);
+            for b in &memos {
+                assert_eq!(a == b, a.cmp(b) == Ordering::Equal);
+                assert_eq!(a.cmp(b), b.cmp(a).reverse());
+                assert_eq!(a.partial_cmp(b), Some(a.cmp(b)));
+                if a == b {
+                    assert_eq!(hash(a), hash(b));
+                }
+                for c in &memos {
+                    if a <= b && b <= c {
+                        assert!(a <= c);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn works_as_a_map_key_under_its_key_semantics() {
+        let mut counts: HashMap<_, usize> = HashMap::new();
+        for word in WORDS {
+            *counts
+                .entry(SortKeyMemo::new(word, AsciiCaseInsensitive))
+                .or_default() += 1;
+        }
+        assert_eq!(counts[&SortKeyMemo::new("APPLE", AsciiCaseInsensitive)], 2);
+        assert_eq!(counts[&SortKeyMemo::new("fig", AsciiCaseInsensitive)], 2);
+        // Only ASCII letters are folded.
+        assert_eq!(counts[&SortKeyMemo::new("éclair", AsciiCaseInsensitive)], 1);
+        assert_eq!(counts.len(), 9);
+    }
+}
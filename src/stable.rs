@@ -0,0 +1,214 @@
+//! A portable hasher for memoized hashes that outlive the process.
+//!
+//! Persisting cached hashes, or shipping them between machines, is only
+//! sound if every reader computes the same hash for the same value.
+//! `DefaultHasher` promises this for no two Rust releases, and the
+//! `metrohash`, `t1ha` and `gxhash` hashers vary with endianness or CPU.
+//! [`StableState`] pins the algorithm down byte for byte:
+//!
+//! - The algorithm is SipHash-1-3, as specified by Aumasson and Bernstein,
+//!   with the fixed key `k0 = 0x0706_0504_0302_0100`,
+//!   `k1 = 0x0f0e_0d0c_0b0a_0908` (the bytes `00..=0f`, as in the
+//!   reference test vectors).
+//! - `write` feeds its bytes to the stream unchanged.
+//! - Every integer write feeds the integer's little-endian bytes, whatever
+//!   the target's endianness.
+//! - `usize` and `isize` are widened to 64 bits first, so 32-bit and 64-bit
+//!   targets agree.
+//!
+//! The result is the SipHash of the concatenated stream. This is frozen:
+//! changing it is a breaking change of this crate, which the known-answer
+//! tests below guard. What a type writes in its `Hash` impl remains up to
+//! that type. The standard library's impls for integers, `str` and slices
+//! have been stable in practice, but are not formally guaranteed, and a
+//! slice of integers wider than a byte, such as `[u16]`, hashes its memory
+//! in one native-endian `write` that no hasher can reorder. The key
+//! is public, so the hash is not DoS-resistant; use the `siphash` feature's
+//! keyed memos for untrusted input.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::{HashMemo, StableMemo};
+//!
+//! let memo: StableMemo<&str> = HashMemo::new_stable("persisted key");
+//! assert_eq!(memo.hash_one_cached(), 0xc0d2_cbe7_47fa_e4f0);
+//! ```
+
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use ::siphasher::sip::SipHasher13;
+
+use crate::HashMemo;
+
+/// A [`HashMemo`] whose hash is stable across runs, releases and
+/// platforms.
+pub type StableMemo<T> = HashMemo<T, StableState>;
+
+/// A `BuildHasher` for the documented, portable algorithm of the
+/// [`stable`](crate::stable) module.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StableState;
+
+impl StableState {
+    const K0: u64 = 0x0706_0504_0302_0100;
+    const K1: u64 = 0x0f0e_0d0c_0b0a_0908;
+}
+
+impl BuildHasher for StableState {
+    type Hasher = StableHasher;
+
+    #[inline]
+    fn build_hasher(&self) -> StableHasher {
+        StableHasher(SipHasher13::new_with_keys(Self::K0, Self::K1))
+    }
+}
+
+/// The `Hasher` built by [`StableState`].
+#[derive(Debug, Clone)]
+pub struct StableHasher(SipHasher13);
+
+impl Hasher for StableHasher {
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+    }
+
+    #[inline]
+    fn write_u8(&mut self, i: u8) {
+        self.0.write(&[i]);
+    }
+
+    #[inline]
+    fn write_u16(&mut self, i: u16) {
+        self.0.write(&i.to_le_bytes());
+    }
+
+    #[inline]
+    fn write_u32(&mut self, i: u32) {
+        self.0.write(&i.to_le_bytes());
+    }
+
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.0.write(&i.to_le_bytes());
+    }
+
+    #[inline]
+    fn write_u128(&mut self, i: u128) {
+        self.0.write(&i.to_le_bytes());
+    }
+
+    #[inline]
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    #[inline]
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as i64 as u64);
+    }
+}
+
+impl<T> HashMemo<T, StableState>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new `HashMemo` using the portable [`StableState`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::{HashMemo, StableMemo};
+    ///
+    /// let memo: StableMemo<u64> = HashMemo::new_stable(42);
+    /// ```
+    pub const fn new_stable(value: T) -> Self {
+        Self::with_hasher(value, StableState)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(PartialEq, Eq, Hash)]
+    struct Record {
+        id: u32,
+        name: String,
+        tags: Vec<u8>,
+        offset: isize,
+    }
+
+    fn record() -> Record {
+        Record {
+            id: 7,
+            name: "seven".to_string(),
+            tags: vec![1, 2, 3],
+            offset: -2,
+        }
+    }
+
+    /// Hashes a raw byte stream with the documented algorithm, bypassing
+    /// `StableHasher`, as a big-endian or 32-bit target must see it.
+    fn reference(chunks: &[&[u8]]) -> u64 {
+        let mut hasher = SipHasher13::new_with_keys(StableState::K0, StableState::K1);
+        for chunk in chunks {
+            hasher.write(chunk);
+        }
+        hasher.finish()
+    }
+
+    #[test]
+    fn known_answers() {
+        let state = StableState;
+        assert_eq!(state.hash_one("persisted key"), 0xc0d2_cbe7_47fa_e4f0);
+        assert_eq!(state.hash_one(""), 0x336d_3897_9e4a_286b);
+        assert_eq!(state.hash_one(0x0102_0304u32), 0xb055_4a1b_b419_2120);
+        assert_eq!(state.hash_one(-1i64), 0x823f_3073_1145_3347);
+        assert_eq!(state.hash_one(u128::MAX - 1), 0x68b8_e880_4fe9_e1af);
+        assert_eq!(state.hash_one(vec![1u8, 2, 3]), 0x00b4_be5f_67f7_1b10);
+        assert_eq!(state.hash_one(record()), 0xb3ea_7cfe_1818_3bcf);
+    }
+
+    #[test]
+    fn integer_writes_are_little_endian_byte_streams() {
+        let state = StableState;
+        assert_eq!(
+            state.hash_one(0x0102_0304u32),
+            reference(&[&[0x04, 0x03, 0x02, 0x01]])
+        );
+        assert_eq!(state.hash_one(-1i64), reference(&[&[0xff; 8]]));
+
+        let mut wide = [0xff; 16];
+        wide[0] = 0xfe;
+        assert_eq!(state.hash_one(u128::MAX - 1), reference(&[&wide]));
+
+        // A `str` is its bytes, then a 0xff terminator.
+        assert_eq!(state.hash_one("ab"), reference(&[b"ab", &[0xff]]));
+        // A `Vec<u8>` is its length, as a 64-bit integer, then its bytes.
+        assert_eq!(
+            state.hash_one(vec![1u8, 2, 3]),
+            reference(&[&3u64.to_le_bytes(), &[1, 2, 3]])
+        );
+    }
+
+    #[test]
+    fn nested_structs_hash_their_fields_in_order() {
+        let expected = reference(&[
+            &[7, 0, 0, 0],
+            b"seven",
+            &[0xff],
+            &[3, 0, 0, 0, 0, 0, 0, 0],
+            &[1, 2, 3],
+            &[0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+        ]);
+        assert_eq!(StableState.hash_one(record()), expected);
+        assert_eq!(HashMemo::new_stable(record()).hash_one_cached(), expected);
+    }
+}
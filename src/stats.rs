@@ -0,0 +1,214 @@
+//! Process-wide cache statistics, for checking that memoization pays off.
+//!
+//! With the `stats` feature enabled, every memo, [`Memoized`] field and
+//! derived hash cache in the process counts the hashes it serves from its
+//! cache, the hashes it computes and the times its cache is emptied. Read
+//! the counters with [`snapshot`] and zero them with [`reset`]. Without the
+//! feature none of this is compiled, and the hashing paths carry no extra
+//! instructions.
+//!
+//! To find the key type that misbehaves, build its memos with
+//! [`HashMemo::with_stats`], which counts the computations of one memo.
+//!
+//! [`Memoized`]: crate::Memoized
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::{stats, HashMemo};
+//! use std::collections::HashSet;
+//!
+//! let mut set = HashSet::new();
+//! set.insert(HashMemo::new("key".to_string()));
+//! set.contains(&HashMemo::new("key".to_string()));
+//!
+//! let stats = stats::snapshot();
+//! println!("{} hits, {} computations", stats.hits, stats.computations);
+//! ```
+
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash};
+use std::sync::atomic::Ordering;
+
+use crate::{AtomicU64, HashMemo};
+
+static HITS: AtomicU64 = AtomicU64::new(0);
+static COMPUTATIONS: AtomicU64 = AtomicU64::new(0);
+static INVALIDATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// A reading of the process-wide counters, taken by [`snapshot`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Hashes served from a warm cache.
+    pub hits: u64,
+    /// Hashes computed because the cache was cold.
+    pub computations: u64,
+    /// Caches emptied by `forget_hash`, `Memoized::set` and the like.
+    pub invalidations: u64,
+}
+
+/// Reads the process-wide counters.
+///
+/// Each counter is read on its own, so while other threads are hashing the
+/// three values need not come from the same instant.
+pub fn snapshot() -> Stats {
+    Stats {
+        hits: HITS.load(Ordering::Relaxed),
+        computations: COMPUTATIONS.load(Ordering::Relaxed),
+        invalidations: INVALIDATIONS.load(Ordering::Relaxed),
+    }
+}
+
+/// Sets the process-wide counters back to zero.
+pub fn reset() {
+    HITS.store(0, Ordering::Relaxed);
+    COMPUTATIONS.store(0, Ordering::Relaxed);
+    INVALIDATIONS.store(0, Ordering::Relaxed);
+}
+
+#[inline]
+pub(crate) fn hit() {
+    HITS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[inline]
+pub(crate) fn computation() {
+    COMPUTATIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[inline]
+pub(crate) fn invalidation() {
+    INVALIDATIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A `BuildHasher` that counts the hashes built through it.
+///
+/// Memos made with [`HashMemo::with_stats`] use it to count their own
+/// computations, read with [`HashMemo::recomputes`]. Every hash the memo
+/// builds counts, so besides cold lookups this includes
+/// [`is_zero_remapped`](HashMemo::is_zero_remapped) checks and hasher
+/// fingerprints written during serialization. A clone starts from zero.
+pub struct StatsState<S = BuildHasherDefault<DefaultHasher>> {
+    inner: S,
+    recomputes: AtomicU64,
+}
+
+impl StatsState {
+    /// Creates a counting wrapper around the default hasher.
+    #[inline]
+    pub const fn new() -> Self {
+        Self::wrap(BuildHasherDefault::new())
+    }
+}
+
+impl<S> StatsState<S> {
+    /// Creates a counting wrapper around `inner`.
+    #[inline]
+    pub const fn wrap(inner: S) -> Self {
+        Self {
+            inner,
+            recomputes: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns how many hashes have been built so far.
+    #[inline]
+    pub fn recomputes(&self) -> u64 {
+        self.recomputes.load(Ordering::Relaxed)
+    }
+}
+
+impl<S: BuildHasher> BuildHasher for StatsState<S> {
+    type Hasher = S::Hasher;
+
+    #[inline]
+    fn build_hasher(&self) -> S::Hasher {
+        self.recomputes.fetch_add(1, Ordering::Relaxed);
+        self.inner.build_hasher()
+    }
+}
+
+impl<S: Clone> Clone for StatsState<S> {
+    fn clone(&self) -> Self {
+        Self::wrap(self.inner.clone())
+    }
+}
+
+impl<S: Default> Default for StatsState<S> {
+    fn default() -> Self {
+        Self::wrap(S::default())
+    }
+}
+
+impl<S> fmt::Debug for StatsState<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StatsState")
+            .field("recomputes", &self.recomputes())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> HashMemo<T, StatsState>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new `HashMemo` with the default hasher that counts its own
+    /// hash computations, see [`recomputes`](Self::recomputes).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    /// use std::collections::HashSet;
+    ///
+    /// let memo = HashMemo::with_stats("key".to_string());
+    /// let mut set = HashSet::new();
+    /// set.insert(&memo);
+    /// assert!(set.contains(&memo));
+    /// # #[cfg(not(feature = "disable-cache"))]
+    /// assert_eq!(memo.recomputes(), 1);
+    /// ```
+    #[inline]
+    pub const fn with_stats(value: T) -> Self {
+        Self::with_hasher(value, StatsState::new())
+    }
+}
+
+impl<T, S> HashMemo<T, StatsState<S>>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+    /// Returns how many times this memo has hashed its value.
+    #[inline]
+    pub fn recomputes(&self) -> u64 {
+        self.hasher.recomputes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn with_stats_counts_one_memo() {
+        let memo = HashMemo::with_stats("local".to_string());
+        for _ in 0..10 {
+            memo.precompute();
+        }
+        assert_eq!(memo.recomputes(), 1);
+
+        memo.forget_hash();
+        memo.precompute();
+        assert_eq!(memo.recomputes(), 2);
+
+        let clone = memo.clone();
+        assert_eq!(clone.recomputes(), 0);
+        assert_eq!(
+            clone.hash_one_cached(),
+            HashMemo::new("local".to_string()).hash_one_cached()
+        );
+        assert_eq!(clone.recomputes(), 0, "the clone carries the cache");
+    }
+}
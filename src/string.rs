@@ -0,0 +1,342 @@
+//! A first-class memoized string key.
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::convert::Infallible;
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher};
+use std::ops::Deref;
+use std::str::FromStr;
+
+use crate::{HashMemo, MemoLookup};
+
+/// A memoized `String`, for use wherever a `String` key was used before.
+///
+/// It derefs to `str` and converts, compares, formats and serializes like
+/// `String`, so most code switches over by changing the key type. Borrowed
+/// lookups are the one exception: `MemoString` cannot implement
+/// `Borrow<str>`, because it hashes as its cached `u64` while `str` hashes
+/// its bytes, and such a lookup would miss. Probe maps with
+/// [`Probe`](crate::Probe) instead, which finds the key without allocating.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{MemoString, Probe};
+/// use std::collections::HashMap;
+///
+/// let mut map: HashMap<MemoString, u32> = HashMap::new();
+/// map.insert("key".into(), 1);
+///
+/// assert_eq!(map.get(&MemoString::from("key")), Some(&1));
+/// assert_eq!(map.get(Probe::new("key").as_lookup()), Some(&1));
+/// ```
+#[derive(Clone)]
+pub struct MemoString<H = BuildHasherDefault<DefaultHasher>>(HashMemo<String, H>)
+where
+    H: BuildHasher;
+
+impl MemoString {
+    /// Creates a new `MemoString` with the default hasher.
+    #[inline]
+    pub const fn new(value: String) -> Self {
+        Self(HashMemo::new(value))
+    }
+}
+
+impl<H> MemoString<H>
+where
+    H: BuildHasher,
+{
+    /// Creates a new `MemoString` with a custom hasher.
+    #[inline]
+    pub const fn with_hasher(value: String, hasher: H) -> Self {
+        Self(HashMemo::with_hasher(value, hasher))
+    }
+
+    /// Returns the string slice.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        self.0.value.as_str()
+    }
+
+    /// Consumes the `MemoString` and returns the `String`.
+    #[inline]
+    #[must_use]
+    pub fn into_string(self) -> String {
+        self.0.into_inner()
+    }
+
+    /// Returns the underlying memo.
+    #[inline]
+    pub fn memo(&self) -> &HashMemo<String, H> {
+        &self.0
+    }
+
+    /// Consumes the `MemoString` and returns the underlying memo, cache
+    /// included.
+    #[inline]
+    pub fn into_memo(self) -> HashMemo<String, H> {
+        self.0
+    }
+}
+
+impl<H> Deref for MemoString<H>
+where
+    H: BuildHasher,
+{
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<H> AsRef<str> for MemoString<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<'a, H> Borrow<dyn MemoLookup<str> + 'a> for MemoString<H>
+where
+    H: BuildHasher + 'a,
+{
+    #[inline]
+    fn borrow(&self) -> &(dyn MemoLookup<str> + 'a) {
+        &self.0
+    }
+}
+
+impl<H> Hash for MemoString<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn hash<H2: Hasher>(&self, state: &mut H2) {
+        self.0.hash(state);
+    }
+}
+
+impl<H> PartialEq for MemoString<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<H> Eq for MemoString<H> where H: BuildHasher {}
+
+impl<H> PartialEq<str> for MemoString<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<H> PartialEq<&str> for MemoString<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl<H> PartialEq<String> for MemoString<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<H> PartialOrd for MemoString<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<H> Ord for MemoString<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl<H> fmt::Debug for MemoString<H>
+where
+    H: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl<H> fmt::Display for MemoString<H>
+where
+    H: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl<H> Default for MemoString<H>
+where
+    H: BuildHasher + Default,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::from(String::new())
+    }
+}
+
+impl<H> From<String> for MemoString<H>
+where
+    H: BuildHasher + Default,
+{
+    #[inline]
+    fn from(value: String) -> Self {
+        Self::with_hasher(value, H::default())
+    }
+}
+
+impl<H> From<&str> for MemoString<H>
+where
+    H: BuildHasher + Default,
+{
+    #[inline]
+    fn from(value: &str) -> Self {
+        Self::from(value.to_owned())
+    }
+}
+
+impl<H> From<HashMemo<String, H>> for MemoString<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn from(memo: HashMemo<String, H>) -> Self {
+        Self(memo)
+    }
+}
+
+impl<H> FromStr for MemoString<H>
+where
+    H: BuildHasher + Default,
+{
+    type Err = Infallible;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Infallible> {
+        Ok(Self::from(s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap, HashSet};
+
+    use super::*;
+    use crate::Probe;
+
+    /// Word counting as written for `String` keys, instantiated for both
+    /// key types without changes.
+    macro_rules! word_report {
+        ($key:ty, $text:expr) => {{
+            let mut counts: HashMap<$key, usize> = HashMap::new();
+            for word in $text.split_whitespace() {
+                let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+                if word.is_empty() {
+                    continue;
+                }
+                *counts.entry(word.to_lowercase().into()).or_insert(0) += 1;
+            }
+
+            let mut seen: HashSet<$key> = HashSet::new();
+            let mut firsts: Vec<$key> = Vec::new();
+            for word in $text.split_whitespace() {
+                let key: $key = word.parse().unwrap();
+                if seen.insert(key.clone()) && key.starts_with(char::is_uppercase) {
+                    firsts.push(key);
+                }
+            }
+
+            let by_word: BTreeMap<$key, usize> = counts.clone().into_iter().collect();
+            let report: Vec<String> = by_word
+                .iter()
+                .filter(|(word, _)| word.len() > 2 && **word != "the")
+                .map(|(word, n)| format!("{word}={n}"))
+                .collect();
+
+            let the = counts.get(&<$key>::from("the")).copied();
+            let firsts: Vec<String> = firsts.iter().map(|w| w.to_string()).collect();
+            (report, the, firsts, format!("{:?}", by_word.keys().next()))
+        }};
+    }
+
+    const TEXT: &str = "The quick brown fox jumps over the lazy dog. \
+                        The dog sleeps; the Fox runs, and Quick wins.";
+
+    #[test]
+    fn string_keyed_code_ports_unchanged() {
+        assert_eq!(word_report!(MemoString, TEXT), word_report!(String, TEXT));
+    }
+
+    #[test]
+    fn probes_find_keys_without_allocating() {
+        let mut map: HashMap<MemoString, u32> = HashMap::new();
+        map.insert("alpha".into(), 1);
+        map.insert(MemoString::new("beta".to_string()), 2);
+
+        assert_eq!(map.get(Probe::new("alpha").as_lookup()), Some(&1));
+        assert!(map.contains_key(Probe::new("beta").as_lookup()));
+        assert_eq!(map.remove(Probe::new("alpha").as_lookup()), Some(1));
+        assert_eq!(map.get(Probe::new("alpha").as_lookup()), None);
+    }
+
+    #[test]
+    fn hashes_like_a_string_memo() {
+        let state = std::hash::RandomState::new();
+        let key: MemoString = "key".into();
+        let memo = HashMemo::new("key".to_string());
+        assert_eq!(state.hash_one(&key), state.hash_one(&memo));
+        assert_eq!(key.memo(), &memo);
+        assert_eq!(key.into_memo(), memo);
+    }
+
+    #[test]
+    fn compares_and_formats_like_string() {
+        let key: MemoString = "key".into();
+        assert!(key == *"key");
+        assert!(key == "key");
+        let owned = String::from("key");
+        assert!(key == owned);
+        assert_eq!(format!("{key} {key:?}"), "key \"key\"");
+        assert_eq!(key.as_str(), "key");
+        assert_eq!(key.len(), 3);
+        assert_eq!(key.into_string(), "key");
+    }
+}
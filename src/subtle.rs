@@ -0,0 +1,176 @@
+//! Constant-time equality for secret-bearing keys via [`subtle`](::subtle).
+
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher};
+
+use ::subtle::{Choice, ConstantTimeEq};
+
+use crate::HashMemo;
+
+/// A [`HashMemo`] whose equality runs in constant time.
+///
+/// `PartialEq` compares the bytes of `T::as_ref()` through
+/// [`subtle::ConstantTimeEq`](::subtle::ConstantTimeEq) instead of
+/// short-circuiting on the first differing byte. Values of different lengths
+/// are compared over the longer length with the missing bytes treated as
+/// zero, and the length check is folded into the result instead of returning
+/// early, so the time taken depends only on the longer length.
+///
+/// `Hash` still writes the memoized hash. The bucket a key lands in is
+/// therefore *not* hidden: maps probe by hash first and only compare keys
+/// whose hashes match. Pair `CtMemo` with a randomly seeded hasher, such as
+/// [`HashMemo::new_random`], so that hashes reveal nothing useful.
+///
+/// `T`'s own `Eq` must agree with comparing `as_ref()` bytes.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::CtMemo;
+///
+/// let token = CtMemo::new(b"bearer-token".to_vec());
+/// assert!(token == CtMemo::new(b"bearer-token".to_vec()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CtMemo<T, H = BuildHasherDefault<DefaultHasher>>(HashMemo<T, H>)
+where
+    T: Eq + Hash,
+    H: BuildHasher;
+
+impl<T> CtMemo<T>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new `CtMemo` with the default hasher.
+    pub fn new(value: T) -> Self {
+        Self(HashMemo::new(value))
+    }
+}
+
+impl<T, H> CtMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Creates a new `CtMemo` with a custom hasher.
+    pub const fn with_hasher(value: T, hasher: H) -> Self {
+        Self(HashMemo::with_hasher(value, hasher))
+    }
+
+    /// Consumes the `CtMemo` and returns the wrapped value.
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> T {
+        self.0.into_inner()
+    }
+}
+
+impl<T, H> From<HashMemo<T, H>> for CtMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    fn from(memo: HashMemo<T, H>) -> Self {
+        Self(memo)
+    }
+}
+
+impl<T, H> AsRef<T> for CtMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    fn as_ref(&self) -> &T {
+        &self.0.value
+    }
+}
+
+impl<T, H> PartialEq for CtMemo<T, H>
+where
+    T: Eq + Hash + AsRef<[u8]>,
+    H: BuildHasher,
+{
+    fn eq(&self, other: &Self) -> bool {
+        ct_eq_padded(self.0.value.as_ref(), other.0.value.as_ref()).into()
+    }
+}
+
+impl<T, H> Eq for CtMemo<T, H>
+where
+    T: Eq + Hash + AsRef<[u8]>,
+    H: BuildHasher,
+{
+}
+
+impl<T, H> Hash for CtMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    fn hash<H2: Hasher>(&self, state: &mut H2) {
+        self.0.hash(state);
+    }
+}
+
+/// Compares `a` and `b` without branching on their contents or lengths.
+fn ct_eq_padded(a: &[u8], b: &[u8]) -> Choice {
+    let len = a.len().max(b.len());
+    let mut equal = (a.len() as u64).ct_eq(&(b.len() as u64));
+    for i in 0..len {
+        let x = a.get(i).copied().unwrap_or(0);
+        let y = b.get(i).copied().unwrap_or(0);
+        equal &= x.ct_eq(&y);
+    }
+    equal
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    #[test]
+    fn equality_is_functionally_correct() {
+        let token = CtMemo::new(b"secret".to_vec());
+        assert!(token == CtMemo::new(b"secret".to_vec()));
+        assert!(token != CtMemo::new(b"secreT".to_vec()));
+        assert!(token != CtMemo::new(b"secret\0".to_vec()));
+        assert!(token != CtMemo::new(b"secre".to_vec()));
+        assert!(token != CtMemo::new(Vec::new()));
+        assert!(CtMemo::new(Vec::<u8>::new()) == CtMemo::new(Vec::new()));
+    }
+
+    #[test]
+    fn works_as_a_set_key() {
+        let mut set = HashSet::new();
+        set.insert(CtMemo::new("alpha".to_string()));
+        set.insert(CtMemo::new("beta".to_string()));
+        assert!(set.contains(&CtMemo::new("alpha".to_string())));
+        assert!(!set.contains(&CtMemo::new("gamma".to_string())));
+    }
+
+    #[test]
+    #[ignore = "statistical timing test, run explicitly"]
+    fn comparison_time_does_not_depend_on_mismatch_position() {
+        fn time(a: &CtMemo<Vec<u8>>, b: &CtMemo<Vec<u8>>) -> Duration {
+            let start = Instant::now();
+            for _ in 0..2_000 {
+                std::hint::black_box(a == b);
+            }
+            start.elapsed()
+        }
+
+        let secret = CtMemo::new(vec![0x55u8; 1 << 14]);
+        let mut early = vec![0x55u8; 1 << 14];
+        early[0] = 0;
+        let mut late = vec![0x55u8; 1 << 14];
+        late[(1 << 14) - 1] = 0;
+        let (early, late) = (CtMemo::new(early), CtMemo::new(late));
+
+        let early = (0..5).map(|_| time(&secret, &early)).min().unwrap();
+        let late = (0..5).map(|_| time(&secret, &late)).min().unwrap();
+        let ratio = early.as_secs_f64() / late.as_secs_f64();
+        assert!((0.8..1.25).contains(&ratio), "ratio {ratio}");
+    }
+}
@@ -0,0 +1,126 @@
+//! [`t1ha`](::t1ha) support, for fast in-memory hashing.
+//!
+//! [`T1haMemo`] memoizes with t1ha0 through [`T1haState`], which carries
+//! the seed the `t1ha` crate's own `BuildHasher` lacks. t1ha is a
+//! non-cryptographic hash: it is not DoS-resistant, even with a secret
+//! seed, so keep it away from keys an attacker controls and use
+//! [`HashMemo::new_random`] there. t1ha0 picks the fastest implementation
+//! for the CPU at run time, and the implementations hash differently, so
+//! cached hashes are stable within a process but must not be persisted or
+//! compared across machines.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::HashMemo;
+//! use std::collections::HashMap;
+//!
+//! let mut map = HashMap::new();
+//! map.insert(HashMemo::new_t1ha(vec![7u8; 1 << 20]), "blob");
+//! assert_eq!(map.get(&HashMemo::new_t1ha(vec![7u8; 1 << 20])), Some(&"blob"));
+//! ```
+
+use std::hash::{BuildHasher, Hash};
+
+use ::t1ha::T1haHasher;
+
+use crate::HashMemo;
+
+/// A [`HashMemo`] hashing with t1ha, seeded or not.
+pub type T1haMemo<T> = HashMemo<T, T1haState>;
+
+/// A `BuildHasher` for `T1haHasher` with a fixed seed.
+///
+/// The default seed is `0`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct T1haState {
+    seed: u64,
+}
+
+impl T1haState {
+    /// Creates a `T1haState` building hashers seeded with `seed`.
+    #[inline]
+    pub const fn with_seed(seed: u64) -> Self {
+        Self { seed }
+    }
+}
+
+impl BuildHasher for T1haState {
+    type Hasher = T1haHasher;
+
+    #[inline]
+    fn build_hasher(&self) -> T1haHasher {
+        T1haHasher::with_seed(self.seed)
+    }
+}
+
+impl<T> HashMemo<T, T1haState>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new `HashMemo` using t1ha with a seed of `0`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::{HashMemo, T1haMemo};
+    ///
+    /// let memo: T1haMemo<&str> = HashMemo::new_t1ha("hello world");
+    /// ```
+    pub const fn new_t1ha(value: T) -> Self {
+        Self::new_t1ha_with_seed(value, 0)
+    }
+
+    /// Creates a new `HashMemo` using t1ha with `seed`.
+    ///
+    /// Memos compared or looked up together must share the seed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let memo = HashMemo::new_t1ha_with_seed("hello world", 0x5eed);
+    /// ```
+    pub const fn new_t1ha_with_seed(value: T, seed: u64) -> Self {
+        Self::with_hasher(value, T1haState::with_seed(seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::hash::Hasher;
+
+    use super::*;
+    use crate::remap_zero;
+
+    #[test]
+    fn works_as_a_hashmap_key() {
+        let mut map = HashMap::new();
+        for i in 0..100u32 {
+            map.insert(HashMemo::new_t1ha(i.to_string().repeat(100)), i);
+        }
+        for i in 0..100u32 {
+            let key = HashMemo::new_t1ha(i.to_string().repeat(100));
+            assert_eq!(map.get(&key), Some(&i));
+        }
+        assert_eq!(map.get(&HashMemo::new_t1ha("absent".repeat(100))), None);
+    }
+
+    #[test]
+    fn hashes_with_the_configured_seed() {
+        let value = "seeded".to_string();
+        let unseeded = HashMemo::new_t1ha(value.clone());
+        let seeded = HashMemo::new_t1ha_with_seed(value.clone(), 42);
+
+        let mut hasher = T1haHasher::with_seed(42);
+        value.hash(&mut hasher);
+        assert_eq!(seeded.memoized_hash(), remap_zero(hasher.finish()));
+        assert_eq!(
+            unseeded.memoized_hash(),
+            remap_zero(T1haState::default().hash_one(&value))
+        );
+        assert_ne!(seeded.memoized_hash(), unseeded.memoized_hash());
+    }
+}
@@ -0,0 +1,158 @@
+//! A seeded, deterministic hasher for reproducible tests.
+//!
+//! Snapshot tests of structures whose iteration order or derived ids follow
+//! hash values need hashes that are the same on every run, machine and Rust
+//! release, and seeds to vary them deliberately. `DefaultHasher` promises
+//! none of this and `RandomState` is different on every run. [`DetState`]
+//! is [`FixedState`]'s frozen v1 algorithm started from a seeded state:
+//!
+//! - The state starts at `0x243f_6a88_85a3_08d3 ^ seed`.
+//! - Each word `w` is mixed in as
+//!   `state = (state.rotate_left(5) ^ w).wrapping_mul(0x517c_c1b7_2722_0a95)`.
+//! - Bytes are consumed as little-endian 8-byte words, then a 4-, 2- and
+//!   1-byte tail; integers are one word (`u128` low then high, `usize`
+//!   widened to 64 bits).
+//! - `finish` applies the murmur3 `fmix64` finalizer.
+//!
+//! Seed `0` therefore hashes exactly like `FixedState`. The algorithm is
+//! pinned by known-answer tests, so changing it is a breaking change. It is
+//! meant for tests and is not DoS-resistant.
+//!
+//! [`FixedState`]: crate::FixedState
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::{DetMemo, HashMemo};
+//!
+//! let memo: DetMemo<&str> = HashMemo::deterministic("snapshot", 7);
+//! assert_eq!(memo.hash_one_cached(), 0xf1b8_0582_1e0c_73ce);
+//! ```
+
+use std::hash::{BuildHasher, Hash};
+
+use crate::{FixedHasher, HashMemo};
+
+/// A [`HashMemo`] hashing with a seeded [`DetState`].
+pub type DetMemo<T> = HashMemo<T, DetState>;
+
+/// A `BuildHasher` with the documented, seeded algorithm of the
+/// [`test_util`](crate::test_util) module, for tests whose results depend
+/// on hash values.
+///
+/// The default seed is `0`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct DetState {
+    seed: u64,
+}
+
+impl DetState {
+    /// Creates a `DetState` hashing with `seed`.
+    #[inline]
+    pub const fn with_seed(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Returns the seed.
+    #[inline]
+    pub const fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl BuildHasher for DetState {
+    type Hasher = FixedHasher;
+
+    #[inline]
+    fn build_hasher(&self) -> Self::Hasher {
+        FixedHasher::seeded(self.seed)
+    }
+}
+
+impl<T> HashMemo<T, DetState>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new `HashMemo` hashing with [`DetState::with_seed`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let a = HashMemo::deterministic("key", 1);
+    /// let b = HashMemo::deterministic("key", 2);
+    /// assert_ne!(a.hash_one_cached(), b.hash_one_cached());
+    /// ```
+    pub const fn deterministic(value: T, seed: u64) -> Self {
+        Self::with_hasher(value, DetState::with_seed(seed))
+    }
+}
+
+/// Counts the hashers it builds, one per hash computed, for the crate's own
+/// tests. Clones share the count.
+#[cfg(test)]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CountingState(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+#[cfg(test)]
+impl CountingState {
+    /// Returns the number of hashers built so far.
+    pub(crate) fn count(&self) -> usize {
+        self.0.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+impl BuildHasher for CountingState {
+    type Hasher = std::hash::DefaultHasher;
+
+    fn build_hasher(&self) -> std::hash::DefaultHasher {
+        self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        std::hash::DefaultHasher::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FixedState;
+
+    #[test]
+    fn known_answers() {
+        let state = DetState::with_seed(0x5eed);
+        assert_eq!(state.hash_one("hello"), 0x4da0_dcba_9404_dbb4);
+        assert_eq!(state.hash_one(42u64), 0x374c_fd38_cf70_bba9);
+        assert_eq!(
+            state.hash_one([1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]),
+            0xb751_07f0_10e4_4747
+        );
+        assert_eq!(state.hash_one(u128::MAX), 0xe06e_7eab_0fb8_a3f0);
+        assert_eq!(
+            DetState::with_seed(u64::MAX).hash_one("hello"),
+            0xc024_653e_9650_dd5c
+        );
+    }
+
+    #[test]
+    fn seed_zero_hashes_like_fixed_state() {
+        for value in ["", "a", "hello", "the quick brown fox"] {
+            assert_eq!(
+                DetState::default().hash_one(value),
+                FixedState.hash_one(value)
+            );
+        }
+    }
+
+    #[test]
+    fn seeds_vary_the_hash() {
+        let hashes: std::collections::HashSet<u64> = (0..64)
+            .map(|seed| HashMemo::deterministic("key", seed).hash_one_cached())
+            .collect();
+        assert_eq!(hashes.len(), 64);
+        assert_eq!(
+            HashMemo::deterministic("key", 3).hash_one_cached(),
+            crate::remap_zero(DetState::with_seed(3).hash_one("key"))
+        );
+    }
+}
@@ -0,0 +1,245 @@
+//! [`tokio`](::tokio) support, for priming expensive hashes off the async
+//! runtime threads.
+//!
+//! The first hash of a large memo walks the whole value, and inside a
+//! future that stalls the executor thread and every task queued on it.
+//! [`ArcMemo::prime_async`] runs that computation on the blocking pool
+//! instead, so by the time the memo is inserted or looked up the hash is
+//! cached. Concurrent callers priming the same cold memo share one
+//! computation, and [`ArcMemo::prime_all_async`] warms a batch with bounded
+//! concurrency.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::ArcMemo;
+//! use std::collections::HashSet;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let blob = ArcMemo::new(vec![7u8; 1 << 20]);
+//! blob.prime_async().await;
+//!
+//! let mut set = HashSet::new();
+//! set.insert(blob);
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::num::NonZeroUsize;
+use std::panic;
+use std::sync::atomic::Ordering;
+use std::sync::{Mutex, OnceLock};
+
+use ::tokio::sync::watch;
+use ::tokio::task::{self, JoinSet};
+
+use crate::{ArcMemo, HashMemo};
+
+/// Computations in flight, by the address of the shared memo.
+type InFlight = Mutex<HashMap<usize, watch::Receiver<Option<u64>>>>;
+
+fn in_flight() -> &'static InFlight {
+    static IN_FLIGHT: OnceLock<InFlight> = OnceLock::new();
+    IN_FLIGHT.get_or_init(Default::default)
+}
+
+/// The address of the shared memo, which identifies it while any clone is
+/// alive.
+fn address<T, H>(memo: &ArcMemo<T, H>) -> usize
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    memo.memo() as *const HashMemo<T, H> as usize
+}
+
+/// A computation on the blocking pool, deregistered when it finishes or
+/// panics.
+///
+/// It holds the memo so the allocation, and with it the registry key,
+/// cannot be reused by another memo until the entry is gone.
+struct Flight<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    memo: ArcMemo<T, H>,
+}
+
+impl<T, H> Drop for Flight<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    fn drop(&mut self) {
+        let key = address(&self.memo);
+        in_flight().lock().unwrap().remove(&key);
+    }
+}
+
+impl<T, H> ArcMemo<T, H>
+where
+    T: Eq + Hash + Send + Sync + 'static,
+    H: BuildHasher + Send + Sync + 'static,
+{
+    /// Returns the cached hash, computing it on the blocking pool if the
+    /// cache is cold.
+    ///
+    /// Callers priming the same memo while a computation is in flight wait
+    /// for that computation instead of starting another. It runs to
+    /// completion even if every caller stops waiting, so cancelling a
+    /// caller never wastes the work done so far.
+    ///
+    /// # Panics
+    ///
+    /// Panics if hashing the value panics, in every caller that was
+    /// waiting for the computation, or if called outside a Tokio runtime.
+    pub async fn prime_async(&self) -> u64 {
+        let hash = self.memo().hash.load(Ordering::Relaxed);
+        if hash != 0 {
+            return hash;
+        }
+
+        let mut flight = self.join_flight();
+        let hash = flight.wait_for(Option::is_some).await.map(|hash| *hash);
+        match hash {
+            Ok(hash) => hash.expect("waited for a hash"),
+            Err(_) => panic!("hashing the memo panicked on the blocking pool"),
+        }
+    }
+
+    /// Primes every memo in `memos`, with at most `concurrency` computations
+    /// running at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if hashing any of the values panics, or if called outside a
+    /// Tokio runtime.
+    pub async fn prime_all_async(memos: &[Self], concurrency: NonZeroUsize) {
+        let mut tasks = JoinSet::new();
+        for memo in memos {
+            if tasks.len() == concurrency.get() {
+                Self::join_one(&mut tasks).await;
+            }
+            let memo = memo.clone();
+            tasks.spawn(async move { memo.prime_async().await });
+        }
+        while !tasks.is_empty() {
+            Self::join_one(&mut tasks).await;
+        }
+    }
+
+    /// Returns a receiver for the computation of this memo's hash, starting
+    /// one unless it is already in flight.
+    fn join_flight(&self) -> watch::Receiver<Option<u64>> {
+        let key = address(self);
+        let mut in_flight = in_flight().lock().unwrap();
+        if let Some(flight) = in_flight.get(&key) {
+            return flight.clone();
+        }
+
+        let (sender, receiver) = watch::channel(None);
+        in_flight.insert(key, receiver.clone());
+        let flight = Flight { memo: self.clone() };
+        task::spawn_blocking(move || {
+            sender.send_replace(Some(flight.memo.memo().memoized_hash()));
+            drop(flight);
+        });
+        receiver
+    }
+
+    async fn join_one(tasks: &mut JoinSet<u64>) {
+        if let Some(Err(err)) = tasks.join_next().await {
+            if let Ok(payload) = err.try_into_panic() {
+                panic::resume_unwind(payload);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hasher;
+    use std::sync::atomic::{AtomicBool, AtomicUsize};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    static COMPUTATIONS: AtomicUsize = AtomicUsize::new(0);
+
+    /// Counts its hash computations and takes long enough for callers to
+    /// pile up.
+    #[derive(PartialEq, Eq)]
+    struct Slow(u32);
+
+    impl Hash for Slow {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            COMPUTATIONS.fetch_add(1, Ordering::Relaxed);
+            thread::sleep(Duration::from_millis(50));
+            self.0.hash(state);
+        }
+    }
+
+    static OPENED: AtomicBool = AtomicBool::new(false);
+
+    /// Hashes only once another task has run, or gives up after a while.
+    #[derive(PartialEq, Eq)]
+    struct Gated;
+
+    impl Hash for Gated {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while !OPENED.load(Ordering::Relaxed) {
+                assert!(Instant::now() < deadline, "the executor was blocked");
+                thread::sleep(Duration::from_millis(1));
+            }
+            state.write_u8(0);
+        }
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[::tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_callers_share_one_computation() {
+        let memo = ArcMemo::new(Slow(7));
+        let callers: Vec<_> = (0..16)
+            .map(|_| {
+                let memo = memo.clone();
+                ::tokio::spawn(async move { memo.prime_async().await })
+            })
+            .collect();
+
+        for caller in callers {
+            assert_eq!(caller.await.unwrap(), HashMemo::new(7u32).memoized_hash());
+        }
+        assert_eq!(COMPUTATIONS.load(Ordering::Relaxed), 1);
+        assert_eq!(memo.prime_async().await, memo.memo().memoized_hash());
+        assert_eq!(COMPUTATIONS.load(Ordering::Relaxed), 1);
+    }
+
+    /// With one executor thread, hashing inline would keep the opener from
+    /// ever running.
+    #[::tokio::test(flavor = "current_thread")]
+    async fn priming_leaves_the_executor_free() {
+        let opener = ::tokio::spawn(async {
+            ::tokio::task::yield_now().await;
+            OPENED.store(true, Ordering::Relaxed);
+        });
+
+        ArcMemo::new(Gated).prime_async().await;
+        assert!(opener.is_finished());
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[::tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn prime_all_warms_every_memo() {
+        let memos: Vec<_> = (0..32u32).map(|i| ArcMemo::new(i.to_string())).collect();
+        ArcMemo::prime_all_async(&memos, NonZeroUsize::new(4).unwrap()).await;
+
+        assert!(memos
+            .iter()
+            .all(|memo| memo.memo().hash.load(Ordering::Relaxed) != 0));
+    }
+}
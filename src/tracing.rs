@@ -0,0 +1,127 @@
+//! `tracing` events for cold hash computations.
+//!
+//! With the `tracing` feature, a memo or [`Memoized`](crate::Memoized)
+//! field that computes its hash emits a `DEBUG` event with target
+//! `hashmemo`, carrying the value's `type_name`, the resulting `hash` and
+//! the number of `bytes` fed to the hasher, an approximation of the size of
+//! the value. A warm cache emits nothing and formats nothing, so only the
+//! computations that can cause a latency spike show up in traces.
+
+use std::any::type_name;
+use std::hash::{BuildHasher, Hash};
+
+use crate::counting::hash_counted;
+
+/// Hashes `value` like `hasher.hash_one(value)`, remapping `0` to `1`, and
+/// emits the cold-path event.
+pub(crate) fn hash_cold<T, H>(hasher: &H, value: &T) -> u64
+where
+    T: Hash + ?Sized,
+    H: BuildHasher,
+{
+    let (hash, bytes) = hash_counted(hasher, value);
+    cold_event::<T>(hash, bytes);
+    hash
+}
+
+/// Emits the cold-path event for a hash computed over `bytes` bytes.
+pub(crate) fn cold_event<T: ?Sized>(hash: u64, bytes: usize) {
+    ::tracing::debug!(
+        target: "hashmemo",
+        type_name = type_name::<T>(),
+        hash,
+        bytes,
+        "computed memoized hash",
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::{BuildHasherDefault, DefaultHasher, RandomState};
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{remap_zero, HashMemo, Memoized};
+
+    /// Collects the formatted events.
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Runs `f` with a subscriber recording `DEBUG` events and returns them,
+    /// one per line.
+    fn events(f: impl FnOnce()) -> Vec<String> {
+        let capture = Capture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(::tracing::Level::DEBUG)
+            .with_ansi(false)
+            .without_time()
+            .with_writer(move || writer.clone())
+            .finish();
+        ::tracing::subscriber::with_default(subscriber, f);
+
+        let output = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        output.lines().map(str::to_owned).collect()
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn one_event_per_cold_computation() {
+        let state = RandomState::new();
+        let memos: Vec<_> = ["a", "b", "c"]
+            .iter()
+            .map(|s| HashMemo::new(s.repeat(1000)))
+            .collect();
+
+        let cold = events(|| {
+            for _ in 0..5 {
+                memos.iter().for_each(|memo| {
+                    state.hash_one(memo);
+                });
+            }
+        });
+        assert_eq!(cold.len(), 3);
+        for (event, memo) in cold.iter().zip(&memos) {
+            assert!(event.contains("computed memoized hash"), "{event}");
+            assert!(event.contains("type_name=\"alloc::string::String\""));
+            assert!(event.contains(&format!("hash={}", memo.hash_one_cached())));
+            // `str` hashing writes the bytes and a terminator.
+            assert!(event.contains("bytes=1001"), "{event}");
+        }
+
+        let warm = events(|| {
+            memos.iter().for_each(|memo| {
+                state.hash_one(memo);
+            });
+        });
+        assert!(warm.is_empty(), "{warm:?}");
+    }
+
+    #[test]
+    fn counting_does_not_change_the_hash() {
+        let hasher = BuildHasherDefault::<DefaultHasher>::default();
+        let value = (1u8, -2i64, 3u128, "four", vec![5usize]);
+        assert_eq!(
+            hash_cold(&hasher, &value),
+            remap_zero(hasher.hash_one(&value))
+        );
+
+        let field = Memoized::new("field".to_string());
+        let lines = events(|| {
+            RandomState::new().hash_one(&field);
+        });
+        assert_eq!(lines.len(), 1);
+    }
+}
@@ -0,0 +1,281 @@
+//! A memo that hashes exactly like its value.
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher};
+use std::ops::Deref;
+
+use crate::HashMemo;
+
+/// A [`HashMemo`] whose `Hash` impl replays the value's own hashing, for
+/// introducing memos gradually.
+///
+/// Hashing a `TransparentMemo<T>` feeds the outer hasher exactly what
+/// hashing `T` would, so it agrees with `T` under every hasher. Maps keyed
+/// by it answer plain `&T` lookups through `Borrow<T>`, and code keyed by
+/// `T` can look entries up through the memo's `Deref`. That makes it safe
+/// to mix with code that has not switched over yet, at the price of
+/// rehashing the value on every map operation, as `T` itself does.
+///
+/// The memoized hash is still there: [`memo`](Self::memo) hands out the
+/// underlying [`HashMemo`] for maps keyed by memos and
+/// [`Probe`](crate::Probe) lookups, and [`into_memo`](Self::into_memo)
+/// finishes the migration with the cache intact. For the same reason this
+/// type does not implement `Borrow<dyn MemoLookup<Q>>`: those lookups hash
+/// the cached `u64`, not the value.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::TransparentMemo;
+/// use std::collections::HashMap;
+/// use std::hash::{BuildHasher, RandomState};
+///
+/// let key = TransparentMemo::new("key".to_string());
+/// let state = RandomState::new();
+/// assert_eq!(state.hash_one(&key), state.hash_one("key".to_string()));
+///
+/// let mut legacy: HashMap<String, u32> = HashMap::new();
+/// legacy.insert("key".to_string(), 1);
+/// assert_eq!(legacy.get(&*key), Some(&1));
+///
+/// let mut migrated: HashMap<TransparentMemo<String>, u32> = HashMap::new();
+/// migrated.insert(key, 2);
+/// assert_eq!(migrated.get("key"), Some(&2));
+/// ```
+pub struct TransparentMemo<T, H = BuildHasherDefault<DefaultHasher>>(HashMemo<T, H>)
+where
+    T: Eq + Hash,
+    H: BuildHasher;
+
+impl<T> TransparentMemo<T>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new `TransparentMemo` with the default hasher.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self(HashMemo::new(value))
+    }
+}
+
+impl<T, H> TransparentMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Creates a new `TransparentMemo` with a custom hasher for the
+    /// memoized hash.
+    ///
+    /// The hasher does not affect `Hash`, which always replays the value.
+    #[inline]
+    pub const fn with_hasher(value: T, hasher: H) -> Self {
+        Self(HashMemo::with_hasher(value, hasher))
+    }
+
+    /// Returns the underlying memo, which hashes as its cached `u64`.
+    #[inline]
+    pub fn memo(&self) -> &HashMemo<T, H> {
+        &self.0
+    }
+
+    /// Consumes the `TransparentMemo` and returns the underlying memo,
+    /// cache included.
+    #[inline]
+    pub fn into_memo(self) -> HashMemo<T, H> {
+        self.0
+    }
+
+    /// Consumes the `TransparentMemo` and returns the value.
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> T {
+        self.0.into_inner()
+    }
+}
+
+impl<T, H> Deref for TransparentMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.0.value
+    }
+}
+
+impl<T, H> AsRef<T> for TransparentMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    #[inline]
+    fn as_ref(&self) -> &T {
+        &self.0.value
+    }
+}
+
+impl<T, H> Borrow<T> for TransparentMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    #[inline]
+    fn borrow(&self) -> &T {
+        &self.0.value
+    }
+}
+
+impl<H> Borrow<str> for TransparentMemo<String, H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn borrow(&self) -> &str {
+        &self.0.value
+    }
+}
+
+impl<T, H> Hash for TransparentMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    #[inline]
+    fn hash<H2: Hasher>(&self, state: &mut H2) {
+        self.0.value.hash(state);
+    }
+}
+
+impl<T, H> PartialEq for TransparentMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T, H> Eq for TransparentMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+}
+
+impl<T, H> Clone for TransparentMemo<T, H>
+where
+    T: Eq + Hash + Clone,
+    H: BuildHasher + Clone,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T, H> Default for TransparentMemo<T, H>
+where
+    T: Eq + Hash + Default,
+    H: BuildHasher + Default,
+{
+    #[inline]
+    fn default() -> Self {
+        Self(HashMemo::default())
+    }
+}
+
+impl<T, H> fmt::Debug for TransparentMemo<T, H>
+where
+    T: Eq + Hash + fmt::Debug,
+    H: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.value.fmt(f)
+    }
+}
+
+impl<T, H> From<HashMemo<T, H>> for TransparentMemo<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    #[inline]
+    fn from(memo: HashMemo<T, H>) -> Self {
+        Self(memo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use std::hash::RandomState;
+
+    use super::*;
+    use crate::{FixedState, Probe};
+
+    fn assert_hashes_like_the_value<T, S>(value: T, state: &S)
+    where
+        T: Eq + Hash + Clone + fmt::Debug,
+        S: BuildHasher,
+    {
+        let memo = TransparentMemo::new(value.clone());
+        memo.memo().precompute();
+        assert_eq!(state.hash_one(&memo), state.hash_one(&value), "{value:?}");
+    }
+
+    #[test]
+    fn hashes_like_the_bare_value() {
+        let random = RandomState::new();
+        for value in ["", "key", "a much longer key than the others"] {
+            assert_hashes_like_the_value(value.to_string(), &random);
+            assert_hashes_like_the_value(value.to_string(), &FixedState);
+            assert_hashes_like_the_value(value.to_string(), &ahash::RandomState::new());
+            assert_hashes_like_the_value(
+                value.to_string(),
+                &BuildHasherDefault::<DefaultHasher>::default(),
+            );
+        }
+        assert_hashes_like_the_value(vec![1u64, 2, 3], &random);
+        assert_hashes_like_the_value((7u8, 'x', -1i64), &FixedState);
+    }
+
+    #[test]
+    fn interoperates_with_plain_string_maps() {
+        let mut legacy: HashMap<String, usize> = HashMap::new();
+        legacy.insert("alpha".to_string(), 1);
+        legacy.insert("beta".to_string(), 2);
+
+        let alpha = TransparentMemo::new("alpha".to_string());
+        assert_eq!(legacy.get(&*alpha), Some(&1));
+        assert_eq!(legacy.get(alpha.as_str()), Some(&1));
+
+        let mut migrated: HashMap<TransparentMemo<String>, usize> = HashMap::new();
+        migrated.insert(alpha.clone(), 1);
+        migrated.insert(TransparentMemo::new("beta".to_string()), 2);
+        assert_eq!(migrated.get("alpha"), Some(&1));
+        let beta = String::from("beta");
+        assert_eq!(migrated.get(&beta), Some(&2));
+        assert_eq!(migrated.get(&alpha), Some(&1));
+        assert_eq!(migrated.get("gamma"), None);
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn the_cache_serves_memo_keyed_maps() {
+        let key = TransparentMemo::new("key".to_string());
+        let set: HashSet<HashMemo<String>> = [key.memo().clone()].into_iter().collect();
+        assert!(set.contains(key.memo()));
+        assert!(set.contains(Probe::new("key").as_lookup()));
+
+        let hash = key.memo().memoized_hash();
+        let memo = key.into_memo();
+        assert_eq!(memo.hash.load(std::sync::atomic::Ordering::Relaxed), hash);
+        assert_eq!(TransparentMemo::from(memo).into_inner(), "key");
+    }
+}
@@ -0,0 +1,592 @@
+//! [`url`](::url) support for deduplicating URLs.
+//!
+//! `url::Url` compares and hashes its serialization, so URLs that a
+//! crawler would treat as the same page, differing only in a fragment, the
+//! case of a percent-escape or the order of query parameters, are distinct
+//! keys. [`MemoUrl`] parses and normalizes its text once, on construction,
+//! and memoizes the hash of the normalized URL, keeping the original text
+//! alongside. [`UrlNormalization`] picks which differences are normalized
+//! away.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::{MemoUrl, Probe};
+//! use std::collections::HashSet;
+//!
+//! let mut seen = HashSet::new();
+//! for link in [
+//!     "https://example.com/a?x=1&y=2#top",
+//!     "HTTPS://Example.com.:443/a?y=2&x=1",
+//!     "https://example.com/b",
+//! ] {
+//!     seen.insert(MemoUrl::try_from(link).unwrap());
+//! }
+//! assert_eq!(seen.len(), 2);
+//!
+//! // Maps keyed by `MemoUrl` are probed with normalized text.
+//! let url = MemoUrl::try_from("https://EXAMPLE.com/b#bottom").unwrap();
+//! assert_eq!(url.as_str(), "https://example.com/b");
+//! assert!(seen.contains(Probe::new(url.as_str()).as_lookup()));
+//! ```
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher};
+
+use ::url::{ParseError, Url};
+
+use crate::{HashMemo, MemoLookup};
+
+/// Default ports of schemes the `url` parser does not know.
+const DEFAULT_PORTS: [(&str, u16); 6] = [
+    ("git", 9418),
+    ("gopher", 70),
+    ("ldap", 389),
+    ("sftp", 22),
+    ("ssh", 22),
+    ("telnet", 23),
+];
+
+/// The differences between URLs that a [`MemoUrl`] normalizes away.
+///
+/// Every option is on by default. They apply on top of the `url` parser's
+/// own normalization, which always lowercases the scheme, lowercases the
+/// host and drops the default port of the special schemes (`http`,
+/// `https`, `ws`, `wss` and `ftp`), and resolves `.` and `..` path
+/// segments.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::UrlNormalization;
+///
+/// let keep_fragments = UrlNormalization::new().strip_fragment(false);
+/// let url = keep_fragments.normalize("https://example.com/a?b=1&a=2#top").unwrap();
+/// assert_eq!(url.as_str(), "https://example.com/a?a=2&b=1#top");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UrlNormalization {
+    strip_fragment: bool,
+    lowercase_host: bool,
+    trim_host_dot: bool,
+    drop_default_port: bool,
+    uppercase_escapes: bool,
+    sort_query: bool,
+}
+
+impl UrlNormalization {
+    /// Normalizes everything this type can.
+    pub const ALL: Self = Self {
+        strip_fragment: true,
+        lowercase_host: true,
+        trim_host_dot: true,
+        drop_default_port: true,
+        uppercase_escapes: true,
+        sort_query: true,
+    };
+
+    /// Normalizes nothing beyond what the `url` parser does.
+    pub const NONE: Self = Self {
+        strip_fragment: false,
+        lowercase_host: false,
+        trim_host_dot: false,
+        drop_default_port: false,
+        uppercase_escapes: false,
+        sort_query: false,
+    };
+
+    /// Creates a normalization with every option on.
+    #[inline]
+    pub const fn new() -> Self {
+        Self::ALL
+    }
+
+    /// Sets whether the fragment is dropped, so `/a#top` and `/a` are one
+    /// URL.
+    pub const fn strip_fragment(mut self, strip_fragment: bool) -> Self {
+        self.strip_fragment = strip_fragment;
+        self
+    }
+
+    /// Sets whether hosts of the non-special schemes, such as `ssh` or
+    /// `git`, are ASCII-lowercased as the parser does for the special ones.
+    pub const fn lowercase_host(mut self, lowercase_host: bool) -> Self {
+        self.lowercase_host = lowercase_host;
+        self
+    }
+
+    /// Sets whether a domain's trailing root dot is dropped, so
+    /// `example.com.` and `example.com` are one host.
+    pub const fn trim_host_dot(mut self, trim_host_dot: bool) -> Self {
+        self.trim_host_dot = trim_host_dot;
+        self
+    }
+
+    /// Sets whether a port is dropped when it is the scheme's default.
+    ///
+    /// The parser always does this for the special schemes; this extends it
+    /// to `git`, `gopher`, `ldap`, `sftp`, `ssh` and `telnet`.
+    pub const fn drop_default_port(mut self, drop_default_port: bool) -> Self {
+        self.drop_default_port = drop_default_port;
+        self
+    }
+
+    /// Sets whether the hex digits of percent-escapes are uppercased, so
+    /// `%2f` and `%2F` are one escape.
+    pub const fn uppercase_escapes(mut self, uppercase_escapes: bool) -> Self {
+        self.uppercase_escapes = uppercase_escapes;
+        self
+    }
+
+    /// Sets whether query parameters are sorted, so `?a=1&b=2` and
+    /// `?b=2&a=1` are one query.
+    ///
+    /// Parameters are sorted by name as written, without decoding them, and
+    /// the sort is stable, so the values of a repeated name keep their
+    /// order.
+    pub const fn sort_query(mut self, sort_query: bool) -> Self {
+        self.sort_query = sort_query;
+        self
+    }
+
+    /// Parses `text` and normalizes the URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns the parser's error if `text` is not an absolute URL.
+    pub fn normalize(&self, text: &str) -> Result<Url, ParseError> {
+        let mut url = Url::parse(text)?;
+        if self.uppercase_escapes {
+            if let Some(upper) = uppercase_escapes(url.as_str()) {
+                url = Url::parse(&upper)?;
+            }
+        }
+        if self.strip_fragment {
+            url.set_fragment(None);
+        }
+        if self.lowercase_host || self.trim_host_dot {
+            if let Some(host) = url.host_str() {
+                let mut normal = host;
+                if self.trim_host_dot && url.domain().is_some() {
+                    normal = normal.strip_suffix('.').unwrap_or(normal);
+                }
+                let normal = if self.lowercase_host {
+                    normal.to_ascii_lowercase()
+                } else {
+                    normal.to_string()
+                };
+                if normal != host {
+                    url.set_host(Some(&normal))?;
+                }
+            }
+        }
+        if self.drop_default_port {
+            if let Some(port) = url.port() {
+                let default = DEFAULT_PORTS
+                    .iter()
+                    .find(|&&(scheme, _)| scheme == url.scheme());
+                if default.is_some_and(|&(_, default)| default == port) {
+                    // Only fails for URLs without a host, which have no port.
+                    let _ = url.set_port(None);
+                }
+            }
+        }
+        if self.sort_query {
+            if let Some(query) = url.query() {
+                let mut params: Vec<&str> = query.split('&').collect();
+                if params
+                    .windows(2)
+                    .any(|pair| param_name(&pair[0]) > param_name(&pair[1]))
+                {
+                    params.sort_by_key(param_name);
+                    let sorted = params.join("&");
+                    url.set_query(Some(&sorted));
+                }
+            }
+        }
+        Ok(url)
+    }
+}
+
+impl Default for UrlNormalization {
+    #[inline]
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// Returns the name of a query parameter, the part before any `=`.
+fn param_name<'a>(param: &&'a str) -> &'a str {
+    param.split('=').next().unwrap_or_default()
+}
+
+/// Returns `text` with the hex digits of its percent-escapes uppercased,
+/// or `None` if they already are.
+fn uppercase_escapes(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let lower = |i: usize| bytes.get(i).is_some_and(u8::is_ascii_lowercase);
+    let hex = |i: usize| bytes.get(i).is_some_and(u8::is_ascii_hexdigit);
+    let needs = (0..bytes.len())
+        .any(|i| bytes[i] == b'%' && hex(i + 1) && hex(i + 2) && (lower(i + 1) || lower(i + 2)));
+    if !needs {
+        return None;
+    }
+
+    let mut out = bytes.to_vec();
+    for i in 0..bytes.len() {
+        if bytes[i] == b'%' && hex(i + 1) && hex(i + 2) {
+            out[i + 1].make_ascii_uppercase();
+            out[i + 2].make_ascii_uppercase();
+        }
+    }
+    // Only ASCII letters changed case.
+    Some(String::from_utf8(out).expect("uppercasing ASCII keeps UTF-8 valid"))
+}
+
+/// A URL normalized on construction, with a memoized hash of the normalized
+/// form.
+///
+/// Two `MemoUrl`s are equal when their normalized URLs are, whatever their
+/// original text, and they hash as a `HashMemo<String, H>` of the
+/// normalized text would. [`original`](Self::original) returns the text the
+/// URL was parsed from.
+///
+/// A map or set keyed by `MemoUrl` can be probed with the normalized text
+/// through [`Probe`](crate::Probe), built with the same hasher; normalize
+/// raw text with [`UrlNormalization::normalize`] first, or build a
+/// `MemoUrl` and probe with [`as_str`](Self::as_str). URLs that meet in one
+/// collection must be normalized alike.
+pub struct MemoUrl<H = BuildHasherDefault<DefaultHasher>>
+where
+    H: BuildHasher,
+{
+    url: HashMemo<Url, H>,
+    /// The text parsed, if it differs from the normalized URL.
+    original: Option<Box<str>>,
+}
+
+impl MemoUrl {
+    /// Parses and normalizes `text` with every [`UrlNormalization`] option
+    /// on.
+    ///
+    /// # Errors
+    ///
+    /// Returns the parser's error if `text` is not an absolute URL.
+    #[inline]
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        Self::parse_with(text, &UrlNormalization::ALL)
+    }
+
+    /// Parses `text` and normalizes it with `normalization`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the parser's error if `text` is not an absolute URL.
+    #[inline]
+    pub fn parse_with(text: &str, normalization: &UrlNormalization) -> Result<Self, ParseError> {
+        Self::parse_with_hasher(text, normalization, BuildHasherDefault::new())
+    }
+}
+
+impl<H> MemoUrl<H>
+where
+    H: BuildHasher,
+{
+    /// Parses `text`, normalizes it with `normalization` and memoizes its
+    /// hash with `hasher`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the parser's error if `text` is not an absolute URL.
+    pub fn parse_with_hasher(
+        text: &str,
+        normalization: &UrlNormalization,
+        hasher: H,
+    ) -> Result<Self, ParseError> {
+        let url = normalization.normalize(text)?;
+        let original = (url.as_str() != text).then(|| text.into());
+        Ok(Self {
+            url: HashMemo::with_hasher(url, hasher),
+            original,
+        })
+    }
+
+    /// Returns the normalized URL.
+    #[inline]
+    pub fn as_url(&self) -> &Url {
+        &self.url.value
+    }
+
+    /// Returns the normalized URL's text.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        self.url.value.as_str()
+    }
+
+    /// Returns the text the URL was parsed from.
+    #[inline]
+    pub fn original(&self) -> &str {
+        self.original.as_deref().unwrap_or_else(|| self.as_str())
+    }
+
+    /// Returns the memoized hash of the normalized URL, computing and
+    /// caching it if needed.
+    #[inline]
+    pub fn memoized_hash(&self) -> u64 {
+        self.url.hash_one_cached()
+    }
+
+    /// Returns the normalized URL, dropping the original text.
+    #[inline]
+    pub fn into_url(self) -> Url {
+        self.url.into_inner()
+    }
+}
+
+impl TryFrom<&str> for MemoUrl {
+    type Error = ParseError;
+
+    #[inline]
+    fn try_from(text: &str) -> Result<Self, ParseError> {
+        Self::parse(text)
+    }
+}
+
+impl<H> Clone for MemoUrl<H>
+where
+    H: BuildHasher + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            url: self.url.clone(),
+            original: self.original.clone(),
+        }
+    }
+}
+
+impl<H> PartialEq for MemoUrl<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.url == other.url
+    }
+}
+
+impl<H> Eq for MemoUrl<H> where H: BuildHasher {}
+
+impl<H> Hash for MemoUrl<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn hash<S: Hasher>(&self, state: &mut S) {
+        self.url.hash(state);
+    }
+}
+
+impl<H> MemoLookup<str> for MemoUrl<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn memo_hash(&self) -> u64 {
+        self.memoized_hash()
+    }
+
+    #[inline]
+    fn memo_value(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<'a, H> Borrow<dyn MemoLookup<str> + 'a> for MemoUrl<H>
+where
+    H: BuildHasher + 'a,
+{
+    #[inline]
+    fn borrow(&self) -> &(dyn MemoLookup<str> + 'a) {
+        self
+    }
+}
+
+impl<H> AsRef<Url> for MemoUrl<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn as_ref(&self) -> &Url {
+        self.as_url()
+    }
+}
+
+impl<H> fmt::Display for MemoUrl<H>
+where
+    H: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<H> fmt::Debug for MemoUrl<H>
+where
+    H: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoUrl")
+            .field("url", &self.as_str())
+            .field("original", &self.original())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{FixedState, Probe};
+
+    #[test]
+    fn equivalent_urls_are_one_key() {
+        let equivalent = [
+            ("http://example.com/", "HTTP://EXAMPLE.COM:80/"),
+            ("https://example.com/a", "https://example.com/a#section"),
+            ("https://example.com/a", "https://example.com./a"),
+            ("https://example.com/a%2Fb", "https://example.com/a%2fb"),
+            (
+                "https://example.com/?a=1&b=2",
+                "https://example.com/?b=2&a=1",
+            ),
+            ("https://example.com/a/c", "https://example.com/a/b/../c"),
+            ("ssh://host.example/repo", "ssh://HOST.example:22/repo"),
+            ("git://example.com/r", "git://example.com:9418/r"),
+        ];
+        for (a, b) in equivalent {
+            let (a, b) = (MemoUrl::parse(a).unwrap(), MemoUrl::parse(b).unwrap());
+            assert!(a == b, "{a:?} == {b:?}");
+            assert_eq!(a.memoized_hash(), b.memoized_hash(), "{a:?}");
+        }
+
+        let different = [
+            ("http://example.com/", "https://example.com/"),
+            ("https://example.com/", "https://example.com:8443/"),
+            ("https://example.com/a", "https://example.com/A"),
+            ("https://example.com/a", "https://example.com/a/"),
+            ("https://example.com/?a=1", "https://example.com/?a=2"),
+            (
+                "https://example.com/?a=1&a=2",
+                "https://example.com/?a=2&a=1",
+            ),
+            ("https://example.com/%2F", "https://example.com//"),
+            ("ssh://host/", "ssh://host:2222/"),
+        ];
+        for (a, b) in different {
+            let (a, b) = (MemoUrl::parse(a).unwrap(), MemoUrl::parse(b).unwrap());
+            assert!(a != b, "{a:?} != {b:?}");
+        }
+    }
+
+    #[test]
+    fn options_can_be_turned_off() {
+        let fragment = "https://example.com/a#top";
+        let plain = UrlNormalization::NONE;
+        assert_eq!(plain.normalize(fragment).unwrap().as_str(), fragment);
+        assert_eq!(
+            plain
+                .strip_fragment(true)
+                .normalize(fragment)
+                .unwrap()
+                .as_str(),
+            "https://example.com/a"
+        );
+
+        let messy = "ssh://Host.Example.:22/a%2fb?b=2&a=1";
+        assert_eq!(plain.normalize(messy).unwrap().as_str(), messy);
+        assert_eq!(
+            UrlNormalization::ALL.normalize(messy).unwrap().as_str(),
+            "ssh://host.example/a%2Fb?a=1&b=2"
+        );
+        assert_eq!(
+            UrlNormalization::ALL
+                .sort_query(false)
+                .normalize(messy)
+                .unwrap()
+                .as_str(),
+            "ssh://host.example/a%2Fb?b=2&a=1"
+        );
+    }
+
+    #[test]
+    fn the_original_text_is_kept() {
+        let url = MemoUrl::parse("HTTPS://Example.com/a#x").unwrap();
+        assert_eq!(url.as_str(), "https://example.com/a");
+        assert_eq!(url.original(), "HTTPS://Example.com/a#x");
+        assert_eq!(url.to_string(), "https://example.com/a");
+
+        let normal = MemoUrl::parse("https://example.com/a").unwrap();
+        assert!(normal.original.is_none());
+        assert_eq!(normal.original(), "https://example.com/a");
+    }
+
+    #[test]
+    fn parse_failures_are_errors() {
+        assert_eq!(
+            MemoUrl::try_from("not a url").unwrap_err(),
+            ParseError::RelativeUrlWithoutBase
+        );
+        assert_eq!(
+            MemoUrl::try_from("http://[::1/").unwrap_err(),
+            ParseError::InvalidIpv6Address
+        );
+        assert_eq!(
+            MemoUrl::try_from("http://example.com:99999/").unwrap_err(),
+            ParseError::InvalidPort
+        );
+    }
+
+    #[test]
+    fn crawled_links_dedup_in_a_map() {
+        let crawled = [
+            "https://example.com/",
+            "https://EXAMPLE.com:443/#nav",
+            "https://example.com/docs?page=2&lang=en",
+            "https://example.com/docs?lang=en&page=2#install",
+            "https://example.com/docs?lang=fr&page=2",
+            "https://example.com./docs/../",
+        ];
+        let mut counts: HashMap<MemoUrl, usize> = HashMap::new();
+        for link in crawled {
+            *counts.entry(MemoUrl::try_from(link).unwrap()).or_default() += 1;
+        }
+        assert_eq!(counts.len(), 3);
+
+        let lookup = |text: &str| {
+            let url = UrlNormalization::ALL.normalize(text).unwrap();
+            counts.get(Probe::new(url.as_str()).as_lookup()).copied()
+        };
+        assert_eq!(lookup("https://example.com"), Some(3));
+        assert_eq!(lookup("https://example.com/docs?page=2&lang=en"), Some(2));
+        assert_eq!(lookup("https://example.com/docs?lang=fr&page=2"), Some(1));
+        assert_eq!(lookup("https://example.com/blog"), None);
+    }
+
+    #[test]
+    fn hashes_like_a_string_memo_of_the_normalized_text() {
+        let url = MemoUrl::parse_with_hasher(
+            "https://example.com/a#b",
+            &UrlNormalization::ALL,
+            FixedState,
+        )
+        .unwrap();
+        let text = HashMemo::new_fixed("https://example.com/a".to_string());
+        assert_eq!(url.memoized_hash(), text.hash_one_cached());
+        assert_eq!(
+            Probe::with_hasher(url.as_str(), &FixedState).hash(),
+            url.memoized_hash()
+        );
+    }
+}
@@ -0,0 +1,265 @@
+//! Conversions between vectors of values and vectors of memos.
+//!
+//! Each conversion moves the elements without cloning them, in a single
+//! pass. A `HashMemo<T>` is larger than `T` by its cached hash, so wrapping
+//! cannot reuse the buffer of the values, which is too small for the memos:
+//! the result is allocated once, at exactly the source's length. Unwrapping
+//! goes through `collect`, which keeps the memos' buffer for the values
+//! when the standard library can shrink it in place, and otherwise also
+//! allocates once, exactly.
+
+use std::collections::HashSet;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash};
+
+use crate::cell::HashCell;
+use crate::{HashMemo, PassthroughState};
+
+/// Wraps every element of `values` in a cold [`HashMemo`], in order.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{unwrap_vec, wrap_vec};
+///
+/// let memos = wrap_vec(vec!["a".to_string(), "b".to_string()]);
+/// assert_eq!(memos[1].as_ref(), "b");
+/// assert_eq!(unwrap_vec(memos), ["a", "b"]);
+/// ```
+pub fn wrap_vec<T>(values: Vec<T>) -> Vec<HashMemo<T>>
+where
+    T: Eq + Hash,
+{
+    values.into_iter().map(HashMemo::new).collect()
+}
+
+/// Unwraps every memo of `memos` into its value, in order, dropping the
+/// cached hashes.
+pub fn unwrap_vec<T, H>(memos: Vec<HashMemo<T, H>>) -> Vec<T>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    memos.into_iter().map(HashMemo::into_inner).collect()
+}
+
+/// Like [`wrap_vec`], but hashes every element while wrapping it, so each
+/// memo comes out primed.
+pub fn wrap_vec_eager<T>(values: Vec<T>) -> Vec<HashMemo<T>>
+where
+    T: Eq + Hash,
+{
+    wrap_vec_eager_with(values, BuildHasherDefault::<DefaultHasher>::new())
+}
+
+/// Like [`wrap_vec_eager`], with every memo hashing with a clone of
+/// `hasher`.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{wrap_vec_eager_with, FixedState};
+/// use std::hash::BuildHasher;
+///
+/// let memos = wrap_vec_eager_with(vec![1u64, 2, 3], FixedState);
+/// assert_eq!(memos[0].hash_one_cached(), FixedState.hash_one(1u64));
+/// ```
+pub fn wrap_vec_eager_with<T, H>(values: Vec<T>, hasher: H) -> Vec<HashMemo<T, H>>
+where
+    T: Eq + Hash,
+    H: BuildHasher + Clone,
+{
+    values
+        .into_iter()
+        .map(|value| {
+            let memo = HashMemo::with_hasher(value, hasher.clone());
+            memo.precompute();
+            memo
+        })
+        .collect()
+}
+
+/// Removes repeated values from `items`, keeping the first occurrence of
+/// each in order.
+///
+/// Each value is hashed once, into a memo. The set of values seen uses
+/// [`PassthroughState`], so growing it reuses the cached hashes where a
+/// plain `HashSet<T>` would hash every value again on each resize, which
+/// dominates for large values such as long strings.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::dedup_memoized;
+///
+/// let words = vec!["b", "a", "b", "c", "a"];
+/// assert_eq!(dedup_memoized(words), ["b", "a", "c"]);
+/// ```
+pub fn dedup_memoized<T>(items: Vec<T>) -> Vec<T>
+where
+    T: Eq + Hash,
+{
+    let memos = wrap_vec(items);
+    let mut seen = HashSet::with_capacity_and_hasher(memos.len(), PassthroughState);
+    let keep: Vec<bool> = memos.iter().map(|memo| seen.insert(memo)).collect();
+    drop(seen);
+
+    memos
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(memo, keep)| keep.then(|| memo.into_inner()))
+        .collect()
+}
+
+impl<T, H> HashMemo<Vec<T>, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Appends `value`, clearing the cached hash.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let mut memo = HashMemo::new(vec![1, 2]);
+    /// memo.hash_one_cached();
+    /// memo.push(3);
+    /// assert_eq!(memo.hash_one_cached(), HashMemo::new(vec![1, 2, 3]).hash_one_cached());
+    /// ```
+    #[inline]
+    pub fn push(&mut self, value: T) {
+        self.value.push(value);
+        self.invalidate();
+    }
+
+    /// Appends every item of `iter`, clearing the cached hash.
+    pub fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        self.value.extend(iter);
+        self.invalidate();
+    }
+
+    /// Shortens the vector to `len` elements, clearing the cached hash if
+    /// any were dropped.
+    #[inline]
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.value.len() {
+            self.value.truncate(len);
+            self.invalidate();
+        }
+    }
+
+    #[inline]
+    fn invalidate(&mut self) {
+        #[cfg(feature = "stats")]
+        crate::stats::invalidation();
+        self.hash.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hasher;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    /// Counts how often it is hashed, across all instances.
+    #[derive(Debug, PartialEq, Eq)]
+    struct Counted(u32);
+
+    impl Hash for Counted {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            COUNT.fetch_add(1, Ordering::Relaxed);
+            self.0.hash(state);
+        }
+    }
+
+    #[test]
+    fn round_trips_preserve_order() {
+        let values: Vec<String> = (0..100).map(|i| i.to_string()).collect();
+        let memos = wrap_vec(values.clone());
+        assert!(memos.iter().map(AsRef::as_ref).eq(&values));
+        assert_eq!(unwrap_vec(memos), values);
+
+        let memos = wrap_vec_eager(values.clone());
+        assert!(memos.iter().map(AsRef::as_ref).eq(&values));
+        assert_eq!(unwrap_vec(memos), values);
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn wrapping_hashes_each_element_at_most_once() {
+        let before = COUNT.load(Ordering::Relaxed);
+        let memos = wrap_vec_eager((0..64).map(Counted).collect());
+        assert_eq!(COUNT.load(Ordering::Relaxed) - before, 64);
+
+        for (i, memo) in memos.iter().enumerate() {
+            assert_eq!(
+                memo.hash_one_cached(),
+                HashMemo::new(Counted(i as u32)).hash_one_cached()
+            );
+        }
+        assert_eq!(COUNT.load(Ordering::Relaxed) - before, 128);
+
+        let before = COUNT.load(Ordering::Relaxed);
+        let values = unwrap_vec(wrap_vec((0..64).map(Counted).collect()));
+        assert_eq!(COUNT.load(Ordering::Relaxed), before);
+        assert_eq!(values.len(), 64);
+
+        let before = COUNT.load(Ordering::Relaxed);
+        let counted: Vec<Counted> = (0..300).map(|i| Counted(i % 100)).collect();
+        assert_eq!(
+            dedup_memoized(counted),
+            (0..100).map(Counted).collect::<Vec<_>>()
+        );
+        assert_eq!(COUNT.load(Ordering::Relaxed) - before, 300);
+    }
+
+    #[test]
+    fn dedup_keeps_first_occurrences_in_order() {
+        let large = |c: char| c.to_string().repeat(10_000);
+        let items: Vec<String> = "abacbdca".chars().map(large).collect();
+        let expected: Vec<String> = "abcd".chars().map(large).collect();
+        assert_eq!(dedup_memoized(items), expected);
+    }
+
+    #[test]
+    fn empty_vectors_stay_empty() {
+        assert!(dedup_memoized(Vec::<String>::new()).is_empty());
+        assert!(wrap_vec(Vec::<String>::new()).is_empty());
+        assert!(wrap_vec_eager(Vec::<String>::new()).is_empty());
+        assert!(unwrap_vec(Vec::<HashMemo<String>>::new()).is_empty());
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn mutations_rehash_like_fresh_memos() {
+        let mut memo = HashMemo::new(vec!["a".to_string()]);
+        let check = |memo: &HashMemo<Vec<String>>| {
+            let fresh = HashMemo::new(memo.as_ref().clone());
+            assert_eq!(memo.hash.load(Ordering::Relaxed), 0);
+            assert_eq!(memo.hash_one_cached(), fresh.hash_one_cached());
+            assert_eq!(*memo, fresh);
+        };
+        memo.precompute();
+
+        memo.push("b".to_string());
+        check(&memo);
+        memo.extend(["c", "d", "e"].map(String::from));
+        check(&memo);
+        memo.truncate(2);
+        check(&memo);
+        assert_eq!(memo.as_ref(), &["a", "b"]);
+
+        // Truncating to the current length or beyond keeps the cache.
+        let hash = memo.hash_one_cached();
+        memo.truncate(5);
+        assert_eq!(memo.hash.load(Ordering::Relaxed), hash);
+    }
+}
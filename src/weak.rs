@@ -0,0 +1,348 @@
+//! A cache coalescing equal values into shared memos it does not own.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash};
+use std::sync::{Arc, Weak};
+
+use crate::{ArcMemo, HashMemo, PassthroughState, Probe};
+
+/// The fewest entries a full prune waits for.
+const MIN_PRUNE_AT: usize = 32;
+
+/// Hands out [`ArcMemo`]s, one per distinct live value, holding only weak
+/// references to them.
+///
+/// Building equal values through the set yields clones of one `ArcMemo`
+/// for as long as any of them is alive, so they share one allocation and
+/// one cached hash, and compare by pointer. The set never keeps a value
+/// alive: once the last handle is dropped, so is the value, and its entry
+/// is dead until pruned. A dead entry still holds the memo's allocation,
+/// but not the value's heap.
+///
+/// The index maps memoized hashes to the entries with that hash, so a
+/// lookup hashes the query once and compares it with live values of the
+/// same hash only. [`prune`](Self::prune) drops every dead entry; inserting
+/// prunes the entries sharing the new value's hash, and prunes the whole
+/// set whenever its entries have doubled since the last full prune, so dead
+/// entries cost amortized O(1) per insert, and the set holds at most twice
+/// the entries the last full prune left, or 32.
+///
+/// Unlike a global interner, the set is a plain value: share it between
+/// threads behind a `Mutex`.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{ArcMemo, WeakMemoSet};
+///
+/// let mut set: WeakMemoSet<String> = WeakMemoSet::new();
+/// let a = set.get_or_insert_with("config", || "config".to_string());
+/// let b = set.get_or_insert_with("config", || unreachable!());
+/// assert!(ArcMemo::ptr_eq(&a, &b));
+///
+/// drop((a, b));
+/// assert!(set.get("config").is_none());
+/// set.prune();
+/// assert_eq!(set.len_alive(), 0);
+/// ```
+pub struct WeakMemoSet<T, H = BuildHasherDefault<DefaultHasher>>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    index: HashMap<u64, Vec<Weak<HashMemo<T, H>>>, PassthroughState>,
+    /// The number of entries, live or dead.
+    entries: usize,
+    /// The number of entries that triggers a full prune.
+    prune_at: usize,
+    hasher: H,
+}
+
+impl<T> WeakMemoSet<T>
+where
+    T: Eq + Hash,
+{
+    /// Creates an empty set whose memos use the default hasher.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_hasher(BuildHasherDefault::new())
+    }
+}
+
+impl<T> Default for WeakMemoSet<T>
+where
+    T: Eq + Hash,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, H> WeakMemoSet<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher,
+{
+    /// Creates an empty set whose memos hash with `hasher`.
+    pub fn with_hasher(hasher: H) -> Self {
+        Self {
+            index: HashMap::default(),
+            entries: 0,
+            prune_at: MIN_PRUNE_AT,
+            hasher,
+        }
+    }
+
+    /// Returns a handle to the live value equal to `value`, if there is
+    /// one.
+    pub fn get<Q>(&self, value: &Q) -> Option<ArcMemo<T, H>>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.find(Probe::with_hasher(value, &self.hasher).hash(), value)
+    }
+
+    /// Drops every dead entry.
+    pub fn prune(&mut self) {
+        self.index.retain(|_, bucket| {
+            bucket.retain(|weak| weak.strong_count() > 0);
+            !bucket.is_empty()
+        });
+        self.entries = self.index.values().map(Vec::len).sum();
+        self.prune_at = MIN_PRUNE_AT.max(2 * self.entries);
+    }
+
+    /// Returns the number of live values.
+    ///
+    /// This walks every entry; dead entries are not counted, whether or
+    /// not they have been pruned.
+    pub fn len_alive(&self) -> usize {
+        self.index
+            .values()
+            .flatten()
+            .filter(|weak| weak.strong_count() > 0)
+            .count()
+    }
+
+    /// Finds the live memo equal to `value` among those hashing to `hash`.
+    fn find<Q>(&self, hash: u64, value: &Q) -> Option<ArcMemo<T, H>>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Eq,
+    {
+        self.index
+            .get(&hash)?
+            .iter()
+            .filter_map(Weak::upgrade)
+            .find(|memo| memo.value.borrow() == value)
+            .map(ArcMemo)
+    }
+}
+
+impl<T, H> WeakMemoSet<T, H>
+where
+    T: Eq + Hash,
+    H: BuildHasher + Clone,
+{
+    /// Returns a handle to the live value equal to `value`, or registers
+    /// and returns a new one built by `make`.
+    ///
+    /// `value` is hashed once, and the new memo is seeded with that hash,
+    /// so `make` must return a value equal to `value`. This is checked in
+    /// debug builds.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if `make` returns a value that differs from
+    /// `value`.
+    pub fn get_or_insert_with<Q, F>(&mut self, value: &Q, make: F) -> ArcMemo<T, H>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+        F: FnOnce() -> T,
+    {
+        let hash = Probe::with_hasher(value, &self.hasher).hash();
+        if let Some(memo) = self.find(hash, value) {
+            return memo;
+        }
+
+        let made = make();
+        debug_assert!(
+            made.borrow() == value,
+            "get_or_insert_with: `make` returned a value unequal to the query"
+        );
+        let memo = Arc::new(HashMemo::with_hasher_const(made, self.hasher.clone(), hash));
+
+        let bucket = self.index.entry(hash).or_default();
+        let before = bucket.len();
+        bucket.retain(|weak| weak.strong_count() > 0);
+        bucket.push(Arc::downgrade(&memo));
+        self.entries = self.entries - before + bucket.len();
+        if self.entries >= self.prune_at {
+            self.prune();
+        }
+        ArcMemo(memo)
+    }
+}
+
+impl<T, H> fmt::Debug for WeakMemoSet<T, H>
+where
+    T: Eq + Hash + fmt::Debug,
+    H: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut set = f.debug_set();
+        for memo in self.index.values().flatten().filter_map(Weak::upgrade) {
+            set.entry(&memo.value);
+        }
+        set.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hasher;
+    use std::sync::Mutex;
+    use std::thread;
+
+    use super::*;
+
+    /// Hashes every value alike, so all entries share one bucket.
+    #[derive(Clone, Default)]
+    struct ConstantState;
+
+    impl BuildHasher for ConstantState {
+        type Hasher = ConstantHasher;
+
+        fn build_hasher(&self) -> ConstantHasher {
+            ConstantHasher
+        }
+    }
+
+    struct ConstantHasher;
+
+    impl Hasher for ConstantHasher {
+        fn finish(&self) -> u64 {
+            42
+        }
+
+        fn write(&mut self, _: &[u8]) {}
+    }
+
+    fn get_or_insert(set: &mut WeakMemoSet<String>, value: &str) -> ArcMemo<String> {
+        set.get_or_insert_with(value, || value.to_string())
+    }
+
+    #[test]
+    fn equal_values_coalesce_into_one_handle() {
+        let mut set = WeakMemoSet::new();
+        let a = get_or_insert(&mut set, "alpha");
+        let b = get_or_insert(&mut set, "beta");
+        let again = set.get_or_insert_with("alpha", || panic!("alpha is alive"));
+
+        assert!(ArcMemo::ptr_eq(&a, &again));
+        assert!(!ArcMemo::ptr_eq(&a, &b));
+        assert!(ArcMemo::ptr_eq(&set.get("beta").unwrap(), &b));
+        assert!(set.get("gamma").is_none());
+        assert_eq!(set.len_alive(), 2);
+        assert_eq!(a.as_ref(), "alpha");
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn handles_carry_the_probe_hash() {
+        let mut set = WeakMemoSet::new();
+        let memo = get_or_insert(&mut set, "alpha");
+        assert_eq!(memo.memo().hash_one_cached(), Probe::new("alpha").hash());
+    }
+
+    #[test]
+    fn dropped_handles_become_unreachable_and_are_pruned() {
+        let mut set = WeakMemoSet::new();
+        let kept = get_or_insert(&mut set, "kept");
+        let dropped = get_or_insert(&mut set, "dropped");
+        let clone = dropped.clone();
+
+        drop(dropped);
+        assert!(set.get("dropped").is_some(), "a clone is still alive");
+        drop(clone);
+        assert!(set.get("dropped").is_none());
+        assert_eq!(set.len_alive(), 1);
+        assert_eq!(set.entries, 2);
+
+        set.prune();
+        assert_eq!(set.entries, 1);
+        assert_eq!(set.index.len(), 1);
+
+        // A dropped value is built afresh.
+        let fresh = get_or_insert(&mut set, "dropped");
+        assert_eq!(fresh.as_ref(), "dropped");
+        assert!(ArcMemo::ptr_eq(&set.get("kept").unwrap(), &kept));
+    }
+
+    #[test]
+    fn inserts_bound_the_dead_entries() {
+        let mut set = WeakMemoSet::new();
+        let live: Vec<_> = (0..10)
+            .map(|i| get_or_insert(&mut set, &format!("live-{i}")))
+            .collect();
+        for i in 0..10_000 {
+            get_or_insert(&mut set, &format!("dead-{i}"));
+            assert!(set.entries <= MIN_PRUNE_AT.max(2 * live.len()));
+        }
+        assert_eq!(set.len_alive(), 10);
+    }
+
+    #[test]
+    fn colliding_values_are_told_apart() {
+        let mut set = WeakMemoSet::with_hasher(ConstantState);
+        let memos: Vec<_> = (0..50u32)
+            .map(|i| set.get_or_insert_with(&i, || i))
+            .collect();
+        for (i, memo) in (0..50u32).zip(&memos) {
+            assert!(ArcMemo::ptr_eq(&set.get(&i).unwrap(), memo));
+        }
+        assert_eq!(set.index.len(), 1);
+
+        // Inserting into the shared bucket prunes it.
+        drop(memos);
+        set.get_or_insert_with(&50, || 50);
+        assert_eq!(set.entries, 1);
+    }
+
+    #[test]
+    fn threads_sharing_a_mutexed_set_get_one_handle_per_value() {
+        let set = Mutex::new(WeakMemoSet::new());
+        let handles: Vec<Vec<ArcMemo<String>>> = thread::scope(|scope| {
+            let threads: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        (0..100)
+                            .map(|i| {
+                                let value = format!("value-{i}");
+                                get_or_insert(&mut set.lock().unwrap(), &value)
+                            })
+                            .collect()
+                    })
+                })
+                .collect();
+            threads.into_iter().map(|t| t.join().unwrap()).collect()
+        });
+
+        for thread in &handles[1..] {
+            for (a, b) in handles[0].iter().zip(thread) {
+                assert!(ArcMemo::ptr_eq(a, b));
+            }
+        }
+        let mut set = set.into_inner().unwrap();
+        assert_eq!(set.len_alive(), 100);
+        drop(handles);
+        set.prune();
+        assert_eq!(set.len_alive(), 0);
+        assert!(set.index.is_empty());
+    }
+}
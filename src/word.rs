@@ -0,0 +1,237 @@
+//! Memos whose cache is narrower than 64 bits.
+
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+use crate::cell;
+
+/// Defines a memo caching the hash truncated to `$word`, stored in
+/// `$atomic` and written to outer hashers with `$write`.
+macro_rules! narrow_memo {
+    ($(#[$attr:meta])* $name:ident, $atomic:ty, $word:ty, $write:ident) => {
+        $(#[$attr])*
+        #[derive(Debug)]
+        pub struct $name<T, H = BuildHasherDefault<DefaultHasher>>
+        where
+            T: Eq + Hash,
+            H: BuildHasher,
+        {
+            value: T,
+            hash: $atomic,
+            hasher: H,
+        }
+
+        impl<T> $name<T>
+        where
+            T: Eq + Hash,
+        {
+            #[doc = concat!("Creates a new `", stringify!($name), "` with the default hasher.")]
+            pub const fn new(value: T) -> Self {
+                Self::with_hasher(value, BuildHasherDefault::new())
+            }
+        }
+
+        impl<T, H> $name<T, H>
+        where
+            T: Eq + Hash,
+            H: BuildHasher,
+        {
+            #[doc = concat!("Creates a new `", stringify!($name), "` with a custom hasher.")]
+            pub const fn with_hasher(value: T, hasher: H) -> Self {
+                Self {
+                    value,
+                    hash: <$atomic>::new(0),
+                    hasher,
+                }
+            }
+
+            #[doc = concat!("Consumes the `", stringify!($name), "` and returns the wrapped value.")]
+            #[inline]
+            #[must_use]
+            pub fn into_inner(self) -> T {
+                self.value
+            }
+
+            /// Returns the cached hash, computing and publishing it on first use.
+            pub(crate) fn memoized_hash(&self) -> $word {
+                // Truncated before `get_or_compute` remaps it, so a zero low
+                // word is cached as `1`.
+                cell::get_or_compute(&self.hash, || {
+                    crate::hash_cold(&self.hasher, &self.value) as $word as u64
+                }) as $word
+            }
+        }
+
+        impl<T, H> PartialEq for $name<T, H>
+        where
+            T: Eq + Hash,
+            H: BuildHasher,
+        {
+            fn eq(&self, other: &Self) -> bool {
+                self.value == other.value
+            }
+        }
+
+        impl<T, H> Eq for $name<T, H>
+        where
+            T: Eq + Hash,
+            H: BuildHasher,
+        {
+        }
+
+        impl<T, H> Hash for $name<T, H>
+        where
+            T: Eq + Hash,
+            H: BuildHasher,
+        {
+            fn hash<H2: Hasher>(&self, state: &mut H2) {
+                state.$write(self.memoized_hash());
+            }
+        }
+
+        impl<T, H> AsRef<T> for $name<T, H>
+        where
+            T: Eq + Hash,
+            H: BuildHasher,
+        {
+            fn as_ref(&self) -> &T {
+                &self.value
+            }
+        }
+
+        impl<T, H> Clone for $name<T, H>
+        where
+            T: Eq + Hash + Clone,
+            H: BuildHasher + Clone,
+        {
+            fn clone(&self) -> Self {
+                Self {
+                    value: self.value.clone(),
+                    hash: <$atomic>::new(self.hash.load(Ordering::Relaxed)),
+                    hasher: self.hasher.clone(),
+                }
+            }
+        }
+    };
+}
+
+narrow_memo! {
+    /// A [`HashMemo`](crate::HashMemo) variant caching a word-sized hash.
+    ///
+    /// On 64-bit targets this behaves exactly like `HashMemo`. On 32-bit
+    /// targets, where `AtomicU64` may lower to a lock or an emulated sequence,
+    /// the cache is a plain `AtomicUsize` and only the low 32 bits of the hash
+    /// are kept. The outer map then sees 32 bits of entropy: among `n` keys
+    /// about `n² / 2³³` pairs collide, each costing an extra equality check, so
+    /// expect the first collisions around 65 000 keys. Prefer `HashMemo` when
+    /// full 64-bit fidelity matters more than the cost of the atomic.
+    ///
+    /// A truncated hash of `0` is remapped to `1`, as with `HashMemo`. `Hash`
+    /// writes the cached word with `write_usize`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemoUsize;
+    ///
+    /// let memo = HashMemoUsize::new("hello world");
+    /// ```
+    HashMemoUsize, AtomicUsize, usize, write_usize
+}
+
+narrow_memo! {
+    /// A [`HashMemo`](crate::HashMemo) variant caching the low 32 bits of the
+    /// hash on every target.
+    ///
+    /// The cache is an `AtomicU32`, which saves four bytes per memo where
+    /// alignment allows, at the cost of the collision rate described on
+    /// [`HashMemoUsize`] for 32-bit targets. A truncated hash of `0` is
+    /// remapped to `1`. `Hash` writes the cached value with `write_u32`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo32;
+    ///
+    /// let memo = HashMemo32::new("hello world");
+    /// ```
+    HashMemo32, AtomicU32, u32, write_u32
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::HashMemo;
+
+    #[derive(PartialEq, Eq)]
+    struct Zero;
+
+    impl Hash for Zero {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            state.write_u64(0);
+        }
+    }
+
+    fn nohash() -> BuildHasherDefault<nohash_hasher::NoHashHasher<u64>> {
+        BuildHasherDefault::default()
+    }
+
+    #[test]
+    fn caches_the_hash_truncated_to_a_word() {
+        let memo = HashMemoUsize::new("word".to_string());
+        let full = BuildHasherDefault::<DefaultHasher>::default().hash_one("word".to_string());
+        assert_eq!(memo.memoized_hash(), full as usize);
+        assert_eq!(memo.hash.load(Ordering::Relaxed), full as usize);
+        if cfg!(target_pointer_width = "64") {
+            assert_eq!(
+                memo.memoized_hash() as u64,
+                HashMemo::new("word".to_string()).memoized_hash()
+            );
+        }
+    }
+
+    #[test]
+    fn caches_the_low_32_bits() {
+        let memo = HashMemo32::new("word".to_string());
+        let full = BuildHasherDefault::<DefaultHasher>::default().hash_one("word".to_string());
+        assert_eq!(memo.memoized_hash(), full as u32);
+        assert_eq!(memo.hash.load(Ordering::Relaxed), full as u32);
+    }
+
+    #[test]
+    fn works_as_a_map_key() {
+        let mut map = HashMap::new();
+        map.insert(HashMemoUsize::new("alpha".to_string()), 1);
+        map.insert(HashMemoUsize::new("beta".to_string()), 2);
+        assert_eq!(map.get(&HashMemoUsize::new("alpha".to_string())), Some(&1));
+        assert_eq!(map.get(&HashMemoUsize::new("gamma".to_string())), None);
+
+        let mut map = HashMap::new();
+        map.insert(HashMemo32::new("alpha".to_string()), 1);
+        assert_eq!(map.get(&HashMemo32::new("alpha".to_string())), Some(&1));
+    }
+
+    #[test]
+    fn zero_word_is_remapped() {
+        assert_eq!(
+            HashMemoUsize::with_hasher(Zero, nohash()).memoized_hash(),
+            1
+        );
+        assert_eq!(HashMemo32::with_hasher(Zero, nohash()).memoized_hash(), 1);
+
+        // Only the low half is kept, so a hash with a zero low half remaps too.
+        #[derive(PartialEq, Eq)]
+        struct HighOnly;
+        impl Hash for HighOnly {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                state.write_u64(1 << 40);
+            }
+        }
+        assert_eq!(
+            HashMemo32::with_hasher(HighOnly, nohash()).memoized_hash(),
+            1
+        );
+    }
+}
@@ -0,0 +1,511 @@
+//! Hashing streamed bytes into a memo as they are produced.
+
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher};
+use std::io;
+use std::string::FromUtf8Error;
+
+use crate::HashMemo;
+
+/// An [`io::Write`] sink hashing bytes as they are written, which
+/// [`finish`](Self::finish)es into a memo with its hash already cached.
+///
+/// Large values built from a stream can be hashed chunk by chunk as they
+/// arrive, instead of in one pass over the finished buffer. The writer
+/// only hashes: collecting the bytes into the value is up to the caller,
+/// for instance by writing each chunk to both the buffer and the writer.
+///
+/// The cached hash is only correct if hashing the finished value with `H`
+/// would give the same result. Two things must hold:
+///
+/// - The value's `Hash` impl must write exactly the streamed bytes. A
+///   `String` writes its bytes then a `0xff` terminator, which
+///   [`finish_string`](Self::finish_string) appends. A `Vec<u8>` or
+///   `Box<[u8]>` writes its length first, which a writer created with
+///   [`with_len_prefix`](Self::with_len_prefix) writes up front.
+/// - The hasher must not depend on how the stream is split into writes.
+///   The writer feeds it whole 8-byte words as they fill up, then the final
+///   partial word, so hashers that consume writes a word at a time agree
+///   with one `write` of the whole stream; `DefaultHasher` and
+///   [`FixedState`](crate::FixedState) do.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{HashMemo, HashMemoWriter};
+/// use std::io::Write;
+///
+/// let mut text = String::new();
+/// let mut writer = HashMemoWriter::new();
+/// for chunk in ["large ", "streamed ", "document"] {
+///     text.push_str(chunk);
+///     writer.write_all(chunk.as_bytes()).unwrap();
+/// }
+///
+/// let memo = writer.finish_string(text);
+/// let fresh = HashMemo::new("large streamed document".to_string());
+/// assert_eq!(memo.hash_one_cached(), fresh.hash_one_cached());
+/// ```
+pub struct HashMemoWriter<H = BuildHasherDefault<DefaultHasher>>
+where
+    H: BuildHasher,
+{
+    hasher: H,
+    state: H::Hasher,
+    tail: [u8; 8],
+    tail_len: usize,
+    written: usize,
+    expected_len: Option<usize>,
+}
+
+impl HashMemoWriter {
+    /// Creates a writer hashing a raw byte stream with the default hasher.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_hasher(BuildHasherDefault::new())
+    }
+}
+
+impl Default for HashMemoWriter {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H> HashMemoWriter<H>
+where
+    H: BuildHasher,
+{
+    /// Creates a writer hashing a raw byte stream with `hasher`.
+    pub fn with_hasher(hasher: H) -> Self {
+        let state = hasher.build_hasher();
+        Self {
+            hasher,
+            state,
+            tail: [0; 8],
+            tail_len: 0,
+            written: 0,
+            expected_len: None,
+        }
+    }
+
+    /// Sets the writer up for a byte buffer of `len` bytes, writing the
+    /// length prefix a `Vec<u8>` or `Box<[u8]>` hashes first.
+    ///
+    /// Debug builds assert on [`finish`](Self::finish) that exactly `len`
+    /// bytes were written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if bytes were already written.
+    pub fn with_len_prefix(mut self, len: usize) -> Self {
+        assert_eq!(self.written, 0, "the length prefix must come first");
+        self.state.write_usize(len);
+        self.expected_len = Some(len);
+        self
+    }
+
+    /// Returns the number of bytes written so far.
+    #[inline]
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Feeds `bytes` to the hasher in whole words, keeping the remainder.
+    fn feed(&mut self, mut bytes: &[u8]) {
+        self.written += bytes.len();
+        if self.tail_len > 0 {
+            let take = bytes.len().min(8 - self.tail_len);
+            self.tail[self.tail_len..self.tail_len + take].copy_from_slice(&bytes[..take]);
+            self.tail_len += take;
+            bytes = &bytes[take..];
+            if self.tail_len < 8 {
+                return;
+            }
+            self.state.write(&self.tail);
+            self.tail_len = 0;
+        }
+        let whole = bytes.len() - bytes.len() % 8;
+        if whole > 0 {
+            self.state.write(&bytes[..whole]);
+        }
+        let rest = &bytes[whole..];
+        self.tail[..rest.len()].copy_from_slice(rest);
+        self.tail_len = rest.len();
+    }
+
+    /// Wraps `value` in a memo caching the hash of the streamed bytes.
+    ///
+    /// `value` must hash, under the writer's hasher, exactly as the
+    /// streamed bytes did: see the [type docs](Self). The memo is not
+    /// checked against the value; a mismatch makes it unequal to memos of
+    /// the same value that computed their own hash, and trips the stale
+    /// cache assertion of `PartialEq` in debug builds.
+    pub fn finish<T>(mut self, value: T) -> HashMemo<T, H>
+    where
+        T: Eq + Hash,
+    {
+        if let Some(len) = self.expected_len {
+            debug_assert_eq!(
+                self.written, len,
+                "wrote a different length than was prefixed"
+            );
+        }
+        self.state.write(&self.tail[..self.tail_len]);
+        let hash = self.state.finish();
+        HashMemo::with_hasher_const(value, self.hasher, hash)
+    }
+
+    /// Like [`finish`](Self::finish) for a `String` of the streamed bytes,
+    /// appending the terminator a `str` hashes after its bytes.
+    pub fn finish_string(mut self, value: String) -> HashMemo<String, H> {
+        debug_assert_eq!(
+            self.written,
+            value.len(),
+            "the string is not the streamed bytes"
+        );
+        self.state.write(&self.tail[..self.tail_len]);
+        self.tail_len = 0;
+        self.state.write_u8(0xff);
+        self.finish(value)
+    }
+}
+
+impl<H> io::Write for HashMemoWriter<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.feed(buf);
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.feed(buf);
+        Ok(())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<H> fmt::Debug for HashMemoWriter<H>
+where
+    H: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HashMemoWriter")
+            .field("written", &self.written)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A writer collecting bytes into a buffer while hashing them, which
+/// finishes into a memo of the buffer with its hash already cached.
+///
+/// This is a [`HashMemoWriter`] that also keeps the bytes, for values
+/// assembled by serializers or templates through [`io::Write`] or
+/// [`fmt::Write`]: the finished memo does not re-read the buffer to hash
+/// it. The hasher must not depend on how the stream is split into writes,
+/// as for `HashMemoWriter`.
+///
+/// A `String` hashes its bytes before a terminator, so text finishes in one
+/// pass with [`finish_string`](Self::finish_string). A `Vec<u8>` hashes its
+/// length before its bytes, so [`finish`](Self::finish) only saves the pass
+/// if the length was declared up front with
+/// [`with_len_prefix`](Self::with_len_prefix); otherwise the memo hashes
+/// the buffer when it is first hashed.
+///
+/// # Examples
+///
+/// ```rust
+/// use hashmemo::{HashMemo, MemoWriter};
+/// use std::fmt::Write;
+///
+/// let mut writer = MemoWriter::new();
+/// for i in 0..3 {
+///     write!(writer, "row {i};").unwrap();
+/// }
+///
+/// let memo = writer.finish_string().unwrap();
+/// assert_eq!(memo.as_ref(), "row 0;row 1;row 2;");
+/// let fresh = HashMemo::new("row 0;row 1;row 2;".to_string());
+/// assert_eq!(memo.hash_one_cached(), fresh.hash_one_cached());
+/// ```
+pub struct MemoWriter<H = BuildHasherDefault<DefaultHasher>>
+where
+    H: BuildHasher,
+{
+    buf: Vec<u8>,
+    hash: HashMemoWriter<H>,
+}
+
+impl MemoWriter {
+    /// Creates an empty writer hashing with the default hasher.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_hasher(BuildHasherDefault::new())
+    }
+}
+
+impl Default for MemoWriter {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H> MemoWriter<H>
+where
+    H: BuildHasher,
+{
+    /// Creates an empty writer hashing with `hasher`.
+    pub fn with_hasher(hasher: H) -> Self {
+        Self {
+            buf: Vec::new(),
+            hash: HashMemoWriter::with_hasher(hasher),
+        }
+    }
+
+    /// Sets the writer up for a byte buffer of exactly `len` bytes, so that
+    /// [`finish`](Self::finish) caches the streamed hash. The buffer
+    /// reserves `len` bytes.
+    ///
+    /// Debug builds assert on `finish` that exactly `len` bytes were
+    /// written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if bytes were already written.
+    pub fn with_len_prefix(mut self, len: usize) -> Self {
+        self.hash = self.hash.with_len_prefix(len);
+        self.buf.reserve_exact(len);
+        self
+    }
+
+    /// Returns the bytes written so far.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Wraps the written bytes in a memo.
+    ///
+    /// The memo caches the streamed hash if the writer was created with
+    /// [`with_len_prefix`](Self::with_len_prefix), and starts cold
+    /// otherwise.
+    pub fn finish(self) -> HashMemo<Vec<u8>, H> {
+        if self.hash.expected_len.is_some() {
+            self.hash.finish(self.buf)
+        } else {
+            HashMemo::with_hasher(self.buf, self.hash.hasher)
+        }
+    }
+
+    /// Wraps the written text in a memo caching the streamed hash.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error holding the bytes if bytes written through
+    /// [`io::Write`] are not valid UTF-8.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the writer was created with
+    /// [`with_len_prefix`](Self::with_len_prefix), whose prefix a `String`
+    /// does not hash.
+    pub fn finish_string(self) -> Result<HashMemo<String, H>, FromUtf8Error> {
+        assert!(
+            self.hash.expected_len.is_none(),
+            "a length-prefixed writer builds a byte buffer"
+        );
+        let text = String::from_utf8(self.buf)?;
+        Ok(self.hash.finish_string(text))
+    }
+}
+
+impl<H> io::Write for MemoWriter<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.buf.extend_from_slice(buf);
+        self.hash.feed(buf);
+        Ok(())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<H> fmt::Write for MemoWriter<H>
+where
+    H: BuildHasher,
+{
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.buf.extend_from_slice(s.as_bytes());
+        self.hash.feed(s.as_bytes());
+        Ok(())
+    }
+}
+
+impl<H> fmt::Debug for MemoWriter<H>
+where
+    H: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoWriter")
+            .field("written", &self.buf.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    #[cfg(not(feature = "disable-cache"))]
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+    use crate::FixedState;
+
+    /// Bytes whose `Hash` writes them raw, with no length or terminator.
+    #[derive(Debug, PartialEq, Eq)]
+    struct Raw(Vec<u8>);
+
+    impl Hash for Raw {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            state.write(&self.0);
+        }
+    }
+
+    fn buffer() -> Vec<u8> {
+        (0..10_000u32).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    /// Splits `bytes` into chunks of awkward, varying sizes.
+    fn chunks(bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
+        let mut rest = bytes;
+        let mut size = 0;
+        std::iter::from_fn(move || {
+            if rest.is_empty() {
+                return None;
+            }
+            size = (size * 5 + 3) % 37;
+            let (chunk, tail) = rest.split_at(size.min(rest.len()));
+            rest = tail;
+            Some(chunk)
+        })
+    }
+
+    fn stream<H: BuildHasher>(mut writer: HashMemoWriter<H>, bytes: &[u8]) -> HashMemoWriter<H> {
+        for chunk in chunks(bytes) {
+            writer.write_all(chunk).unwrap();
+        }
+        assert_eq!(writer.written(), bytes.len());
+        writer
+    }
+
+    #[test]
+    fn streamed_chunks_hash_like_the_full_buffer() {
+        let bytes = buffer();
+        for len in [0, 1, 7, 8, 9, 63, 64, 1000, bytes.len()] {
+            let bytes = &bytes[..len];
+
+            let mut full = FixedState.build_hasher();
+            full.write(bytes);
+            let memo =
+                stream(HashMemoWriter::with_hasher(FixedState), bytes).finish(Raw(bytes.to_vec()));
+            assert_eq!(memo.memoized_hash(), crate::remap_zero(full.finish()));
+            assert_eq!(memo, HashMemo::with_hasher(Raw(bytes.to_vec()), FixedState));
+
+            let memo = stream(
+                HashMemoWriter::with_hasher(FixedState).with_len_prefix(len),
+                bytes,
+            )
+            .finish(bytes.to_vec());
+            let fresh = HashMemo::with_hasher(bytes.to_vec(), FixedState);
+            assert_eq!(memo.memoized_hash(), fresh.memoized_hash());
+        }
+    }
+
+    #[test]
+    fn streamed_strings_and_vecs_match_default_memos() {
+        let text: String = (0..2000)
+            .map(|i| char::from(b'a' + (i % 26) as u8))
+            .collect();
+        let memo = stream(HashMemoWriter::new(), text.as_bytes()).finish_string(text.clone());
+        assert_eq!(memo.memoized_hash(), HashMemo::new(text).memoized_hash());
+
+        let bytes = buffer();
+        let writer = HashMemoWriter::new().with_len_prefix(bytes.len());
+        let memo = stream(writer, &bytes).finish(bytes.clone());
+        assert_eq!(memo.memoized_hash(), HashMemo::new(bytes).memoized_hash());
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn memo_writers_cache_the_hash_of_their_buffer() {
+        use std::fmt::Write as _;
+
+        let bytes = buffer();
+        for len in [0, 1, 7, 8, 9, 1000, bytes.len()] {
+            let bytes = &bytes[..len];
+            let mut writer = MemoWriter::with_hasher(FixedState).with_len_prefix(len);
+            writer.write_all(&[]).unwrap();
+            for chunk in chunks(bytes) {
+                writer.write_all(chunk).unwrap();
+                writer.write_all(&[]).unwrap();
+            }
+            let memo = writer.finish();
+            assert_ne!(memo.hash.load(Ordering::Relaxed), 0);
+            let fresh = HashMemo::with_hasher(bytes.to_vec(), FixedState);
+            assert_eq!(memo.memoized_hash(), fresh.memoized_hash());
+            assert_eq!(memo, fresh);
+
+            let text: String = bytes.iter().map(|&b| char::from(b'a' + b % 26)).collect();
+            let mut writer = MemoWriter::new();
+            for chunk in chunks(text.as_bytes()) {
+                writer
+                    .write_str(std::str::from_utf8(chunk).unwrap())
+                    .unwrap();
+                writer.write_str("").unwrap();
+            }
+            let memo = writer.finish_string().unwrap();
+            assert_ne!(memo.hash.load(Ordering::Relaxed), 0);
+            assert_eq!(memo.memoized_hash(), HashMemo::new(text).memoized_hash());
+        }
+    }
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn memo_writers_without_a_prefix_hash_lazily() {
+        let mut writer = MemoWriter::new();
+        writer.write_all(b"no length up front").unwrap();
+        let memo = writer.finish();
+        assert_eq!(memo.hash.load(Ordering::Relaxed), 0);
+        assert_eq!(memo, HashMemo::new(b"no length up front".to_vec()));
+
+        let mut writer = MemoWriter::new();
+        writer.write_all(&[0xff, 0xfe]).unwrap();
+        assert_eq!(
+            writer.finish_string().unwrap_err().into_bytes(),
+            [0xff, 0xfe]
+        );
+    }
+}
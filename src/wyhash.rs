@@ -0,0 +1,132 @@
+//! [`wyhash`](::wyhash) support, for fast hashing of large values.
+//!
+//! [`WyHashMemo`] memoizes with wyhash through [`WyHashState`], the
+//! `BuildHasher` the `wyhash` crate lacks. Given a fixed seed the hash is
+//! deterministic: equal values hash equally across runs and processes of
+//! the same build. Integer writes reach the hasher in native byte order, so
+//! values containing integers hash differently on targets of the other
+//! endianness or pointer width.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::HashMemo;
+//! use std::collections::HashMap;
+//!
+//! let mut map = HashMap::new();
+//! map.insert(HashMemo::new_wyhash(vec![7u8; 1 << 20]), "blob");
+//! assert_eq!(map.get(&HashMemo::new_wyhash(vec![7u8; 1 << 20])), Some(&"blob"));
+//! ```
+
+use std::hash::{BuildHasher, Hash};
+
+use ::wyhash::WyHash;
+
+use crate::HashMemo;
+
+/// A [`HashMemo`] hashing with wyhash, seeded or not.
+pub type WyHashMemo<T> = HashMemo<T, WyHashState>;
+
+/// A `BuildHasher` for `WyHash` with a fixed seed.
+///
+/// The default seed is `0`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WyHashState {
+    seed: u64,
+}
+
+impl WyHashState {
+    /// Creates a `WyHashState` building hashers seeded with `seed`.
+    #[inline]
+    pub const fn with_seed(seed: u64) -> Self {
+        Self { seed }
+    }
+}
+
+impl BuildHasher for WyHashState {
+    type Hasher = WyHash;
+
+    #[inline]
+    fn build_hasher(&self) -> WyHash {
+        WyHash::with_seed(self.seed)
+    }
+}
+
+impl<T> HashMemo<T, WyHashState>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new `HashMemo` using wyhash with a seed of `0`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::{HashMemo, WyHashMemo};
+    ///
+    /// let memo: WyHashMemo<&str> = HashMemo::new_wyhash("hello world");
+    /// ```
+    pub fn new_wyhash(value: T) -> Self {
+        Self::new_wyhash_with_seed(value, 0)
+    }
+
+    /// Creates a new `HashMemo` using wyhash with `seed`.
+    ///
+    /// Memos compared or looked up together must share the seed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let memo = HashMemo::new_wyhash_with_seed("hello world", 0x5eed);
+    /// ```
+    pub fn new_wyhash_with_seed(value: T, seed: u64) -> Self {
+        Self::with_hasher(value, WyHashState::with_seed(seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::hash::Hasher;
+
+    use super::*;
+    use crate::remap_zero;
+
+    #[test]
+    fn works_as_a_hashmap_key() {
+        let mut map = HashMap::new();
+        for i in 0..100u32 {
+            map.insert(HashMemo::new_wyhash(i.to_string().repeat(100)), i);
+        }
+        for i in 0..100u32 {
+            let key = HashMemo::new_wyhash(i.to_string().repeat(100));
+            assert_eq!(map.get(&key), Some(&i));
+        }
+        assert_eq!(map.get(&HashMemo::new_wyhash("absent".repeat(100))), None);
+    }
+
+    #[test]
+    fn hashes_with_the_configured_seed() {
+        let value = "seeded".to_string();
+        let unseeded = HashMemo::new_wyhash(value.clone());
+        let seeded = HashMemo::new_wyhash_with_seed(value.clone(), 42);
+
+        assert_eq!(
+            unseeded.memoized_hash(),
+            remap_zero(WyHashState::default().hash_one(&value))
+        );
+        assert_eq!(
+            seeded.memoized_hash(),
+            remap_zero(WyHashState::with_seed(42).hash_one(&value))
+        );
+        assert_ne!(seeded.memoized_hash(), unseeded.memoized_hash());
+    }
+
+    #[test]
+    fn state_builds_seeded_wyhash() {
+        let mut hasher = WyHashState::with_seed(3).build_hasher();
+        hasher.write(&[0, 1, 2]);
+        assert_eq!(hasher.finish(), ::wyhash::wyhash(&[0, 1, 2], 3));
+    }
+}
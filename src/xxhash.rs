@@ -0,0 +1,104 @@
+//! [`xxhash-rust`](::xxhash_rust) support, for fast hashing of large
+//! values.
+//!
+//! [`XxHashMemo`] memoizes with XXH3. Unlike `RandomState`, an XXH3 hasher
+//! is deterministic for a given seed: equal values hash equally across runs
+//! and processes of the same build, which suits hashes that are persisted
+//! or compared between processes. Values hashing `usize`s or other
+//! platform-sized integers still hash differently across targets.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use hashmemo::HashMemo;
+//! use std::collections::HashMap;
+//!
+//! let mut map = HashMap::new();
+//! map.insert(HashMemo::new_xxh3(vec![7u8; 1 << 20]), "blob");
+//! assert_eq!(map.get(&HashMemo::new_xxh3(vec![7u8; 1 << 20])), Some(&"blob"));
+//! ```
+
+use std::hash::Hash;
+
+use ::xxhash_rust::xxh3::Xxh3Builder;
+
+use crate::HashMemo;
+
+/// A [`HashMemo`] hashing with XXH3, seeded or not.
+pub type XxHashMemo<T> = HashMemo<T, Xxh3Builder>;
+
+impl<T> HashMemo<T, Xxh3Builder>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new `HashMemo` using unseeded XXH3.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::{HashMemo, XxHashMemo};
+    ///
+    /// let memo: XxHashMemo<&str> = HashMemo::new_xxh3("hello world");
+    /// ```
+    pub const fn new_xxh3(value: T) -> Self {
+        Self::with_hasher(value, Xxh3Builder::new())
+    }
+
+    /// Creates a new `HashMemo` using XXH3 with `seed`.
+    ///
+    /// Memos compared or looked up together must share the seed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hashmemo::HashMemo;
+    ///
+    /// let memo = HashMemo::new_xxh3_with_seed("hello world", 0x5eed);
+    /// ```
+    pub const fn new_xxh3_with_seed(value: T, seed: u64) -> Self {
+        Self::with_hasher(value, Xxh3Builder::new().with_seed(seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::hash::BuildHasher;
+
+    use super::*;
+    use crate::remap_zero;
+
+    #[test]
+    fn works_as_a_hashmap_key() {
+        let mut map = HashMap::new();
+        for i in 0..100u32 {
+            map.insert(HashMemo::new_xxh3(i.to_string().repeat(100)), i);
+        }
+        for i in 0..100u32 {
+            let key = HashMemo::new_xxh3(i.to_string().repeat(100));
+            assert_eq!(map.get(&key), Some(&i));
+        }
+        assert_eq!(map.get(&HashMemo::new_xxh3("absent".repeat(100))), None);
+    }
+
+    #[test]
+    fn hashes_with_the_configured_seed() {
+        let value = "seeded".to_string();
+        let unseeded = HashMemo::new_xxh3(value.clone());
+        let seeded = HashMemo::new_xxh3_with_seed(value.clone(), 42);
+
+        assert_eq!(
+            unseeded.memoized_hash(),
+            remap_zero(Xxh3Builder::new().hash_one(&value))
+        );
+        assert_eq!(
+            seeded.memoized_hash(),
+            remap_zero(Xxh3Builder::new().with_seed(42).hash_one(&value))
+        );
+        assert_ne!(seeded.memoized_hash(), unseeded.memoized_hash());
+        assert_eq!(
+            seeded.memoized_hash(),
+            HashMemo::new_xxh3_with_seed(value, 42).memoized_hash()
+        );
+    }
+}
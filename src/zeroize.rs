@@ -0,0 +1,73 @@
+//! [`zeroize`](::zeroize) support for memos wrapping secret material.
+//!
+//! Zeroizing a memo scrubs the value and the cached hash, since the hash is
+//! derived from the secret and leaks information about it. The memo is left
+//! with an empty cache, so hashing it again recomputes from the scrubbed
+//! value.
+//!
+//! `HashMemo` does not zeroize itself on drop; wrap it in
+//! [`Zeroizing`](::zeroize::Zeroizing) for that. Each clone carries its own
+//! copy of the value and hash and must be zeroized separately, and the value
+//! returned by [`into_inner`](crate::HashMemo::into_inner) becomes the
+//! caller's responsibility.
+
+use std::hash::{BuildHasher, Hash};
+
+use ::zeroize::Zeroize;
+
+use crate::HashMemo;
+
+impl<T, H> Zeroize for HashMemo<T, H>
+where
+    T: Eq + Hash + Zeroize,
+    H: BuildHasher,
+{
+    fn zeroize(&mut self) {
+        self.value.zeroize();
+        #[cfg(not(feature = "disable-cache"))]
+        self.hash.get_mut().zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use ::zeroize::Zeroizing;
+
+    use super::*;
+
+    #[cfg(not(feature = "disable-cache"))]
+    #[test]
+    fn zeroize_scrubs_value_and_cache() {
+        let mut memo = HashMemo::new([0xA5u8; 32]);
+        let _ = memo.memoized_hash();
+        assert_ne!(memo.hash.load(Ordering::Relaxed), 0);
+
+        memo.zeroize();
+        assert_eq!(memo.value, [0u8; 32]);
+        assert_eq!(memo.hash.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn zeroized_memo_rehashes_from_scrubbed_value() {
+        let mut memo = HashMemo::new([0xA5u8; 32]);
+        let _ = memo.memoized_hash();
+        memo.zeroize();
+
+        assert_eq!(
+            memo.memoized_hash(),
+            HashMemo::new([0u8; 32]).memoized_hash()
+        );
+    }
+
+    #[test]
+    fn memo_can_be_wrapped_in_zeroizing() {
+        let memo = Zeroizing::new(HashMemo::new(b"token".to_vec()));
+        assert_eq!(
+            memo.memoized_hash(),
+            HashMemo::new(b"token".to_vec()).memoized_hash()
+        );
+        drop(memo);
+    }
+}
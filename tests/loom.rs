@@ -0,0 +1,93 @@
+//! Loom models of the cache's publication protocol.
+//!
+//! `HashMemo` keeps its cache in a `const`-constructible atomic that loom
+//! cannot stand in for, so these models replay the protocol of
+//! `HashMemo::memoized_hash` and `forget_hash` on loom's atomics instead:
+//! load, and on `0` compute and publish with a plain `Relaxed` store. Keep
+//! them in step with the crate. Run with
+//!
+//! ```text
+//! RUSTFLAGS="--cfg loom" cargo test --test loom --release
+//! ```
+
+#![cfg(loom)]
+
+use loom::sync::atomic::{AtomicU64, Ordering};
+use loom::sync::Arc;
+use loom::thread;
+
+/// The hash every writer computes for the modelled value.
+const HASH: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// The cache word of a memo, with its protocol.
+struct Cache(AtomicU64);
+
+impl Cache {
+    fn cold() -> Arc<Self> {
+        Arc::new(Self(AtomicU64::new(0)))
+    }
+
+    /// `HashMemo::memoized_hash`.
+    fn memoized_hash(&self) -> u64 {
+        let hash = self.0.load(Ordering::Relaxed);
+        if hash != 0 {
+            return hash;
+        }
+        self.0.store(HASH, Ordering::Relaxed);
+        HASH
+    }
+
+    /// `HashMemo::forget_hash`.
+    fn forget_hash(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+
+    /// What a reader of the raw cache can see.
+    fn observe(&self) -> u64 {
+        let hash = self.0.load(Ordering::Relaxed);
+        assert!(hash == 0 || hash == HASH, "torn or foreign hash {hash:#x}");
+        hash
+    }
+}
+
+#[test]
+fn racing_writers_publish_only_the_correct_hash() {
+    loom::model(|| {
+        let cache = Cache::cold();
+        let writers: Vec<_> = (0..2)
+            .map(|_| {
+                let cache = cache.clone();
+                thread::spawn(move || assert_eq!(cache.memoized_hash(), HASH))
+            })
+            .collect();
+
+        cache.observe();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(cache.observe(), HASH);
+    });
+}
+
+#[test]
+fn forgetting_while_hashing_leaves_a_sentinel_or_the_hash() {
+    loom::model(|| {
+        let cache = Cache::cold();
+        cache.memoized_hash();
+
+        let forgetter = {
+            let cache = cache.clone();
+            thread::spawn(move || cache.forget_hash())
+        };
+        let writer = {
+            let cache = cache.clone();
+            thread::spawn(move || assert_eq!(cache.memoized_hash(), HASH))
+        };
+
+        cache.observe();
+        forgetter.join().unwrap();
+        writer.join().unwrap();
+        cache.observe();
+        assert_eq!(cache.memoized_hash(), HASH);
+    });
+}
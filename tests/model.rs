@@ -0,0 +1,273 @@
+//! Differential tests: maps keyed by memos must be observationally
+//! identical to plain maps.
+//!
+//! Each case drives random operations, from a seeded generator, against a
+//! reference `HashMap<K, V>` and against `HashMap<HashMemo<K>, V>` (under
+//! both `RandomState` and `PassthroughState`) and a `MemoCache` too large
+//! to evict, and compares every result. A failing case reports its seed;
+//! add it to `REGRESSION_SEEDS` so it runs on every build. Set
+//! `HASHMEMO_MODEL_CASES` to run more random seeds than the default.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+
+use hashmemo::{HashMemo, MemoCache, PassthroughState, Probe};
+
+/// Seeds of past failures, and of cases that reach rare paths.
+const REGRESSION_SEEDS: &[u64] = &[0, 1, 0x5eed, 0xdead_beef, u64::MAX];
+
+const OPS_PER_CASE: usize = 300;
+
+/// SplitMix64, enough to spread small seeds over varied sequences.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+/// A key type the model can generate and mutate.
+trait ModelKey: Clone + Debug + Eq + Hash {
+    /// Draws a key from a small space, so operations often hit.
+    fn generate(rng: &mut Rng) -> Self;
+
+    /// Changes the key so that it hashes differently, usually, to another
+    /// key `generate` can draw, so later operations look the result up.
+    fn mutate(&mut self);
+}
+
+impl ModelKey for String {
+    fn generate(rng: &mut Rng) -> Self {
+        match rng.below(10) {
+            0 => "x".repeat(300),
+            n => (0..n % 3)
+                .map(|_| (b'a' + rng.below(4) as u8) as char)
+                .collect(),
+        }
+    }
+
+    fn mutate(&mut self) {
+        if self.len() < 2 {
+            self.push('a');
+        } else {
+            self.clear();
+        }
+    }
+}
+
+impl ModelKey for Vec<u8> {
+    fn generate(rng: &mut Rng) -> Self {
+        let len = rng.below(4) as usize;
+        (0..len).map(|_| rng.below(3) as u8).collect()
+    }
+
+    fn mutate(&mut self) {
+        if self.len() < 3 {
+            self.push(0);
+        } else {
+            self.clear();
+        }
+    }
+}
+
+/// A key whose `Hash` ignores most of what `Eq` compares, which is legal,
+/// so unequal keys collide constantly.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Weird {
+    name: String,
+    tag: u8,
+}
+
+impl Hash for Weird {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.name.len());
+        state.write_u8(self.tag % 2);
+    }
+}
+
+impl ModelKey for Weird {
+    fn generate(rng: &mut Rng) -> Self {
+        Weird {
+            name: String::generate(rng),
+            tag: rng.below(4) as u8,
+        }
+    }
+
+    fn mutate(&mut self) {
+        self.tag = (self.tag + 1) % 4;
+    }
+}
+
+/// Every implementation under test, next to the reference.
+struct Maps<K: ModelKey> {
+    reference: HashMap<K, u32>,
+    memo: HashMap<HashMemo<K>, u32>,
+    passthrough: HashMap<HashMemo<K>, u32, PassthroughState>,
+    cache: MemoCache<K, u32>,
+}
+
+impl<K: ModelKey> Maps<K> {
+    fn new() -> Self {
+        Self {
+            reference: HashMap::new(),
+            memo: HashMap::new(),
+            passthrough: HashMap::default(),
+            cache: MemoCache::with_capacity(usize::MAX),
+        }
+    }
+
+    fn insert(&mut self, key: K, value: u32) {
+        let expected = self.reference.insert(key.clone(), value);
+        assert_eq!(
+            self.memo.insert(HashMemo::new(key.clone()), value),
+            expected
+        );
+        assert_eq!(
+            self.passthrough.insert(HashMemo::new(key.clone()), value),
+            expected
+        );
+        assert_eq!(self.cache.put(key, value).map(|(_, v)| v), expected);
+    }
+
+    /// Looks `key` up through a fresh, cold memo and through a probe.
+    fn get(&mut self, key: &K) {
+        let expected = self.reference.get(key);
+        let fresh = HashMemo::new(key.clone());
+        assert_eq!(self.memo.get(&fresh), expected);
+        assert_eq!(self.passthrough.get(&fresh.clone_without_hash()), expected);
+        assert_eq!(self.memo.get(Probe::new(key).as_lookup()), expected);
+        assert_eq!(self.cache.peek(key), expected);
+        assert_eq!(self.cache.get(key), expected);
+    }
+
+    fn remove(&mut self, key: &K) {
+        let expected = self.reference.remove(key);
+        assert_eq!(self.memo.remove(&HashMemo::new(key.clone())), expected);
+        assert_eq!(
+            self.passthrough.remove(Probe::new(key).as_lookup()),
+            expected
+        );
+        assert_eq!(self.cache.pop(key).map(|(_, v)| v), expected);
+    }
+
+    /// Removes `key`, mutates it as the memo APIs allow, and reinserts it.
+    fn mutate(&mut self, key: &K) {
+        let Some(value) = self.reference.remove(key) else {
+            return;
+        };
+        let mut changed = key.clone();
+        changed.mutate();
+        self.reference.insert(changed.clone(), value);
+
+        // In place, emptying the cache first so the new hash is computed.
+        let (mut memo, v) = self.memo.remove_entry(&HashMemo::new(key.clone())).unwrap();
+        memo.forget_hash();
+        memo.with_mut_unchecked(ModelKey::mutate);
+        self.memo.insert(memo, v);
+
+        // Through the value, rewrapped in a cold memo.
+        let (memo, v) = self
+            .passthrough
+            .remove_entry(Probe::new(key).as_lookup())
+            .unwrap();
+        let mut inner = memo.into_inner();
+        inner.mutate();
+        self.passthrough.insert(HashMemo::new(inner), v);
+
+        let (mut inner, v) = self.cache.pop(key).unwrap();
+        inner.mutate();
+        self.cache.put(inner, v);
+    }
+
+    fn clone_all(&mut self) {
+        self.reference = self.reference.clone();
+        self.memo = self.memo.clone();
+        self.passthrough = self.passthrough.clone();
+    }
+
+    fn check_contents(&self) {
+        let len = self.reference.len();
+        assert_eq!(self.memo.len(), len);
+        assert_eq!(self.passthrough.len(), len);
+        assert_eq!(self.cache.len(), len);
+
+        let unwrap = |(k, v): (&HashMemo<K>, &u32)| (k.as_ref().clone(), *v);
+        assert_eq!(
+            self.memo.iter().map(unwrap).collect::<HashMap<_, _>>(),
+            self.reference
+        );
+        assert_eq!(
+            self.passthrough
+                .iter()
+                .map(unwrap)
+                .collect::<HashMap<_, _>>(),
+            self.reference
+        );
+        assert_eq!(
+            self.cache
+                .iter()
+                .map(|(k, v)| (k.clone(), *v))
+                .collect::<HashMap<_, _>>(),
+            self.reference
+        );
+    }
+}
+
+fn run_case<K: ModelKey>(seed: u64) {
+    let mut rng = Rng(seed);
+    let mut maps = Maps::<K>::new();
+
+    for step in 0..OPS_PER_CASE {
+        let key = K::generate(&mut rng);
+        let op = rng.below(100);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| match op {
+            0..=34 => maps.insert(key, rng.below(1000) as u32),
+            35..=64 => maps.get(&key),
+            65..=79 => maps.remove(&key),
+            80..=89 => maps.mutate(&key),
+            90..=94 => maps.clone_all(),
+            _ => maps.check_contents(),
+        }));
+        if let Err(panic) = result {
+            eprintln!(
+                "{} diverged at step {step} (op {op}) of seed {seed:#x}",
+                std::any::type_name::<K>()
+            );
+            std::panic::resume_unwind(panic);
+        }
+    }
+    maps.check_contents();
+}
+
+fn seeds() -> impl Iterator<Item = u64> {
+    let cases = std::env::var("HASHMEMO_MODEL_CASES")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(64u64);
+    REGRESSION_SEEDS.iter().copied().chain(1000..1000 + cases)
+}
+
+#[test]
+fn string_keys_match_the_reference() {
+    seeds().for_each(run_case::<String>);
+}
+
+#[test]
+fn byte_keys_match_the_reference() {
+    seeds().for_each(run_case::<Vec<u8>>);
+}
+
+#[test]
+fn colliding_keys_match_the_reference() {
+    seeds().for_each(run_case::<Weird>);
+}
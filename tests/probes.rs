@@ -0,0 +1,47 @@
+//! Hashes are counted process-wide, so the memoization invariant is checked
+//! here, in a test binary of its own, rather than next to the unit tests.
+
+#![cfg(not(feature = "disable-cache"))]
+
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use hashmemo::HashMemo;
+
+static HASHES: AtomicUsize = AtomicUsize::new(0);
+
+/// A large string key that counts how often it is hashed.
+#[derive(Clone, PartialEq, Eq)]
+struct Counted(String);
+
+impl Hash for Counted {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        HASHES.fetch_add(1, Ordering::Relaxed);
+        self.0.hash(state);
+    }
+}
+
+#[test]
+fn a_key_is_hashed_once_however_often_it_is_probed() {
+    // Clones carry the cache over, so prime the key before cloning it.
+    let key = HashMemo::new(Counted("k".repeat(1 << 16)));
+    key.precompute();
+    let mut set = HashSet::new();
+
+    for round in 0..1000 {
+        assert_eq!(set.insert(key.clone()), round == 0);
+        assert!(set.contains(&key));
+    }
+    assert_eq!(set.len(), 1);
+    assert_eq!(HASHES.load(Ordering::Relaxed), 1);
+
+    // Unmemoized, every probe hashes the whole key again.
+    let raw = Counted("k".repeat(1 << 16));
+    let mut set = HashSet::new();
+    for _ in 0..1000 {
+        set.insert(raw.clone());
+        assert!(set.contains(&raw));
+    }
+    assert_eq!(HASHES.load(Ordering::Relaxed), 1 + 2000);
+}
@@ -0,0 +1,53 @@
+//! The `stats` counters are process-wide, so they are checked here, in a
+//! test binary of their own, rather than next to the unit tests.
+
+#[cfg(feature = "stats")]
+#[test]
+fn counters_follow_a_scripted_sequence() {
+    use hashmemo::stats::{self, Stats};
+    use hashmemo::{HashMemo, Memoized};
+    use std::hash::{BuildHasher, RandomState};
+
+    let state = RandomState::new();
+    stats::reset();
+    assert_eq!(stats::snapshot(), Stats::default());
+
+    let memo = HashMemo::new("scripted".to_string());
+    state.hash_one(&memo);
+    state.hash_one(&memo);
+    state.hash_one(&memo);
+    memo.forget_hash();
+    state.hash_one(&memo);
+
+    let mut field = Memoized::new(vec![1u8, 2, 3]);
+    state.hash_one(&field);
+    field.get_mut().push(4);
+    field.set(vec![5]);
+    state.hash_one(&field);
+    state.hash_one(&field);
+
+    // Without a cache the memo computes every time; the field still caches.
+    let disabled = cfg!(feature = "disable-cache");
+    assert_eq!(
+        stats::snapshot(),
+        Stats {
+            hits: if disabled { 1 } else { 3 },
+            computations: if disabled { 6 } else { 4 },
+            invalidations: 3,
+        }
+    );
+
+    stats::reset();
+    assert_eq!(stats::snapshot(), Stats::default());
+}
+
+#[test]
+fn counter_symbols_follow_the_feature() {
+    // The mangled path of the counters, assembled at run time so that the
+    // needle itself is not in the binary.
+    let (hashmemo, stats) = std::hint::black_box((8, 5));
+    let needle = format!("{hashmemo}hashmemo{stats}stats").into_bytes();
+    let exe = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+    let has_counters = exe.windows(needle.len()).any(|w| w == needle);
+    assert_eq!(has_counters, cfg!(feature = "stats"));
+}
@@ -0,0 +1,7 @@
+use std::hash::RandomState;
+
+use hashmemo::assert_hashmemo_zero_overhead;
+
+assert_hashmemo_zero_overhead!(String, RandomState);
+
+fn main() {}
@@ -0,0 +1,58 @@
+//! Keeps `unsafe` code confined to the features documented as needing it.
+//!
+//! The default build is `#![forbid(unsafe_code)]`, which the compiler
+//! enforces; this test makes sure it stays that way, and that any new
+//! `unsafe` lands in a module listed here, behind its feature.
+
+use std::fs;
+use std::path::Path;
+
+/// The modules allowed `unsafe` code, and the feature each is gated on.
+const UNSAFE_MODULES: &[(&str, &str)] = &[("rkyv.rs", "rkyv")];
+
+#[test]
+fn the_default_build_forbids_unsafe_code() {
+    let lib = fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("src/lib.rs")).unwrap();
+    let features = UNSAFE_MODULES
+        .iter()
+        .map(|(_, feature)| format!("feature = \"{feature}\""))
+        .collect::<Vec<_>>();
+    let gate = match features.as_slice() {
+        [one] => one.clone(),
+        many => format!("any({})", many.join(", ")),
+    };
+    assert!(
+        lib.contains(&format!("#![cfg_attr(not({gate}), forbid(unsafe_code))]")),
+        "src/lib.rs must forbid unsafe code unless {gate}"
+    );
+    for (module, feature) in UNSAFE_MODULES {
+        let module = module.trim_end_matches(".rs");
+        assert!(
+            lib.contains(&format!("#[cfg(feature = \"{feature}\")]\nmod {module};")),
+            "the `{module}` module must be gated on the `{feature}` feature"
+        );
+    }
+}
+
+#[test]
+fn unsafe_code_is_confined_to_listed_modules() {
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+    for entry in fs::read_dir(&src).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_str().unwrap();
+        if UNSAFE_MODULES.iter().any(|(module, _)| *module == name) {
+            continue;
+        }
+        let source = fs::read_to_string(&path).unwrap();
+        for (i, line) in source.lines().enumerate() {
+            let code = line.split("//").next().unwrap();
+            assert!(
+                !code
+                    .split(|c: char| !c.is_alphanumeric() && c != '_')
+                    .any(|word| word == "unsafe"),
+                "src/{name}:{}: `unsafe` outside the modules listed in UNSAFE_MODULES",
+                i + 1
+            );
+        }
+    }
+}